@@ -0,0 +1,45 @@
+use types::KirinType;
+
+/// A library function provided by the VM
+#[derive(Debug)]
+pub struct Builtin {
+    pub name: &'static str,
+    pub parameters: &'static [KirinType],
+    pub return_type: KirinType,
+}
+
+const BUILTINS: &[Builtin] = &[
+    Builtin {
+        name: "print",
+        parameters: &[KirinType::Any],
+        return_type: KirinType::Void,
+    },
+    Builtin {
+        name: "wrapping_add",
+        parameters: &[KirinType::Int, KirinType::Int],
+        return_type: KirinType::Int,
+    },
+    Builtin {
+        name: "wrapping_sub",
+        parameters: &[KirinType::Int, KirinType::Int],
+        return_type: KirinType::Int,
+    },
+    Builtin {
+        name: "wrapping_mul",
+        parameters: &[KirinType::Int, KirinType::Int],
+        return_type: KirinType::Int,
+    },
+    Builtin {
+        name: "wrapping_pow",
+        parameters: &[KirinType::Int, KirinType::Int],
+        return_type: KirinType::Int,
+    },
+];
+
+pub fn lookup_builtin(name: &str) -> Option<&'static Builtin> {
+    BUILTINS.iter().find(|builtin| builtin.name == name)
+}
+
+pub fn builtins() -> &'static [Builtin] {
+    BUILTINS
+}
@@ -1,15 +1,307 @@
-use errors::{KirinError, SpannedError};
+pub mod builtins;
+pub mod lints;
+pub mod names;
+
+use std::collections::{HashMap, HashSet};
+
+use builtins::lookup_builtin;
+use errors::{KirinError, SpannedError, Warning, codes};
+use lints::Lint;
+use names::{Name, Resolution};
 use parser::{
-    expressions::{Assign, Binary, Call, Expression, Grouping, Literal, Unary, Variable},
-    statements::{Statement, VariableDeclaration},
+    expressions::{
+        Assign, Binary, BinaryOp, Call, Expression, Grouping, Literal, Unary, UnaryOp, Variable,
+    },
+    span::AstSpan,
+    statements::{If, Statement, VariableDeclaration, While},
+    value::ParsedValue,
     visitor::{ExpressionVisitor, StatementVisitor},
 };
+use types::KirinType;
 
-pub struct TypeChecker {}
+#[derive(Clone)]
+pub struct TypeChecker {
+    scopes: Vec<HashMap<String, KirinType>>,
+    /// where the variables of each scope are declared and whether they are
+    /// read
+    declarations: Vec<HashMap<String, Declaration>>,
+    allowed: HashSet<Lint>,
+    warnings: Vec<Warning>,
+    /// what the names checked so far refer to
+    names: Vec<Name>,
+    /// the errors of statements inside blocks, which do not stop the
+    /// checking of the statements after them
+    errors: Vec<KirinError>,
+    /// the `return`, or `if` returning in both branches, that ends the
+    /// current block
+    ended: Option<Ending>,
+}
+
+#[derive(Clone)]
+struct Declaration {
+    span: AstSpan,
+    used: bool,
+}
+
+#[derive(Clone)]
+struct Ending {
+    span: AstSpan,
+    /// whether a statement after it was reported, only the first one is
+    reported: bool,
+}
+
+impl Default for TypeChecker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl TypeChecker {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            scopes: vec![HashMap::new()],
+            declarations: vec![HashMap::new()],
+            allowed: HashSet::new(),
+            warnings: Vec::new(),
+            names: Vec::new(),
+            errors: Vec::new(),
+            ended: None,
+        }
+    }
+
+    pub fn allow(&mut self, lint: Lint) {
+        self.allowed.insert(lint);
+    }
+
+    /// Allow the lints named by the `#!allow(...)` lines of `source`
+    pub fn allow_pragmas(&mut self, source: &str) {
+        let (lints, warnings) = lints::pragmas(source);
+
+        self.allowed.extend(lints);
+        self.warnings.extend(warnings);
+    }
+
+    /// Take the warnings reported so far, with the variables declared so far
+    /// that have not been read, ordered by their position
+    pub fn warnings(&mut self) -> Vec<Warning> {
+        let mut unused = self
+            .declarations
+            .iter_mut()
+            .flat_map(|scope| scope.iter_mut())
+            .filter(|(name, declaration)| !declaration.used && !name.starts_with('_'))
+            .collect::<Vec<_>>();
+        unused.sort_by_key(|(_, declaration)| declaration.span.start);
+
+        let mut warnings = unused
+            .into_iter()
+            .map(|(name, declaration)| {
+                // reported once
+                declaration.used = true;
+                unused_variable(name, &declaration.span)
+            })
+            .collect::<Vec<Warning>>();
+        warnings.append(&mut self.warnings);
+
+        warnings.retain(|warning| {
+            Lint::from_name(warning.lint).is_none_or(|lint| !self.allowed.contains(&lint))
+        });
+        warnings.sort_by_key(|warning| (warning.error.line, warning.error.column));
+
+        warnings
+    }
+
+    /// Check the following statements as reachable. A `return` only ends
+    /// the statements checked with it when they run on their own, like the
+    /// entries of a REPL.
+    pub fn resume(&mut self) {
+        self.ended = None;
+    }
+
+    /// Take what the names checked so far refer to, in the order they were
+    /// checked. The names after an error in a statement are not checked.
+    pub fn names(&mut self) -> Vec<Name> {
+        std::mem::take(&mut self.names)
+    }
+
+    fn resolve(&mut self, span: &AstSpan, resolution: Resolution) {
+        self.names.push(Name {
+            range: span.range(),
+            resolution,
+        });
+    }
+
+    /// A variable when `name` is declared
+    fn resolve_variable(&mut self, name: &str, span: &AstSpan) {
+        let resolution = match self.lookup(name) {
+            Some(_) => Resolution::Variable,
+            None => Resolution::Undefined,
+        };
+
+        self.resolve(span, resolution);
+    }
+
+    fn lint(&mut self, lint: Lint, error: SpannedError) {
+        self.warnings.push(lint.warning(error));
+    }
+
+    /// Record the declaration of a variable, warning when it shadows an
+    /// earlier one
+    fn declare(&mut self, name: &str, span: &AstSpan) {
+        let declaration = Declaration {
+            span: span.clone(),
+            used: false,
+        };
+
+        let previous = self
+            .declarations
+            .last_mut()
+            .and_then(|scope| scope.insert(name.to_string(), declaration));
+
+        let previous = match previous {
+            Some(previous) => {
+                if !previous.used && !name.starts_with('_') {
+                    self.warnings.push(unused_variable(name, &previous.span));
+                }
+
+                previous.span
+            }
+            None => {
+                let outer = self.declarations.iter().rev().skip(1);
+                match outer.into_iter().find_map(|scope| scope.get(name)) {
+                    Some(outer) => outer.span.clone(),
+                    None => return,
+                }
+            }
+        };
+
+        self.lint(
+            Lint::Shadowing,
+            span.error(format!("`{}` shadows an earlier declaration", name))
+                .with_label(previous.range(), "declared here".to_string())
+                .with_help(format!("assign with `{} = ...` to change it", name)),
+        );
+    }
+
+    /// The declaration `name` refers to, in the innermost scope declaring it
+    fn declaration(&mut self, name: &str) -> Option<&mut Declaration> {
+        self.declarations
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(name))
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+        self.declarations.push(HashMap::new());
+    }
+
+    /// Leave a block, warning about its variables that were not read
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+
+        let Some(declarations) = self.declarations.pop() else {
+            return;
+        };
+
+        let mut unused = declarations
+            .into_iter()
+            .filter(|(name, declaration)| !declaration.used && !name.starts_with('_'))
+            .collect::<Vec<_>>();
+        unused.sort_by_key(|(_, declaration)| declaration.span.start);
+
+        self.warnings.extend(
+            unused
+                .into_iter()
+                .map(|(name, declaration)| unused_variable(&name, &declaration.span)),
+        );
+    }
+
+    /// Check the statements of a block in a scope of their own. The span of
+    /// what ends the block, when it always ends the program, is returned.
+    fn block(&mut self, statements: &[Statement]) -> (Vec<Statement>, Option<AstSpan>) {
+        self.begin_scope();
+        let outer = self.ended.take();
+
+        let mut typed_statements = Vec::with_capacity(statements.len());
+        for statement in statements {
+            match self.execute(statement) {
+                Ok(statement) => typed_statements.push(statement),
+                Err(error) => self.errors.push(error),
+            }
+        }
+
+        let ended = std::mem::replace(&mut self.ended, outer);
+        self.end_scope();
+
+        (typed_statements, ended.map(|ending| ending.span))
+    }
+
+    /// The checked condition of an `if` or `while`. A condition that does not
+    /// check is reported and replaced by an error node, so the statements
+    /// of the block are still checked.
+    fn condition(&mut self, condition: &Expression, keyword: &str) -> Expression {
+        let checked = self.evaluate(condition).and_then(|checked| {
+            let span = checked.span().clone();
+            Self::expect_bool(Self::expression_type(&checked, &span)?, &span)?;
+
+            Ok(checked)
+        });
+
+        let checked = match checked {
+            Ok(checked) => checked,
+            Err(error) => {
+                self.errors.push(error);
+                return Expression::Error(condition.span().clone());
+            }
+        };
+
+        // `while true` is how a loop ending with `return` is written
+        let endless_loop = keyword == "while"
+            && matches!(
+                ungrouped(&checked),
+                Expression::Literal(Literal {
+                    value: ParsedValue::Bool(true),
+                    ..
+                })
+            );
+
+        if is_constant(&checked) && !endless_loop {
+            self.lint(
+                Lint::ConstantConditions,
+                checked
+                    .span()
+                    .error(format!("constant condition in `{}`", keyword))
+                    .with_help("the condition is the same every time it is checked".to_string()),
+            );
+        }
+
+        checked
+    }
+
+    /// Warn about the first statement after the end of its block
+    fn check_reachable(&mut self, statement: &Statement) {
+        let Some(span) = statement.span() else {
+            return;
+        };
+        let Some(ending) = self.ended.as_mut().filter(|ending| !ending.reported) else {
+            return;
+        };
+        ending.reported = true;
+
+        let error = span
+            .error("unreachable statement".to_string())
+            .with_label(ending.span.range(), "the program ends here".to_string());
+        self.lint(Lint::UnreachableCode, error);
+    }
+
+    /// Record the end of the current block, unless it already ended
+    fn end_block(&mut self, span: &AstSpan) {
+        if self.ended.is_none() {
+            self.ended = Some(Ending {
+                span: span.clone(),
+                reported: false,
+            });
+        }
     }
 
     pub fn infer_types(
@@ -21,6 +313,7 @@ impl TypeChecker {
 
         for statement in statements {
             let result = self.execute(statement);
+            errors.append(&mut self.errors);
 
             match result {
                 Ok(statement) => typed_statements.push(statement),
@@ -28,7 +321,7 @@ impl TypeChecker {
             }
         }
 
-        if errors.len() > 0 {
+        if !errors.is_empty() {
             return Err(errors);
         }
 
@@ -36,12 +329,168 @@ impl TypeChecker {
     }
 
     fn execute(&mut self, statement: &Statement) -> Result<Statement, KirinError> {
+        self.check_reachable(statement);
         statement.accept(self)
     }
 
     fn evaluate(&mut self, expression: &Expression) -> Result<Expression, KirinError> {
         expression.accept(self)
     }
+
+    fn define(&mut self, name: &str, kind: KirinType) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), kind);
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<KirinType> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name).copied())
+    }
+
+    /// Values can always be stored in an `Any` slot, and an `Any` value can be
+    /// stored in a concrete slot through a checked downcast at runtime
+    fn is_assignable(target: KirinType, value: KirinType) -> bool {
+        if target == KirinType::Void || value == KirinType::Void {
+            return false;
+        }
+
+        target == value || target == KirinType::Any || value == KirinType::Any
+    }
+
+    fn arithmetic_type(left: KirinType, right: KirinType) -> Option<KirinType> {
+        match (left, right) {
+            (KirinType::Int, KirinType::Int) => Some(KirinType::Int),
+            (left, right) if left.is_numeric() && right.is_numeric() => Some(KirinType::Float),
+            (KirinType::Any, other) | (other, KirinType::Any)
+                if other == KirinType::Any || other.is_numeric() =>
+            {
+                Some(KirinType::Any)
+            }
+
+            _ => None,
+        }
+    }
+
+    fn equality_type(left: KirinType, right: KirinType) -> Option<KirinType> {
+        let comparable = match (left, right) {
+            (KirinType::Int, KirinType::Int) | (KirinType::Bool, KirinType::Bool) => true,
+            (left, right) => left.is_numeric() && right.is_numeric(),
+        };
+
+        comparable.then_some(KirinType::Bool)
+    }
+
+    fn comparison_type(left: KirinType, right: KirinType) -> Option<KirinType> {
+        (left.is_numeric() && right.is_numeric()).then_some(KirinType::Bool)
+    }
+
+    /// Conditions and logical operands must be bools, ints are not truthy
+    fn expect_bool(kind: KirinType, span: &AstSpan) -> Result<(), KirinError> {
+        match kind {
+            KirinType::Bool | KirinType::Any => Ok(()),
+            kind => Err(type_error(
+                span,
+                codes::MISMATCHED_TYPES,
+                format!("expected `bool` but found `{}`", kind),
+            )),
+        }
+    }
+
+    fn expression_type(expression: &Expression, span: &AstSpan) -> Result<KirinType, KirinError> {
+        expression.inferred_type().ok_or_else(|| {
+            type_error(
+                span,
+                codes::TYPE,
+                "could not infer type of expression".to_string(),
+            )
+        })
+    }
+
+    /// The checked initializer of a declaration and the type of its variable
+    fn check_declaration(
+        &mut self,
+        var_declaration: &VariableDeclaration,
+    ) -> Result<(Option<Expression>, KirinType), KirinError> {
+        let span = &var_declaration.span;
+
+        let initializer = match &var_declaration.initializer {
+            Some(initializer) => Some(self.evaluate(initializer)?),
+            None => None,
+        };
+
+        let value_type = match &initializer {
+            Some(initializer) => Some(Self::expression_type(initializer, span)?),
+            None => None,
+        };
+
+        let declared_type = match (var_declaration.type_annotation, value_type) {
+            (Some(annotation), Some(value_type)) => {
+                if !Self::is_assignable(annotation, value_type) {
+                    return Err(type_error(
+                        span,
+                        codes::MISMATCHED_TYPES,
+                        format!(
+                            "cannot initialize variable `{}` of type `{}` with value of type `{}`",
+                            var_declaration.name, annotation, value_type
+                        ),
+                    ));
+                }
+
+                annotation
+            }
+            (Some(annotation), None) => annotation,
+            (None, Some(value_type)) if value_type != KirinType::Void => value_type,
+            _ => {
+                return Err(KirinError::Type(
+                    span.error(format!(
+                        "cannot infer type of variable `{}` without a type annotation",
+                        var_declaration.name
+                    ))
+                    .with_code(codes::MISSING_TYPE)
+                    .with_help(format!(
+                        "add a type annotation: `let {}: any`",
+                        var_declaration.name
+                    )),
+                ));
+            }
+        };
+
+        Ok((initializer, declared_type))
+    }
+}
+
+fn type_error(span: &AstSpan, code: &'static str, message: String) -> KirinError {
+    KirinError::Type(span.error(message).with_code(code))
+}
+
+fn unused_variable(name: &str, span: &AstSpan) -> Warning {
+    Lint::UnusedVariables.warning(
+        span.error(format!("unused variable `{}`", name))
+            .with_help(format!("prefix it with an underscore: `_{}`", name)),
+    )
+}
+
+/// Whether `expression` is computed from literals only
+fn is_constant(expression: &Expression) -> bool {
+    match expression {
+        Expression::Literal(_) => true,
+        Expression::Grouping(grouping) => is_constant(&grouping.expression),
+        Expression::Unary(unary) => is_constant(&unary.right),
+        Expression::Binary(binary) => is_constant(&binary.left) && is_constant(&binary.right),
+
+        _ => false,
+    }
+}
+
+/// The expression inside any groupings
+fn ungrouped(expression: &Expression) -> &Expression {
+    match expression {
+        Expression::Grouping(grouping) => ungrouped(&grouping.expression),
+        expression => expression,
+    }
 }
 
 impl StatementVisitor for TypeChecker {
@@ -52,102 +501,676 @@ impl StatementVisitor for TypeChecker {
     }
 
     fn visit_var_declaration(&mut self, var_declaration: &VariableDeclaration) -> Self::Output {
-        Err(KirinError::Type(SpannedError {
-            line: var_declaration.span.line,
-            column: var_declaration.span.column,
-            message: format!(
-                "type checking for binary ops not yet implemented `{:?}`",
-                var_declaration.span.filename.clone()
-            ),
-        }))
+        let span = &var_declaration.span;
+        if let Some(annotation_span) = &var_declaration.annotation_span {
+            self.resolve(annotation_span, Resolution::Type);
+        }
+
+        // a declaration that does not check still declares its variable, as
+        // annotated or as `any`, so its uses are not reported as undefined.
+        // It is not reported as unused either, its error is enough.
+        let checked = self.check_declaration(var_declaration);
+        let declared_type = match &checked {
+            Ok((_, declared_type)) => *declared_type,
+            Err(_) => var_declaration.type_annotation.unwrap_or(KirinType::Any),
+        };
+
+        self.define(&var_declaration.name, declared_type);
+        self.declare(&var_declaration.name, span);
+        self.resolve(span, Resolution::Variable);
+
+        if checked.is_err()
+            && let Some(declaration) = self.declaration(&var_declaration.name)
+        {
+            declaration.used = true;
+        }
+
+        let (initializer, declared_type) = checked?;
+
+        let mut declaration =
+            VariableDeclaration::new(var_declaration.name.clone(), initializer, span.clone());
+        declaration.type_annotation = var_declaration.type_annotation;
+        declaration.annotation_span = var_declaration.annotation_span.clone();
+        declaration.inferred_type = Some(declared_type);
+
+        Ok(Statement::VarDeclaration(declaration))
     }
 
     fn visit_expression_statement(&mut self, expression_statement: &Expression) -> Self::Output {
         let expression = self.evaluate(expression_statement)?;
 
+        if let Expression::Call(call) = &expression
+            && let Expression::Variable(callee) = &call.callee
+            && call
+                .inferred_type
+                .is_some_and(|kind| kind != KirinType::Void)
+        {
+            self.lint(
+                Lint::UnusedResults,
+                call.span
+                    .error(format!("unused result of `{}`", callee.name))
+                    .with_help("assign the result to a variable".to_string()),
+            );
+        }
+
         Ok(Statement::ExpressionStatement(expression))
     }
+
+    fn visit_if(&mut self, if_statement: &If) -> Self::Output {
+        let condition = self.condition(&if_statement.condition, "if");
+        let (then_branch, then_ended) = self.block(&if_statement.then_branch);
+
+        let (else_branch, else_ended) = match &if_statement.else_branch {
+            Some(else_branch) => {
+                let (else_branch, ended) = self.block(else_branch);
+                (Some(else_branch), ended)
+            }
+            None => (None, None),
+        };
+
+        if then_ended.is_some() && else_ended.is_some() {
+            self.end_block(&if_statement.span);
+        }
+
+        Ok(Statement::If(Box::new(If::new(
+            condition,
+            then_branch,
+            else_branch,
+            if_statement.span.clone(),
+        ))))
+    }
+
+    fn visit_while(&mut self, while_statement: &While) -> Self::Output {
+        let condition = self.condition(&while_statement.condition, "while");
+        let (body, _) = self.block(&while_statement.body);
+
+        Ok(Statement::While(Box::new(While::new(
+            condition,
+            body,
+            while_statement.span.clone(),
+        ))))
+    }
+
+    fn visit_return(&mut self, span: &AstSpan) -> Self::Output {
+        self.end_block(span);
+
+        Ok(Statement::Return(span.clone()))
+    }
 }
 
 impl ExpressionVisitor for TypeChecker {
     type Output = Result<Expression, KirinError>;
 
     fn visit_binary(&mut self, binary: &Binary) -> Self::Output {
-        Err(KirinError::Type(SpannedError {
-            line: binary.span.line,
-            column: binary.span.column,
-            message: format!(
-                "type checking for binary ops not yet implemented `{:?}`",
-                binary.span.filename.clone()
-            ),
-        }))
+        let left = self.evaluate(&binary.left)?;
+        let right = self.evaluate(&binary.right)?;
+
+        let left_type = Self::expression_type(&left, &binary.span)?;
+        let right_type = Self::expression_type(&right, &binary.span)?;
+
+        let inferred_type = match binary.operator {
+            BinaryOp::Add
+            | BinaryOp::Subtract
+            | BinaryOp::Multiply
+            | BinaryOp::Divide
+            | BinaryOp::Modulus
+            | BinaryOp::Power => Self::arithmetic_type(left_type, right_type),
+
+            BinaryOp::Equal | BinaryOp::NotEqual => Self::equality_type(left_type, right_type),
+
+            BinaryOp::Greater | BinaryOp::GreaterEqual | BinaryOp::Less | BinaryOp::LessEqual => {
+                Self::comparison_type(left_type, right_type)
+            }
+
+            BinaryOp::And | BinaryOp::Or => {
+                Self::expect_bool(left_type, &binary.span)?;
+                Self::expect_bool(right_type, &binary.span)?;
+
+                Some(KirinType::Bool)
+            }
+        };
+
+        if matches!(binary.operator, BinaryOp::Equal | BinaryOp::NotEqual)
+            && (left_type == KirinType::Float || right_type == KirinType::Float)
+        {
+            self.lint(
+                Lint::FloatEquality,
+                binary
+                    .span
+                    .error(format!(
+                        "floats compared with `{}`",
+                        binary.operator.symbol()
+                    ))
+                    .with_note("rounding errors can make equal looking floats differ".to_string())
+                    .with_help("compare the difference with a tolerance".to_string()),
+            );
+        }
+
+        let Some(inferred_type) = inferred_type else {
+            return Err(KirinError::Type(
+                binary
+                    .span
+                    .error(format!(
+                        "operator `{}` cannot be applied to `{}` and `{}`",
+                        binary.operator.symbol(),
+                        left_type,
+                        right_type
+                    ))
+                    .with_code(codes::INVALID_OPERANDS)
+                    .with_label(left.span().range(), format!("this is `{}`", left_type))
+                    .with_label(right.span().range(), format!("this is `{}`", right_type)),
+            ));
+        };
+
+        let mut typed = Binary::new(left, right, binary.operator, binary.span.clone());
+        typed.inferred_type = Some(inferred_type);
+
+        Ok(Expression::Binary(Box::new(typed)))
     }
 
     fn visit_unary(&mut self, unary: &Unary) -> Self::Output {
-        Err(KirinError::Type(SpannedError {
-            line: unary.span.line,
-            column: unary.span.column,
-            message: format!(
-                "type checking for unary ops not yet implemented `{:?}`",
-                unary.span.filename.clone()
-            ),
-        }))
+        let right = self.evaluate(&unary.right)?;
+        let right_type = Self::expression_type(&right, &unary.span)?;
+
+        let inferred_type = match unary.operator {
+            UnaryOp::Negate if right_type.is_numeric() || right_type == KirinType::Any => {
+                right_type
+            }
+            UnaryOp::Negate => {
+                return Err(type_error(
+                    &unary.span,
+                    codes::INVALID_OPERANDS,
+                    format!("cannot negate value of type `{}`", right_type),
+                ));
+            }
+            UnaryOp::Not => {
+                Self::expect_bool(right_type, &unary.span)?;
+
+                KirinType::Bool
+            }
+        };
+
+        let mut typed = Unary::new(right, unary.operator, unary.span.clone());
+        typed.inferred_type = Some(inferred_type);
+
+        Ok(Expression::Unary(Box::new(typed)))
     }
 
     fn visit_grouping(&mut self, grouping: &Grouping) -> Self::Output {
         let expression = self.evaluate(&grouping.expression)?;
+        let inferred_type = expression.inferred_type();
 
-        Ok(Expression::Grouping(Box::new(Grouping::new(
-            expression,
-            grouping.span.clone(),
-        ))))
+        let mut typed = Grouping::new(expression, grouping.span.clone());
+        typed.inferred_type = inferred_type;
+
+        Ok(Expression::Grouping(Box::new(typed)))
     }
 
     fn visit_literal(&mut self, literal: &Literal) -> Self::Output {
-        if let Some(_) = &literal.inferred_type {
+        if literal.inferred_type.is_some() {
             return Ok(Expression::Literal(literal.clone()));
         }
 
-        Err(KirinError::Type(SpannedError {
-            message: format!(
+        Err(type_error(
+            &literal.span,
+            codes::TYPE,
+            format!(
                 "type analyzer for literals not supported. file {}",
                 literal.span.filename.clone().unwrap_or("".to_string())
             ),
-            line: literal.span.line,
-            column: literal.span.column,
-        }))
+        ))
     }
 
     fn visit_call(&mut self, callable: &Call) -> Self::Output {
-        Err(KirinError::Type(SpannedError {
-            line: callable.span.line,
-            column: callable.span.column,
-            message: format!(
-                "type checking for calls not yet implemented `{:?}`",
-                callable.span.filename.clone()
-            ),
-        }))
+        let span = &callable.span;
+
+        let Expression::Variable(callee) = &callable.callee else {
+            return Err(type_error(
+                span,
+                codes::UNDEFINED_FUNCTION,
+                "only library functions can be called".to_string(),
+            ));
+        };
+
+        let Some(builtin) = lookup_builtin(&callee.name) else {
+            self.resolve_variable(&callee.name, &callee.span);
+
+            return Err(type_error(
+                span,
+                codes::UNDEFINED_FUNCTION,
+                format!("undefined function `{}`", callee.name),
+            ));
+        };
+
+        self.resolve(&callee.span, Resolution::Function);
+
+        if callable.arguments.len() != builtin.parameters.len() {
+            return Err(type_error(
+                span,
+                codes::ARGUMENT_COUNT,
+                format!(
+                    "`{}` expects {} arguments but {} were given",
+                    builtin.name,
+                    builtin.parameters.len(),
+                    callable.arguments.len()
+                ),
+            ));
+        }
+
+        let mut arguments = Vec::with_capacity(callable.arguments.len());
+        for (index, (argument, &parameter)) in callable
+            .arguments
+            .iter()
+            .zip(builtin.parameters)
+            .enumerate()
+        {
+            let argument = self.evaluate(argument)?;
+            let argument_type = Self::expression_type(&argument, span)?;
+
+            if !Self::is_assignable(parameter, argument_type) {
+                return Err(KirinError::Type(
+                    span.error(format!(
+                        "argument {} of `{}` expects `{}` but found `{}`",
+                        index + 1,
+                        builtin.name,
+                        parameter,
+                        argument_type
+                    ))
+                    .with_code(codes::MISMATCHED_TYPES)
+                    .with_label(
+                        argument.span().range(),
+                        format!("this is `{}`", argument_type),
+                    ),
+                ));
+            }
+
+            arguments.push(argument);
+        }
+
+        let mut typed = Call::new(callable.callee.clone(), span.clone(), arguments);
+        typed.inferred_type = Some(builtin.return_type);
+
+        Ok(Expression::Call(Box::new(typed)))
     }
 
     fn visit_variable(&mut self, variable: &Variable) -> Self::Output {
-        Err(KirinError::Type(SpannedError {
-            line: variable.span.line,
-            column: variable.span.column,
-            message: format!(
-                "type checking for variables not yet implemented `{:?}`",
-                variable.span.filename.clone()
-            ),
-        }))
+        self.resolve_variable(&variable.name, &variable.span);
+
+        let Some(inferred_type) = self.lookup(&variable.name) else {
+            return Err(type_error(
+                &variable.span,
+                codes::UNDEFINED_VARIABLE,
+                format!("undefined variable `{}`", variable.name),
+            ));
+        };
+
+        if let Some(declaration) = self.declaration(&variable.name) {
+            declaration.used = true;
+        }
+
+        let mut typed = variable.clone();
+        typed.inferred_type = Some(inferred_type);
+
+        Ok(Expression::Variable(Box::new(typed)))
     }
 
     fn visit_assign(&mut self, assign: &Assign) -> Self::Output {
-        Err(KirinError::Parse(SpannedError {
-            line: assign.span.line,
-            column: assign.span.column,
-            message: format!(
-                "type checking for assign ops not yet implemented `{:?}`",
-                assign.span.filename.clone()
-            ),
-        }))
+        self.resolve_variable(&assign.name, &assign.target);
+
+        let Some(target_type) = self.lookup(&assign.name) else {
+            return Err(type_error(
+                &assign.span,
+                codes::UNDEFINED_VARIABLE,
+                format!("assignment to undefined variable `{}`", assign.name),
+            ));
+        };
+
+        if let Expression::Variable(variable) = ungrouped(&assign.value)
+            && variable.name == assign.name
+        {
+            self.lint(
+                Lint::SelfAssignment,
+                assign
+                    .span
+                    .error(format!("`{}` is assigned to itself", assign.name)),
+            );
+        }
+
+        let value = self.evaluate(&assign.value)?;
+        let value_type = Self::expression_type(&value, &assign.span)?;
+
+        if !Self::is_assignable(target_type, value_type) {
+            return Err(type_error(
+                &assign.span,
+                codes::MISMATCHED_TYPES,
+                format!(
+                    "cannot assign value of type `{}` to variable `{}` of type `{}`",
+                    value_type, assign.name, target_type
+                ),
+            ));
+        }
+
+        let mut typed = Assign::new(
+            assign.name.clone(),
+            assign.target.clone(),
+            value,
+            assign.span.clone(),
+        );
+        typed.inferred_type = Some(target_type);
+
+        Ok(Expression::Assign(Box::new(typed)))
+    }
+
+    fn visit_error(&mut self, span: &AstSpan) -> Self::Output {
+        Ok(Expression::Error(span.clone()))
+    }
+}
+
+#[cfg(test)]
+mod analyzer_tests {
+    use crate::TypeChecker;
+    use crate::lints::Lint;
+    use crate::names::Resolution;
+    use errors::KirinError;
+    use parser::statements::Statement;
+    use types::KirinType;
+
+    fn analyze(source: &str) -> Result<Vec<Statement>, Vec<KirinError>> {
+        let tokens = scanner::scan_tokens(source).unwrap();
+        let ast = parser::parse_ast(tokens, None).unwrap();
+
+        TypeChecker::new().infer_types(&ast)
+    }
+
+    fn declared_types(statements: &[Statement]) -> Vec<KirinType> {
+        statements
+            .iter()
+            .filter_map(|statement| match statement {
+                Statement::VarDeclaration(declaration) => declaration.inferred_type,
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_any_variable_accepts_any_value() {
+        let statements = analyze("let a: any = 10\na = 2.5\na = \"text\"\n").unwrap();
+
+        assert_eq!(declared_types(&statements), vec![KirinType::Any]);
+    }
+
+    #[test]
+    fn test_concrete_variable_rejects_other_types() {
+        let errors = analyze("let a: int = 2.5\nb := 4\nb = \"text\"\n").unwrap_err();
+
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[test]
+    fn test_any_value_downcasts_to_concrete_variable() {
+        let statements = analyze("let a: any = 10\nlet b: int = a\n").unwrap();
+
+        assert_eq!(
+            declared_types(&statements),
+            vec![KirinType::Any, KirinType::Int]
+        );
+    }
+
+    #[test]
+    fn test_arithmetic_type_inference() {
+        let source = "a := 1 + 2\nb := 1 + 2.5\nlet c: any = 3\nd := c * a\n";
+        let statements = analyze(source).unwrap();
+
+        assert_eq!(
+            declared_types(&statements),
+            vec![
+                KirinType::Int,
+                KirinType::Float,
+                KirinType::Any,
+                KirinType::Any
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bool_type_inference() {
+        let source = "a := true\nb := 1 < 2.5\nc := !(a and b) or 3 == 4\nlet d: any = false\ne := d and a\n";
+        let statements = analyze(source).unwrap();
+
+        assert_eq!(
+            declared_types(&statements),
+            vec![
+                KirinType::Bool,
+                KirinType::Bool,
+                KirinType::Bool,
+                KirinType::Any,
+                KirinType::Bool
+            ]
+        );
+    }
+
+    #[test]
+    fn test_int_is_not_bool() {
+        let source = "let a: bool = 1\nb := !1\nc := true and 0\nd := true < false\n";
+        let errors = analyze(source).unwrap_err();
+
+        assert_eq!(errors.len(), 4);
+    }
+
+    #[test]
+    fn test_library_calls() {
+        let statements = analyze("a := wrapping_add(1, 2)\n").unwrap();
+        assert_eq!(declared_types(&statements), vec![KirinType::Int]);
+
+        let source = "a := wrapping_mul(1)\nb := wrapping_sub(1, 2.5)\nc := missing(1)\n";
+        let errors = analyze(source).unwrap_err();
+        assert_eq!(errors.len(), 3);
+
+        assert!(analyze("print(1)\nprint(\"text\")\n").is_ok());
+        assert!(analyze("a := print(1)\n").is_err());
+    }
+
+    #[test]
+    fn test_undefined_variable() {
+        let errors = analyze("a := b + 1\n").unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn test_operand_labels() {
+        let source = "a := \"text\"\nb := a + 1\n";
+        let errors = analyze(source).unwrap_err();
+
+        assert_eq!(errors[0].code(), errors::codes::INVALID_OPERANDS);
+        let labels = errors[0].diagnostic().labels;
+        assert_eq!(labels.len(), 2);
+        assert_eq!(&source[labels[0].range.clone()], "a");
+        assert_eq!(&source[labels[1].range.clone()], "1");
+    }
+
+    #[test]
+    fn test_error_nodes_do_not_cascade() {
+        let tokens = scanner::scan_tokens("a := 1 +)\nb := a * 2\nprint((b +))\n").unwrap();
+        let (ast, errors) = parser::parse_with_recovery(tokens, None);
+        assert_eq!(errors.len(), 2);
+
+        let statements = TypeChecker::new().infer_types(&ast).unwrap();
+        assert_eq!(
+            declared_types(&statements),
+            vec![KirinType::Any, KirinType::Any]
+        );
+    }
+
+    #[test]
+    fn test_names() {
+        let source = "let a: int = 1\nprint(a)\nb = a\nf(a)\n";
+        let tokens = scanner::scan_tokens(source).unwrap();
+        let ast = parser::parse_ast(tokens, None).unwrap();
+
+        let mut checker = TypeChecker::new();
+        assert!(checker.infer_types(&ast).is_err());
+
+        let names = checker
+            .names()
+            .into_iter()
+            .map(|name| (&source[name.range], name.resolution))
+            .collect::<Vec<(&str, Resolution)>>();
+        assert_eq!(
+            names,
+            vec![
+                ("int", Resolution::Type),
+                ("a", Resolution::Variable),
+                ("print", Resolution::Function),
+                ("a", Resolution::Variable),
+                ("b", Resolution::Undefined),
+                ("f", Resolution::Undefined),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_failed_declarations_do_not_cascade() {
+        let errors =
+            analyze("let f: float = 1\nx := print(f)\nprint(f + x)\ng := f\n").unwrap_err();
+
+        let codes = errors
+            .iter()
+            .map(|error| error.diagnostic().code)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            codes,
+            vec![errors::codes::MISMATCHED_TYPES, errors::codes::MISSING_TYPE]
+        );
+    }
+
+    #[test]
+    fn test_blocks() {
+        let source = "let a: any = true\nif a\n  b := 1\n  print(b)\nelse\n  return\nend\nwhile a\n  a = false\nend\n";
+        assert!(analyze(source).is_ok());
+
+        // blocks are scopes, and their errors do not stop the statements
+        // after them from being checked
+        let source = "if 1\n  b := 1\n  print(c)\n  print(b + true)\nend\nprint(b)\n";
+        let codes = analyze(source)
+            .unwrap_err()
+            .iter()
+            .map(|error| error.diagnostic().code)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            codes,
+            vec![
+                errors::codes::MISMATCHED_TYPES,
+                errors::codes::UNDEFINED_VARIABLE,
+                errors::codes::INVALID_OPERANDS,
+                errors::codes::UNDEFINED_VARIABLE,
+            ]
+        );
+    }
+
+    fn lints(source: &str, allowed: &[Lint]) -> Vec<(&'static str, usize)> {
+        let tokens = scanner::scan_tokens(source).unwrap();
+        let ast = parser::parse_ast(tokens, None).unwrap();
+
+        let mut checker = TypeChecker::new();
+        allowed.iter().for_each(|&lint| checker.allow(lint));
+        checker.allow_pragmas(source);
+        checker.infer_types(&ast).unwrap();
+
+        checker
+            .warnings()
+            .into_iter()
+            .map(|warning| (warning.lint, warning.error.line))
+            .collect()
+    }
+
+    #[test]
+    fn test_lints() {
+        let source = "\
+a := 1
+b := 2.5
+_c := 3
+a = (a)
+print(a == 0.1)
+wrapping_add(a, 1)
+b := 4
+print(b)
+";
+
+        assert_eq!(
+            lints(source, &[]),
+            vec![
+                ("unused_variables", 2),
+                ("self_assignment", 4),
+                ("float_equality", 5),
+                ("unused_results", 6),
+                ("shadowing", 7),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_control_flow_lints() {
+        let source = "\
+a := 1
+if a > 0
+  b := 2
+  return
+  print(a)
+  print(a)
+end
+while true
+  a := a + 1
+end
+if 1 < 2
+  return
+else
+  return
+end
+print(a)
+";
+
+        assert_eq!(
+            lints(source, &[]),
+            vec![
+                ("unused_variables", 3),
+                ("unreachable_code", 5),
+                ("shadowing", 9),
+                ("unused_variables", 9),
+                ("constant_conditions", 11),
+                ("unreachable_code", 16),
+            ]
+        );
+
+        let source = "while (true)\n  return\nend\nwhile false\nend\n";
+        assert_eq!(lints(source, &[]), vec![("constant_conditions", 4)]);
+    }
+
+    #[test]
+    fn test_allowed_lints() {
+        let source = "#!allow(shadowing, float_equality)\na := 1.5\na := a == 1.5\nprint(a)\n";
+        assert_eq!(lints(source, &[]), vec![]);
+
+        let source = "a := 1\n";
+        assert_eq!(lints(source, &[Lint::UnusedVariables]), vec![]);
+        assert_eq!(lints(source, &[]), vec![("unused_variables", 1)]);
+    }
+
+    #[test]
+    fn test_unknown_lint_pragma() {
+        let source = "print(1)\n  #!allow(shadowing, unused_thing)\n";
+        let tokens = scanner::scan_tokens(source).unwrap();
+        let ast = parser::parse_ast(tokens, None).unwrap();
+
+        let mut checker = TypeChecker::new();
+        checker.allow_pragmas(source);
+        checker.infer_types(&ast).unwrap();
+
+        let warnings = checker.warnings();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].error.message, "unknown lint `unused_thing`");
+        assert_eq!((warnings[0].error.line, warnings[0].error.column), (2, 22));
+        assert_eq!(&source[warnings[0].error.range.clone()], "unused_thing");
+        assert_eq!(warnings[0].error.code, Some(errors::codes::UNKNOWN_LINT));
     }
 }
@@ -0,0 +1,128 @@
+use errors::{SpannedError, Warning, codes};
+
+/// Checks of the type checker that report warnings instead of errors. Each
+/// lint can be allowed with a `#!allow(name)` line in the source or a
+/// driver flag.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Lint {
+    /// `#!allow` pragmas naming lints that do not exist
+    UnknownLints,
+    /// variables that are never read, unless their name starts with `_`
+    UnusedVariables,
+    /// calls whose result is discarded
+    UnusedResults,
+    /// declarations of a name that is already declared
+    Shadowing,
+    /// `==` and `!=` on floats
+    FloatEquality,
+    /// assigning a variable to itself
+    SelfAssignment,
+    /// statements after a `return` in the same block
+    UnreachableCode,
+    /// `if` and `while` conditions computed from literals only, other than
+    /// the `true` of an endless `while`
+    ConstantConditions,
+}
+
+impl Lint {
+    pub const ALL: [Lint; 8] = [
+        Self::UnknownLints,
+        Self::UnusedVariables,
+        Self::UnusedResults,
+        Self::Shadowing,
+        Self::FloatEquality,
+        Self::SelfAssignment,
+        Self::UnreachableCode,
+        Self::ConstantConditions,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::UnknownLints => "unknown_lints",
+            Self::UnusedVariables => "unused_variables",
+            Self::UnusedResults => "unused_results",
+            Self::Shadowing => "shadowing",
+            Self::FloatEquality => "float_equality",
+            Self::SelfAssignment => "self_assignment",
+            Self::UnreachableCode => "unreachable_code",
+            Self::ConstantConditions => "constant_conditions",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|lint| lint.name() == name)
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::UnknownLints => codes::UNKNOWN_LINT,
+            Self::UnusedVariables => codes::UNUSED_VARIABLE,
+            Self::UnusedResults => codes::UNUSED_RESULT,
+            Self::Shadowing => codes::SHADOWED_VARIABLE,
+            Self::FloatEquality => codes::FLOAT_EQUALITY,
+            Self::SelfAssignment => codes::SELF_ASSIGNMENT,
+            Self::UnreachableCode => codes::UNREACHABLE_CODE,
+            Self::ConstantConditions => codes::CONSTANT_CONDITION,
+        }
+    }
+
+    /// A warning of this lint, with a note on how to allow it
+    pub fn warning(&self, error: SpannedError) -> Warning {
+        Warning::new(
+            self.name(),
+            error
+                .with_code(self.code())
+                .with_note(format!("allow with `#!allow({})`", self.name())),
+        )
+    }
+}
+
+/// The lints named by the `#!allow(name, ...)` lines of `source`, and
+/// warnings for the names that are not lints
+pub fn pragmas(source: &str) -> (Vec<Lint>, Vec<Warning>) {
+    let mut lints = Vec::new();
+    let mut warnings = Vec::new();
+
+    let mut line_start = 0;
+    for (index, line) in source.split_inclusive('\n').enumerate() {
+        let offset = line_start;
+        line_start += line.len();
+
+        let indentation = line.len() - line.trim_start().len();
+        let Some(names) = line
+            .trim()
+            .strip_prefix("#!allow(")
+            .and_then(|rest| rest.strip_suffix(')'))
+        else {
+            continue;
+        };
+
+        // byte offset of the names in the line
+        let mut column = indentation + "#!allow(".len();
+        for name in names.split(',') {
+            let start = column + name.len() - name.trim_start().len();
+            column += name.len() + 1;
+
+            let name = name.trim();
+            match Lint::from_name(name) {
+                Some(lint) => lints.push(lint),
+                None => warnings.push(
+                    Lint::UnknownLints.warning(
+                        SpannedError::new(format!("unknown lint `{}`", name), index + 1, start + 1)
+                            .with_range(offset + start..offset + start + name.len())
+                            .with_help(format!(
+                                "the lints are {}",
+                                Lint::ALL
+                                    .iter()
+                                    .map(|lint| format!("`{}`", lint.name()))
+                                    .collect::<Vec<String>>()
+                                    .join(", ")
+                            )),
+                    ),
+                ),
+            }
+        }
+    }
+
+    (lints, warnings)
+}
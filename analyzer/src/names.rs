@@ -0,0 +1,22 @@
+use std::ops::Range;
+
+/// What a name written in the source refers to
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Resolution {
+    /// a declared variable, or its declaration
+    Variable,
+    /// a library function
+    Function,
+    /// a type annotation
+    Type,
+    /// a name that is not declared
+    Undefined,
+}
+
+/// A name and what the type checker resolved it to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Name {
+    /// bytes of the source
+    pub range: Range<usize>,
+    pub resolution: Resolution,
+}
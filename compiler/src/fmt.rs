@@ -0,0 +1,81 @@
+use errors::use_color;
+use formatter::{Config, Declarations};
+
+const USAGE: &str = "\
+Usage: cargo run --bin compiler -- fmt [--check] [--declarations=short|let] [--max-width=<columns>] <file.kn>...
+Files are formatted in place, with --check they are only compared and the
+command fails when one is not formatted";
+
+/// Format the files named by the arguments after `fmt`, returning the exit
+/// code: 1 when a file could not be formatted or, with `--check`, is not
+/// formatted
+pub fn run(args: &[String]) -> i32 {
+    let mut config = Config::default();
+    let mut check = false;
+    let mut paths = Vec::new();
+
+    for arg in args {
+        if arg == "--check" {
+            check = true;
+        } else if let Some(name) = arg.strip_prefix("--declarations=") {
+            match Declarations::from_name(name) {
+                Some(declarations) => config.declarations = declarations,
+                None => return usage_error(&format!("unknown declaration style `{}`", name)),
+            }
+        } else if let Some(width) = arg.strip_prefix("--max-width=") {
+            match width.parse() {
+                Ok(width) => config.max_width = width,
+                Err(_) => return usage_error(&format!("invalid width `{}`", width)),
+            }
+        } else if arg.starts_with("--") {
+            return usage_error(&format!("unknown flag `{}`", arg));
+        } else {
+            paths.push(arg.as_str());
+        }
+    }
+
+    if paths.is_empty() {
+        return usage_error("no files given");
+    }
+
+    let color = use_color(&std::io::stderr());
+    let mut status = 0;
+    for path in paths {
+        let source = match std::fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(error) => {
+                eprintln!("failed to read {}: {}", path, error);
+                status = 1;
+                continue;
+            }
+        };
+
+        let formatted = match formatter::format(&source, &config) {
+            Ok(formatted) => formatted,
+            Err(error) => {
+                eprint!("{}", error.in_file(path).render(Some(&source), color));
+                status = 1;
+                continue;
+            }
+        };
+
+        if formatted == source {
+            continue;
+        }
+
+        if check {
+            println!("{} is not formatted", path);
+            status = 1;
+        } else if let Err(error) = std::fs::write(path, formatted) {
+            eprintln!("failed to write {}: {}", path, error);
+            status = 1;
+        }
+    }
+
+    status
+}
+
+fn usage_error(message: &str) -> i32 {
+    eprintln!("{}\n{}", message, USAGE);
+    2
+}
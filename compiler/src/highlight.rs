@@ -0,0 +1,72 @@
+use errors::use_color;
+
+const USAGE: &str = "\
+Usage: cargo run --bin compiler -- highlight [--format=ansi|html] <file.kn>
+       cargo run --bin compiler -- highlight --format=textmate
+Prints the file with its highlighting, or the TextMate grammar of Kirin";
+
+/// How `highlight` writes its output
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Format {
+    Ansi,
+    Html,
+    TextMate,
+}
+
+/// Highlight the file named by the arguments after `highlight`, returning
+/// the exit code
+pub fn run(args: &[String]) -> i32 {
+    let mut format = Format::Ansi;
+    let mut path = None;
+
+    for arg in args {
+        match arg.strip_prefix("--format=") {
+            Some("ansi") => format = Format::Ansi,
+            Some("html") => format = Format::Html,
+            Some("textmate") => format = Format::TextMate,
+            Some(name) => return usage_error(&format!("unknown format `{}`", name)),
+            None if arg.starts_with("--") => {
+                return usage_error(&format!("unknown flag `{}`", arg));
+            }
+            None => path = Some(arg.as_str()),
+        }
+    }
+
+    if format == Format::TextMate {
+        print!("{}", highlighter::textmate::grammar());
+        return 0;
+    }
+
+    let Some(path) = path else {
+        return usage_error("no file given");
+    };
+
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(error) => {
+            eprintln!("failed to read {}: {}", path, error);
+            return 1;
+        }
+    };
+
+    let highlights = match highlighter::highlight(&source) {
+        Ok(highlights) => highlights,
+        Err(error) => {
+            let color = use_color(&std::io::stderr());
+            eprint!("{}", error.in_file(path).render(Some(&source), color));
+            return 1;
+        }
+    };
+
+    match format {
+        Format::Html => print!("{}", highlighter::to_html(&source, &highlights)),
+        _ => print!("{}", highlighter::to_ansi(&source, &highlights)),
+    }
+
+    0
+}
+
+fn usage_error(message: &str) -> i32 {
+    eprintln!("{}\n{}", message, USAGE);
+    2
+}
@@ -0,0 +1,174 @@
+//! A typed SSA representation between the analyzed AST and bytecode. Every
+//! value is defined by exactly one instruction, variables name the value they
+//! were last assigned, and conversions between types are explicit.
+//!
+//! ```text
+//! %0: int = const 2                    ; a
+//! %1: any = convert %0                 ; b
+//! %2: float = const 2.5
+//! %3: any = convert %2
+//! %4: any = mul %1, %3                 ; b
+//! call print(%4)
+//! ```
+
+mod builder;
+mod lowering;
+mod printer;
+
+pub use builder::build;
+
+use parser::expressions::BinaryOp;
+use parser::span::AstSpan;
+use types::KirinType;
+
+/// A value defined by an instruction, printed as `%index`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Value(pub usize);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Constant {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    String(String),
+    Null,
+}
+
+#[derive(Debug, Clone)]
+pub enum Operation {
+    Const(Constant),
+    /// arithmetic, comparisons and logical operators on operands of the same type
+    Binary(BinaryOp, Value, Value),
+    Negate(Value),
+    Not(Value),
+    /// int to float, boxing into `any` and checked unboxing
+    Convert(Value),
+    /// library functions like `print` and `wrapping_add`
+    Call(String, Vec<Value>),
+}
+
+#[derive(Debug, Clone)]
+pub struct IrInstruction {
+    /// `None` for instructions without a result, e.g. calls to `print`
+    pub result: Option<Value>,
+    pub kind: KirinType,
+    pub operation: Operation,
+    /// the variable bound to the result, shown by the printer
+    pub name: Option<String>,
+    pub span: AstSpan,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct IrProgram {
+    pub instructions: Vec<IrInstruction>,
+    /// the type of each value
+    pub types: Vec<KirinType>,
+}
+
+impl IrProgram {
+    pub fn value_type(&self, value: Value) -> KirinType {
+        self.types[value.0]
+    }
+}
+
+#[cfg(test)]
+mod ir_tests {
+    use crate::{Compiler, OptimizationLevel, ir};
+    use errors::KirinError;
+    use parser::statements::Statement;
+    use vm::{Program, VM};
+
+    fn analyze(source: &str) -> Vec<Statement> {
+        let tokens = scanner::scan_tokens(source).unwrap();
+        let ast = parser::parse_ast(tokens, None).unwrap();
+        analyzer::TypeChecker::new().infer_types(&ast).unwrap()
+    }
+
+    fn lower(source: &str, level: OptimizationLevel) -> Program {
+        let program = ir::build(&analyze(source)).unwrap();
+
+        let mut compiler = Compiler::new().with_optimization(level);
+        compiler.compile_ir(&program).unwrap();
+        compiler.emit_program()
+    }
+
+    fn run(program: Program) -> Result<String, KirinError> {
+        let mut vm = VM::new();
+        vm.capture_output();
+        vm.load_program(program)?;
+        vm.start_with_offset(0)?;
+
+        Ok(vm.take_output())
+    }
+
+    #[test]
+    fn test_print_ir() {
+        let program = ir::build(&analyze(
+            "a := 2\nlet b: any = a\nb = b * 2.5\nprint(-a != 1 or !true)\n",
+        ))
+        .unwrap();
+
+        assert_eq!(
+            program.to_string(),
+            "%0: int = const 2                    ; a\n\
+             %1: any = convert %0                 ; b\n\
+             %2: float = const 2.5\n\
+             %3: any = convert %2\n\
+             %4: any = mul %1, %3                 ; b\n\
+             %5: int = neg %0\n\
+             %6: int = const 1\n\
+             %7: bool = ne %5, %6\n\
+             %8: bool = const true\n\
+             %9: bool = not %8\n\
+             %10: bool = or %7, %9\n\
+             %11: any = convert %10\n\
+             call print(%11)\n"
+        );
+    }
+
+    #[test]
+    fn test_variables_name_their_latest_value() {
+        let program = ir::build(&analyze("a := 1\nb := a\na = 2\nprint(b)\n")).unwrap();
+
+        let names = program
+            .instructions
+            .iter()
+            .filter_map(|instruction| instruction.name.as_deref())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["a", "a"]);
+        assert!(program.to_string().contains("%2: any = convert %0\n"));
+    }
+
+    #[test]
+    fn test_lowered_ir_matches_compiled_ast() {
+        let sources = [
+            "a := 2 + 3 * 4\nb := a - 1\nprint(b)\nprint(-(2 ^ 3) + a)\n",
+            "let a: any = 10\na = a * 2.5\nlet b: float = a\nprint(b / 4)\nprint(-a)\n",
+            "a := 3 > 2.5\nb := !a or 1 != 1\nlet c: any = b\nprint(c)\nprint(c and true)\n",
+            "x := 9223372036854775807\nprint(wrapping_add(x, 1))\nprint(7 % 3 + 0.5)\n",
+            "let a: any = none\nprint(a)\na = \"text\"\nprint(a)\nlet b: int\nprint(b)\n",
+        ];
+
+        for source in sources {
+            let mut compiler = Compiler::new();
+            compiler.compile(&analyze(source)).unwrap();
+            let expected = run(compiler.emit_program()).unwrap();
+
+            for level in [OptimizationLevel::O0, OptimizationLevel::O2] {
+                let output = run(lower(source, level)).unwrap();
+                assert_eq!(output, expected, "{:?} lowering of {:?}", level, source);
+            }
+        }
+    }
+
+    #[test]
+    fn test_lowered_runtime_error_location() {
+        match run(lower("a := 0\n\nb := 10 / a\n", OptimizationLevel::O0)) {
+            Err(KirinError::Runtime(error)) => {
+                assert_eq!(error.line, 3);
+                assert_eq!(error.column, 9);
+            }
+            result => panic!("expected runtime error, got {:?}", result),
+        }
+    }
+}
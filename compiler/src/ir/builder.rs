@@ -0,0 +1,304 @@
+use crate::ir::{Constant, IrInstruction, IrProgram, Operation, Value};
+use crate::{Compiler, compile_error};
+use errors::KirinError;
+use parser::expressions::{
+    Assign, Binary, Call, Expression, Grouping, Literal, Unary, UnaryOp, Variable,
+};
+use parser::span::AstSpan;
+use parser::statements::{If, Statement, VariableDeclaration, While};
+use parser::value::ParsedValue;
+use parser::visitor::{ExpressionVisitor, StatementVisitor};
+use std::collections::HashMap;
+use types::KirinType;
+
+/// Build the IR of type checked statements
+pub fn build(statements: &[Statement]) -> Result<IrProgram, KirinError> {
+    let mut builder = IrBuilder {
+        program: IrProgram::default(),
+        variables: HashMap::new(),
+    };
+
+    for statement in statements {
+        statement.accept(&mut builder)?;
+    }
+
+    Ok(builder.program)
+}
+
+struct IrBuilder {
+    program: IrProgram,
+    /// the current value of each variable
+    variables: HashMap<String, Value>,
+}
+
+impl IrBuilder {
+    fn evaluate(&mut self, expression: &Expression) -> Result<Value, KirinError> {
+        expression.accept(self)
+    }
+
+    fn push(&mut self, kind: KirinType, operation: Operation, span: &AstSpan) -> Value {
+        let value = Value(self.program.types.len());
+        self.program.types.push(kind);
+
+        self.program.instructions.push(IrInstruction {
+            result: Some(value),
+            kind,
+            operation,
+            name: None,
+            span: span.clone(),
+        });
+
+        value
+    }
+
+    /// Convert `value` to `target` unless it already has that type
+    fn convert(&mut self, value: Value, target: KirinType, span: &AstSpan) -> Value {
+        if self.program.value_type(value) == target {
+            return value;
+        }
+
+        self.push(target, Operation::Convert(value), span)
+    }
+
+    /// Bind a variable to a value, naming the instruction defining it
+    fn bind(&mut self, name: &str, value: Value) {
+        if let Some(instruction) = self
+            .program
+            .instructions
+            .iter_mut()
+            .rev()
+            .find(|instruction| instruction.result == Some(value))
+            && instruction.name.is_none()
+        {
+            instruction.name = Some(name.to_string());
+        }
+
+        self.variables.insert(name.to_string(), value);
+    }
+
+    fn expression_type(expression: &Expression, span: &AstSpan) -> Result<KirinType, KirinError> {
+        expression
+            .inferred_type()
+            .ok_or_else(|| compile_error(span, "expression has not been type checked".to_string()))
+    }
+}
+
+impl StatementVisitor for IrBuilder {
+    type Output = Result<(), KirinError>;
+
+    fn visit_none(&mut self) -> Self::Output {
+        Ok(())
+    }
+
+    fn visit_var_declaration(&mut self, var_declaration: &VariableDeclaration) -> Self::Output {
+        let span = &var_declaration.span;
+        let Some(kind) = var_declaration.inferred_type else {
+            return Err(compile_error(
+                span,
+                format!(
+                    "variable `{}` has not been type checked",
+                    var_declaration.name
+                ),
+            ));
+        };
+
+        let value = match &var_declaration.initializer {
+            Some(initializer) => {
+                let value = self.evaluate(initializer)?;
+                self.convert(value, kind, span)
+            }
+            None => {
+                let constant = match kind {
+                    KirinType::Int => Constant::Int(0),
+                    KirinType::Float => Constant::Float(0.0),
+                    KirinType::Bool => Constant::Bool(false),
+                    KirinType::String => Constant::String(String::new()),
+                    _ => Constant::Null,
+                };
+                self.push(kind, Operation::Const(constant), span)
+            }
+        };
+
+        self.bind(&var_declaration.name, value);
+
+        Ok(())
+    }
+
+    fn visit_expression_statement(&mut self, expression_statement: &Expression) -> Self::Output {
+        self.evaluate(expression_statement)?;
+
+        Ok(())
+    }
+
+    fn visit_if(&mut self, if_statement: &If) -> Self::Output {
+        Err(control_flow_error(&if_statement.span, "if"))
+    }
+
+    fn visit_while(&mut self, while_statement: &While) -> Self::Output {
+        Err(control_flow_error(&while_statement.span, "while"))
+    }
+
+    fn visit_return(&mut self, span: &AstSpan) -> Self::Output {
+        Err(control_flow_error(span, "return"))
+    }
+}
+
+/// The IR is a single basic block
+fn control_flow_error(span: &AstSpan, keyword: &str) -> KirinError {
+    compile_error(
+        span,
+        format!("`{}` is not supported by the IR yet", keyword),
+    )
+}
+
+impl ExpressionVisitor for IrBuilder {
+    type Output = Result<Value, KirinError>;
+
+    fn visit_binary(&mut self, binary: &Binary) -> Self::Output {
+        let span = &binary.span;
+        let kind = binary.inferred_type.ok_or_else(|| {
+            compile_error(span, "expression has not been type checked".to_string())
+        })?;
+
+        let left_type = Self::expression_type(&binary.left, span)?;
+        let right_type = Self::expression_type(&binary.right, span)?;
+        let operand_type = Compiler::operand_type(binary.operator, left_type, right_type, kind);
+
+        let left = self.evaluate(&binary.left)?;
+        let left = self.convert(left, operand_type, span);
+        let right = self.evaluate(&binary.right)?;
+        let right = self.convert(right, operand_type, span);
+
+        Ok(self.push(kind, Operation::Binary(binary.operator, left, right), span))
+    }
+
+    fn visit_unary(&mut self, unary: &Unary) -> Self::Output {
+        let span = &unary.span;
+        let right = self.evaluate(&unary.right)?;
+
+        match unary.operator {
+            UnaryOp::Negate => {
+                let kind = self.program.value_type(right);
+                Ok(self.push(kind, Operation::Negate(right), span))
+            }
+            UnaryOp::Not => {
+                let right = self.convert(right, KirinType::Bool, span);
+                Ok(self.push(KirinType::Bool, Operation::Not(right), span))
+            }
+        }
+    }
+
+    fn visit_grouping(&mut self, grouping: &Grouping) -> Self::Output {
+        self.evaluate(&grouping.expression)
+    }
+
+    fn visit_literal(&mut self, literal: &Literal) -> Self::Output {
+        let span = &literal.span;
+
+        let (kind, constant) = match &literal.value {
+            ParsedValue::Int(value) => (KirinType::Int, Constant::Int(*value)),
+            ParsedValue::Float(value) => (KirinType::Float, Constant::Float(*value)),
+            ParsedValue::Bool(value) => (KirinType::Bool, Constant::Bool(*value)),
+            ParsedValue::String(value) => (KirinType::String, Constant::String(value.clone())),
+            ParsedValue::Null => (KirinType::Null, Constant::Null),
+
+            value => {
+                return Err(compile_error(
+                    span,
+                    format!("literal `{:?}` not yet supported", value),
+                ));
+            }
+        };
+
+        Ok(self.push(kind, Operation::Const(constant), span))
+    }
+
+    fn visit_call(&mut self, callable: &Call) -> Self::Output {
+        let span = &callable.span;
+
+        let Expression::Variable(callee) = &callable.callee else {
+            return Err(compile_error(
+                span,
+                "only library functions can be called".to_string(),
+            ));
+        };
+
+        // `print` takes any value, the `wrapping_*` functions take two ints
+        let (parameter_type, arity, kind) = match callee.name.as_str() {
+            "print" => (KirinType::Any, 1, KirinType::Void),
+            name if Compiler::library_opcode(name).is_some() => (KirinType::Int, 2, KirinType::Int),
+
+            name => {
+                return Err(compile_error(
+                    span,
+                    format!("undefined function `{}`", name),
+                ));
+            }
+        };
+
+        if callable.arguments.len() != arity {
+            return Err(compile_error(
+                span,
+                format!("`{}` expects {} arguments", callee.name, arity),
+            ));
+        }
+
+        let mut arguments = Vec::with_capacity(arity);
+        for argument in &callable.arguments {
+            let value = self.evaluate(argument)?;
+            arguments.push(self.convert(value, parameter_type, span));
+        }
+
+        if kind != KirinType::Void {
+            let operation = Operation::Call(callee.name.clone(), arguments);
+            return Ok(self.push(kind, operation, span));
+        }
+
+        // a call without a result evaluates to its argument like in the compiler
+        let value = arguments[0];
+        self.program.instructions.push(IrInstruction {
+            result: None,
+            kind,
+            operation: Operation::Call(callee.name.clone(), arguments),
+            name: None,
+            span: span.clone(),
+        });
+
+        Ok(value)
+    }
+
+    fn visit_variable(&mut self, variable: &Variable) -> Self::Output {
+        self.variables.get(&variable.name).copied().ok_or_else(|| {
+            compile_error(
+                &variable.span,
+                format!("undefined variable `{}`", variable.name),
+            )
+        })
+    }
+
+    fn visit_assign(&mut self, assign: &Assign) -> Self::Output {
+        let span = &assign.span;
+
+        let Some(&current) = self.variables.get(&assign.name) else {
+            return Err(compile_error(
+                span,
+                format!("assignment to undefined variable `{}`", assign.name),
+            ));
+        };
+
+        let target = self.program.value_type(current);
+        let value = self.evaluate(&assign.value)?;
+        let value = self.convert(value, target, span);
+
+        self.bind(&assign.name, value);
+
+        Ok(value)
+    }
+
+    fn visit_error(&mut self, span: &AstSpan) -> Self::Output {
+        Err(compile_error(
+            span,
+            "cannot compile source that failed to parse".to_string(),
+        ))
+    }
+}
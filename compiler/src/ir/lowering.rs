@@ -0,0 +1,117 @@
+use crate::ir::{Constant, IrProgram, Operation, Value};
+use crate::{Compiler, compile_error};
+use errors::KirinError;
+use instructions::{Instruction, OpCode};
+use parser::expressions::BinaryOp;
+use parser::value::ParsedValue;
+use types::KirinType;
+
+impl Compiler {
+    /// Emit the instructions of an IR program, `emit_program` then wraps
+    /// them like the instructions compiled from the AST. Every value gets
+    /// its own registers, `-O2` shares them between values afterwards.
+    pub fn compile_ir(&mut self, program: &IrProgram) -> Result<(), KirinError> {
+        let mut registers = vec![None; program.types.len()];
+
+        for instruction in &program.instructions {
+            let span = &instruction.span;
+            let register = |value: Value| {
+                registers[value.0].ok_or_else(|| {
+                    compile_error(span, format!("{} is used before it is defined", value))
+                })
+            };
+
+            let destination = match &instruction.operation {
+                Operation::Const(constant) => {
+                    let value = match constant {
+                        Constant::Int(value) => ParsedValue::Int(*value),
+                        Constant::Float(value) => ParsedValue::Float(*value),
+                        Constant::Bool(value) => ParsedValue::Bool(*value),
+                        Constant::String(value) => ParsedValue::String(value.clone()),
+                        Constant::Null => ParsedValue::Null,
+                    };
+                    self.load_value(&value, span)?
+                }
+                Operation::Binary(operator, left, right) => {
+                    let operand_type = program.value_type(*left);
+                    self.emit_binary(
+                        *operator,
+                        instruction.kind,
+                        operand_type,
+                        register(*left)?,
+                        register(*right)?,
+                        span,
+                    )?
+                }
+                Operation::Negate(value) => {
+                    // negation is compiled as `0 - value`
+                    let zero = self.allocate_temp(KirinType::Int, span)?;
+                    self.emit(OpCode::LoadInt16, &[zero as Instruction, 0], span);
+                    let zero = self.convert(zero, KirinType::Int, instruction.kind, span)?;
+
+                    self.emit_binary(
+                        BinaryOp::Subtract,
+                        instruction.kind,
+                        instruction.kind,
+                        zero,
+                        register(*value)?,
+                        span,
+                    )?
+                }
+                Operation::Not(value) => {
+                    let destination = self.allocate_temp(KirinType::Bool, span)?;
+                    self.emit(
+                        OpCode::Not,
+                        &[destination as Instruction, register(*value)? as Instruction],
+                        span,
+                    );
+                    destination
+                }
+                Operation::Convert(value) => {
+                    let source = register(*value)?;
+                    self.convert(source, program.value_type(*value), instruction.kind, span)?
+                }
+                Operation::Call(function, arguments) => {
+                    let arguments = arguments
+                        .iter()
+                        .map(|&argument| register(argument))
+                        .collect::<Result<Vec<_>, _>>()?;
+
+                    let opcode = Compiler::library_opcode(function);
+                    match (function.as_str(), opcode, &arguments[..]) {
+                        ("print", _, &[value]) => {
+                            self.emit_print(value, span);
+                            value
+                        }
+                        (_, Some(opcode), &[first, second]) => {
+                            let destination = self.allocate_temp(KirinType::Int, span)?;
+                            self.emit(
+                                opcode,
+                                &[
+                                    destination as Instruction,
+                                    first as Instruction,
+                                    second as Instruction,
+                                ],
+                                span,
+                            );
+                            destination
+                        }
+
+                        _ => {
+                            return Err(compile_error(
+                                span,
+                                format!("invalid call to `{}`", function),
+                            ));
+                        }
+                    }
+                }
+            };
+
+            if let Some(result) = instruction.result {
+                registers[result.0] = Some(destination);
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,89 @@
+use crate::ir::{Constant, IrInstruction, IrProgram, Operation, Value};
+use parser::expressions::BinaryOp;
+use std::fmt::{Display, Formatter};
+
+/// column of the variable names bound to results
+const NAME_COLUMN: usize = 36;
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "%{}", self.0)
+    }
+}
+
+impl Display for Constant {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Constant::Int(value) => write!(f, "{}", value),
+            Constant::Float(value) => write!(f, "{:?}", value),
+            Constant::Bool(value) => write!(f, "{}", value),
+            Constant::String(value) => write!(f, "{:?}", value),
+            Constant::Null => write!(f, "none"),
+        }
+    }
+}
+
+fn mnemonic(operator: BinaryOp) -> &'static str {
+    match operator {
+        BinaryOp::Add => "add",
+        BinaryOp::Subtract => "sub",
+        BinaryOp::Multiply => "mul",
+        BinaryOp::Divide => "div",
+        BinaryOp::Modulus => "mod",
+        BinaryOp::Power => "pow",
+        BinaryOp::Equal => "eq",
+        BinaryOp::NotEqual => "ne",
+        BinaryOp::Greater => "gt",
+        BinaryOp::GreaterEqual => "ge",
+        BinaryOp::Less => "lt",
+        BinaryOp::LessEqual => "le",
+        BinaryOp::And => "and",
+        BinaryOp::Or => "or",
+    }
+}
+
+impl Display for Operation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Operation::Const(constant) => write!(f, "const {}", constant),
+            Operation::Binary(operator, left, right) => {
+                write!(f, "{} {}, {}", mnemonic(*operator), left, right)
+            }
+            Operation::Negate(value) => write!(f, "neg {}", value),
+            Operation::Not(value) => write!(f, "not {}", value),
+            Operation::Convert(value) => write!(f, "convert {}", value),
+            Operation::Call(function, arguments) => {
+                let arguments = arguments
+                    .iter()
+                    .map(Value::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(f, "call {}({})", function, arguments)
+            }
+        }
+    }
+}
+
+impl Display for IrInstruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let text = match self.result {
+            Some(result) => format!("{}: {} = {}", result, self.kind, self.operation),
+            None => self.operation.to_string(),
+        };
+
+        match &self.name {
+            Some(name) => write!(f, "{:<width$} ; {}", text, name, width = NAME_COLUMN),
+            None => write!(f, "{}", text),
+        }
+    }
+}
+
+impl Display for IrProgram {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for instruction in &self.instructions {
+            writeln!(f, "{}", instruction)?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,24 +1,72 @@
-use errors::{KirinError, SpannedError};
+use errors::{KirinError, codes};
 use instructions::{Instruction, InstructionBuilder, OpCode};
 use parser::expressions::{
-    Assign, Binary, BinaryOp, Call, Expression, Grouping, Literal, Unary, Variable,
+    Assign, Binary, BinaryOp, Call, Expression, Grouping, Literal, Unary, UnaryOp, Variable,
 };
-use parser::statements::{Statement, VariableDeclaration};
+use parser::span::AstSpan;
+use parser::statements::{If, Statement, VariableDeclaration, While};
+use parser::value::ParsedValue;
 use parser::visitor::{ExpressionVisitor, StatementVisitor};
 use std::collections::HashMap;
 use types::KirinType;
-use vm::{Program, ProgramConstant};
+use vm::{DebugInfo, Program, ProgramConstant};
 
+pub mod ir;
+mod optimizer;
+
+pub use optimizer::OptimizationLevel;
+
+/// registers are 16 bits wide with an `Extend` prefix
+const MAX_REGISTERS: usize = 1 << 16;
+
+#[derive(Debug, Copy, Clone)]
 enum Register {
     Temp(Option<KirinType>),
     Variable(Option<KirinType>),
 }
 
+#[derive(Debug, Copy, Clone)]
+enum JumpTarget {
+    /// an index into the compiled instructions
+    Instruction(usize),
+    /// the epilogue after them, where `return` goes
+    End,
+}
+
+/// A jump of the compiled instructions. Addresses are absolute, so they are
+/// patched in when the prologue is known. Jumps always have an `Extend`
+/// prefix, patching their address never moves the instructions after them.
+#[derive(Debug, Clone)]
+struct Jump {
+    /// index of the prefix in the compiled instructions
+    position: usize,
+    opcode: OpCode,
+    /// the register of a `JumpIfFalse` condition
+    condition: Option<Instruction>,
+    target: JumpTarget,
+}
+
+#[derive(Clone)]
 pub struct Compiler {
     instructions: Vec<Instruction>,
     constants: Vec<ProgramConstant>,
+    debug_info: DebugInfo,
     locals: Vec<HashMap<String, usize>>,
+    jumps: Vec<Jump>,
     registers: Vec<Register>,
+    max_registers: usize,
+    optimization: OptimizationLevel,
+    /// registers, constants and instructions of the chunks already emitted
+    /// by `emit_chunk`
+    emitted_registers: usize,
+    emitted_constants: usize,
+    emitted_instructions: usize,
+}
+
+impl Default for Compiler {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Compiler {
@@ -26,11 +74,23 @@ impl Compiler {
         Self {
             instructions: Vec::new(),
             constants: Vec::new(),
-            locals: Vec::new(),
+            debug_info: DebugInfo::new(),
+            locals: vec![HashMap::new()],
+            jumps: Vec::new(),
             registers: Vec::new(),
+            max_registers: 0,
+            optimization: OptimizationLevel::default(),
+            emitted_registers: 0,
+            emitted_constants: 0,
+            emitted_instructions: 0,
         }
     }
 
+    pub fn with_optimization(mut self, level: OptimizationLevel) -> Self {
+        self.optimization = level;
+        self
+    }
+
     pub fn compile(&mut self, statements: &Vec<Statement>) -> Result<(), KirinError> {
         for statement in statements {
             self.execute(statement)?;
@@ -39,18 +99,585 @@ impl Compiler {
         Ok(())
     }
 
+    /// Wrap the compiled statements with the register allocation for the
+    /// top level frame and the trailing `Return`/`Halt` pair, then run the
+    /// optimization passes of the selected level
     pub fn emit_program(self) -> Program {
-        Program::new(self.instructions, self.constants)
+        let register_count = self.max_registers as Instruction;
+
+        let prologue = InstructionBuilder::extended(OpCode::AllocReg, &[register_count]);
+        let body_start = prologue.len();
+
+        let mut body = self.instructions;
+        Self::link(&mut body, &self.jumps, body_start);
+
+        let mut instructions = Vec::with_capacity(body.len() + 6);
+        instructions.extend(prologue);
+        instructions.extend(body);
+        let epilogue_start = instructions.len();
+        instructions.extend(InstructionBuilder::extended(
+            OpCode::DeallocReg,
+            &[register_count],
+        ));
+        instructions.push(InstructionBuilder::simple(OpCode::Return));
+        instructions.push(InstructionBuilder::simple(OpCode::Halt));
+
+        // the prologue and epilogue have no source location
+        let mut debug_info = DebugInfo::new();
+        debug_info.push_unknown(0);
+        debug_info.append(&self.debug_info, body_start);
+        debug_info.push_unknown(epilogue_start);
+
+        let program = Program::new(instructions, self.constants).with_debug_info(debug_info);
+        optimizer::optimize(program, self.optimization)
+    }
+
+    /// Emit the statements compiled since the previous chunk as a program
+    /// for `VM::resume`. Variables stay in their registers for the following
+    /// chunks, so a chunk only allocates the registers it adds, never
+    /// releases them and is not optimized. Jump addresses count the
+    /// instructions of the previous chunks, which precede it in the VM.
+    pub fn emit_chunk(&mut self) -> Program {
+        let register_count = (self.max_registers - self.emitted_registers) as Instruction;
+        self.emitted_registers = self.max_registers;
+
+        let mut instructions = InstructionBuilder::extended(OpCode::AllocReg, &[register_count]);
+        let body_start = instructions.len();
+        Self::link(
+            &mut self.instructions,
+            &std::mem::take(&mut self.jumps),
+            self.emitted_instructions + body_start,
+        );
+        instructions.append(&mut self.instructions);
+        let epilogue_start = instructions.len();
+        instructions.push(InstructionBuilder::simple(OpCode::Return));
+        instructions.push(InstructionBuilder::simple(OpCode::Halt));
+        self.emitted_instructions += instructions.len();
+
+        let mut debug_info = DebugInfo::new();
+        debug_info.push_unknown(0);
+        debug_info.append(&std::mem::take(&mut self.debug_info), body_start);
+        debug_info.push_unknown(epilogue_start);
+
+        // constant indices count the constants of the previous chunks
+        let constants = self.constants[self.emitted_constants..].to_vec();
+        self.emitted_constants = self.constants.len();
+
+        Program::new(instructions, constants).with_debug_info(debug_info)
+    }
+
+    /// The constants of every chunk, chunk constant indices refer to them
+    pub fn constants(&self) -> &[ProgramConstant] {
+        &self.constants
+    }
+
+    /// Patch the addresses of the jumps of `body`, which starts at
+    /// `body_start` and is followed by the epilogue
+    fn link(body: &mut [Instruction], jumps: &[Jump], body_start: usize) {
+        let body_end = body_start + body.len();
+
+        for jump in jumps {
+            let address = match jump.target {
+                JumpTarget::Instruction(index) => body_start + index,
+                JumpTarget::End => body_end,
+            };
+
+            let operands = jump
+                .condition
+                .into_iter()
+                .chain([address as Instruction])
+                .collect::<Vec<_>>();
+            let encoded = InstructionBuilder::prefixed(jump.opcode, &operands);
+            body[jump.position..jump.position + 2].copy_from_slice(&encoded);
+        }
+    }
+
+    /// Emit a jump whose address is patched when the program is emitted,
+    /// returns its index for `land`
+    fn emit_jump(
+        &mut self,
+        opcode: OpCode,
+        condition: Option<usize>,
+        target: JumpTarget,
+        span: &AstSpan,
+    ) -> usize {
+        let position = self.instructions.len();
+        let condition = condition.map(|register| register as Instruction);
+        let operands = condition.into_iter().chain([0]).collect::<Vec<_>>();
+
+        self.debug_info
+            .push(position, span.filename.as_deref(), span.line, span.column);
+        self.instructions
+            .extend(InstructionBuilder::prefixed(opcode, &operands));
+
+        self.jumps.push(Jump {
+            position,
+            opcode,
+            condition,
+            target,
+        });
+
+        self.jumps.len() - 1
+    }
+
+    /// Make a jump continue with the next instruction emitted
+    fn land(&mut self, jump: usize) {
+        self.jumps[jump].target = JumpTarget::Instruction(self.instructions.len());
+    }
+
+    /// Evaluate a condition and emit a jump taken when it is false. The
+    /// temporaries of the condition are released, the jump has read it.
+    fn jump_if_false(
+        &mut self,
+        condition: &Expression,
+        span: &AstSpan,
+    ) -> Result<usize, KirinError> {
+        let kind = Self::expression_type(condition, span)?;
+        let value = self.evaluate(condition)?;
+        let value = self.convert(value, kind, KirinType::Bool, span)?;
+
+        let jump = self.emit_jump(OpCode::JumpIfFalse, Some(value), JumpTarget::End, span);
+        self.free_temporaries();
+
+        Ok(jump)
+    }
+
+    /// Compile the statements of a block in a scope of their own. Their
+    /// registers are not released, the debug info of the variables keeps
+    /// referring to them.
+    fn block(&mut self, statements: &[Statement]) -> Result<(), KirinError> {
+        self.locals.push(HashMap::new());
+        let result = statements
+            .iter()
+            .try_for_each(|statement| self.execute(statement));
+        self.locals.pop();
+
+        result
     }
 
     fn execute(&mut self, statement: &Statement) -> Result<(), KirinError> {
         statement.accept(self)?;
+        self.free_temporaries();
+
         Ok(())
     }
 
-    fn evaluate(&mut self, expression: &Expression) -> Result<(), KirinError> {
+    fn evaluate(&mut self, expression: &Expression) -> Result<usize, KirinError> {
         expression.accept(self)
     }
+
+    /// Emit an instruction, preceded by an `Extend` prefix if any operand
+    /// does not fit its slot
+    fn emit(&mut self, opcode: OpCode, operands: &[Instruction], span: &AstSpan) {
+        self.debug_info.push(
+            self.instructions.len(),
+            span.filename.as_deref(),
+            span.line,
+            span.column,
+        );
+        self.instructions
+            .extend(InstructionBuilder::extended(opcode, operands));
+    }
+
+    fn add_constant(&mut self, constant: ProgramConstant) -> usize {
+        self.constants.push(constant);
+        self.constants.len() - 1
+    }
+
+    /// Any values and `none` occupy a register pair: the type tag followed by the value
+    fn register_width(kind: KirinType) -> usize {
+        match kind {
+            KirinType::Any | KirinType::Null => 2,
+            _ => 1,
+        }
+    }
+
+    fn allocate_register(
+        &mut self,
+        register: Register,
+        span: &AstSpan,
+    ) -> Result<usize, KirinError> {
+        let kind = match register {
+            Register::Temp(kind) | Register::Variable(kind) => kind,
+        };
+        let width = kind.map(Self::register_width).unwrap_or(1);
+
+        let index = self.registers.len();
+        if index + width > MAX_REGISTERS {
+            return Err(KirinError::Compile(
+                span.error(format!(
+                    "expression requires more than {} registers",
+                    MAX_REGISTERS
+                ))
+                .with_code(codes::TOO_MANY_REGISTERS),
+            ));
+        }
+
+        for _ in 0..width {
+            self.registers.push(register);
+        }
+        self.max_registers = self.max_registers.max(self.registers.len());
+
+        Ok(index)
+    }
+
+    fn allocate_temp(&mut self, kind: KirinType, span: &AstSpan) -> Result<usize, KirinError> {
+        self.allocate_register(Register::Temp(Some(kind)), span)
+    }
+
+    /// Temporaries only live for the duration of a statement, variables are
+    /// always allocated below them
+    fn free_temporaries(&mut self) {
+        while let Some(Register::Temp(_)) = self.registers.last() {
+            self.registers.pop();
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<usize> {
+        self.locals
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name).copied())
+    }
+
+    fn variable_type(&self, register: usize) -> Option<KirinType> {
+        match self.registers.get(register) {
+            Some(Register::Variable(kind)) | Some(Register::Temp(kind)) => *kind,
+            None => None,
+        }
+    }
+
+    fn expression_type(expression: &Expression, span: &AstSpan) -> Result<KirinType, KirinError> {
+        expression
+            .inferred_type()
+            .ok_or_else(|| compile_error(span, "expression has not been type checked".to_string()))
+    }
+
+    /// Copy or convert the value in `source` into `destination`, boxing into
+    /// an Any pair or emitting a checked downcast where the types differ
+    fn store(
+        &mut self,
+        destination: usize,
+        target: KirinType,
+        source: usize,
+        value: KirinType,
+        span: &AstSpan,
+    ) -> Result<(), KirinError> {
+        let (destination_register, source_register) =
+            (destination as Instruction, source as Instruction);
+
+        if target == value || (target == KirinType::Any && value == KirinType::Null) {
+            for offset in 0..Self::register_width(target) as Instruction {
+                self.emit(
+                    OpCode::Move,
+                    &[destination_register + offset, source_register + offset],
+                    span,
+                );
+            }
+            return Ok(());
+        }
+
+        let opcode = match (target, value) {
+            (KirinType::Any, KirinType::Int) => OpCode::IntToAny,
+            (KirinType::Any, KirinType::Float) => OpCode::FloatToAny,
+            (KirinType::Any, KirinType::String) => OpCode::StringToAny,
+            (KirinType::Any, KirinType::Bool) => OpCode::BoolToAny,
+            (KirinType::Int, KirinType::Any) => OpCode::AnyToInt,
+            (KirinType::Float, KirinType::Any) => OpCode::AnyToFloat,
+            (KirinType::String, KirinType::Any) => OpCode::AnyToString,
+            (KirinType::Bool, KirinType::Any) => OpCode::AnyToBool,
+
+            _ => {
+                return Err(compile_error(
+                    span,
+                    format!("cannot convert `{}` to `{}`", value, target),
+                ));
+            }
+        };
+
+        self.emit(opcode, &[destination_register, source_register], span);
+
+        Ok(())
+    }
+
+    /// Move a value into a fresh temporary of the requested type
+    fn convert(
+        &mut self,
+        source: usize,
+        value: KirinType,
+        target: KirinType,
+        span: &AstSpan,
+    ) -> Result<usize, KirinError> {
+        if value == target {
+            return Ok(source);
+        }
+
+        let destination = self.allocate_temp(target, span)?;
+
+        if value == KirinType::Int && target == KirinType::Float {
+            self.emit(
+                OpCode::Move,
+                &[destination as Instruction, source as Instruction],
+                span,
+            );
+            self.emit(OpCode::IntToFloat, &[destination as Instruction], span);
+            return Ok(destination);
+        }
+
+        self.store(destination, target, source, value, span)?;
+
+        Ok(destination)
+    }
+
+    /// The type both operands of a binary operation are converted to
+    fn operand_type(
+        operator: BinaryOp,
+        left_type: KirinType,
+        right_type: KirinType,
+        kind: KirinType,
+    ) -> KirinType {
+        match operator {
+            BinaryOp::Equal
+            | BinaryOp::NotEqual
+            | BinaryOp::Greater
+            | BinaryOp::GreaterEqual
+            | BinaryOp::Less
+            | BinaryOp::LessEqual
+                if left_type != right_type =>
+            {
+                KirinType::Float
+            }
+            BinaryOp::Equal
+            | BinaryOp::NotEqual
+            | BinaryOp::Greater
+            | BinaryOp::GreaterEqual
+            | BinaryOp::Less
+            | BinaryOp::LessEqual => left_type,
+            BinaryOp::And | BinaryOp::Or => KirinType::Bool,
+
+            _ => kind,
+        }
+    }
+
+    /// Emit a binary operation on operands already converted to `operand_type`
+    fn emit_binary(
+        &mut self,
+        operator: BinaryOp,
+        kind: KirinType,
+        operand_type: KirinType,
+        left: usize,
+        right: usize,
+        span: &AstSpan,
+    ) -> Result<usize, KirinError> {
+        let opcode = Self::arithmetic_opcode(operator, kind)
+            .map(|opcode| (opcode, false))
+            .or_else(|| Self::comparison_opcode(operator, operand_type));
+
+        let Some((opcode, swap_operands)) = opcode else {
+            return Err(compile_error(
+                span,
+                format!(
+                    "binary operator `{}` not implemented for `{}`",
+                    operator.symbol(),
+                    operand_type
+                ),
+            ));
+        };
+
+        let (first, second) = if swap_operands {
+            (right, left)
+        } else {
+            (left, right)
+        };
+
+        let destination = self.allocate_temp(kind, span)?;
+        self.emit(
+            opcode,
+            &[
+                destination as Instruction,
+                first as Instruction,
+                second as Instruction,
+            ],
+            span,
+        );
+
+        if let BinaryOp::NotEqual = operator {
+            self.emit(
+                OpCode::Not,
+                &[destination as Instruction, destination as Instruction],
+                span,
+            );
+        }
+
+        Ok(destination)
+    }
+
+    /// Comparisons and logical operators, `>` and `>=` are emitted as `<` and
+    /// `<=` with swapped operands. `!=` is emitted as `==` followed by `Not`
+    fn comparison_opcode(operator: BinaryOp, operand_type: KirinType) -> Option<(OpCode, bool)> {
+        let opcode = match (operator, operand_type) {
+            (BinaryOp::Equal | BinaryOp::NotEqual, KirinType::Int | KirinType::Bool) => {
+                (OpCode::EqualInt, false)
+            }
+            (BinaryOp::Equal | BinaryOp::NotEqual, KirinType::Float) => (OpCode::EqualFloat, false),
+            (BinaryOp::Less, KirinType::Int) => (OpCode::LessInt, false),
+            (BinaryOp::Less, KirinType::Float) => (OpCode::LessFloat, false),
+            (BinaryOp::LessEqual, KirinType::Int) => (OpCode::LessEqualInt, false),
+            (BinaryOp::LessEqual, KirinType::Float) => (OpCode::LessEqualFloat, false),
+            (BinaryOp::Greater, KirinType::Int) => (OpCode::LessInt, true),
+            (BinaryOp::Greater, KirinType::Float) => (OpCode::LessFloat, true),
+            (BinaryOp::GreaterEqual, KirinType::Int) => (OpCode::LessEqualInt, true),
+            (BinaryOp::GreaterEqual, KirinType::Float) => (OpCode::LessEqualFloat, true),
+            (BinaryOp::And, KirinType::Bool) => (OpCode::And, false),
+            (BinaryOp::Or, KirinType::Bool) => (OpCode::Or, false),
+
+            _ => return None,
+        };
+
+        Some(opcode)
+    }
+
+    /// Load a literal or folded constant into a fresh temporary
+    fn load_value(&mut self, value: &ParsedValue, span: &AstSpan) -> Result<usize, KirinError> {
+        match value {
+            ParsedValue::Int(value) => {
+                let destination = self.allocate_temp(KirinType::Int, span)?;
+
+                // ints up to 32 bits are loaded as an immediate with an `Extend` prefix
+                if let Ok(value) = i32::try_from(*value) {
+                    self.emit(
+                        OpCode::LoadInt16,
+                        &[destination as Instruction, value as Instruction],
+                        span,
+                    );
+                } else {
+                    let index = self.add_constant(ProgramConstant::Int64(*value));
+                    self.emit(
+                        OpCode::LoadConst,
+                        &[destination as Instruction, index as Instruction],
+                        span,
+                    );
+                }
+
+                Ok(destination)
+            }
+            ParsedValue::Float(value) => {
+                let destination = self.allocate_temp(KirinType::Float, span)?;
+                let index = self.add_constant(ProgramConstant::Float(*value));
+                self.emit(
+                    OpCode::LoadConst,
+                    &[destination as Instruction, index as Instruction],
+                    span,
+                );
+
+                Ok(destination)
+            }
+            ParsedValue::String(value) => {
+                let destination = self.allocate_temp(KirinType::String, span)?;
+                let index = self.add_constant(ProgramConstant::String(value.clone()));
+                self.emit(
+                    OpCode::LoadConst,
+                    &[destination as Instruction, index as Instruction],
+                    span,
+                );
+
+                Ok(destination)
+            }
+            ParsedValue::Bool(value) => {
+                let destination = self.allocate_temp(KirinType::Bool, span)?;
+                self.emit(
+                    OpCode::LoadBool,
+                    &[destination as Instruction, *value as Instruction],
+                    span,
+                );
+
+                Ok(destination)
+            }
+            ParsedValue::Null => {
+                let destination = self.allocate_temp(KirinType::Null, span)?;
+                self.emit(OpCode::LoadNull, &[destination as Instruction], span);
+
+                Ok(destination)
+            }
+
+            value => Err(compile_error(
+                span,
+                format!("literal `{:?}` not yet supported", value),
+            )),
+        }
+    }
+
+    /// `print(value)` writes the value followed by a newline. The call has
+    /// no result, the register of the printed value is returned instead
+    fn print(&mut self, callable: &Call) -> Result<usize, KirinError> {
+        let span = &callable.span;
+
+        let [argument] = &callable.arguments[..] else {
+            return Err(compile_error(
+                span,
+                "`print` expects 1 argument".to_string(),
+            ));
+        };
+
+        let argument_type = Self::expression_type(argument, span)?;
+        let value = self.evaluate(argument)?;
+        let value = self.convert(value, argument_type, KirinType::Any, span)?;
+
+        self.emit_print(value, span);
+
+        Ok(value)
+    }
+
+    /// Print an Any register pair followed by a newline
+    fn emit_print(&mut self, value: usize, span: &AstSpan) {
+        self.emit(OpCode::PrintAny, &[value as Instruction], span);
+        self.emit(OpCode::PrintChar, &[b'\n' as Instruction], span);
+    }
+
+    /// The instruction of the `wrapping_*` library functions
+    fn library_opcode(name: &str) -> Option<OpCode> {
+        let opcode = match name {
+            "wrapping_add" => OpCode::WrappingAddInt,
+            "wrapping_sub" => OpCode::WrappingSubInt,
+            "wrapping_mul" => OpCode::WrappingMulInt,
+            "wrapping_pow" => OpCode::WrappingPowInt,
+
+            _ => return None,
+        };
+
+        Some(opcode)
+    }
+
+    fn arithmetic_opcode(operator: BinaryOp, kind: KirinType) -> Option<OpCode> {
+        let opcode = match (operator, kind) {
+            (BinaryOp::Add, KirinType::Int) => OpCode::AddInt,
+            (BinaryOp::Subtract, KirinType::Int) => OpCode::SubInt,
+            (BinaryOp::Multiply, KirinType::Int) => OpCode::MulInt,
+            (BinaryOp::Divide, KirinType::Int) => OpCode::DivInt,
+            (BinaryOp::Modulus, KirinType::Int) => OpCode::ModInt,
+            (BinaryOp::Power, KirinType::Int) => OpCode::PowInt,
+            (BinaryOp::Add, KirinType::Float) => OpCode::AddFloat,
+            (BinaryOp::Subtract, KirinType::Float) => OpCode::SubFloat,
+            (BinaryOp::Multiply, KirinType::Float) => OpCode::MulFloat,
+            (BinaryOp::Divide, KirinType::Float) => OpCode::DivFloat,
+            (BinaryOp::Modulus, KirinType::Float) => OpCode::ModFloat,
+            (BinaryOp::Power, KirinType::Float) => OpCode::PowFloat,
+            (BinaryOp::Add, KirinType::Any) => OpCode::AddAny,
+            (BinaryOp::Subtract, KirinType::Any) => OpCode::SubAny,
+            (BinaryOp::Multiply, KirinType::Any) => OpCode::MulAny,
+            (BinaryOp::Divide, KirinType::Any) => OpCode::DivAny,
+            (BinaryOp::Modulus, KirinType::Any) => OpCode::ModAny,
+            (BinaryOp::Power, KirinType::Any) => OpCode::PowAny,
+
+            _ => return None,
+        };
+
+        Some(opcode)
+    }
+}
+
+fn compile_error(span: &AstSpan, message: String) -> KirinError {
+    KirinError::Compile(span.error(message))
 }
 
 impl StatementVisitor for Compiler {
@@ -61,63 +688,702 @@ impl StatementVisitor for Compiler {
     }
 
     fn visit_var_declaration(&mut self, var_declaration: &VariableDeclaration) -> Self::Output {
-        todo!()
+        let span = &var_declaration.span;
+        let Some(kind) = var_declaration.inferred_type else {
+            return Err(compile_error(
+                span,
+                format!(
+                    "variable `{}` has not been type checked",
+                    var_declaration.name
+                ),
+            ));
+        };
+
+        let destination = self.allocate_register(Register::Variable(Some(kind)), span)?;
+
+        match &var_declaration.initializer {
+            Some(initializer) => {
+                let value_type = Self::expression_type(initializer, span)?;
+                let source = self.evaluate(initializer)?;
+
+                self.store(destination, kind, source, value_type, span)?;
+            }
+            None if Self::register_width(kind) == 2 => {
+                self.emit(OpCode::LoadNull, &[destination as Instruction], span);
+            }
+            // string registers hold a constant index, so zero would be the first constant
+            None if kind == KirinType::String => {
+                let index = self.add_constant(ProgramConstant::String(String::new()));
+                self.emit(
+                    OpCode::LoadConst,
+                    &[destination as Instruction, index as Instruction],
+                    span,
+                );
+            }
+            None => {
+                self.emit(OpCode::LoadInt16, &[destination as Instruction, 0], span);
+            }
+        }
+
+        if let Some(scope) = self.locals.last_mut() {
+            scope.insert(var_declaration.name.clone(), destination);
+        }
+        self.debug_info.declare(
+            &var_declaration.name,
+            destination,
+            kind,
+            self.instructions.len(),
+        );
+
+        Ok(())
     }
 
     fn visit_expression_statement(&mut self, expression_statement: &Expression) -> Self::Output {
-        todo!()
+        self.evaluate(expression_statement)?;
+
+        Ok(())
+    }
+
+    fn visit_if(&mut self, if_statement: &If) -> Self::Output {
+        let span = &if_statement.span;
+
+        let skip_then = self.jump_if_false(&if_statement.condition, span)?;
+        self.block(&if_statement.then_branch)?;
+
+        match &if_statement.else_branch {
+            Some(else_branch) => {
+                let skip_else = self.emit_jump(OpCode::Jump, None, JumpTarget::End, span);
+                self.land(skip_then);
+                self.block(else_branch)?;
+                self.land(skip_else);
+            }
+            None => self.land(skip_then),
+        }
+
+        Ok(())
+    }
+
+    fn visit_while(&mut self, while_statement: &While) -> Self::Output {
+        let span = &while_statement.span;
+        let start = self.instructions.len();
+
+        let exit = self.jump_if_false(&while_statement.condition, span)?;
+        self.block(&while_statement.body)?;
+        self.emit_jump(OpCode::Jump, None, JumpTarget::Instruction(start), span);
+        self.land(exit);
+
+        Ok(())
+    }
+
+    fn visit_return(&mut self, span: &AstSpan) -> Self::Output {
+        self.emit_jump(OpCode::Jump, None, JumpTarget::End, span);
+
+        Ok(())
     }
 }
 
 impl ExpressionVisitor for Compiler {
-    type Output = Result<(), KirinError>;
+    type Output = Result<usize, KirinError>;
 
     fn visit_binary(&mut self, binary: &Binary) -> Self::Output {
-        self.evaluate(&binary.left)?;
-        self.evaluate(&binary.right)?;
+        let span = &binary.span;
 
-        let opcode = match binary.operator {
-            BinaryOp::Add => OpCode::AddInt,
-            BinaryOp::Subtract => OpCode::SubInt,
-            BinaryOp::Multiply => OpCode::MulInt,
-            BinaryOp::Divide => OpCode::DivInt,
+        if self.optimization >= OptimizationLevel::O1
+            && let Some(value) = optimizer::constant_folding::fold_binary(binary)
+        {
+            return self.load_value(&value, span);
+        }
 
-            _ => {
-                return Err(KirinError::Compile(SpannedError {
-                    line: binary.span.line,
-                    column: binary.span.column,
-                    message: format!(
-                        "binary operator `{:?}` not implemented in file: `{:?}`",
-                        binary.operator, binary.span.filename
-                    ),
-                }));
-            }
-        };
+        let kind = binary.inferred_type.ok_or_else(|| {
+            compile_error(span, "expression has not been type checked".to_string())
+        })?;
 
-        Ok(())
+        let left_type = Self::expression_type(&binary.left, span)?;
+        let right_type = Self::expression_type(&binary.right, span)?;
+
+        let operand_type = Self::operand_type(binary.operator, left_type, right_type, kind);
+
+        let left = self.evaluate(&binary.left)?;
+        let left = self.convert(left, left_type, operand_type, span)?;
+        let right = self.evaluate(&binary.right)?;
+        let right = self.convert(right, right_type, operand_type, span)?;
+
+        self.emit_binary(binary.operator, kind, operand_type, left, right, span)
     }
 
     fn visit_unary(&mut self, unary: &Unary) -> Self::Output {
-        todo!()
+        let span = &unary.span;
+
+        if self.optimization >= OptimizationLevel::O1
+            && let Some(value) = optimizer::constant_folding::fold_unary(unary)
+        {
+            return self.load_value(&value, span);
+        }
+        let kind = Self::expression_type(&unary.right, span)?;
+
+        match unary.operator {
+            UnaryOp::Negate => {
+                // negation is compiled as `0 - value`
+                let zero = self.allocate_temp(KirinType::Int, span)?;
+                self.emit(OpCode::LoadInt16, &[zero as Instruction, 0], span);
+                let zero = self.convert(zero, KirinType::Int, kind, span)?;
+
+                let right = self.evaluate(&unary.right)?;
+
+                let Some(opcode) = Self::arithmetic_opcode(BinaryOp::Subtract, kind) else {
+                    return Err(compile_error(
+                        span,
+                        format!("cannot negate value of type `{}`", kind),
+                    ));
+                };
+
+                let destination = self.allocate_temp(kind, span)?;
+                self.emit(
+                    opcode,
+                    &[
+                        destination as Instruction,
+                        zero as Instruction,
+                        right as Instruction,
+                    ],
+                    span,
+                );
+
+                Ok(destination)
+            }
+            UnaryOp::Not => {
+                let right = self.evaluate(&unary.right)?;
+                let right = self.convert(right, kind, KirinType::Bool, span)?;
+
+                let destination = self.allocate_temp(KirinType::Bool, span)?;
+                self.emit(
+                    OpCode::Not,
+                    &[destination as Instruction, right as Instruction],
+                    span,
+                );
+
+                Ok(destination)
+            }
+        }
     }
 
     fn visit_grouping(&mut self, grouping: &Grouping) -> Self::Output {
-        todo!()
+        self.evaluate(&grouping.expression)
     }
 
     fn visit_literal(&mut self, literal: &Literal) -> Self::Output {
-        todo!()
+        self.load_value(&literal.value, &literal.span)
     }
 
     fn visit_call(&mut self, callable: &Call) -> Self::Output {
-        todo!()
+        let span = &callable.span;
+
+        let Expression::Variable(callee) = &callable.callee else {
+            return Err(compile_error(
+                span,
+                "only library functions can be called".to_string(),
+            ));
+        };
+
+        if callee.name == "print" {
+            return self.print(callable);
+        }
+
+        let Some(opcode) = Self::library_opcode(&callee.name) else {
+            return Err(compile_error(
+                span,
+                format!("undefined function `{}`", callee.name),
+            ));
+        };
+
+        let mut operands = Vec::with_capacity(callable.arguments.len());
+        for argument in &callable.arguments {
+            let argument_type = Self::expression_type(argument, span)?;
+            let operand = self.evaluate(argument)?;
+            operands.push(self.convert(operand, argument_type, KirinType::Int, span)?);
+        }
+
+        let [first, second] = operands[..] else {
+            return Err(compile_error(
+                span,
+                format!("`{}` expects 2 arguments", callee.name),
+            ));
+        };
+
+        let destination = self.allocate_temp(KirinType::Int, span)?;
+        self.emit(
+            opcode,
+            &[
+                destination as Instruction,
+                first as Instruction,
+                second as Instruction,
+            ],
+            span,
+        );
+
+        Ok(destination)
     }
 
     fn visit_variable(&mut self, variable: &Variable) -> Self::Output {
-        todo!()
+        self.lookup(&variable.name).ok_or_else(|| {
+            compile_error(
+                &variable.span,
+                format!("undefined variable `{}`", variable.name),
+            )
+        })
     }
 
     fn visit_assign(&mut self, assign: &Assign) -> Self::Output {
-        todo!()
+        let span = &assign.span;
+
+        let Some(destination) = self.lookup(&assign.name) else {
+            return Err(compile_error(
+                span,
+                format!("assignment to undefined variable `{}`", assign.name),
+            ));
+        };
+
+        let target = self.variable_type(destination).ok_or_else(|| {
+            compile_error(span, format!("variable `{}` has no type", assign.name))
+        })?;
+        let value_type = Self::expression_type(&assign.value, span)?;
+
+        let source = self.evaluate(&assign.value)?;
+        self.store(destination, target, source, value_type, span)?;
+
+        Ok(destination)
+    }
+
+    fn visit_error(&mut self, span: &AstSpan) -> Self::Output {
+        Err(compile_error(
+            span,
+            "cannot compile source that failed to parse".to_string(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod compiler_tests {
+    use crate::{Compiler, OptimizationLevel};
+    use errors::KirinError;
+    use instructions::{InstructionDecoder, OpCode};
+    use types::KirinType;
+    use vm::{Program, ProgramConstant, VM};
+
+    fn compile(source: &str) -> Program {
+        compile_optimized(source, OptimizationLevel::O0)
+    }
+
+    fn compile_optimized(source: &str, level: OptimizationLevel) -> Program {
+        let tokens = scanner::scan_tokens(source).unwrap();
+        let ast = parser::parse_ast(tokens, None).unwrap();
+        let analyzed_ast = analyzer::TypeChecker::new().infer_types(&ast).unwrap();
+
+        let mut compiler = Compiler::new().with_optimization(level);
+        compiler.compile(&analyzed_ast).unwrap();
+        compiler.emit_program()
+    }
+
+    fn run_with_output(program: Program) -> Result<String, KirinError> {
+        let mut vm = VM::new();
+        vm.capture_output();
+        vm.load_program(program)?;
+        vm.start_with_offset(0)?;
+
+        Ok(vm.take_output())
+    }
+
+    fn run(program: Program) -> Result<(), KirinError> {
+        let mut vm = VM::new();
+        vm.load_program(program)?;
+        vm.start_with_offset(0)
+    }
+
+    fn contains_opcode(program: &Program, opcode: OpCode) -> bool {
+        program
+            .instructions
+            .iter()
+            .any(|&instruction| InstructionDecoder::decode_opcode(instruction) == opcode as u8)
+    }
+
+    #[test]
+    fn test_compile_arithmetic() {
+        let program = compile("first := 40 - 9\nsecond := first * 300\nthird := first / 2.5\n");
+
+        assert!(contains_opcode(&program, OpCode::MulInt));
+        assert!(contains_opcode(&program, OpCode::DivFloat));
+        assert!(run(program).is_ok());
+    }
+
+    #[test]
+    fn test_compile_any_arithmetic() {
+        let program = compile("let a: any = 10\na = a * 2.5\nlet b: float = a\n");
+
+        assert!(contains_opcode(&program, OpCode::IntToAny));
+        assert!(contains_opcode(&program, OpCode::MulAny));
+        assert!(contains_opcode(&program, OpCode::AnyToFloat));
+        assert!(run(program).is_ok());
+    }
+
+    #[test]
+    fn test_compile_bool_operations() {
+        let source = "a := 3 > 2.5\nb := !a or 1 != 1\nlet c: any = b\nlet d: bool = c and true\n";
+        let program = compile(source);
+
+        assert!(contains_opcode(&program, OpCode::LoadBool));
+        assert!(contains_opcode(&program, OpCode::LessFloat));
+        assert!(contains_opcode(&program, OpCode::BoolToAny));
+        assert!(contains_opcode(&program, OpCode::AnyToBool));
+        assert!(run(program).is_ok());
+    }
+
+    #[test]
+    fn test_integer_overflow_reports_location() {
+        let program = compile("a := 9223372036854775807\n\nb := a + 1\n");
+
+        match run(program) {
+            Err(KirinError::Runtime(error)) => {
+                assert_eq!(error.line, 3);
+                assert_eq!(error.column, 8);
+            }
+            result => panic!("expected runtime error, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_debug_info_covers_statements() {
+        let tokens = scanner::scan_tokens("a := 1\nb := a * 2\n").unwrap();
+        let ast = parser::parse_ast(tokens, Some("lines.kn".to_string())).unwrap();
+        let analyzed_ast = analyzer::TypeChecker::new().infer_types(&ast).unwrap();
+
+        let mut compiler = Compiler::new();
+        compiler.compile(&analyzed_ast).unwrap();
+        let program = compiler.emit_program();
+
+        let last = program.instructions.len() - 1;
+        assert!(program.location(0).is_none());
+        assert!(program.location(last).is_none());
+
+        let lines = (0..program.instructions.len())
+            .filter_map(|index| program.location(index))
+            .map(|location| (location.file, location.line))
+            .collect::<Vec<_>>();
+
+        assert!(lines.contains(&(Some("lines.kn"), 1)));
+        assert!(lines.contains(&(Some("lines.kn"), 2)));
+        assert!(program.debug_info.entries().len() < program.instructions.len());
+    }
+
+    #[test]
+    fn test_debug_info_records_variables() {
+        let program = compile("a := 1\nlet b: any = a\na = 2\n");
+
+        let variables = program
+            .debug_info
+            .variables()
+            .iter()
+            .map(|variable| (variable.name.as_str(), variable.register, variable.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            variables,
+            [("a", 0, KirinType::Int), ("b", 1, KirinType::Any)]
+        );
+
+        // visible once the instructions of the declaration ran
+        let b = &program.debug_info.variables()[1];
+        assert_eq!(program.debug_info.variables_at(b.instruction - 1).len(), 1);
+        assert_eq!(program.debug_info.variables_at(b.instruction).len(), 2);
+
+        let optimized = compile_optimized("a := 1\nprint(a)\n", OptimizationLevel::O2);
+        assert!(optimized.debug_info.variables().is_empty());
+    }
+
+    #[test]
+    fn test_runtime_error_location() {
+        let program = compile("a := 0\nb := 10 / a\n");
+
+        match run(program) {
+            Err(KirinError::Runtime(error)) => {
+                assert_eq!(error.line, 2);
+                assert_eq!(error.column, 9);
+            }
+            result => panic!("expected runtime error, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_division_by_zero_is_runtime_error() {
+        let program = compile("a := 0\nb := 10 % a\n");
+
+        assert!(matches!(run(program), Err(KirinError::Runtime(_))));
+    }
+
+    #[test]
+    fn test_wrapping_library_functions() {
+        let program = compile(
+            "a := 9223372036854775807\nb := wrapping_add(a, 1)\nc := wrapping_pow(2, 64)\n",
+        );
+
+        assert!(contains_opcode(&program, OpCode::WrappingAddInt));
+        assert!(run(program).is_ok());
+    }
+
+    #[test]
+    fn test_failed_downcast_is_runtime_error() {
+        let program = compile("let a: any = \"text\"\nlet b: int = a\n");
+
+        assert!(run(program).is_err());
+    }
+
+    #[test]
+    fn test_compile_wide_operands() {
+        // more variables than 8 bit registers and an int beyond 16 bits
+        let mut source = (0..300)
+            .map(|index| format!("v{} := {}\n", index, index))
+            .collect::<String>();
+        source.push_str("total := v299 + 100000\n");
+
+        let program = compile(&source);
+        assert!(contains_opcode(&program, OpCode::Extend));
+        assert!(run(program).is_ok());
+
+        // a constant index beyond 16 bits
+        let tokens = scanner::scan_tokens("x := 2.5\ny := x * 2\n").unwrap();
+        let ast = parser::parse_ast(tokens, None).unwrap();
+        let analyzed_ast = analyzer::TypeChecker::new().infer_types(&ast).unwrap();
+
+        let mut compiler = Compiler::new();
+        compiler.constants = vec![ProgramConstant::Int32(0); 70_000];
+        compiler.compile(&analyzed_ast).unwrap();
+        let program = compiler.emit_program();
+
+        assert!(program.disassemble(None).contains("LOAD_CONST r1, #70000"));
+        assert!(run(program).is_ok());
+    }
+
+    #[test]
+    fn test_disassemble_program() {
+        let source = "x := 2.5\ny := x * x\n";
+        let program = compile(source);
+
+        let disassembly = program.disassemble(Some(source));
+
+        assert!(disassembly.starts_with("0000  ALLOC_REG 3\n; 1 | x := 2.5\n"));
+        assert!(disassembly.contains("LOAD_CONST r1, #0         ; 2.5\n0002  MOVE r0, r1\n"));
+        assert!(disassembly.contains("; 2 | y := x * x\n0003  MUL_FLOAT r2, r0, r0\n"));
+        assert!(disassembly.ends_with("0006  RETURN\n0007  HALT\n"));
+    }
+
+    #[test]
+    fn test_compile_print() {
+        let program = compile("a := 2\nprint(a * 1.5)\nprint(\"text\")\nprint(a > 1)\n");
+
+        assert_eq!(run_with_output(program).unwrap(), "3\ntext\ntrue\n");
+    }
+
+    #[test]
+    fn test_uninitialized_variables() {
+        let source = "x := \"hello\"\nprint(x)\nlet s: string\nprint(s)\nlet f: float\nprint(f)\n";
+
+        for level in [OptimizationLevel::O0, OptimizationLevel::O2] {
+            let program = compile_optimized(source, level);
+            assert_eq!(run_with_output(program).unwrap(), "hello\n\n0\n");
+        }
+
+        // the first constant is not a string
+        let program = compile("let s: string\nprint(2.5)\nprint(s)\n");
+        assert_eq!(run_with_output(program).unwrap(), "2.5\n\n");
+    }
+
+    #[test]
+    fn test_chunks_share_variables() {
+        let mut checker = analyzer::TypeChecker::new();
+        let mut compiler = Compiler::new();
+        let mut vm = VM::new();
+        vm.capture_output();
+
+        for source in [
+            "a := 2\nb := \"text\"\n",
+            "print(a * 21)\nprint(b)\nprint(\"more\")\n",
+        ] {
+            let tokens = scanner::scan_tokens(source).unwrap();
+            let ast = parser::parse_ast(tokens, None).unwrap();
+            compiler
+                .compile(&checker.infer_types(&ast).unwrap())
+                .unwrap();
+
+            vm.resume(compiler.emit_chunk()).unwrap();
+        }
+
+        assert_eq!(vm.take_output(), "42\ntext\nmore\n");
+    }
+
+    #[test]
+    fn test_optimization_levels_print_the_same_output() {
+        let sources = [
+            "a := 2 + 3 * 4\nb := a - 1\nprint(b)\nprint(-(2 ^ 3) + a)\n",
+            "let a: any = 10\na = a * 2.5\nlet b: float = a\nprint(b / 4)\nprint(a)\n",
+            "a := 3 > 2.5\nb := !a or 1 != 1\nlet c: any = b\nprint(c)\nprint(c and true)\n",
+            "x := 9223372036854775807\nprint(wrapping_add(x, 1))\nprint(7 % 3 + 0.5)\n",
+            "let a: any = none\nprint(a)\na = \"text\"\nprint(a)\n",
+        ];
+
+        for source in sources {
+            let expected = run_with_output(compile(source)).unwrap();
+
+            for level in [OptimizationLevel::O1, OptimizationLevel::O2] {
+                let output = run_with_output(compile_optimized(source, level)).unwrap();
+                assert_eq!(
+                    output, expected,
+                    "{:?} changed the output of {:?}",
+                    level, source
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_control_flow() {
+        let sources = [
+            (
+                "a := 0\nwhile a < 3\n  a = a + 1\n  print(a)\nend\n",
+                "1\n2\n3\n",
+            ),
+            (
+                "a := 1\nif a == 1\n  a := \"inner\"\n  print(a)\nelse\n  print(0)\nend\nprint(a)\n",
+                "inner\n1\n",
+            ),
+            (
+                "let c: any = true\nwhile c\n  c = false\n  print(1)\nend\nif c\n  print(2)\nend\n",
+                "1\n",
+            ),
+            (
+                "a := 0\nwhile a < 10\n  a = a + 1\n  if a == 3\n    print(a)\n    return\n  end\nend\nprint(a)\n",
+                "3\n",
+            ),
+        ];
+
+        for (source, expected) in sources {
+            for level in [
+                OptimizationLevel::O0,
+                OptimizationLevel::O1,
+                OptimizationLevel::O2,
+            ] {
+                let output = run_with_output(compile_optimized(source, level)).unwrap();
+                assert_eq!(output, expected, "{:?} of {:?}", level, source);
+            }
+        }
+
+        let program = compile("a := true\nif a\n  print(1)\nend\n");
+        assert!(contains_opcode(&program, OpCode::JumpIfFalse));
+    }
+
+    #[test]
+    fn test_optimization_shrinks_programs() {
+        let source = "a := 2 + 3 * 4\nb := a - 1\nc := b + 2\nprint(c)\nd := a + b\nprint(d)\n";
+
+        let unoptimized = compile(source);
+        let folded = compile_optimized(source, OptimizationLevel::O1);
+        let allocated = compile_optimized(source, OptimizationLevel::O2);
+
+        assert!(!contains_opcode(&folded, OpCode::MulInt));
+        assert!(folded.instructions.len() < unoptimized.instructions.len());
+        assert!(allocated.instructions.len() <= folded.instructions.len());
+
+        let register_count = |program: &Program| program.instructions[0] & 0xffff;
+        assert!(register_count(&allocated) < register_count(&folded));
+        assert!(register_count(&folded) <= register_count(&unoptimized));
+    }
+
+    #[test]
+    fn test_optimization_keeps_runtime_errors() {
+        let source = "a := 0\nprint(a)\nb := 10 / a\nc := 10 % 0\n";
+
+        for level in [
+            OptimizationLevel::O0,
+            OptimizationLevel::O1,
+            OptimizationLevel::O2,
+        ] {
+            match run(compile_optimized(source, level)) {
+                Err(KirinError::Runtime(error)) => assert_eq!(error.line, 3),
+                result => panic!("expected runtime error at {:?}, got {:?}", level, result),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod differential_tests {
+    use crate::{Compiler, OptimizationLevel};
+    use errors::KirinError;
+    use interpreter::Interpreter;
+    use std::path::PathBuf;
+    use vm::VM;
+
+    /// The printed output and the runtime error as `(code, message, line, column)`
+    type Outcome = (String, Option<(&'static str, String, usize, usize)>);
+
+    fn outcome(output: String, result: Result<(), KirinError>) -> Outcome {
+        match result {
+            Ok(()) => (output, None),
+            Err(KirinError::Runtime(error)) => {
+                // the VM appends a stack trace to errors raised in calls
+                let message = error.message.lines().next().unwrap_or_default();
+                let code = error.code.unwrap_or_default();
+                (
+                    output,
+                    Some((code, message.to_string(), error.line, error.column)),
+                )
+            }
+            Err(error) => panic!("unexpected error: {:?}", error),
+        }
+    }
+
+    fn test_programs() -> Vec<PathBuf> {
+        let directory = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../test-code");
+        let mut paths = std::fs::read_dir(directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "kn"))
+            .collect::<Vec<_>>();
+        paths.sort();
+
+        paths
+    }
+
+    #[test]
+    fn test_interpreter_matches_vm() {
+        let paths = test_programs();
+        assert!(!paths.is_empty());
+
+        for path in paths {
+            let source = std::fs::read_to_string(&path).unwrap();
+            let tokens = scanner::scan_tokens(&source).unwrap();
+            let ast = parser::parse_ast(tokens, None).unwrap();
+            let analyzed_ast = analyzer::TypeChecker::new().infer_types(&ast).unwrap();
+
+            let mut interpreter = Interpreter::new();
+            interpreter.capture_output();
+            let result = interpreter.interpret(&analyzed_ast);
+            let expected = outcome(interpreter.take_output(), result);
+
+            for level in [OptimizationLevel::O0, OptimizationLevel::O2] {
+                let mut compiler = Compiler::new().with_optimization(level);
+                compiler.compile(&analyzed_ast).unwrap();
+
+                let mut vm = VM::new();
+                vm.capture_output();
+                let result = vm
+                    .load_program(compiler.emit_program())
+                    .and_then(|_| vm.start_with_offset(0));
+
+                assert_eq!(
+                    outcome(vm.take_output(), result),
+                    expected,
+                    "{} at {:?}",
+                    path.display(),
+                    level
+                );
+            }
+        }
     }
 }
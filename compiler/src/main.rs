@@ -1,31 +1,305 @@
-use compiler::Compiler;
+mod fmt;
+mod highlight;
+mod repl;
+
+use analyzer::TypeChecker;
+use analyzer::lints::Lint;
+use compiler::{Compiler, OptimizationLevel, ir};
+use errors::{ErrorFormat, KirinError, Warning, use_color};
+use interpreter::Interpreter;
+use parser::statements::Statement;
+use std::fs::File;
+use std::io::BufWriter;
+use vm::{Debugger, Prompt, VM};
+
+/// How the compiled program is executed, if at all
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Backend {
+    /// only write or print the program
+    None,
+    /// run the compiled bytecode
+    Vm,
+    /// walk the analyzed AST without compiling it
+    Interpreter,
+    /// run the compiled bytecode under the debugger prompt
+    Debug,
+}
 
 fn main() {
     let args = std::env::args().collect::<Vec<String>>();
 
     if args.len() < 2 {
-        println!("Usage: cargo run --bin compiler -- <file.kn>");
+        repl::run();
         return;
     }
 
-    compile_file(args[1].as_str());
+    if args[1] == "fmt" {
+        std::process::exit(fmt::run(&args[2..]));
+    }
+
+    if args[1] == "highlight" {
+        std::process::exit(highlight::run(&args[2..]));
+    }
+
+    if args[1] == "--help" {
+        println!(
+            "Usage: cargo run --bin compiler -- [<file.kn> [-o <out.knc>] [-O0|-O1|-O2] [--emit-ir] [--disassemble] [--run [--trace] [--profile[=<interval>]] [--folded=<file>]|--interpret|--debug] [--error-format=human|json] [--allow=<lint>]...]\n\
+             Without a file an interactive REPL is started, `fmt` formats files and\n\
+             `highlight` prints them highlighted"
+        );
+        return;
+    }
+
+    let output = match args.iter().position(|arg| arg == "-o") {
+        Some(index) => match args.get(index + 1) {
+            Some(output) => Some(output.as_str()),
+            None => {
+                println!("Missing output path after -o");
+                return;
+            }
+        },
+        None => None,
+    };
+
+    let disassemble = args.iter().any(|arg| arg == "--disassemble");
+    let emit_ir = args.iter().any(|arg| arg == "--emit-ir");
+
+    let backend = args
+        .iter()
+        .rev()
+        .find_map(|arg| match arg.as_str() {
+            "--run" => Some(Backend::Vm),
+            "--interpret" => Some(Backend::Interpreter),
+            "--debug" => Some(Backend::Debug),
+            _ => None,
+        })
+        .unwrap_or(Backend::None);
+
+    let optimization = args
+        .iter()
+        .rev()
+        .find_map(|arg| OptimizationLevel::from_flag(arg))
+        .unwrap_or_default();
+
+    let error_format = args
+        .iter()
+        .rev()
+        .find_map(|arg| ErrorFormat::from_flag(arg))
+        .unwrap_or_default();
+
+    let mut allowed = Vec::new();
+    for name in args.iter().filter_map(|arg| arg.strip_prefix("--allow=")) {
+        match Lint::from_name(name) {
+            Some(lint) => allowed.push(lint),
+            None => report(
+                vec![KirinError::General(format!("unknown lint `{}`", name))],
+                &args[1],
+                "",
+                error_format,
+            ),
+        }
+    }
+
+    let folded = args.iter().find_map(|arg| arg.strip_prefix("--folded="));
+    let profile = args
+        .iter()
+        .find_map(|arg| match arg.as_str() {
+            "--profile" => Some(1),
+            arg => arg.strip_prefix("--profile=")?.parse::<usize>().ok(),
+        })
+        .or(folded.map(|_| 1));
+
+    compile_file(
+        args[1].as_str(),
+        &Options {
+            output,
+            optimization,
+            backend,
+            emit_ir,
+            disassemble,
+            error_format,
+            allowed,
+            trace: args.iter().any(|arg| arg == "--trace"),
+            profile,
+            folded,
+        },
+    );
+}
+
+/// What the driver does with a file, set by the command line flags
+struct Options<'a> {
+    output: Option<&'a str>,
+    optimization: OptimizationLevel,
+    backend: Backend,
+    emit_ir: bool,
+    disassemble: bool,
+    error_format: ErrorFormat,
+    /// lints allowed in addition to the `#!allow` lines of the file
+    allowed: Vec<Lint>,
+    /// write the executed instructions to stderr when running
+    trace: bool,
+    /// instructions per profiler sample when running
+    profile: Option<usize>,
+    /// where to write the folded call stacks of the profile
+    folded: Option<&'a str>,
 }
 
-fn compile_file(path: &str) {
-    let source = std::fs::read_to_string(path).unwrap();
-    let tokens = scanner::scan_tokens(source.as_str()).unwrap();
-    let ast = parser::parse_ast(tokens, Some(path.to_string())).unwrap();
-    let analyzed_ast = analyzer::TypeChecker::new().infer_types(&ast).unwrap();
+fn compile_file(path: &str, options: &Options) {
+    let &Options {
+        output,
+        optimization,
+        backend,
+        emit_ir,
+        disassemble,
+        error_format,
+        ..
+    } = options;
 
-    let mut compiler = Compiler::new();
-    let result = compiler.compile(&analyzed_ast);
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(error) => report(
+            vec![KirinError::General(format!(
+                "failed to read {}: {}",
+                path, error
+            ))],
+            path,
+            "",
+            error_format,
+        ),
+    };
 
-    if result.is_err() {
-        println!("Compilation failed: {:?}", result.err().unwrap());
+    let mut checker = TypeChecker::new();
+    options.allowed.iter().for_each(|&lint| checker.allow(lint));
+    checker.allow_pragmas(&source);
+
+    let result = analyze(&mut checker, path, &source);
+    warn(checker.warnings(), path, &source, error_format);
+
+    let analyzed_ast = match result {
+        Ok(analyzed_ast) => analyzed_ast,
+        Err(errors) => report(errors, path, &source, error_format),
+    };
+
+    if backend == Backend::Interpreter {
+        if let Err(error) = Interpreter::new().interpret(&analyzed_ast) {
+            report(vec![error], path, &source, error_format);
+        }
         return;
     }
 
+    if emit_ir {
+        match ir::build(&analyzed_ast) {
+            Ok(program) => print!("{}", program),
+            Err(error) => report(vec![error], path, &source, error_format),
+        }
+    }
+
+    let mut compiler = Compiler::new().with_optimization(optimization);
+    if let Err(error) = compiler.compile(&analyzed_ast) {
+        report(vec![error], path, &source, error_format);
+    }
+
     let program = compiler.emit_program();
 
-    println!("Program: {:?}", program);
+    if disassemble {
+        print!("{}", program.disassemble(Some(source.as_str())));
+    }
+
+    if backend == Backend::Vm {
+        let mut vm = VM::new();
+        if options.trace {
+            vm.trace(BufWriter::new(std::io::stderr()));
+        }
+        if let Some(interval) = options.profile {
+            vm.profile(interval);
+        }
+
+        let result = vm
+            .load_program(program.clone())
+            .and_then(|_| vm.start_with_offset(0));
+
+        // also for failed runs, which stop at the error
+        if let Some(profile) = vm.take_profile() {
+            eprint!("{}", profile.report(Some(&source)));
+
+            if let Some(folded) = options.folded
+                && let Err(error) = std::fs::write(folded, profile.folded())
+            {
+                eprintln!("Failed to write {}: {}", folded, error);
+            }
+        }
+
+        if let Err(error) = result {
+            report(vec![error], path, &source, error_format);
+        }
+    }
+
+    if backend == Backend::Debug {
+        match Debugger::new(program.clone()) {
+            Ok(debugger) => Prompt::new(debugger, Some(source.clone())).run(),
+            Err(error) => report(vec![error], path, &source, error_format),
+        }
+    }
+
+    let Some(output) = output else {
+        if !disassemble && !emit_ir && backend == Backend::None {
+            println!("Program: {:?}", program);
+        }
+        return;
+    };
+
+    let mut file = match File::create(output) {
+        Ok(file) => file,
+        Err(error) => {
+            println!("Failed to create {}: {}", output, error);
+            return;
+        }
+    };
+
+    if let Err(error) = program.write_to(&mut file) {
+        println!("{}", error);
+    }
+}
+
+/// Parse and type check the source. Statements that fail to parse do not
+/// stop the type checker, so the errors of both stages are reported at once.
+fn analyze(
+    checker: &mut TypeChecker,
+    path: &str,
+    source: &str,
+) -> Result<Vec<Statement>, Vec<KirinError>> {
+    let tokens = scanner::scan_tokens(source).map_err(|error| vec![error])?;
+    let (ast, mut errors) = parser::parse_with_recovery(tokens, Some(path.to_string()));
+
+    match checker.infer_types(&ast) {
+        Ok(analyzed_ast) if errors.is_empty() => Ok(analyzed_ast),
+        Ok(_) => Err(errors),
+        Err(type_errors) => {
+            errors.extend(type_errors);
+            Err(errors)
+        }
+    }
+}
+
+/// Show the errors with snippets of the source, or as JSON, and exit
+fn report(errors: Vec<KirinError>, path: &str, source: &str, format: ErrorFormat) -> ! {
+    let color = use_color(&std::io::stderr());
+    for error in errors {
+        eprint!(
+            "{}",
+            error.in_file(path).render_as(format, Some(source), color)
+        );
+    }
+
+    std::process::exit(1);
+}
+
+fn warn(warnings: Vec<Warning>, path: &str, source: &str, format: ErrorFormat) {
+    let color = use_color(&std::io::stderr());
+    for warning in warnings {
+        eprint!(
+            "{}",
+            warning.in_file(path).render_as(format, Some(source), color)
+        );
+    }
 }
@@ -0,0 +1,57 @@
+mod code;
+pub(crate) mod constant_folding;
+mod copy_propagation;
+mod dead_stores;
+mod jump_threading;
+mod register_allocation;
+
+use crate::optimizer::code::Code;
+use crate::optimizer::copy_propagation::propagate_copies;
+use crate::optimizer::dead_stores::eliminate_dead_stores;
+use crate::optimizer::jump_threading::thread_jumps;
+use crate::optimizer::register_allocation::allocate_registers;
+use vm::Program;
+
+/// `O1` folds constant expressions and removes redundant jumps, moves and
+/// stores, `O2` additionally reassigns registers to shrink the frame
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OptimizationLevel {
+    #[default]
+    O0,
+    O1,
+    O2,
+}
+
+impl OptimizationLevel {
+    /// Parse a `-O0`, `-O1` or `-O2` command line flag
+    pub fn from_flag(flag: &str) -> Option<Self> {
+        match flag {
+            "-O0" => Some(Self::O0),
+            "-O1" => Some(Self::O1),
+            "-O2" => Some(Self::O2),
+
+            _ => None,
+        }
+    }
+}
+
+/// Run the bytecode passes of `level`. Programs the passes cannot decode are
+/// returned unchanged.
+pub(crate) fn optimize(program: Program, level: OptimizationLevel) -> Program {
+    if level == OptimizationLevel::O0 {
+        return program;
+    }
+
+    let Some(mut code) = Code::decode(&program) else {
+        return program;
+    };
+
+    thread_jumps(&mut code);
+    while propagate_copies(&mut code) | eliminate_dead_stores(&mut code) {}
+
+    if level >= OptimizationLevel::O2 {
+        allocate_registers(&mut code);
+    }
+
+    code.encode(&program)
+}
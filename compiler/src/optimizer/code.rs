@@ -0,0 +1,401 @@
+use instructions::{
+    Instruction, InstructionBuilder, InstructionDecoder, OpCode, OperandKind, OperandSlot,
+};
+use std::collections::HashSet;
+use vm::{DebugInfo, Program};
+
+/// A decoded instruction. Operands are widened by their `Extend` prefix and
+/// addresses are indices into `Code::ops`.
+#[derive(Debug, Clone)]
+pub(crate) struct Op {
+    pub(crate) opcode: OpCode,
+    /// values in the order of the opcode's operand layout
+    pub(crate) operands: Vec<Instruction>,
+    /// index of the instruction in the program, locates its debug info
+    pub(crate) origin: usize,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Access {
+    Read,
+    Write,
+    /// conversions that replace the value of their register
+    ReadWrite,
+}
+
+/// Registers referenced by one operand of an instruction
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct RegisterOperand {
+    /// position of the operand in `Op::operands`
+    pub(crate) index: usize,
+    pub(crate) register: Instruction,
+    /// `2` for the register pairs of Any values
+    pub(crate) width: Instruction,
+    pub(crate) access: Access,
+}
+
+impl RegisterOperand {
+    pub(crate) fn registers(&self) -> impl Iterator<Item = Instruction> + use<> {
+        self.register..self.register + self.width
+    }
+
+    pub(crate) fn reads(&self) -> bool {
+        self.access != Access::Write
+    }
+
+    pub(crate) fn writes(&self) -> bool {
+        self.access != Access::Read
+    }
+}
+
+impl Op {
+    pub(crate) fn register_operands(&self) -> impl Iterator<Item = RegisterOperand> + '_ {
+        self.opcode
+            .operands()
+            .iter()
+            .zip(&self.operands)
+            .enumerate()
+            .filter_map(|(index, (operand, &register))| {
+                let width = match operand.kind {
+                    OperandKind::Register => 1,
+                    OperandKind::RegisterPair => 2,
+                    _ => return None,
+                };
+
+                let access = match (operand.slot, self.opcode) {
+                    // the condition of a jump
+                    (OperandSlot::Destination, OpCode::JumpIfFalse) => Access::Read,
+                    (OperandSlot::Destination, _) => Access::Write,
+                    (_, OpCode::IntToFloat | OpCode::FloatToInt) => Access::ReadWrite,
+                    _ => Access::Read,
+                };
+
+                Some(RegisterOperand {
+                    index,
+                    register,
+                    width,
+                    access,
+                })
+            })
+    }
+
+    pub(crate) fn reads(&self) -> impl Iterator<Item = Instruction> + '_ {
+        self.register_operands()
+            .filter(RegisterOperand::reads)
+            .flat_map(|operand| operand.registers())
+    }
+
+    pub(crate) fn writes(&self) -> impl Iterator<Item = Instruction> + '_ {
+        self.register_operands()
+            .filter(RegisterOperand::writes)
+            .flat_map(|operand| operand.registers())
+    }
+
+    /// Whether a register is written before all sources have been read.
+    /// Handlers writing register pairs store the type tag first, so their
+    /// destination must not overlap a source.
+    pub(crate) fn overlaps_destination(&self) -> bool {
+        self.register_operands()
+            .filter(|operand| operand.access == Access::Write && operand.width > 1)
+            .flat_map(|operand| operand.registers())
+            .any(|register| {
+                self.register_operands()
+                    .filter(|operand| operand.access == Access::Read)
+                    .any(|operand| operand.registers().any(|read| read == register))
+            })
+    }
+
+    /// Index of the instruction this one may jump or call to
+    pub(crate) fn target(&self) -> Option<usize> {
+        self.opcode
+            .operands()
+            .iter()
+            .zip(&self.operands)
+            .find(|(operand, _)| operand.kind == OperandKind::Address)
+            .map(|(_, &target)| target as usize)
+    }
+
+    pub(crate) fn set_target(&mut self, target: usize) {
+        if let Some(position) = self
+            .opcode
+            .operands()
+            .iter()
+            .position(|operand| operand.kind == OperandKind::Address)
+        {
+            self.operands[position] = target as Instruction;
+        }
+    }
+
+    pub(crate) fn falls_through(&self) -> bool {
+        !matches!(self.opcode, OpCode::Jump | OpCode::Return | OpCode::Halt)
+    }
+
+    /// Whether the instruction only writes its destination and cannot fail,
+    /// so it can be removed when the destination is not read afterwards
+    pub(crate) fn is_pure(&self) -> bool {
+        matches!(
+            self.opcode,
+            OpCode::None
+                | OpCode::LoadConst
+                | OpCode::LoadInt16
+                | OpCode::LoadNull
+                | OpCode::LoadBool
+                | OpCode::AddFloat
+                | OpCode::SubFloat
+                | OpCode::MulFloat
+                | OpCode::DivFloat
+                | OpCode::ModFloat
+                | OpCode::PowFloat
+                | OpCode::WrappingAddInt
+                | OpCode::WrappingSubInt
+                | OpCode::WrappingMulInt
+                | OpCode::EqualInt
+                | OpCode::EqualFloat
+                | OpCode::LessInt
+                | OpCode::LessFloat
+                | OpCode::LessEqualInt
+                | OpCode::LessEqualFloat
+                | OpCode::Not
+                | OpCode::And
+                | OpCode::Or
+                | OpCode::IntToAny
+                | OpCode::FloatToAny
+                | OpCode::StringToAny
+                | OpCode::BoolToAny
+                | OpCode::IntToFloat
+                | OpCode::FloatToInt
+                | OpCode::TypeOf
+                | OpCode::IsType
+                | OpCode::Move
+        )
+    }
+}
+
+/// The instructions of a program in a form the passes can rewrite
+#[derive(Debug, Clone)]
+pub(crate) struct Code {
+    pub(crate) ops: Vec<Op>,
+}
+
+impl Code {
+    /// Returns `None` for instruction streams the passes do not understand,
+    /// e.g. unknown opcodes or jumps into an extended instruction
+    pub(crate) fn decode(program: &Program) -> Option<Code> {
+        let mut ops = Vec::with_capacity(program.instructions.len());
+        // the op starting at each instruction index
+        let mut starts = vec![None; program.instructions.len()];
+        let mut extension = None;
+
+        for (index, &instruction) in program.instructions.iter().enumerate() {
+            let opcode = InstructionDecoder::decode_known_opcode(instruction)?;
+
+            if opcode == OpCode::Extend {
+                if extension.is_some() {
+                    return None;
+                }
+
+                extension = Some((index, instruction));
+                continue;
+            }
+
+            let (origin, prefix) = match extension.take() {
+                Some((prefix_index, prefix)) => (prefix_index, Some(prefix)),
+                None => (index, None),
+            };
+
+            let operands = opcode
+                .operands()
+                .iter()
+                .map(|operand| match operand.kind {
+                    OperandKind::Int => {
+                        InstructionDecoder::decode_extended_int(prefix, instruction) as Instruction
+                    }
+                    _ => InstructionDecoder::decode_extended_operand(
+                        prefix,
+                        instruction,
+                        operand.slot,
+                    ),
+                })
+                .collect();
+
+            starts[origin] = Some(ops.len());
+            ops.push(Op {
+                opcode,
+                operands,
+                origin,
+            });
+        }
+
+        if extension.is_some() {
+            return None;
+        }
+
+        for op in &mut ops {
+            if let Some(address) = op.target() {
+                let target = starts.get(address).copied().flatten()?;
+                op.set_target(target);
+            }
+        }
+
+        Some(Code { ops })
+    }
+
+    /// Encode the ops, keeping the constants and the debug info of `program`
+    pub(crate) fn encode(&self, program: &Program) -> Program {
+        // extended instructions take two slots, so addresses depend on the
+        // encoding of the instructions before them
+        let mut sizes = vec![1; self.ops.len()];
+
+        let (addresses, encoded) = loop {
+            let mut addresses = Vec::with_capacity(sizes.len());
+            let mut address = 0;
+            for size in &sizes {
+                addresses.push(address);
+                address += size;
+            }
+
+            let encoded = self
+                .ops
+                .iter()
+                .map(|op| {
+                    let mut operands = op.operands.clone();
+                    let mut op = op.clone();
+                    if let Some(target) = op.target() {
+                        op.set_target(addresses[target]);
+                        operands = op.operands;
+                    }
+
+                    InstructionBuilder::extended(op.opcode, &operands)
+                })
+                .collect::<Vec<_>>();
+
+            let encoded_sizes = encoded.iter().map(Vec::len).collect::<Vec<_>>();
+            if encoded_sizes == sizes {
+                break (addresses, encoded);
+            }
+
+            sizes = encoded_sizes;
+        };
+
+        // variables are not kept, the passes move values out of their
+        // registers
+        let mut debug_info = DebugInfo::new();
+        for (op, &address) in self.ops.iter().zip(&addresses) {
+            match program.location(op.origin) {
+                Some(location) => {
+                    debug_info.push(address, location.file, location.line, location.column)
+                }
+                None => debug_info.push_unknown(address),
+            }
+        }
+
+        let instructions = encoded.into_iter().flatten().collect();
+
+        Program::new(instructions, program.constants.clone()).with_debug_info(debug_info)
+    }
+
+    /// Indices of the instructions that may execute after the one at `index`.
+    /// Calls continue with the next instruction, the callee is analyzed as a
+    /// separate function.
+    pub(crate) fn successors(&self, index: usize) -> impl Iterator<Item = usize> + '_ {
+        let op = &self.ops[index];
+
+        let next = (op.falls_through() && index + 1 < self.ops.len()).then_some(index + 1);
+        let target = match op.opcode {
+            OpCode::Jump | OpCode::JumpIfFalse => op.target(),
+            _ => None,
+        };
+
+        next.into_iter().chain(target)
+    }
+
+    /// Instructions that start a basic block
+    pub(crate) fn leaders(&self) -> Vec<bool> {
+        let mut leaders = vec![false; self.ops.len()];
+
+        for (index, op) in self.ops.iter().enumerate() {
+            if let Some(target) = op.target()
+                && let Some(leader) = leaders.get_mut(target)
+            {
+                *leader = true;
+            }
+
+            let ends_block = op.target().is_some() || !op.falls_through();
+            if ends_block && let Some(leader) = leaders.get_mut(index + 1) {
+                *leader = true;
+            }
+        }
+
+        if let Some(first) = leaders.first_mut() {
+            *first = true;
+        }
+
+        leaders
+    }
+
+    /// Registers that may be read after each instruction before they are written
+    pub(crate) fn live_out(&self) -> Vec<HashSet<Instruction>> {
+        let mut live_in = vec![HashSet::new(); self.ops.len()];
+        let mut live_out = vec![HashSet::new(); self.ops.len()];
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+
+            for index in (0..self.ops.len()).rev() {
+                let out = self
+                    .successors(index)
+                    .flat_map(|successor| live_in[successor].iter().copied())
+                    .collect::<HashSet<_>>();
+
+                let op = &self.ops[index];
+                let mut input = out.clone();
+                for register in op.writes() {
+                    input.remove(&register);
+                }
+                input.extend(op.reads());
+
+                if input != live_in[index] {
+                    live_in[index] = input;
+                    changed = true;
+                }
+                live_out[index] = out;
+            }
+        }
+
+        live_out
+    }
+
+    /// Remove the marked instructions. Jumps to a removed instruction continue
+    /// with the instruction following it.
+    pub(crate) fn remove(&mut self, removed: &[bool]) -> bool {
+        if !removed.contains(&true) {
+            return false;
+        }
+
+        let mut new_indices = Vec::with_capacity(self.ops.len() + 1);
+        let mut kept = 0;
+        for &removed in removed {
+            new_indices.push(kept);
+            if !removed {
+                kept += 1;
+            }
+        }
+        new_indices.push(kept);
+
+        let ops = std::mem::take(&mut self.ops);
+        self.ops = ops
+            .into_iter()
+            .zip(removed)
+            .filter(|(_, removed)| !**removed)
+            .map(|(mut op, _)| {
+                if let Some(target) = op.target() {
+                    op.set_target(new_indices[target]);
+                }
+                op
+            })
+            .collect();
+
+        true
+    }
+}
@@ -0,0 +1,168 @@
+use parser::expressions::{Binary, BinaryOp, Expression, Unary, UnaryOp};
+use parser::value::ParsedValue;
+
+/// Evaluate a binary expression of literals at compile time. Returns `None`
+/// if an operand is not constant or the operation would fail at runtime, the
+/// emitted code then reports the error as before.
+pub(crate) fn fold_binary(binary: &Binary) -> Option<ParsedValue> {
+    let left = fold(&binary.left)?;
+    let right = fold(&binary.right)?;
+
+    let value = match (left, right) {
+        (ParsedValue::Int(left), ParsedValue::Int(right)) => {
+            fold_int(binary.operator, left, right)?
+        }
+        (ParsedValue::Bool(left), ParsedValue::Bool(right)) => {
+            fold_bool(binary.operator, left, right)?
+        }
+        (left, right) => fold_float(binary.operator, as_float(&left)?, as_float(&right)?)?,
+    };
+
+    // the analyzer decides the type of the result, e.g. for `int == bool`
+    (value.try_infer_type() == binary.inferred_type).then_some(value)
+}
+
+pub(crate) fn fold_unary(unary: &Unary) -> Option<ParsedValue> {
+    let value = match (unary.operator, fold(&unary.right)?) {
+        (UnaryOp::Negate, ParsedValue::Int(value)) => ParsedValue::Int(0i64.checked_sub(value)?),
+        // negation is `0 - value`, which keeps the sign of zero positive
+        (UnaryOp::Negate, ParsedValue::Float(value)) => ParsedValue::Float(0.0 - value),
+        (UnaryOp::Not, ParsedValue::Bool(value)) => ParsedValue::Bool(!value),
+
+        _ => return None,
+    };
+
+    (value.try_infer_type() == unary.inferred_type).then_some(value)
+}
+
+fn fold(expression: &Expression) -> Option<ParsedValue> {
+    match expression {
+        Expression::Literal(literal) => match literal.value {
+            ParsedValue::Int(_) | ParsedValue::Float(_) | ParsedValue::Bool(_) => {
+                Some(literal.value.clone())
+            }
+            _ => None,
+        },
+        Expression::Grouping(grouping) => fold(&grouping.expression),
+        Expression::Binary(binary) => fold_binary(binary),
+        Expression::Unary(unary) => fold_unary(unary),
+
+        _ => None,
+    }
+}
+
+fn as_float(value: &ParsedValue) -> Option<f64> {
+    match value {
+        ParsedValue::Int(value) => Some(*value as f64),
+        ParsedValue::Float(value) => Some(*value),
+
+        _ => None,
+    }
+}
+
+/// Mirrors the checked integer arithmetic of the VM
+fn fold_int(operator: BinaryOp, left: i64, right: i64) -> Option<ParsedValue> {
+    let value = match operator {
+        BinaryOp::Add => ParsedValue::Int(left.checked_add(right)?),
+        BinaryOp::Subtract => ParsedValue::Int(left.checked_sub(right)?),
+        BinaryOp::Multiply => ParsedValue::Int(left.checked_mul(right)?),
+        BinaryOp::Divide => ParsedValue::Int(left.checked_div(right)?),
+        BinaryOp::Modulus => ParsedValue::Int(left.checked_rem(right)?),
+        BinaryOp::Power => ParsedValue::Int(left.checked_pow(u32::try_from(right).ok()?)?),
+        BinaryOp::Equal => ParsedValue::Bool(left == right),
+        BinaryOp::NotEqual => ParsedValue::Bool(left != right),
+        BinaryOp::Greater => ParsedValue::Bool(left > right),
+        BinaryOp::GreaterEqual => ParsedValue::Bool(left >= right),
+        BinaryOp::Less => ParsedValue::Bool(left < right),
+        BinaryOp::LessEqual => ParsedValue::Bool(left <= right),
+
+        BinaryOp::And | BinaryOp::Or => return None,
+    };
+
+    Some(value)
+}
+
+fn fold_float(operator: BinaryOp, left: f64, right: f64) -> Option<ParsedValue> {
+    let value = match operator {
+        BinaryOp::Add => ParsedValue::Float(left + right),
+        BinaryOp::Subtract => ParsedValue::Float(left - right),
+        BinaryOp::Multiply => ParsedValue::Float(left * right),
+        BinaryOp::Divide => ParsedValue::Float(left / right),
+        BinaryOp::Modulus => ParsedValue::Float(left % right),
+        BinaryOp::Power => ParsedValue::Float(left.powf(right)),
+        BinaryOp::Equal => ParsedValue::Bool(left == right),
+        BinaryOp::NotEqual => ParsedValue::Bool(left != right),
+        BinaryOp::Greater => ParsedValue::Bool(left > right),
+        BinaryOp::GreaterEqual => ParsedValue::Bool(left >= right),
+        BinaryOp::Less => ParsedValue::Bool(left < right),
+        BinaryOp::LessEqual => ParsedValue::Bool(left <= right),
+
+        BinaryOp::And | BinaryOp::Or => return None,
+    };
+
+    Some(value)
+}
+
+fn fold_bool(operator: BinaryOp, left: bool, right: bool) -> Option<ParsedValue> {
+    let value = match operator {
+        BinaryOp::And => left && right,
+        BinaryOp::Or => left || right,
+        BinaryOp::Equal => left == right,
+        BinaryOp::NotEqual => left != right,
+
+        _ => return None,
+    };
+
+    Some(ParsedValue::Bool(value))
+}
+
+#[cfg(test)]
+mod constant_folding_tests {
+    use crate::optimizer::constant_folding::fold;
+    use parser::statements::Statement;
+    use parser::value::ParsedValue;
+
+    fn fold_initializer(source: &str) -> Option<ParsedValue> {
+        let tokens = scanner::scan_tokens(source).unwrap();
+        let ast = parser::parse_ast(tokens, None).unwrap();
+        let analyzed_ast = analyzer::TypeChecker::new().infer_types(&ast).unwrap();
+
+        let Some(Statement::VarDeclaration(declaration)) = analyzed_ast.last() else {
+            panic!("expected a variable declaration");
+        };
+        fold(declaration.initializer.as_ref().unwrap())
+    }
+
+    #[test]
+    fn test_fold_literal_expressions() {
+        assert_eq!(
+            fold_initializer("a := (2 + 3) * 4\n"),
+            Some(ParsedValue::Int(20))
+        );
+        assert_eq!(fold_initializer("a := 7 / 2\n"), Some(ParsedValue::Int(3)));
+        assert_eq!(
+            fold_initializer("a := 1 + 0.5\n"),
+            Some(ParsedValue::Float(1.5))
+        );
+        assert_eq!(
+            fold_initializer("a := -(2 ^ 3)\n"),
+            Some(ParsedValue::Int(-8))
+        );
+        assert_eq!(
+            fold_initializer("a := 3 > 2.5\n"),
+            Some(ParsedValue::Bool(true))
+        );
+        assert_eq!(
+            fold_initializer("a := !(1 != 1) and true\n"),
+            Some(ParsedValue::Bool(true))
+        );
+    }
+
+    #[test]
+    fn test_keep_failing_and_variable_expressions() {
+        assert_eq!(fold_initializer("a := 10 / 0\n"), None);
+        assert_eq!(fold_initializer("a := 9223372036854775807 + 1\n"), None);
+        assert_eq!(fold_initializer("a := 2 ^ -1\n"), None);
+        assert_eq!(fold_initializer("b := 2\na := b + 1\n"), None);
+    }
+}
@@ -0,0 +1,204 @@
+use crate::optimizer::code::{Access, Code};
+use instructions::{Instruction, OpCode};
+use std::collections::HashMap;
+
+/// Replace reads of registers holding a copy with the original register and
+/// let instructions write their result directly into the register it is
+/// moved to. The moves left without readers are removed by dead-store
+/// elimination.
+pub(crate) fn propagate_copies(code: &mut Code) -> bool {
+    let coalesced = coalesce_moves(code);
+    let forwarded = forward_copies(code);
+
+    coalesced || forwarded
+}
+
+/// Within each basic block, rewrite reads of `d` after `MOVE d, s` to read `s`
+/// until either register is written again
+fn forward_copies(code: &mut Code) -> bool {
+    let leaders = code.leaders();
+    let mut copies = HashMap::<Instruction, Instruction>::new();
+    let mut removed = vec![false; code.ops.len()];
+    let mut changed = false;
+
+    for (index, op) in code.ops.iter_mut().enumerate() {
+        if leaders[index] {
+            copies.clear();
+        }
+
+        let mut rewritten = op.clone();
+        for operand in op.register_operands() {
+            if operand.access != Access::Read {
+                continue;
+            }
+
+            let Some(&source) = copies.get(&operand.register) else {
+                continue;
+            };
+
+            // a pair is only replaced when both of its registers are copies
+            let copied = (1..operand.width)
+                .all(|offset| copies.get(&(operand.register + offset)) == Some(&(source + offset)));
+            if copied {
+                rewritten.operands[operand.index] = source;
+            }
+        }
+
+        if rewritten.operands != op.operands && !rewritten.overlaps_destination() {
+            *op = rewritten;
+            changed = true;
+        }
+
+        for register in op.writes().collect::<Vec<_>>() {
+            copies.retain(|&copy, &mut source| copy != register && source != register);
+        }
+
+        if op.opcode == OpCode::Move {
+            let (destination, source) = (op.operands[0], op.operands[1]);
+
+            if destination == source {
+                removed[index] = true;
+            } else {
+                copies.insert(destination, source);
+            }
+        }
+    }
+
+    code.remove(&removed) || changed
+}
+
+/// Rewrite `OP t, ...` followed by `MOVE d, t` into `OP d, ...` when `t` is
+/// not read afterwards, the register pairs of Any values move with two moves
+fn coalesce_moves(code: &mut Code) -> bool {
+    let leaders = code.leaders();
+    let live_out = code.live_out();
+    let mut removed = vec![false; code.ops.len()];
+
+    for index in 0..code.ops.len() {
+        if removed[index] {
+            continue;
+        }
+
+        let op = &code.ops[index];
+        let destinations = op
+            .register_operands()
+            .filter(|operand| operand.access != Access::Read)
+            .collect::<Vec<_>>();
+        let [destination] = destinations[..] else {
+            continue;
+        };
+        if destination.access != Access::Write {
+            continue;
+        }
+
+        let width = destination.width as usize;
+        let moves = index + 1..index + 1 + width;
+        let Some(ops) = code.ops.get(moves.clone()) else {
+            continue;
+        };
+
+        // `MOVE d + k, t + k` for every register of the destination
+        let target = ops[0].operands[0];
+        let coalescible = ops.iter().enumerate().all(|(offset, next)| {
+            let offset = offset as Instruction;
+
+            next.opcode == OpCode::Move
+                && next.operands[0] == target + offset
+                && next.operands[1] == destination.register + offset
+        }) && moves.clone().all(|index| !leaders[index])
+            && target != destination.register
+            && destination
+                .registers()
+                .all(|register| !live_out[moves.end - 1].contains(&register));
+
+        if !coalescible {
+            continue;
+        }
+
+        let mut rewritten = op.clone();
+        rewritten.operands[destination.index] = target;
+
+        let reads_target = (target..target + destination.width)
+            .any(|register| rewritten.reads().any(|read| read == register));
+        if reads_target {
+            continue;
+        }
+
+        code.ops[index] = rewritten;
+        for index in moves {
+            removed[index] = true;
+        }
+    }
+
+    code.remove(&removed)
+}
+
+#[cfg(test)]
+mod copy_propagation_tests {
+    use crate::optimizer::code::Code;
+    use crate::optimizer::copy_propagation::propagate_copies;
+    use instructions::{InstructionBuilder, OpCode};
+
+    fn optimize(source: &str) -> Vec<u32> {
+        let program = vm::assemble(source).unwrap();
+
+        let mut code = Code::decode(&program).unwrap();
+        propagate_copies(&mut code);
+        code.encode(&program).instructions
+    }
+
+    #[test]
+    fn test_forward_copies() {
+        let instructions = optimize(
+            "ALLOC_REG 4
+             LOAD_INT16 r0, 2
+             MOVE r1, r0
+             ADD_INT r2, r1, r0
+             MOVE r3, r3
+             LOAD_INT16 r0, 5
+             ADD_INT r2, r1, r0
+             DEALLOC_REG 4
+             RETURN
+             HALT",
+        );
+
+        assert_eq!(
+            instructions[2..6],
+            [
+                InstructionBuilder::move_register(1, 0),
+                InstructionBuilder::add_int(2, 0, 0),
+                InstructionBuilder::load_16bit_int(0, 5),
+                // r0 was overwritten, the copy is no longer valid
+                InstructionBuilder::add_int(2, 1, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_coalesce_moves() {
+        let instructions = optimize(
+            "ALLOC_REG 6
+             LOAD_INT16 r0, 2
+             INT_TO_ANY r2, r0
+             MOVE r4, r2
+             MOVE r5, r3
+             PRINT_ANY r4
+             DEALLOC_REG 6
+             RETURN
+             HALT",
+        );
+
+        assert_eq!(
+            instructions,
+            vec![
+                InstructionBuilder::allocate_registers(6),
+                InstructionBuilder::load_16bit_int(0, 2),
+                InstructionBuilder::cast(OpCode::IntToAny, 4, 0),
+                InstructionBuilder::print_any(4),
+                InstructionBuilder::deallocate_registers(6),
+                InstructionBuilder::simple(OpCode::Return),
+                InstructionBuilder::simple(OpCode::Halt),
+            ]
+        );
+    }
+}
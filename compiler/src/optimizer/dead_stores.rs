@@ -0,0 +1,66 @@
+use crate::optimizer::code::Code;
+
+/// Remove instructions whose results are never read. Instructions that can
+/// fail at runtime are kept so the program reports the same errors.
+pub(crate) fn eliminate_dead_stores(code: &mut Code) -> bool {
+    let mut changed = false;
+
+    // removing a store can make the stores feeding it dead
+    loop {
+        let live_out = code.live_out();
+
+        let removed = code
+            .ops
+            .iter()
+            .zip(&live_out)
+            .map(|(op, live)| op.is_pure() && op.writes().all(|register| !live.contains(&register)))
+            .collect::<Vec<_>>();
+
+        if !code.remove(&removed) {
+            return changed;
+        }
+        changed = true;
+    }
+}
+
+#[cfg(test)]
+mod dead_stores_tests {
+    use crate::optimizer::code::Code;
+    use crate::optimizer::dead_stores::eliminate_dead_stores;
+    use instructions::{InstructionBuilder, OpCode};
+
+    #[test]
+    fn test_eliminate_dead_stores() {
+        let program = vm::assemble(
+            "ALLOC_REG 4
+             LOAD_INT16 r0, 2
+             LOAD_INT16 r1, 3
+             ADD_FLOAT r2, r0, r1       ; never read
+             MUL_INT r3, r0, r1         ; may overflow
+             LOAD_BOOL r2, true
+     loop:   JUMP_IF_FALSE r2, loop
+             DEALLOC_REG 4
+             RETURN
+             HALT",
+        )
+        .unwrap();
+
+        let mut code = Code::decode(&program).unwrap();
+        assert!(eliminate_dead_stores(&mut code));
+
+        assert_eq!(
+            code.encode(&program).instructions,
+            vec![
+                InstructionBuilder::allocate_registers(4),
+                InstructionBuilder::load_16bit_int(0, 2),
+                InstructionBuilder::load_16bit_int(1, 3),
+                InstructionBuilder::binary_operation(OpCode::MulInt, 3, 0, 1),
+                InstructionBuilder::load_bool(2, true),
+                InstructionBuilder::jump_if_false(2, 5),
+                InstructionBuilder::deallocate_registers(4),
+                InstructionBuilder::simple(OpCode::Return),
+                InstructionBuilder::simple(OpCode::Halt),
+            ]
+        );
+    }
+}
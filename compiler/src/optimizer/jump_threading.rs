@@ -0,0 +1,137 @@
+use crate::optimizer::code::Code;
+use instructions::OpCode;
+
+/// Point jumps to the end of the chain of unconditional jumps they lead to
+/// and remove jumps to the instruction that follows them anyway
+pub(crate) fn thread_jumps(code: &mut Code) -> bool {
+    let mut changed = false;
+
+    for index in 0..code.ops.len() {
+        let op = &code.ops[index];
+        if !matches!(op.opcode, OpCode::Jump | OpCode::JumpIfFalse) {
+            continue;
+        }
+
+        let Some(target) = op.target() else {
+            continue;
+        };
+
+        let destination = final_destination(code, target);
+        if destination != target {
+            code.ops[index].set_target(destination);
+            changed = true;
+        }
+    }
+
+    // removing a jump can make the jumps before it target their successor
+    loop {
+        let removed = code
+            .ops
+            .iter()
+            .enumerate()
+            .map(|(index, op)| {
+                matches!(op.opcode, OpCode::Jump | OpCode::JumpIfFalse)
+                    && op.target() == Some(index + 1)
+            })
+            .collect::<Vec<_>>();
+
+        if !code.remove(&removed) {
+            return changed;
+        }
+        changed = true;
+    }
+}
+
+fn final_destination(code: &Code, target: usize) -> usize {
+    let mut destination = target;
+    let mut visited = vec![false; code.ops.len()];
+
+    while let Some(op) = code.ops.get(destination)
+        && op.opcode == OpCode::Jump
+        && !visited[destination]
+    {
+        visited[destination] = true;
+
+        match op.target() {
+            Some(next) => destination = next,
+            None => break,
+        }
+    }
+
+    // a cycle of jumps never reaches an instruction, keep the original target
+    if code.ops.get(destination).map(|op| op.opcode) == Some(OpCode::Jump) {
+        return target;
+    }
+
+    destination
+}
+
+#[cfg(test)]
+mod jump_threading_tests {
+    use crate::optimizer::code::Code;
+    use crate::optimizer::jump_threading::thread_jumps;
+    use instructions::{InstructionBuilder, OpCode};
+    use vm::Program;
+
+    #[test]
+    fn test_thread_jump_chains() {
+        let program = vm::assemble(
+            "ALLOC_REG 1
+             LOAD_BOOL r0, false
+             JUMP_IF_FALSE r0, first
+             LOAD_BOOL r0, true
+     first:  JUMP second
+     second: JUMP end
+             LOAD_BOOL r0, true
+     end:    JUMP exit
+     exit:   DEALLOC_REG 1
+             RETURN
+             HALT",
+        )
+        .unwrap();
+
+        let mut code = Code::decode(&program).unwrap();
+        assert!(thread_jumps(&mut code));
+
+        let optimized = code.encode(&program);
+        assert_eq!(
+            optimized.instructions,
+            vec![
+                InstructionBuilder::allocate_registers(1),
+                InstructionBuilder::load_bool(0, false),
+                InstructionBuilder::jump_if_false(0, 7),
+                InstructionBuilder::load_bool(0, true),
+                InstructionBuilder::jump(7),
+                InstructionBuilder::jump(7),
+                InstructionBuilder::load_bool(0, true),
+                InstructionBuilder::deallocate_registers(1),
+                InstructionBuilder::simple(OpCode::Return),
+                InstructionBuilder::simple(OpCode::Halt),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_keep_jump_cycles() {
+        let program = Program::new(
+            vec![
+                InstructionBuilder::jump(1),
+                InstructionBuilder::jump(0),
+                InstructionBuilder::simple(OpCode::Halt),
+            ],
+            Vec::new(),
+        );
+
+        let mut code = Code::decode(&program).unwrap();
+        thread_jumps(&mut code);
+
+        // the first jump leads to the next instruction, the loop remains
+        assert_eq!(
+            code.encode(&program).instructions,
+            vec![
+                InstructionBuilder::jump(0),
+                InstructionBuilder::simple(OpCode::Halt),
+            ]
+        );
+    }
+}
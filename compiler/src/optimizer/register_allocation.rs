@@ -0,0 +1,476 @@
+use crate::optimizer::code::{Code, Op};
+use instructions::{Instruction, OpCode};
+use std::collections::{BTreeSet, HashMap};
+
+/// Assign the registers of the top level frame with a linear scan over the
+/// live intervals of their values, so values that are never live at the same
+/// time share a register and `AllocReg` reserves fewer of them.
+///
+/// Programs with calls are left unchanged, the frames of the callees would
+/// need their own allocation.
+pub(crate) fn allocate_registers(code: &mut Code) -> bool {
+    let Some(count) = frame_size(code) else {
+        return false;
+    };
+    let Some(webs) = Webs::build(code) else {
+        return false;
+    };
+
+    let intervals = webs.intervals(code);
+
+    // the first free run of registers that fits each interval in order of start
+    let mut busy_until = Vec::<Option<usize>>::new();
+    let mut bases = HashMap::new();
+
+    for interval in &intervals {
+        let is_free = |end: &Option<usize>| end.is_none_or(|end| end < interval.start);
+
+        let base = (0..)
+            .find(|&base| {
+                (base..base + interval.width)
+                    .all(|register| busy_until.get(register).is_none_or(is_free))
+            })
+            .unwrap_or(0);
+
+        if busy_until.len() < base + interval.width {
+            busy_until.resize(base + interval.width, None);
+        }
+        for end in &mut busy_until[base..base + interval.width] {
+            *end = Some(interval.end);
+        }
+
+        bases.insert(interval.unit, base as Instruction);
+    }
+
+    let allocated = busy_until.len() as Instruction;
+    if allocated > count {
+        return false;
+    }
+
+    let mut changed = allocated != count;
+    for position in 0..code.ops.len() {
+        let op = &code.ops[position];
+        if matches!(op.opcode, OpCode::AllocReg | OpCode::DeallocReg) {
+            code.ops[position].operands[0] = allocated;
+            continue;
+        }
+
+        let operands = op.register_operands().collect::<Vec<_>>();
+        for operand in operands {
+            let (unit, offset) = webs.slot(position, operand.register, operand.writes());
+            let register = bases[&unit] + offset;
+
+            changed |= register != operand.register;
+            code.ops[position].operands[operand.index] = register;
+        }
+    }
+
+    changed
+}
+
+/// The register count of a program made of a single frame, allocated by its
+/// first instruction and released once
+fn frame_size(code: &Code) -> Option<Instruction> {
+    let first = code.ops.first()?;
+    if first.opcode != OpCode::AllocReg {
+        return None;
+    }
+    let count = first.operands[0];
+
+    let mut allocations = 0;
+    let mut deallocations = 0;
+
+    for op in &code.ops {
+        match op.opcode {
+            OpCode::AllocReg => allocations += 1,
+            OpCode::DeallocReg if op.operands[0] == count => deallocations += 1,
+            OpCode::DeallocReg | OpCode::Call | OpCode::InitFrame | OpCode::DropFrame => {
+                return None;
+            }
+            _ => {}
+        }
+    }
+
+    (allocations == 1 && deallocations == 1).then_some(count)
+}
+
+/// The points during which a unit of registers is referenced or live
+#[derive(Debug, Copy, Clone)]
+struct Interval {
+    unit: usize,
+    width: usize,
+    start: usize,
+    end: usize,
+}
+
+fn writes_pair(op: &Op) -> bool {
+    op.register_operands()
+        .any(|operand| operand.writes() && operand.width > 1)
+}
+
+type ReachingDefinitions = HashMap<Instruction, BTreeSet<usize>>;
+
+/// Definitions of registers, grouped into units that are assigned registers
+/// as a whole. A read joins all definitions reaching it and the registers of
+/// a pair operand are placed next to each other.
+struct Webs {
+    /// the original register of each definition
+    registers: Vec<Instruction>,
+    /// union-find over definitions with the offset to the parent
+    parents: Vec<(usize, i64)>,
+    /// the definition made by each written register of an instruction
+    definitions: HashMap<(usize, Instruction), usize>,
+    /// definitions of each register reaching each instruction
+    reaching: Vec<ReachingDefinitions>,
+    /// the smallest offset in each unit, registers are assigned from it
+    minimum_offsets: HashMap<usize, i64>,
+}
+
+impl Webs {
+    /// Returns `None` if the placement of register pairs contradicts itself
+    /// or would merge values of different registers
+    fn build(code: &Code) -> Option<Webs> {
+        let mut webs = Webs {
+            registers: Vec::new(),
+            parents: Vec::new(),
+            definitions: HashMap::new(),
+            reaching: Vec::new(),
+            minimum_offsets: HashMap::new(),
+        };
+
+        // the zero-filled value of each register when the frame is allocated
+        let mut entry = ReachingDefinitions::new();
+        for (position, op) in code.ops.iter().enumerate() {
+            for operand in op.register_operands() {
+                for register in operand.registers() {
+                    entry
+                        .entry(register)
+                        .or_insert_with(|| BTreeSet::from([webs.define(register)]));
+
+                    if operand.writes() {
+                        let definition = webs.define(register);
+                        webs.definitions.insert((position, register), definition);
+                    }
+                }
+            }
+        }
+
+        webs.reaching = webs.reaching_definitions(code, entry);
+
+        for (position, op) in code.ops.iter().enumerate() {
+            for operand in op.register_operands() {
+                let first = webs.slot_definition(position, operand.register, operand.writes());
+
+                for (offset, register) in operand.registers().enumerate() {
+                    let definition = webs.slot_definition(position, register, operand.writes());
+                    webs.constrain(first, definition, offset as i64)?;
+
+                    // a read continues the values of all definitions reaching it
+                    if operand.reads() {
+                        let reaching = webs.reaching[position][&register].clone();
+                        for reaching in reaching {
+                            webs.constrain(definition, reaching, 0)?;
+                        }
+                    }
+                }
+            }
+        }
+
+        // definitions sharing a slot must be values of the same register
+        let mut slots = HashMap::new();
+        for definition in 0..webs.registers.len() {
+            let (unit, offset) = webs.find(definition);
+            let register = webs.registers[definition];
+            if *slots.entry((unit, offset)).or_insert(register) != register {
+                return None;
+            }
+
+            let minimum = webs.minimum_offsets.entry(unit).or_insert(offset);
+            *minimum = (*minimum).min(offset);
+        }
+
+        Some(webs)
+    }
+
+    fn define(&mut self, register: Instruction) -> usize {
+        let definition = self.registers.len();
+        self.registers.push(register);
+        self.parents.push((definition, 0));
+        definition
+    }
+
+    fn reaching_definitions(
+        &self,
+        code: &Code,
+        entry: ReachingDefinitions,
+    ) -> Vec<ReachingDefinitions> {
+        let mut predecessors = vec![Vec::new(); code.ops.len()];
+        for position in 0..code.ops.len() {
+            for successor in code.successors(position) {
+                predecessors[successor].push(position);
+            }
+        }
+
+        let mut reached = vec![false; code.ops.len()];
+        let mut pending = vec![0];
+        while let Some(position) = pending.pop() {
+            if position < reached.len() && !reached[position] {
+                reached[position] = true;
+                pending.extend(code.successors(position));
+            }
+        }
+
+        let mut reaching_in = vec![ReachingDefinitions::new(); code.ops.len()];
+        let mut reaching_out = vec![ReachingDefinitions::new(); code.ops.len()];
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+
+            for position in 0..code.ops.len() {
+                // instructions that are never reached, like the code after
+                // a `return`, see the values of the entry as well
+                let mut input = if position == 0 || !reached[position] {
+                    entry.clone()
+                } else {
+                    ReachingDefinitions::new()
+                };
+                for &predecessor in &predecessors[position] {
+                    for (&register, definitions) in &reaching_out[predecessor] {
+                        input.entry(register).or_default().extend(definitions);
+                    }
+                }
+
+                let mut output = input.clone();
+                for register in code.ops[position].writes() {
+                    let definition = self.definitions[&(position, register)];
+                    output.insert(register, BTreeSet::from([definition]));
+                }
+
+                if output != reaching_out[position] {
+                    reaching_out[position] = output;
+                    changed = true;
+                }
+                reaching_in[position] = input;
+            }
+        }
+
+        reaching_in
+    }
+
+    /// The definition standing for a register operand of an instruction
+    fn slot_definition(&self, position: usize, register: Instruction, writes: bool) -> usize {
+        if writes {
+            return self.definitions[&(position, register)];
+        }
+
+        // every register has its entry definition, so at least one reaches
+        self.reaching[position][&register]
+            .first()
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// The unit of a register operand and the offset of the register in it
+    fn slot(&self, position: usize, register: Instruction, writes: bool) -> (usize, Instruction) {
+        let (unit, offset) = self.find(self.slot_definition(position, register, writes));
+        (unit, (offset - self.minimum_offsets[&unit]) as Instruction)
+    }
+
+    fn find(&self, definition: usize) -> (usize, i64) {
+        let mut current = definition;
+        let mut offset = 0;
+
+        while self.parents[current].0 != current {
+            let (parent, parent_offset) = self.parents[current];
+            offset += parent_offset;
+            current = parent;
+        }
+
+        (current, offset)
+    }
+
+    /// Place `second` `delta` registers after `first`
+    fn constrain(&mut self, first: usize, second: usize, delta: i64) -> Option<()> {
+        let (first_unit, first_offset) = self.find(first);
+        let (second_unit, second_offset) = self.find(second);
+
+        if first_unit == second_unit {
+            return (second_offset - first_offset == delta).then_some(());
+        }
+
+        self.parents[second_unit] = (first_unit, first_offset + delta - second_offset);
+        Some(())
+    }
+
+    /// The range between the first and the last reference or live point of
+    /// each unit, sorted by start. Instruction `i` reads its sources at
+    /// point `2i` and writes its destinations at `2i + 1`, so a value read
+    /// for the last time can share a register with the result.
+    fn intervals(&self, code: &Code) -> Vec<Interval> {
+        let live_out = code.live_out();
+        let mut ranges = HashMap::<usize, (usize, usize)>::new();
+        let mut extend = |definition: usize, point: usize| {
+            let (unit, _) = self.find(definition);
+            let range = ranges.entry(unit).or_insert((point, point));
+            range.0 = range.0.min(point);
+            range.1 = range.1.max(point);
+        };
+
+        for (position, op) in code.ops.iter().enumerate() {
+            let (read, write) = (2 * position, 2 * position + 1);
+            // handlers writing a pair store the type tag before reading
+            let read = if writes_pair(op) { write } else { read };
+
+            for operand in op.register_operands() {
+                for register in operand.registers() {
+                    let definition = self.slot_definition(position, register, operand.writes());
+                    if operand.reads() {
+                        extend(definition, read);
+                    }
+                    if operand.writes() {
+                        extend(definition, write);
+                    }
+                }
+            }
+
+            // a value live after the instruction is written by it or reaches it
+            for &register in &live_out[position] {
+                match self.definitions.get(&(position, register)) {
+                    Some(&definition) => extend(definition, write),
+                    None => {
+                        for &definition in &self.reaching[position][&register] {
+                            extend(definition, write);
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut widths = HashMap::<usize, usize>::new();
+        for definition in 0..self.registers.len() {
+            let (unit, offset) = self.find(definition);
+            let width = (offset - self.minimum_offsets[&unit]) as usize + 1;
+            let entry = widths.entry(unit).or_insert(width);
+            *entry = (*entry).max(width);
+        }
+
+        let mut intervals = ranges
+            .into_iter()
+            .map(|(unit, (start, end))| Interval {
+                unit,
+                width: widths[&unit],
+                start,
+                end,
+            })
+            .collect::<Vec<_>>();
+        intervals.sort_by_key(|interval| (interval.start, interval.unit));
+
+        intervals
+    }
+}
+
+#[cfg(test)]
+mod register_allocation_tests {
+    use crate::optimizer::code::Code;
+    use crate::optimizer::register_allocation::allocate_registers;
+    use instructions::{InstructionBuilder, OpCode};
+
+    fn allocate(source: &str) -> Option<Vec<u32>> {
+        let program = vm::assemble(source).unwrap();
+
+        let mut code = Code::decode(&program).unwrap();
+        allocate_registers(&mut code).then(|| code.encode(&program).instructions)
+    }
+
+    #[test]
+    fn test_reuse_registers_of_dead_values() {
+        let instructions = allocate(
+            "ALLOC_REG 8
+             LOAD_INT16 r0, 2
+             LOAD_INT16 r1, 3
+             ADD_INT r2, r0, r1
+             INT_TO_ANY r4, r2
+             PRINT_ANY r4
+             LOAD_INT16 r6, 4
+             INT_TO_ANY r3, r6
+             PRINT_ANY r3
+             DEALLOC_REG 8
+             RETURN
+             HALT",
+        );
+
+        assert_eq!(
+            instructions,
+            Some(vec![
+                InstructionBuilder::allocate_registers(3),
+                InstructionBuilder::load_16bit_int(0, 2),
+                InstructionBuilder::load_16bit_int(1, 3),
+                InstructionBuilder::add_int(0, 0, 1),
+                InstructionBuilder::cast(OpCode::IntToAny, 1, 0),
+                InstructionBuilder::print_any(1),
+                InstructionBuilder::load_16bit_int(0, 4),
+                InstructionBuilder::cast(OpCode::IntToAny, 1, 0),
+                InstructionBuilder::print_any(1),
+                InstructionBuilder::deallocate_registers(3),
+                InstructionBuilder::simple(OpCode::Return),
+                InstructionBuilder::simple(OpCode::Halt),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_values_live_across_loops_keep_their_register() {
+        let instructions = allocate(
+            "ALLOC_REG 4
+             LOAD_BOOL r3, true
+             LOAD_BOOL r0, false
+     loop:   LOAD_BOOL r1, true
+             JUMP_IF_FALSE r3, loop
+             DEALLOC_REG 4
+             RETURN
+             HALT",
+        )
+        .unwrap();
+
+        // r3 is read in every iteration and cannot share a register with r1
+        assert_eq!(instructions[0], InstructionBuilder::allocate_registers(2));
+        assert_eq!(instructions[1], InstructionBuilder::load_bool(0, true));
+        assert_eq!(instructions[3], InstructionBuilder::load_bool(1, true));
+        assert_eq!(instructions[4], InstructionBuilder::jump_if_false(0, 3));
+    }
+
+    #[test]
+    fn test_unreachable_instructions() {
+        let instructions = allocate(
+            "ALLOC_REG 2
+             LOAD_BOOL r1, true
+     loop:   JUMP_IF_FALSE r1, end
+             LOAD_BOOL r1, false
+             JUMP loop
+             JUMP loop
+     end:    DEALLOC_REG 2
+             RETURN
+             HALT",
+        )
+        .unwrap();
+
+        // the second jump is never reached, r1 is live after it all the same
+        assert_eq!(instructions[0], InstructionBuilder::allocate_registers(1));
+        assert_eq!(instructions[2], InstructionBuilder::jump_if_false(0, 6));
+    }
+
+    #[test]
+    fn test_skip_programs_with_calls() {
+        let instructions = allocate(
+            "ALLOC_REG 2
+             CALL function
+             DEALLOC_REG 2
+             RETURN
+             HALT
+ function:   RETURN",
+        );
+
+        assert_eq!(instructions, None);
+    }
+}
@@ -0,0 +1,427 @@
+use analyzer::TypeChecker;
+use analyzer::lints::Lint;
+use compiler::Compiler;
+use errors::{ErrorFormat, KirinError, use_color};
+use parser::expressions::{Call, Expression, Variable};
+use parser::statements::Statement;
+use scanner::TokenType;
+use std::fs::OpenOptions;
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+use vm::{Program, VM};
+
+const HELP: &str = "\
+:type <expr>   show the type of an expression
+:ast <expr>    show the analyzed syntax tree of an expression
+:dis <expr>    show the bytecode of an expression
+:reset         forget all variables
+:history       show the entered lines
+:quit          leave the REPL
+";
+
+/// The state kept between inputs: the symbols of the type checker, the
+/// variable registers of the compiler and the registers of the VM. Each
+/// input is compiled into a chunk that continues the previous ones.
+pub struct Session {
+    checker: TypeChecker,
+    compiler: Compiler,
+    vm: VM,
+    /// lines of an entry whose blocks are still waiting for their `end`
+    pending: String,
+    history: Vec<String>,
+    /// whether errors are rendered with colours
+    color: bool,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Session {
+    pub fn new() -> Self {
+        let mut vm = VM::new();
+        vm.capture_output();
+
+        // variables are declared to be used by later entries
+        let mut checker = TypeChecker::new();
+        checker.allow(Lint::UnusedVariables);
+
+        Self {
+            checker,
+            compiler: Compiler::new(),
+            vm,
+            pending: String::new(),
+            history: Vec::new(),
+            color: false,
+        }
+    }
+
+    /// Whether the entry continues on the next line
+    pub fn is_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Handle one line of input and return the text to show for it
+    pub fn input(&mut self, line: &str) -> String {
+        if !line.trim().is_empty() {
+            self.history.push(line.to_string());
+        }
+
+        if !self.is_pending()
+            && let Some(command) = line.trim().strip_prefix(':')
+        {
+            return self.command(command);
+        }
+
+        self.pending.push_str(line);
+        self.pending.push('\n');
+
+        if open_blocks(&self.pending) > 0 {
+            return String::new();
+        }
+
+        let source = std::mem::take(&mut self.pending);
+        if source.trim().is_empty() {
+            return String::new();
+        }
+
+        self.evaluate(&source)
+            .unwrap_or_else(|errors| self.format_errors(&errors, &source))
+    }
+
+    fn command(&mut self, command: &str) -> String {
+        let (name, argument) = command.split_once(' ').unwrap_or((command, ""));
+        let source = format!("{}\n", argument.trim());
+
+        let result = match name {
+            "type" => self.type_of(&source),
+            "ast" => self.ast(&source),
+            "dis" => self.disassemble(&source),
+            "reset" => {
+                let history = std::mem::take(&mut self.history);
+                let color = self.color;
+                *self = Self::new();
+                self.history = history;
+                self.color = color;
+
+                Ok("state cleared\n".to_string())
+            }
+            "history" => Ok(self
+                .history
+                .iter()
+                .enumerate()
+                .map(|(index, line)| format!("{:>4}  {}\n", index + 1, line))
+                .collect()),
+            "help" => Ok(HELP.to_string()),
+
+            _ => Ok(format!("unknown command `:{}`, try `:help`\n", name)),
+        };
+
+        result.unwrap_or_else(|errors| self.format_errors(&errors, &source))
+    }
+
+    /// Run an entry. Symbols and variables are only kept once the entry has
+    /// been compiled, runtime errors keep the variables declared before them.
+    fn evaluate(&mut self, source: &str) -> Result<String, Vec<KirinError>> {
+        let statements = parse(source)?.into_iter().map(print_expression).collect();
+
+        let mut checker = self.checker.clone();
+        checker.allow_pragmas(source);
+        checker.resume();
+        let statements = checker.infer_types(&statements)?;
+        let warnings = checker.warnings();
+
+        let mut compiler = self.compiler.clone();
+        compiler.compile(&statements).map_err(|error| vec![error])?;
+        let chunk = compiler.emit_chunk();
+
+        self.checker = checker;
+        self.compiler = compiler;
+
+        let mut output = warnings
+            .iter()
+            .map(|warning| warning.render_as(ErrorFormat::Human, Some(source), self.color))
+            .collect::<String>();
+
+        let result = self.vm.resume(chunk);
+        output.push_str(&self.vm.take_output());
+        if let Err(error) = result {
+            output.push_str(&self.format_errors(&[error], source));
+        }
+
+        Ok(output)
+    }
+
+    fn type_of(&self, source: &str) -> Result<String, Vec<KirinError>> {
+        let expression = analyze_expression(&mut self.checker.clone(), source)?;
+
+        match expression.inferred_type() {
+            Some(kind) => Ok(format!("{}\n", kind)),
+            None => Ok("void\n".to_string()),
+        }
+    }
+
+    fn ast(&self, source: &str) -> Result<String, Vec<KirinError>> {
+        let expression = analyze_expression(&mut self.checker.clone(), source)?;
+
+        Ok(format!("{:#?}\n", expression))
+    }
+
+    fn disassemble(&self, source: &str) -> Result<String, Vec<KirinError>> {
+        let expression = analyze_expression(&mut self.checker.clone(), source)?;
+
+        let mut compiler = self.compiler.clone();
+        compiler
+            .compile(&vec![Statement::ExpressionStatement(expression)])
+            .map_err(|error| vec![error])?;
+        let chunk = compiler.emit_chunk();
+
+        let program = Program::new(chunk.instructions, compiler.constants().to_vec())
+            .with_debug_info(chunk.debug_info);
+
+        Ok(program.disassemble(Some(source)))
+    }
+
+    /// Render the errors of an entry with snippets of the entry
+    fn format_errors(&self, errors: &[KirinError], source: &str) -> String {
+        errors
+            .iter()
+            .map(|error| error.render(Some(source), self.color))
+            .collect()
+    }
+}
+
+fn parse(source: &str) -> Result<Vec<Statement>, Vec<KirinError>> {
+    let tokens = scanner::scan_tokens(source).map_err(|error| vec![error])?;
+
+    parser::parse_ast(tokens, None)
+}
+
+fn analyze_expression(
+    checker: &mut TypeChecker,
+    source: &str,
+) -> Result<Expression, Vec<KirinError>> {
+    let statements = checker.infer_types(&parse(source)?)?;
+
+    match <[Statement; 1]>::try_from(statements) {
+        Ok([Statement::ExpressionStatement(expression)]) => Ok(expression),
+        _ => Err(vec![KirinError::General(
+            "expected an expression".to_string(),
+        )]),
+    }
+}
+
+/// Bare expressions print their value, assignments and calls to `print`
+/// already show what they did
+fn print_expression(statement: Statement) -> Statement {
+    let Statement::ExpressionStatement(expression) = statement else {
+        return statement;
+    };
+
+    match &expression {
+        Expression::Assign(_) => Statement::ExpressionStatement(expression),
+        Expression::Call(call) if is_print(&call.callee) => {
+            Statement::ExpressionStatement(expression)
+        }
+
+        _ => {
+            let span = expression.span().clone();
+            let callee =
+                Expression::Variable(Box::new(Variable::new("print".to_string(), span.clone())));
+
+            Statement::ExpressionStatement(Expression::Call(Box::new(Call::new(
+                callee,
+                span,
+                vec![expression],
+            ))))
+        }
+    }
+}
+
+fn is_print(callee: &Expression) -> bool {
+    matches!(callee, Expression::Variable(variable) if variable.name == "print")
+}
+
+/// The number of `fn`, `if` and `while` blocks without their `end`. Input
+/// that cannot be scanned is complete, the error is reported when it runs.
+fn open_blocks(source: &str) -> usize {
+    let Ok(tokens) = scanner::scan_tokens(source) else {
+        return 0;
+    };
+
+    let depth = tokens
+        .iter()
+        .fold(0isize, |depth, token| match token.token_type {
+            TokenType::Fn | TokenType::If | TokenType::While => depth + 1,
+            TokenType::End => depth - 1,
+            _ => depth,
+        });
+
+    depth.max(0) as usize
+}
+
+fn history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".kirin_history"))
+}
+
+/// Read lines from stdin until `:quit` or the end of input. Entered lines
+/// are appended to `~/.kirin_history` and loaded again by the next REPL.
+pub fn run() {
+    let mut session = Session::new();
+    session.color = use_color(&std::io::stdout());
+
+    let path = history_path();
+    if let Some(path) = &path
+        && let Ok(history) = std::fs::read_to_string(path)
+    {
+        session.history = history.lines().map(str::to_string).collect();
+    }
+    let mut history_file =
+        path.and_then(|path| OpenOptions::new().create(true).append(true).open(path).ok());
+
+    println!("kirin REPL, `:help` lists the commands");
+
+    let stdin = std::io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("{}", if session.is_pending() { "... " } else { "> " });
+        std::io::stdout().flush().ok();
+
+        let Some(Ok(line)) = lines.next() else {
+            println!();
+            break;
+        };
+
+        if line.trim() == ":quit" {
+            break;
+        }
+
+        if let Some(file) = &mut history_file
+            && !line.trim().is_empty()
+        {
+            writeln!(file, "{}", line).ok();
+        }
+
+        print!("{}", session.input(&line));
+    }
+}
+
+#[cfg(test)]
+mod repl_tests {
+    use super::Session;
+
+    fn run(session: &mut Session, lines: &[&str]) -> String {
+        lines.iter().map(|line| session.input(line)).collect()
+    }
+
+    #[test]
+    fn test_variables_persist_between_inputs() {
+        let mut session = Session::new();
+
+        let output = run(
+            &mut session,
+            &[
+                "a := 20",
+                "let b: any = \"text\"",
+                "a = a + 1",
+                "a * 2",
+                "b",
+            ],
+        );
+
+        assert_eq!(output, "42\ntext\n");
+    }
+
+    #[test]
+    fn test_errors_keep_the_session() {
+        let mut session = Session::new();
+
+        let output = run(
+            &mut session,
+            &["a := 1", "b := a +", "a / 0", "c := 3", "a + c"],
+        );
+
+        assert!(output.starts_with("error[E0102]: "));
+        assert!(output.contains("division by zero: `1 / 0`\n --> <input>:1:3\n"));
+        assert!(output.contains("1 | a / 0\n  |   ^\n"));
+        assert!(output.ends_with("4\n"));
+    }
+
+    #[test]
+    fn test_warnings_are_shown() {
+        let mut session = Session::new();
+
+        let output = run(&mut session, &["a := 1.5", "a = (a)", "a == 1.5", "b := 2"]);
+
+        assert!(output.contains("warning[W0006]: `a` is assigned to itself"));
+        assert!(output.contains("warning[W0005]: floats compared with `==`"));
+        assert!(output.ends_with("true\n"));
+        assert!(!output.contains("unused variable"));
+    }
+
+    #[test]
+    fn test_blocks_continue_until_end() {
+        let mut session = Session::new();
+
+        assert_eq!(session.input("while a"), "");
+        assert!(session.is_pending());
+        assert_eq!(session.input("if b"), "");
+        assert_eq!(session.input("end"), "");
+        assert!(session.is_pending());
+
+        // the block is checked once it is complete
+        assert!(session.input("end").contains("undefined variable `a`"));
+        assert!(!session.is_pending());
+    }
+
+    #[test]
+    fn test_control_flow() {
+        let mut session = Session::new();
+
+        let output = run(
+            &mut session,
+            &[
+                "a := 0",
+                "while a < 3",
+                "a = a + 1",
+                "if a == 2",
+                "return",
+                "end",
+                "end",
+                "a",
+                "if a > 1",
+                "print(\"big\")",
+                "else",
+                "print(\"small\")",
+                "end",
+            ],
+        );
+
+        // `return` ends its entry only
+        assert_eq!(output, "2\nbig\n");
+    }
+
+    #[test]
+    fn test_commands() {
+        let mut session = Session::new();
+        run(&mut session, &["a := 2", "let b: any = a"]);
+
+        assert_eq!(session.input(":type a * 1.5"), "float\n");
+        assert_eq!(session.input(":type b"), "any\n");
+        // the tree is shown with the types the analyzer inferred
+        let ast = session.input(":ast a + 1");
+        assert!(ast.contains("Binary"));
+        assert!(!ast.contains("inferred_type: None"));
+        assert!(session.input(":dis a + 1").contains("ADD_INT"));
+
+        // inspecting an assignment does not run it
+        session.input(":dis a = 5");
+        assert_eq!(session.input("a"), "2\n");
+
+        assert_eq!(session.input(":reset"), "state cleared\n");
+        assert!(session.input("a").contains("undefined"));
+        assert_eq!(session.history.len(), 10);
+    }
+}
@@ -0,0 +1,822 @@
+pub mod values;
+
+use analyzer::TypeChecker;
+use compiler::Compiler;
+use errors::{ErrorFormat, KirinError};
+use json::Json;
+use json::rpc;
+use std::io::{BufRead, Write};
+use vm::{Breakpoint, Debugger, Program, Stop};
+
+/// The only thread of a program
+const THREAD_ID: usize = 1;
+
+/// `variablesReference` of the scopes of the current frame
+const LOCALS_REFERENCE: usize = 1;
+const REGISTERS_REFERENCE: usize = 2;
+
+/// A launched program under the debugger
+struct Session {
+    debugger: Debugger,
+    path: String,
+    source: String,
+    /// numbers of the breakpoints set in the debugger for the source
+    breakpoints: Vec<usize>,
+    /// whether the `terminated` event has been sent
+    terminated: bool,
+    /// whether the program stopped at a runtime error, it exits with 1
+    failed: bool,
+}
+
+/// A debug adapter running one .kn program in the VM. The program is
+/// compiled without optimizations, so each variable keeps its register.
+#[derive(Default)]
+pub struct Adapter {
+    /// sequence number of the last message sent
+    seq: usize,
+    session: Option<Session>,
+    stop_on_entry: bool,
+    disconnected: bool,
+}
+
+/// Serve the messages of `input` until the client disconnects or closes
+/// it, returning the exit code
+pub fn run(mut input: impl BufRead, mut output: impl Write) -> std::io::Result<i32> {
+    let mut adapter = Adapter::new();
+
+    while let Some(message) = rpc::read_message(&mut input)? {
+        let replies = match message {
+            Ok(message) => adapter.handle(&message),
+            Err(error) => vec![adapter.number(event(
+                "output",
+                Json::object([
+                    ("category", Json::from("stderr")),
+                    (
+                        "output",
+                        Json::from(format!("invalid message: {}\n", error)),
+                    ),
+                ]),
+            ))],
+        };
+
+        for reply in replies {
+            rpc::write_message(&mut output, &reply)?;
+        }
+
+        if adapter.disconnected {
+            return Ok(0);
+        }
+    }
+
+    Ok(1)
+}
+
+impl Adapter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handle a request, returning its response followed by the events it
+    /// caused
+    pub fn handle(&mut self, message: &Json) -> Vec<Json> {
+        if message.get("type").and_then(Json::as_str) != Some("request") {
+            return Vec::new();
+        }
+
+        let request_seq = message.get("seq").and_then(Json::as_usize).unwrap_or(0);
+        let command = message.get("command").and_then(Json::as_str).unwrap_or("");
+        let arguments = message.get("arguments").unwrap_or(&Json::Null);
+
+        let mut events = Vec::new();
+        let result = match command {
+            "initialize" => Ok(capabilities()),
+            "launch" => self.launch(arguments, &mut events),
+            "setBreakpoints" => self.set_breakpoints(arguments),
+            "configurationDone" | "continue" | "next" | "stepIn" | "stepOut" => {
+                self.execute(command, &mut events)
+            }
+            "threads" => Ok(Json::object([(
+                "threads",
+                Json::from(vec![Json::object([
+                    ("id", Json::from(THREAD_ID)),
+                    ("name", Json::from("main")),
+                ])]),
+            )])),
+            "stackTrace" => self.stack_trace(),
+            "scopes" => self.scopes(arguments),
+            "variables" => self.variables(arguments),
+            "evaluate" => self.evaluate(arguments),
+            "disconnect" => {
+                self.disconnected = true;
+                Ok(Json::Null)
+            }
+
+            _ => Err(format!("unknown command `{}`", command)),
+        };
+
+        std::iter::once(response(request_seq, command, result))
+            .chain(events)
+            .map(|message| self.number(message))
+            .collect()
+    }
+
+    /// Give a message the next sequence number, in the order they are sent
+    fn number(&mut self, mut message: Json) -> Json {
+        self.seq += 1;
+
+        if let Json::Object(fields) = &mut message {
+            fields.insert(0, ("seq".to_string(), Json::from(self.seq)));
+        }
+        message
+    }
+
+    fn session(&self) -> Result<&Session, String> {
+        self.session
+            .as_ref()
+            .ok_or_else(|| "no program has been launched".to_string())
+    }
+
+    /// Compile the program, configuration requests follow the
+    /// `initialized` event
+    fn launch(&mut self, arguments: &Json, events: &mut Vec<Json>) -> Result<Json, String> {
+        let path = arguments
+            .get("program")
+            .and_then(Json::as_str)
+            .ok_or("missing the program to launch")?;
+        self.stop_on_entry = arguments
+            .get("stopOnEntry")
+            .and_then(Json::as_bool)
+            .unwrap_or(false);
+
+        let source = std::fs::read_to_string(path)
+            .map_err(|error| format!("failed to read {}: {}", path, error))?;
+        let program = compile(path, &source).map_err(|errors| {
+            errors
+                .into_iter()
+                .map(|error| {
+                    error
+                        .in_file(path)
+                        .render_as(ErrorFormat::Human, Some(&source), false)
+                })
+                .collect::<String>()
+        })?;
+        let debugger = Debugger::new(program).map_err(|error| error.diagnostic().message)?;
+
+        self.session = Some(Session {
+            debugger,
+            path: path.to_string(),
+            source,
+            breakpoints: Vec::new(),
+            terminated: false,
+            failed: false,
+        });
+        events.push(event("initialized", Json::Null));
+
+        Ok(Json::Null)
+    }
+
+    /// Replace the breakpoints of the program, lines without instructions
+    /// are reported as unverified
+    fn set_breakpoints(&mut self, arguments: &Json) -> Result<Json, String> {
+        let path = arguments.at(&["source", "path"]).and_then(Json::as_str);
+        let lines = arguments
+            .get("breakpoints")
+            .and_then(Json::as_array)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|breakpoint| breakpoint.get("line").and_then(Json::as_usize))
+            .collect::<Vec<usize>>();
+
+        let session = self
+            .session
+            .as_mut()
+            .filter(|session| Some(session.path.as_str()) == path);
+
+        let breakpoints: Vec<Json> = match session {
+            Some(session) => {
+                for number in std::mem::take(&mut session.breakpoints) {
+                    session.debugger.remove_breakpoint(number);
+                }
+
+                lines
+                    .iter()
+                    .map(
+                        |&line| match session.debugger.add_breakpoint(Breakpoint::Line(line)) {
+                            Ok(number) => {
+                                session.breakpoints.push(number);
+                                breakpoint_json(line, None)
+                            }
+                            Err(message) => breakpoint_json(line, Some(message)),
+                        },
+                    )
+                    .collect()
+            }
+            None => lines
+                .iter()
+                .map(|&line| breakpoint_json(line, Some("not the launched program".to_string())))
+                .collect(),
+        };
+
+        Ok(Json::object([("breakpoints", Json::from(breakpoints))]))
+    }
+
+    /// Start or resume the program for a configuration or stepping request
+    fn execute(&mut self, command: &str, events: &mut Vec<Json>) -> Result<Json, String> {
+        if self.session()?.terminated {
+            return Err("the program has terminated".to_string());
+        }
+
+        *events = match command {
+            "configurationDone" if self.stop_on_entry => {
+                self.run(|session| step_line(session, false), "entry")
+            }
+            "next" => self.run(|session| step_line(session, true), "step"),
+            "stepIn" => self.run(|session| step_line(session, false), "step"),
+            "stepOut" => self.run(|session| session.debugger.step_out(), "step"),
+            _ => self.run(|session| session.debugger.resume(), "step"),
+        };
+
+        Ok(match command {
+            "continue" => Json::object([("allThreadsContinued", Json::from(true))]),
+            _ => Json::Null,
+        })
+    }
+
+    /// Run the program and return the events reporting where it stopped.
+    /// Runtime errors stop it like an exception, so its state can still be
+    /// inspected.
+    fn run(
+        &mut self,
+        run: impl FnOnce(&mut Session) -> Result<Stop, KirinError>,
+        reason: &str,
+    ) -> Vec<Json> {
+        let Some(session) = &mut self.session else {
+            return Vec::new();
+        };
+
+        let stop = run(session);
+        let output = session.debugger.take_output();
+
+        let mut events = Vec::new();
+        if !output.is_empty() {
+            events.push(event(
+                "output",
+                Json::object([
+                    ("category", Json::from("stdout")),
+                    ("output", Json::from(output)),
+                ]),
+            ));
+        }
+
+        let stopped = |reason: &str| {
+            Json::object([
+                ("reason", Json::from(reason)),
+                ("threadId", Json::from(THREAD_ID)),
+                ("allThreadsStopped", Json::from(true)),
+            ])
+        };
+
+        match stop {
+            Ok(Stop::Finished) => {
+                session.terminated = true;
+                let exit_code = if session.failed { 1 } else { 0 };
+                events.push(event(
+                    "exited",
+                    Json::object([("exitCode", Json::from(exit_code))]),
+                ));
+                events.push(event("terminated", Json::Null));
+            }
+            Ok(Stop::Breakpoint(_)) => events.push(event("stopped", stopped("breakpoint"))),
+            Ok(Stop::Step) => events.push(event("stopped", stopped(reason))),
+            Err(error) => {
+                session.failed = true;
+                let message = error.diagnostic().message;
+                let rendered = error.in_file(&session.path).render_as(
+                    ErrorFormat::Human,
+                    Some(&session.source),
+                    false,
+                );
+
+                events.push(event(
+                    "output",
+                    Json::object([
+                        ("category", Json::from("stderr")),
+                        ("output", Json::from(rendered)),
+                    ]),
+                ));
+
+                let mut body = stopped("exception");
+                if let Json::Object(fields) = &mut body {
+                    fields.push(("text".to_string(), Json::from(message)));
+                }
+                events.push(event("stopped", body));
+            }
+        }
+
+        events
+    }
+
+    /// The current location followed by the calls of the open frames
+    fn stack_trace(&self) -> Result<Json, String> {
+        let session = self.session()?;
+        let debugger = &session.debugger;
+
+        let mut addresses = vec![debugger.instruction_pointer()];
+        addresses.extend(
+            debugger
+                .frames()
+                .iter()
+                .rev()
+                .filter_map(|frame| frame.return_address)
+                .map(|address| address - 1),
+        );
+        let count = addresses.len();
+
+        let frames = addresses
+            .iter()
+            .enumerate()
+            .map(|(id, &address)| {
+                // the caller of each frame is the next one
+                let name = match addresses.get(id + 1) {
+                    Some(call) => format!("frame called at instruction {}", call),
+                    None => "main".to_string(),
+                };
+                let location = debugger.location_of(address);
+
+                Json::object([
+                    ("id", Json::from(id)),
+                    ("name", Json::from(name)),
+                    (
+                        "source",
+                        Json::object([
+                            ("name", Json::from(file_name(&session.path))),
+                            ("path", Json::from(session.path.as_str())),
+                        ]),
+                    ),
+                    (
+                        "line",
+                        Json::from(location.map(|location| location.line).unwrap_or(0)),
+                    ),
+                    (
+                        "column",
+                        Json::from(location.map(|location| location.column).unwrap_or(0)),
+                    ),
+                    (
+                        "instructionPointerReference",
+                        Json::from(address.to_string()),
+                    ),
+                ])
+            })
+            .collect::<Vec<Json>>();
+
+        Ok(Json::object([
+            ("stackFrames", Json::from(frames)),
+            ("totalFrames", Json::from(count)),
+        ]))
+    }
+
+    /// Only the current frame has scopes, the registers of the frames
+    /// below it are not kept apart
+    fn scopes(&self, arguments: &Json) -> Result<Json, String> {
+        self.session()?;
+
+        let scopes = match arguments.get("frameId").and_then(Json::as_usize) {
+            Some(0) => vec![
+                scope_json("Locals", LOCALS_REFERENCE),
+                scope_json("Registers", REGISTERS_REFERENCE),
+            ],
+            _ => Vec::new(),
+        };
+
+        Ok(Json::object([("scopes", Json::from(scopes))]))
+    }
+
+    fn variables(&self, arguments: &Json) -> Result<Json, String> {
+        let debugger = &self.session()?.debugger;
+
+        let variables = match arguments.get("variablesReference").and_then(Json::as_usize) {
+            Some(LOCALS_REFERENCE) => debugger
+                .variables()
+                .into_iter()
+                .map(|variable| {
+                    let value = values::read_variable(debugger, variable)
+                        .map(|value| values::display(&value))
+                        .unwrap_or_else(|| "<unavailable>".to_string());
+
+                    variable_json(&variable.name, value, variable.kind.name())
+                })
+                .collect(),
+            Some(REGISTERS_REFERENCE) => (0..debugger.register_count())
+                .filter_map(|index| {
+                    let register = debugger.register(index, None)?;
+                    Some(variable_json(&format!("r{}", index), register, "register"))
+                })
+                .collect(),
+
+            _ => Vec::new(),
+        };
+
+        Ok(Json::object([("variables", Json::from(variables))]))
+    }
+
+    fn evaluate(&self, arguments: &Json) -> Result<Json, String> {
+        let debugger = &self.session()?.debugger;
+        let expression = arguments
+            .get("expression")
+            .and_then(Json::as_str)
+            .ok_or("missing the expression")?;
+
+        let value = values::evaluate(debugger, expression)?;
+
+        Ok(Json::object([
+            ("result", Json::from(values::display(&value))),
+            ("type", Json::from(value.kind().name())),
+            ("variablesReference", Json::from(0)),
+        ]))
+    }
+}
+
+/// Run until the source line changes, a `CALL` is run to its return when
+/// stepping `over` it
+fn step_line(session: &mut Session, over: bool) -> Result<Stop, KirinError> {
+    let debugger = &mut session.debugger;
+    let line = |debugger: &Debugger| debugger.location().map(|location| location.line);
+    let start = line(debugger);
+
+    loop {
+        let stop = if over {
+            debugger.step_over()?
+        } else {
+            debugger.step()?
+        };
+        if stop != Stop::Step {
+            return Ok(stop);
+        }
+
+        // instructions without a location are stepped through
+        if line(debugger).is_some_and(|line| Some(line) != start) {
+            return Ok(stop);
+        }
+    }
+}
+
+/// Parse, type check and compile without optimizations
+fn compile(path: &str, source: &str) -> Result<Program, Vec<KirinError>> {
+    let tokens = scanner::scan_tokens(source).map_err(|error| vec![error])?;
+    let ast = parser::parse_ast(tokens, Some(path.to_string()))?;
+
+    let mut checker = TypeChecker::new();
+    checker.allow_pragmas(source);
+    let analyzed_ast = checker.infer_types(&ast)?;
+
+    let mut compiler = Compiler::new();
+    compiler
+        .compile(&analyzed_ast)
+        .map_err(|error| vec![error])?;
+
+    Ok(compiler.emit_program())
+}
+
+fn response(request_seq: usize, command: &str, result: Result<Json, String>) -> Json {
+    let mut fields = vec![
+        ("type", Json::from("response")),
+        ("request_seq", Json::from(request_seq)),
+        ("command", Json::from(command)),
+        ("success", Json::from(result.is_ok())),
+    ];
+    match result {
+        Ok(Json::Null) => {}
+        Ok(body) => fields.push(("body", body)),
+        Err(message) => fields.push(("message", Json::from(message))),
+    }
+
+    Json::object(fields)
+}
+
+fn event(event: &str, body: Json) -> Json {
+    Json::object([
+        ("type", Json::from("event")),
+        ("event", Json::from(event)),
+        ("body", body),
+    ])
+}
+
+fn capabilities() -> Json {
+    Json::object([
+        ("supportsConfigurationDoneRequest", Json::from(true)),
+        ("supportsEvaluateForHovers", Json::from(true)),
+    ])
+}
+
+fn breakpoint_json(line: usize, message: Option<String>) -> Json {
+    let mut fields = vec![
+        ("verified", Json::from(message.is_none())),
+        ("line", Json::from(line)),
+    ];
+    if let Some(message) = message {
+        fields.push(("message", Json::from(message)));
+    }
+
+    Json::object(fields)
+}
+
+fn scope_json(name: &str, reference: usize) -> Json {
+    Json::object([
+        ("name", Json::from(name)),
+        ("variablesReference", Json::from(reference)),
+        ("expensive", Json::from(false)),
+    ])
+}
+
+fn variable_json(name: &str, value: String, kind: &str) -> Json {
+    Json::object([
+        ("name", Json::from(name)),
+        ("value", Json::from(value)),
+        ("type", Json::from(kind)),
+        ("variablesReference", Json::from(0)),
+    ])
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+#[cfg(test)]
+mod adapter_tests {
+    use crate::run;
+    use json::Json;
+    use json::rpc;
+
+    fn program(name: &str) -> String {
+        format!("{}/../test-code/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    /// Feed the requests to the adapter as a client would over stdio and
+    /// collect what it writes back
+    fn session(requests: &[(&str, Json)]) -> (i32, Vec<Json>) {
+        let mut input = Vec::new();
+        for (seq, (command, arguments)) in requests.iter().enumerate() {
+            let request = Json::object([
+                ("seq", Json::from(seq + 1)),
+                ("type", Json::from("request")),
+                ("command", Json::from(*command)),
+                ("arguments", arguments.clone()),
+            ]);
+            rpc::write_message(&mut input, &request).unwrap();
+        }
+
+        let mut output = Vec::new();
+        let code = run(input.as_slice(), &mut output).unwrap();
+
+        let mut reader = output.as_slice();
+        let mut messages = Vec::new();
+        while let Some(message) = rpc::read_message(&mut reader).unwrap() {
+            messages.push(message.unwrap());
+        }
+
+        (code, messages)
+    }
+
+    fn launch(path: &str, stop_on_entry: bool) -> (&'static str, Json) {
+        (
+            "launch",
+            Json::object([
+                ("program", Json::from(path)),
+                ("stopOnEntry", Json::from(stop_on_entry)),
+            ]),
+        )
+    }
+
+    fn set_breakpoints(path: &str, lines: &[usize]) -> (&'static str, Json) {
+        let breakpoints = lines
+            .iter()
+            .map(|&line| Json::object([("line", Json::from(line))]))
+            .collect::<Vec<Json>>();
+
+        (
+            "setBreakpoints",
+            Json::object([
+                ("source", Json::object([("path", Json::from(path))])),
+                ("breakpoints", Json::from(breakpoints)),
+            ]),
+        )
+    }
+
+    fn response<'a>(messages: &'a [Json], command: &str) -> &'a Json {
+        messages
+            .iter()
+            .find(|message| {
+                message.get("type").and_then(Json::as_str) == Some("response")
+                    && message.get("command").and_then(Json::as_str) == Some(command)
+            })
+            .unwrap()
+    }
+
+    /// Event names with the reason of `stopped` and the text of `output`
+    fn events(messages: &[Json]) -> Vec<String> {
+        messages
+            .iter()
+            .filter_map(|message| {
+                let event = message.get("event")?.as_str()?;
+                let text = |path: &[&str]| message.at(path)?.as_str().map(str::to_string);
+                let detail = match event {
+                    "stopped" => text(&["body", "reason"]),
+                    "output" => text(&["body", "output"]),
+                    "exited" => message
+                        .at(&["body", "exitCode"])
+                        .and_then(Json::as_usize)
+                        .map(|code| code.to_string()),
+                    _ => None,
+                };
+
+                Some(match detail {
+                    Some(detail) => format!("{} {}", event, detail),
+                    None => event.to_string(),
+                })
+            })
+            .collect()
+    }
+
+    fn variables(message: &Json) -> Vec<String> {
+        message
+            .at(&["body", "variables"])
+            .and_then(Json::as_array)
+            .unwrap()
+            .iter()
+            .map(|variable| {
+                format!(
+                    "{}: {} = {}",
+                    variable.get("name").and_then(Json::as_str).unwrap(),
+                    variable.get("type").and_then(Json::as_str).unwrap(),
+                    variable.get("value").and_then(Json::as_str).unwrap()
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_breakpoints_and_variables() {
+        let path = program("any.kn");
+
+        let (code, messages) = session(&[
+            (
+                "initialize",
+                Json::object([("adapterID", Json::from("kirin"))]),
+            ),
+            launch(&path, false),
+            set_breakpoints(&path, &[1, 4, 12]),
+            ("configurationDone", Json::Null),
+            ("threads", Json::Null),
+            ("stackTrace", Json::object([("threadId", Json::from(1))])),
+            ("scopes", Json::object([("frameId", Json::from(0))])),
+            (
+                "variables",
+                Json::object([("variablesReference", Json::from(1))]),
+            ),
+            (
+                "evaluate",
+                Json::object([("expression", Json::from("value * 2"))]),
+            ),
+            ("continue", Json::object([("threadId", Json::from(1))])),
+            (
+                "variables",
+                Json::object([("variablesReference", Json::from(1))]),
+            ),
+            ("next", Json::object([("threadId", Json::from(1))])),
+            ("stepIn", Json::object([("threadId", Json::from(1))])),
+            ("continue", Json::object([("threadId", Json::from(1))])),
+            ("disconnect", Json::Null),
+        ]);
+
+        assert_eq!(code, 0);
+
+        // numbered in the order they are sent, events after their response
+        let numbers = messages
+            .iter()
+            .map(|message| message.get("seq").and_then(Json::as_usize).unwrap())
+            .collect::<Vec<usize>>();
+        assert_eq!(numbers, (1..=messages.len()).collect::<Vec<usize>>());
+
+        assert_eq!(
+            response(&messages, "initialize").at(&["body", "supportsConfigurationDoneRequest"]),
+            Some(&Json::Bool(true))
+        );
+
+        let breakpoints = response(&messages, "setBreakpoints")
+            .at(&["body", "breakpoints"])
+            .and_then(Json::as_array)
+            .unwrap()
+            .iter()
+            .map(|breakpoint| breakpoint.get("verified").and_then(Json::as_bool).unwrap())
+            .collect::<Vec<bool>>();
+        assert_eq!(breakpoints, [false, true, true]);
+
+        let frame = response(&messages, "stackTrace")
+            .at(&["body", "stackFrames"])
+            .and_then(Json::as_array)
+            .unwrap()[0]
+            .clone();
+        assert_eq!(frame.get("name").and_then(Json::as_str), Some("main"));
+        assert_eq!(frame.get("line").and_then(Json::as_usize), Some(4));
+        assert_eq!(
+            frame.at(&["source", "name"]).and_then(Json::as_str),
+            Some("any.kn")
+        );
+
+        let locals = messages
+            .iter()
+            .filter(|message| message.get("command").and_then(Json::as_str) == Some("variables"))
+            .map(variables)
+            .collect::<Vec<_>>();
+        assert_eq!(locals[0], ["value: any = 25.0"]);
+        assert_eq!(locals[1], ["value: any = none", "number: any = 4"]);
+
+        assert_eq!(
+            response(&messages, "evaluate").at(&["body", "result"]),
+            Some(&Json::from("50.0"))
+        );
+
+        assert_eq!(
+            events(&messages),
+            [
+                "initialized",
+                "stopped breakpoint",
+                "output 25\n-25\ntext\nnone\n",
+                "stopped breakpoint",
+                "stopped step",
+                "output 64\n",
+                "exited 0",
+                "terminated",
+            ]
+        );
+        assert_eq!(
+            messages[messages.len() - 2].get("message"),
+            Some(&Json::from("the program has terminated"))
+        );
+    }
+
+    #[test]
+    fn test_runtime_errors_stop_as_exceptions() {
+        let path = program("overflow.kn");
+
+        let (_, messages) = session(&[
+            launch(&path, true),
+            ("configurationDone", Json::Null),
+            ("stackTrace", Json::object([("threadId", Json::from(1))])),
+            ("continue", Json::object([("threadId", Json::from(1))])),
+            (
+                "evaluate",
+                Json::object([("expression", Json::from("count"))]),
+            ),
+            ("continue", Json::object([("threadId", Json::from(1))])),
+        ]);
+
+        let events = events(&messages);
+        assert_eq!(events[..2], ["initialized", "stopped entry"]);
+        assert_eq!(
+            events[2],
+            "output 9223372036854775806\n9223372036854775807\n"
+        );
+        assert!(events[3].starts_with("output error[E0402]: integer overflow"));
+        assert_eq!(events[4..], ["stopped exception", "exited 1", "terminated"]);
+
+        assert_eq!(
+            response(&messages, "stackTrace")
+                .at(&["body", "stackFrames"])
+                .and_then(Json::as_array)
+                .unwrap()[0]
+                .get("line")
+                .and_then(Json::as_usize),
+            Some(2)
+        );
+        assert_eq!(
+            response(&messages, "evaluate").at(&["body", "result"]),
+            Some(&Json::from("9223372036854775807"))
+        );
+    }
+
+    #[test]
+    fn test_launch_errors() {
+        let (code, messages) = session(&[
+            launch("missing.kn", false),
+            ("next", Json::Null),
+            ("unknown", Json::Null),
+        ]);
+
+        assert_eq!(code, 1);
+        for message in &messages {
+            assert_eq!(message.get("success"), Some(&Json::Bool(false)));
+        }
+        assert!(
+            messages[0]
+                .get("message")
+                .and_then(Json::as_str)
+                .unwrap()
+                .starts_with("failed to read missing.kn")
+        );
+        assert_eq!(
+            messages[1].get("message").and_then(Json::as_str),
+            Some("no program has been launched")
+        );
+    }
+}
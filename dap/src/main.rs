@@ -0,0 +1,13 @@
+/// A debug adapter for .kn programs, speaking the Debug Adapter Protocol
+/// over stdin and stdout
+fn main() {
+    let code = match dap::run(std::io::stdin().lock(), std::io::stdout().lock()) {
+        Ok(code) => code,
+        Err(error) => {
+            eprintln!("debug adapter failed: {}", error);
+            1
+        }
+    };
+
+    std::process::exit(code);
+}
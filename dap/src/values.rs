@@ -0,0 +1,151 @@
+use analyzer::TypeChecker;
+use analyzer::lints::Lint;
+use errors::KirinError;
+use interpreter::{Interpreter, Value};
+use parser::statements::Statement;
+use std::fmt::Write;
+use types::KirinType;
+use vm::{DebugVariable, Debugger, ProgramConstant};
+
+/// The value of a variable of the current frame
+pub fn read_variable(debugger: &Debugger, variable: &DebugVariable) -> Option<Value> {
+    read(debugger, variable.register, variable.kind)
+}
+
+/// The value of type `kind` in register `register`, `any` values are the
+/// pair of their type tag and value
+fn read(debugger: &Debugger, register: usize, kind: KirinType) -> Option<Value> {
+    let bits = debugger.register_bits(register)?;
+
+    Some(match kind {
+        KirinType::Int => Value::Int(bits as i64),
+        KirinType::Float => Value::Float(f64::from_bits(bits)),
+        KirinType::Bool => Value::Bool(bits != 0),
+        KirinType::String => match debugger.constants().get(bits as usize)? {
+            ProgramConstant::String(string) => Value::String(string.clone()),
+            _ => return None,
+        },
+        KirinType::Any | KirinType::Null => match KirinType::from_u8(bits as u8)? {
+            KirinType::Null => Value::Null,
+            KirinType::Any => return None,
+            kind => read(debugger, register + 1, kind)?,
+        },
+
+        _ => return None,
+    })
+}
+
+/// A value as written in the source, strings are quoted
+pub fn display(value: &Value) -> String {
+    match value {
+        Value::String(string) => format!("{:?}", string),
+        Value::Float(float) => format!("{:?}", float),
+        value => value.to_string(),
+    }
+}
+
+/// Evaluate an expression over the variables of the current frame. It is
+/// type checked like a statement following their declarations and run by
+/// the interpreter on copies of their values, so assignments and printing
+/// do not affect the program.
+pub fn evaluate(debugger: &Debugger, expression: &str) -> Result<Value, String> {
+    let variables = debugger.variables();
+
+    let mut source = String::new();
+    for variable in &variables {
+        writeln!(source, "let {}: {}", variable.name, variable.kind).ok();
+    }
+    writeln!(source, "{}", expression.trim()).ok();
+
+    let first_error = |errors: Vec<KirinError>| match errors.first() {
+        Some(error) => error.diagnostic().message,
+        None => "invalid expression".to_string(),
+    };
+
+    let tokens = scanner::scan_tokens(&source).map_err(|error| first_error(vec![error]))?;
+    let ast = parser::parse_ast(tokens, None).map_err(first_error)?;
+    if ast.len() != variables.len() + 1 {
+        return Err("expected a single expression".to_string());
+    }
+
+    let mut checker = TypeChecker::new();
+    checker.allow(Lint::UnusedVariables);
+    let analyzed_ast = checker.infer_types(&ast).map_err(first_error)?;
+
+    let Some(Statement::ExpressionStatement(expression)) = analyzed_ast.last() else {
+        return Err("expected an expression, not a declaration".to_string());
+    };
+
+    let mut interpreter = Interpreter::new();
+    interpreter.capture_output();
+    for variable in &variables {
+        if let Some(value) = read_variable(debugger, variable) {
+            interpreter.define(&variable.name, variable.kind, value);
+        }
+    }
+
+    interpreter
+        .evaluate(expression)
+        .map_err(|error| error.diagnostic().message)
+}
+
+#[cfg(test)]
+mod values_tests {
+    use crate::values::{display, evaluate, read_variable};
+    use compiler::Compiler;
+    use vm::{Breakpoint, Debugger};
+
+    const SOURCE: &str =
+        "a := 20\nlet b: any = \"text\"\nlet c: float = 2.5\nlet d: any = none\nprint(a)\n";
+
+    /// A debugger stopped at the last line
+    fn debugger() -> Debugger {
+        let tokens = scanner::scan_tokens(SOURCE).unwrap();
+        let ast = parser::parse_ast(tokens, None).unwrap();
+        let analyzed_ast = analyzer::TypeChecker::new().infer_types(&ast).unwrap();
+
+        let mut compiler = Compiler::new();
+        compiler.compile(&analyzed_ast).unwrap();
+
+        let mut debugger = Debugger::new(compiler.emit_program()).unwrap();
+        debugger.add_breakpoint(Breakpoint::Line(5)).unwrap();
+        debugger.resume().unwrap();
+        debugger
+    }
+
+    #[test]
+    fn test_read_variables() {
+        let debugger = debugger();
+
+        let variables = debugger
+            .variables()
+            .iter()
+            .map(|variable| {
+                let value = read_variable(&debugger, variable).unwrap();
+                format!("{} = {}", variable.name, display(&value))
+            })
+            .collect::<Vec<String>>();
+
+        assert_eq!(variables, ["a = 20", "b = \"text\"", "c = 2.5", "d = none"]);
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let debugger = debugger();
+
+        let value = |expression| evaluate(&debugger, expression).map(|value| display(&value));
+
+        assert_eq!(value("a * 2 + 1"), Ok("41".to_string()));
+        assert_eq!(value("c > 2"), Ok("true".to_string()));
+        assert_eq!(value("b"), Ok("\"text\"".to_string()));
+        assert_eq!(value("a = 3"), Ok("3".to_string()));
+        assert_eq!(value("a"), Ok("20".to_string()));
+
+        assert!(value("a +").is_err());
+        assert!(value("e := 1").is_err());
+        assert_eq!(
+            value("missing"),
+            Err("undefined variable `missing`".to_string())
+        );
+    }
+}
@@ -0,0 +1,45 @@
+//! Stable codes of the diagnostics, grouped by the stage reporting them. A
+//! code keeps its meaning once released, new errors get new codes.
+
+/// errors without a more specific code
+pub const GENERAL: &str = "E0000";
+
+pub const UNEXPECTED_CHARACTER: &str = "E0001";
+pub const UNTERMINATED_LITERAL: &str = "E0002";
+
+pub const SYNTAX: &str = "E0100";
+pub const EXPECTED_TOKEN: &str = "E0101";
+pub const EXPECTED_EXPRESSION: &str = "E0102";
+pub const INVALID_ASSIGNMENT_TARGET: &str = "E0103";
+pub const UNKNOWN_TYPE: &str = "E0104";
+pub const INVALID_NUMBER: &str = "E0105";
+pub const TOO_MANY_ARGUMENTS: &str = "E0106";
+pub const UNSUPPORTED_BLOCK: &str = "E0107";
+
+pub const TYPE: &str = "E0200";
+pub const MISMATCHED_TYPES: &str = "E0201";
+pub const INVALID_OPERANDS: &str = "E0202";
+pub const UNDEFINED_VARIABLE: &str = "E0203";
+pub const UNDEFINED_FUNCTION: &str = "E0204";
+pub const ARGUMENT_COUNT: &str = "E0205";
+pub const MISSING_TYPE: &str = "E0206";
+
+pub const COMPILE: &str = "E0300";
+pub const TOO_MANY_REGISTERS: &str = "E0301";
+
+pub const RUNTIME: &str = "E0400";
+pub const DIVISION_BY_ZERO: &str = "E0401";
+pub const INTEGER_OVERFLOW: &str = "E0402";
+pub const NEGATIVE_EXPONENT: &str = "E0403";
+pub const FAILED_DOWNCAST: &str = "E0404";
+pub const UNSUPPORTED_OPERANDS: &str = "E0405";
+
+/// warnings of the analyzer lints, one code per lint
+pub const UNKNOWN_LINT: &str = "W0001";
+pub const UNUSED_VARIABLE: &str = "W0002";
+pub const UNUSED_RESULT: &str = "W0003";
+pub const SHADOWED_VARIABLE: &str = "W0004";
+pub const FLOAT_EQUALITY: &str = "W0005";
+pub const SELF_ASSIGNMENT: &str = "W0006";
+pub const UNREACHABLE_CODE: &str = "W0007";
+pub const CONSTANT_CONDITION: &str = "W0008";
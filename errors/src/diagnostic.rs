@@ -0,0 +1,396 @@
+use crate::codes;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::io::IsTerminal;
+use std::ops::Range;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Error => write!(f, "error"),
+            Self::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// The part of the toolchain reporting a diagnostic
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Stage {
+    General,
+    Scan,
+    Parse,
+    Type,
+    Compile,
+    Runtime,
+}
+
+impl Stage {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::General => "General",
+            Self::Scan => "Scan",
+            Self::Parse => "Parse",
+            Self::Type => "Type",
+            Self::Compile => "Compile",
+            Self::Runtime => "Runtime",
+        }
+    }
+
+    pub fn default_code(&self) -> &'static str {
+        match self {
+            Self::General => codes::GENERAL,
+            Self::Scan => codes::UNEXPECTED_CHARACTER,
+            Self::Parse => codes::SYNTAX,
+            Self::Type => codes::TYPE,
+            Self::Compile => codes::COMPILE,
+            Self::Runtime => codes::RUNTIME,
+        }
+    }
+}
+
+/// A secondary part of the source shown with a diagnostic
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    /// bytes of the source, in the file of the diagnostic
+    pub range: Range<usize>,
+    pub message: String,
+}
+
+/// An error or warning with everything needed to show it: where it is in
+/// the source, a stable code and optional labels, notes and help.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub stage: Stage,
+    pub code: &'static str,
+    pub message: String,
+    /// `None` for sources without a path
+    pub file: Option<String>,
+    /// `0` when the diagnostic has no location
+    pub line: usize,
+    pub column: usize,
+    /// bytes of the source the diagnostic points at, empty when only the
+    /// position is known
+    pub range: Range<usize>,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Option<String>,
+}
+
+/// An underlined part of a source line
+struct Mark<'a> {
+    line: usize,
+    column: usize,
+    width: usize,
+    primary: bool,
+    message: Option<&'a str>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, stage: Stage, code: &'static str, message: &str) -> Self {
+        Self {
+            severity,
+            stage,
+            code,
+            message: message.to_string(),
+            file: None,
+            line: 0,
+            column: 0,
+            range: 0..0,
+            labels: Vec::new(),
+            notes: Vec::new(),
+            help: None,
+        }
+    }
+
+    /// Render like rustc: a header with the code, the location and, given
+    /// the source of the file, the lines of the primary location and the
+    /// labels with the pointed at parts underlined
+    ///
+    /// ```text
+    /// error[E0202]: operator `+` cannot be applied to `string` and `int`
+    ///  --> main.kn:2:8
+    ///   |
+    /// 2 | b := a + 1
+    ///   |        ^
+    ///   |      - this is `string`
+    ///   |          - this is `int`
+    /// ```
+    pub fn render(&self, source: Option<&str>, color: bool) -> String {
+        let paint = |text: &str, style: &str| match color {
+            true => format!("{}{}{}", style, text, RESET),
+            false => text.to_string(),
+        };
+
+        let severity_style = match self.severity {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+        };
+
+        let mut output = format!(
+            "{}{}\n",
+            paint(&format!("{}[{}]", self.severity, self.code), severity_style),
+            paint(&format!(": {}", self.message), BOLD)
+        );
+
+        let marks = match (source, self.line) {
+            (_, 0) => Vec::new(),
+            (Some(source), _) => self.marks(source),
+            (None, _) => Vec::new(),
+        };
+
+        let last_line = marks
+            .iter()
+            .map(|mark| mark.line)
+            .chain([self.line])
+            .max()
+            .unwrap_or_default();
+        let gutter = " ".repeat(last_line.to_string().len());
+
+        if self.line != 0 {
+            output.push_str(&format!(
+                "{}{} {}:{}:{}\n",
+                gutter,
+                paint("-->", BLUE),
+                self.file.as_deref().unwrap_or("<input>"),
+                self.line,
+                self.column
+            ));
+        }
+
+        let mut lines = BTreeMap::<usize, Vec<&Mark>>::new();
+        for mark in &marks {
+            lines.entry(mark.line).or_default().push(mark);
+        }
+
+        if let Some(source) = source
+            && !lines.is_empty()
+        {
+            let bar = paint("|", BLUE);
+            output.push_str(&format!("{} {}\n", gutter, bar));
+
+            for (line, marks) in lines {
+                let text = source.lines().nth(line - 1).unwrap_or_default();
+                output.push_str(&format!(
+                    "{} {} {}\n",
+                    paint(&format!("{:>width$}", line, width = gutter.len()), BLUE),
+                    bar,
+                    text
+                ));
+
+                for mark in marks {
+                    let (underline, style) = match mark.primary {
+                        true => ("^".repeat(mark.width), severity_style),
+                        false => ("-".repeat(mark.width), BLUE),
+                    };
+                    let underline = match mark.message {
+                        Some(message) => format!("{} {}", underline, message),
+                        None => underline,
+                    };
+
+                    output.push_str(&format!(
+                        "{} {} {}{}\n",
+                        gutter,
+                        bar,
+                        indentation(text, mark.column),
+                        paint(&underline, style)
+                    ));
+                }
+            }
+        }
+
+        for note in &self.notes {
+            output.push_str(&format!("{} {} note: {}\n", gutter, paint("=", BLUE), note));
+        }
+        if let Some(help) = &self.help {
+            output.push_str(&format!("{} {} help: {}\n", gutter, paint("=", BLUE), help));
+        }
+
+        output
+    }
+
+    fn marks<'a>(&'a self, source: &str) -> Vec<Mark<'a>> {
+        let primary = Mark {
+            line: self.line,
+            column: self.column,
+            width: width(source, &self.range),
+            primary: true,
+            message: None,
+        };
+
+        let labels = self
+            .labels
+            .iter()
+            .filter(|label| label.range.start <= source.len())
+            .map(|label| {
+                let (line, column) = position(source, label.range.start);
+
+                Mark {
+                    line,
+                    column,
+                    width: width(source, &label.range),
+                    primary: false,
+                    message: Some(&label.message),
+                }
+            });
+
+        std::iter::once(primary).chain(labels).collect()
+    }
+}
+
+/// The line and column of a byte offset, counted like the scanner does
+pub(crate) fn position(source: &str, offset: usize) -> (usize, usize) {
+    let before = source.get(..offset).unwrap_or(source);
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map(|index| index + 1).unwrap_or(0);
+
+    (line, offset - line_start + 1)
+}
+
+/// The byte offset of a line and column, the end of the source when it
+/// has no such position
+pub(crate) fn offset(source: &str, line: usize, column: usize) -> usize {
+    let line_start = match line {
+        0 | 1 => Some(0),
+        _ => source
+            .match_indices('\n')
+            .nth(line - 2)
+            .map(|(index, _)| index + 1),
+    };
+
+    line_start
+        .map(|start| start + column.saturating_sub(1))
+        .unwrap_or(source.len())
+        .min(source.len())
+}
+
+/// The number of characters of a range up to the end of its first line,
+/// at least one so empty ranges are still pointed at
+fn width(source: &str, range: &Range<usize>) -> usize {
+    let text = source.get(range.clone()).unwrap_or_default();
+
+    text.lines()
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .count()
+        .max(1)
+}
+
+/// Whitespace up to a column, keeping tabs so the underline lines up
+fn indentation(text: &str, column: usize) -> String {
+    let offset = column.saturating_sub(1);
+    let prefix = text.get(..offset.min(text.len())).unwrap_or_default();
+
+    let mut indentation = prefix
+        .chars()
+        .map(|character| if character == '\t' { '\t' } else { ' ' })
+        .collect::<String>();
+    indentation.push_str(&" ".repeat(offset.saturating_sub(prefix.len())));
+
+    indentation
+}
+
+/// Whether diagnostics written to `stream` should be coloured: only on a
+/// terminal and unless `NO_COLOR` is set
+pub fn use_color(stream: &impl IsTerminal) -> bool {
+    stream.is_terminal() && std::env::var_os("NO_COLOR").is_none()
+}
+
+#[cfg(test)]
+mod diagnostic_tests {
+    use crate::{Diagnostic, KirinError, Severity, SpannedError, Stage, codes};
+
+    #[test]
+    fn test_render_snippet_with_labels() {
+        let source = "a := \"text\"\nb := a + 1\n";
+        let error = KirinError::Type(
+            SpannedError::new(
+                "operator `+` cannot be applied to `string` and `int`".to_string(),
+                2,
+                8,
+            )
+            .with_file(Some("main.kn".to_string()))
+            .with_range(19..20)
+            .with_code(codes::INVALID_OPERANDS)
+            .with_label(17..18, "this is `string`".to_string())
+            .with_label(21..22, "this is `int`".to_string())
+            .with_help("convert the operands to the same type".to_string()),
+        );
+
+        assert_eq!(
+            error.render(Some(source), false),
+            "error[E0202]: operator `+` cannot be applied to `string` and `int`\n \
+             --> main.kn:2:8\n  \
+             |\n\
+             2 | b := a + 1\n  \
+             |        ^\n  \
+             |      - this is `string`\n  \
+             |          - this is `int`\n  \
+             = help: convert the operands to the same type\n"
+        );
+    }
+
+    #[test]
+    fn test_render_without_source() {
+        let error = KirinError::Runtime(
+            SpannedError::new("division by zero: `1 / 0`".to_string(), 3, 9)
+                .with_code(codes::DIVISION_BY_ZERO),
+        );
+
+        assert_eq!(
+            error.to_string(),
+            "error[E0401]: division by zero: `1 / 0`\n --> <input>:3:9"
+        );
+        assert_eq!(
+            KirinError::General("file not found".to_string()).to_string(),
+            "error[E0000]: file not found"
+        );
+    }
+
+    #[test]
+    fn test_render_range_and_color() {
+        let source = "\tvalue := 12345 +\n";
+        let mut diagnostic = Diagnostic::new(
+            Severity::Warning,
+            Stage::Parse,
+            codes::SYNTAX,
+            "unused value",
+        );
+        diagnostic.line = 1;
+        diagnostic.column = 11;
+        diagnostic.range = 10..15;
+
+        let plain = diagnostic.render(Some(source), false);
+        assert!(plain.starts_with("warning[E0100]: unused value\n"));
+        assert!(plain.contains("\n  | \t         ^^^^^\n"));
+        assert!(!plain.contains('\x1b'));
+
+        let colored = diagnostic.render(Some(source), true);
+        assert!(colored.contains("\x1b[1;33mwarning[E0100]\x1b[0m"));
+    }
+
+    #[test]
+    fn test_error_codes() {
+        let error = KirinError::Parse(SpannedError::new("unexpected".to_string(), 1, 1));
+        assert_eq!(error.code(), codes::SYNTAX);
+        assert_eq!(error.stage().name(), "Parse");
+
+        let error = KirinError::Parse(
+            SpannedError::new("unexpected".to_string(), 1, 1).with_code(codes::UNKNOWN_TYPE),
+        );
+        assert_eq!(error.code(), codes::UNKNOWN_TYPE);
+    }
+}
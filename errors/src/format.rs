@@ -0,0 +1,222 @@
+use crate::Diagnostic;
+use crate::diagnostic::{offset, position};
+use json::Json;
+use std::ops::Range;
+
+/// How the drivers write diagnostics
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum ErrorFormat {
+    /// rendered snippets for people
+    #[default]
+    Human,
+    /// one JSON object per line for tools
+    Json,
+}
+
+impl ErrorFormat {
+    /// Parse an `--error-format=human` or `--error-format=json` command line
+    /// flag
+    pub fn from_flag(flag: &str) -> Option<Self> {
+        match flag {
+            "--error-format=human" => Some(Self::Human),
+            "--error-format=json" => Some(Self::Json),
+
+            _ => None,
+        }
+    }
+}
+
+impl Diagnostic {
+    /// Render in `format`, JSON diagnostics end with a newline like rendered
+    /// ones so they can be written one after another
+    pub fn render_as(&self, format: ErrorFormat, source: Option<&str>, color: bool) -> String {
+        match format {
+            ErrorFormat::Human => self.render(source, color),
+            ErrorFormat::Json => format!("{}\n", self.to_json(source)),
+        }
+    }
+
+    /// A single line JSON object:
+    ///
+    /// ```text
+    /// {"severity":"error","stage":"Type","code":"E0202","message":"...",
+    ///  "file":"main.kn","line":2,"column":8,"range":{"start":19,"end":20},
+    ///  "related":[{"message":"this is `string`","line":2,"column":6,
+    ///  "range":{"start":17,"end":18}}],"notes":[],"help":null}
+    /// ```
+    ///
+    /// `file` is `null` for sources without a path, `line`, `column` and
+    /// `range` are `null` for diagnostics without a location. The lines and
+    /// columns of related spans are only known given the `source`, as is the
+    /// offset of diagnostics that only know their position, whose range is
+    /// empty.
+    pub fn to_json(&self, source: Option<&str>) -> String {
+        let located = self.line != 0;
+
+        let primary = match (self.range.is_empty(), source) {
+            (_, _) if !located => None,
+            (false, _) => Some(self.range.clone()),
+            (true, Some(source)) => {
+                let offset = offset(source, self.line, self.column);
+                Some(offset..offset)
+            }
+            (true, None) => None,
+        };
+
+        let related = self
+            .labels
+            .iter()
+            .map(|label| {
+                let (line, column) = match source {
+                    Some(source) if label.range.start <= source.len() => {
+                        let (line, column) = position(source, label.range.start);
+                        (Some(line), Some(column))
+                    }
+                    _ => (None, None),
+                };
+
+                Json::object([
+                    ("message", Json::from(label.message.as_str())),
+                    ("line", Json::from(line)),
+                    ("column", Json::from(column)),
+                    ("range", range(&label.range)),
+                ])
+            })
+            .collect::<Vec<Json>>();
+
+        let notes = self
+            .notes
+            .iter()
+            .map(|note| Json::from(note.as_str()))
+            .collect::<Vec<Json>>();
+
+        Json::object([
+            ("severity", Json::from(self.severity.to_string())),
+            ("stage", Json::from(self.stage.name())),
+            ("code", Json::from(self.code)),
+            ("message", Json::from(self.message.as_str())),
+            ("file", Json::from(self.file.as_deref())),
+            ("line", Json::from(located.then_some(self.line))),
+            ("column", Json::from(located.then_some(self.column))),
+            ("range", primary.as_ref().map(range).unwrap_or(Json::Null)),
+            ("related", Json::from(related)),
+            ("notes", Json::from(notes)),
+            ("help", Json::from(self.help.as_deref())),
+        ])
+        .to_string()
+    }
+}
+
+fn range(range: &Range<usize>) -> Json {
+    Json::object([
+        ("start", Json::from(range.start)),
+        ("end", Json::from(range.end)),
+    ])
+}
+
+#[cfg(test)]
+mod format_tests {
+    use crate::{ErrorFormat, KirinError, SpannedError, codes};
+
+    #[test]
+    fn test_json_schema() {
+        let source = "a := \"text\"\nb := a + 1\n";
+        let error = KirinError::Type(
+            SpannedError::new(
+                "operator `+` cannot be applied to `string` and `int`".to_string(),
+                2,
+                8,
+            )
+            .with_file(Some("main.kn".to_string()))
+            .with_range(19..20)
+            .with_code(codes::INVALID_OPERANDS)
+            .with_label(17..18, "this is `string`".to_string())
+            .with_note("operands are not converted".to_string())
+            .with_help("convert the operands to the same type".to_string()),
+        );
+
+        assert_eq!(
+            error.render_as(ErrorFormat::Json, Some(source), false),
+            "{\"severity\":\"error\",\"stage\":\"Type\",\"code\":\"E0202\",\
+             \"message\":\"operator `+` cannot be applied to `string` and `int`\",\
+             \"file\":\"main.kn\",\"line\":2,\"column\":8,\"range\":{\"start\":19,\"end\":20},\
+             \"related\":[{\"message\":\"this is `string`\",\"line\":2,\"column\":6,\
+             \"range\":{\"start\":17,\"end\":18}}],\
+             \"notes\":[\"operands are not converted\"],\
+             \"help\":\"convert the operands to the same type\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_json_without_location() {
+        let error = KirinError::General("failed to open \"a.kn\"\n".to_string());
+
+        assert_eq!(
+            error.render_as(ErrorFormat::Json, None, false),
+            "{\"severity\":\"error\",\"stage\":\"General\",\"code\":\"E0000\",\
+             \"message\":\"failed to open \\\"a.kn\\\"\\n\",\"file\":null,\
+             \"line\":null,\"column\":null,\"range\":null,\
+             \"related\":[],\"notes\":[],\"help\":null}\n"
+        );
+    }
+
+    #[test]
+    fn test_json_position_only() {
+        let error = KirinError::Runtime(
+            SpannedError::new("division by zero: `4 / 0`".to_string(), 2, 9)
+                .with_code(codes::DIVISION_BY_ZERO),
+        );
+        let source = "a := 0\nprint(4 / a)\n";
+
+        assert!(
+            error
+                .render_as(ErrorFormat::Json, Some(source), false)
+                .contains("\"line\":2,\"column\":9,\"range\":{\"start\":15,\"end\":15}")
+        );
+        assert!(
+            error
+                .render_as(ErrorFormat::Json, None, false)
+                .contains("\"line\":2,\"column\":9,\"range\":null")
+        );
+    }
+
+    #[test]
+    fn test_json_stages() {
+        let stages = [
+            KirinError::Scan(SpannedError::new(String::new(), 1, 1)),
+            KirinError::Parse(SpannedError::new(String::new(), 1, 1)),
+            KirinError::Type(SpannedError::new(String::new(), 1, 1)),
+            KirinError::Compile(SpannedError::new(String::new(), 1, 1)),
+            KirinError::Runtime(
+                SpannedError::new(String::new(), 1, 1).with_label(4..5, "x".into()),
+            ),
+        ]
+        .iter()
+        .map(|error| error.render_as(ErrorFormat::Json, None, false))
+        .collect::<Vec<String>>();
+
+        for (json, stage) in stages
+            .iter()
+            .zip(["Scan", "Parse", "Type", "Compile", "Runtime"])
+        {
+            assert!(json.contains(&format!("\"stage\":\"{}\"", stage)));
+            assert_eq!(json.lines().count(), 1);
+        }
+        assert!(
+            stages[4].contains("\"line\":null,\"column\":null,\"range\":{\"start\":4,\"end\":5}")
+        );
+    }
+
+    #[test]
+    fn test_error_format_flags() {
+        assert_eq!(
+            ErrorFormat::from_flag("--error-format=json"),
+            Some(ErrorFormat::Json)
+        );
+        assert_eq!(
+            ErrorFormat::from_flag("--error-format=human"),
+            Some(ErrorFormat::Human)
+        );
+        assert_eq!(ErrorFormat::from_flag("--error-format"), None);
+    }
+}
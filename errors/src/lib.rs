@@ -1,6 +1,14 @@
+pub mod codes;
+mod diagnostic;
+mod format;
+
+pub use diagnostic::{Diagnostic, Label, Severity, Stage, use_color};
+pub use format::ErrorFormat;
+
 use std::fmt::{Display, Formatter};
+use std::ops::Range;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum KirinError {
     General(String),
     Scan(SpannedError),
@@ -10,42 +18,210 @@ pub enum KirinError {
     Type(SpannedError),
 }
 
+impl KirinError {
+    pub fn stage(&self) -> Stage {
+        match self {
+            Self::General(_) => Stage::General,
+            Self::Scan(_) => Stage::Scan,
+            Self::Parse(_) => Stage::Parse,
+            Self::Runtime(_) => Stage::Runtime,
+            Self::Compile(_) => Stage::Compile,
+            Self::Type(_) => Stage::Type,
+        }
+    }
+
+    pub fn spanned(&self) -> Option<&SpannedError> {
+        match self {
+            Self::General(_) => None,
+            Self::Scan(error)
+            | Self::Parse(error)
+            | Self::Runtime(error)
+            | Self::Compile(error)
+            | Self::Type(error) => Some(error),
+        }
+    }
+
+    /// The code of the error, or the default code of its stage
+    pub fn code(&self) -> &'static str {
+        self.spanned()
+            .and_then(|error| error.code)
+            .unwrap_or_else(|| self.stage().default_code())
+    }
+
+    /// Set the file of errors that do not know which file they are in
+    pub fn in_file(mut self, file: &str) -> Self {
+        if let Self::Scan(error)
+        | Self::Parse(error)
+        | Self::Runtime(error)
+        | Self::Compile(error)
+        | Self::Type(error) = &mut self
+            && error.file.is_none()
+        {
+            error.file = Some(file.to_string());
+        }
+
+        self
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        let stage = self.stage();
+        let code = self.code();
+
+        match self {
+            Self::General(message) => Diagnostic::new(Severity::Error, stage, code, message),
+            Self::Scan(error)
+            | Self::Parse(error)
+            | Self::Runtime(error)
+            | Self::Compile(error)
+            | Self::Type(error) => {
+                let details = error.details.as_deref().cloned().unwrap_or_default();
+
+                Diagnostic {
+                    file: error.file.clone(),
+                    line: error.line,
+                    column: error.column,
+                    range: error.range.clone(),
+                    labels: details.labels,
+                    notes: details.notes,
+                    help: details.help,
+                    ..Diagnostic::new(Severity::Error, stage, code, &error.message)
+                }
+            }
+        }
+    }
+
+    /// Render the error with a snippet of `source`, the text of its file
+    pub fn render(&self, source: Option<&str>, color: bool) -> String {
+        self.diagnostic().render(source, color)
+    }
+
+    pub fn render_as(&self, format: ErrorFormat, source: Option<&str>, color: bool) -> String {
+        self.diagnostic().render_as(format, source, color)
+    }
+}
+
+/// The header and location of the diagnostic, `render` adds the snippet
 impl Display for KirinError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::General(error) => write!(f, "[Error]: {}", error),
-            Self::Scan(error) => write!(
-                f,
-                "[Scan Error] [line: {}, column: {}]: {}\n",
-                error.line, error.column, error.message,
-            ),
-            Self::Parse(error) => write!(
-                f,
-                "[Parse Error] [line: {}, column: {}]: {}\n",
-                error.line, error.column, error.message,
-            ),
-            Self::Runtime(error) => write!(
-                f,
-                "[Runtime Error] [line: {}, column: {}]: {}\n",
-                error.line, error.column, error.message,
-            ),
-            Self::Compile(error) => write!(
-                f,
-                "[Compile Error] [line: {}, column: {}]: {}\n",
-                error.line, error.column, error.message,
-            ),
-            Self::Type(error) => write!(
-                f,
-                "[Type Error] [line: {}, column: {}]: {}\n",
-                error.line, error.column, error.message,
-            ),
+        write!(f, "{}", self.render(None, false).trim_end())
+    }
+}
+
+/// A diagnostic of a lint, which does not stop the compilation
+#[derive(Debug, Clone)]
+pub struct Warning {
+    /// the name of the lint, used to allow it
+    pub lint: &'static str,
+    pub error: SpannedError,
+}
+
+impl Warning {
+    pub fn new(lint: &'static str, error: SpannedError) -> Self {
+        Self { lint, error }
+    }
+
+    /// Set the file of warnings that do not know which file they are in
+    pub fn in_file(mut self, file: &str) -> Self {
+        if self.error.file.is_none() {
+            self.error.file = Some(file.to_string());
         }
+
+        self
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        let details = self.error.details.as_deref().cloned().unwrap_or_default();
+        let code = self.error.code.unwrap_or(codes::GENERAL);
+
+        Diagnostic {
+            file: self.error.file.clone(),
+            line: self.error.line,
+            column: self.error.column,
+            range: self.error.range.clone(),
+            labels: details.labels,
+            notes: details.notes,
+            help: details.help,
+            ..Diagnostic::new(Severity::Warning, Stage::Type, code, &self.error.message)
+        }
+    }
+
+    pub fn render_as(&self, format: ErrorFormat, source: Option<&str>, color: bool) -> String {
+        self.diagnostic().render_as(format, source, color)
     }
 }
 
-#[derive(Debug)]
+impl Display for Warning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.diagnostic().render(None, false).trim_end())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct SpannedError {
     pub message: String,
     pub line: usize,
     pub column: usize,
+    /// `None` for sources without a path
+    pub file: Option<String>,
+    /// bytes of the source the error points at, empty when only the
+    /// position is known
+    pub range: Range<usize>,
+    /// the default code of the stage when `None`
+    pub code: Option<&'static str>,
+    /// boxed so errors without labels, notes or help stay small
+    pub details: Option<Box<Details>>,
+}
+
+/// The optional parts of a diagnostic besides its location
+#[derive(Debug, Clone, Default)]
+pub struct Details {
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Option<String>,
+}
+
+impl SpannedError {
+    pub fn new(message: String, line: usize, column: usize) -> Self {
+        Self {
+            message,
+            line,
+            column,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_file(mut self, file: Option<String>) -> Self {
+        self.file = file;
+        self
+    }
+
+    pub fn with_range(mut self, range: Range<usize>) -> Self {
+        self.range = range;
+        self
+    }
+
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    /// Point at another part of the source
+    pub fn with_label(mut self, range: Range<usize>, message: String) -> Self {
+        self.details().labels.push(Label { range, message });
+        self
+    }
+
+    pub fn with_note(mut self, note: String) -> Self {
+        self.details().notes.push(note);
+        self
+    }
+
+    pub fn with_help(mut self, help: String) -> Self {
+        self.details().help = Some(help);
+        self
+    }
+
+    fn details(&mut self) -> &mut Details {
+        self.details.get_or_insert_default()
+    }
 }
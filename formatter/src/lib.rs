@@ -0,0 +1,666 @@
+use errors::KirinError;
+use parser::expressions::{BinaryOp, UnaryOp};
+use scanner::{Token, TokenType};
+
+/// Keywords of blocks closed by `end`, their bodies are indented
+const BLOCK_KEYWORDS: [TokenType; 6] = [
+    TokenType::Fn,
+    TokenType::Class,
+    TokenType::Block,
+    TokenType::If,
+    TokenType::For,
+    TokenType::While,
+];
+
+/// How variables without a type annotation are declared
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum Declarations {
+    /// `name := value`
+    #[default]
+    Short,
+    /// `let name = value`
+    Let,
+}
+
+impl Declarations {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "short" => Some(Self::Short),
+            "let" => Some(Self::Let),
+
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// lines longer than this have their call arguments wrapped
+    pub max_width: usize,
+    /// spaces per block level
+    pub indent: usize,
+    pub declarations: Declarations,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            max_width: 100,
+            indent: 4,
+            declarations: Declarations::default(),
+        }
+    }
+}
+
+/// A line of the formatted file
+#[derive(Debug)]
+enum Item {
+    Blank,
+    Comment(String),
+    Statement {
+        tokens: Vec<Token>,
+        /// a comment after the statement on its last line
+        trailing: Option<String>,
+        /// whether comments are written inside the statement, which is then
+        /// kept as written
+        commented: bool,
+    },
+}
+
+/// Format a source in the canonical style. Only the tokens and comments are
+/// kept: spacing comes from the kind of each token, block bodies are
+/// indented and runs of blank lines are collapsed into one.
+///
+/// The formatter works on the tokens rather than the syntax tree, so it
+/// keeps comments and formats blocks the parser does not support yet.
+pub fn format(source: &str, config: &Config) -> Result<String, KirinError> {
+    let tokens = scanner::scan_tokens(source)?;
+    let items = items(source, tokens);
+
+    let mut output = String::new();
+    let mut level = 0;
+    let mut blank = false;
+
+    for item in items {
+        let (tokens, trailing, commented) = match item {
+            Item::Blank => {
+                blank = !output.is_empty();
+                continue;
+            }
+            Item::Comment(comment) => {
+                push_line(
+                    &mut output,
+                    &mut blank,
+                    &indentation(level, config),
+                    &comment,
+                );
+                continue;
+            }
+            Item::Statement {
+                tokens,
+                trailing,
+                commented,
+            } => (tokens, trailing, commented),
+        };
+
+        let first = tokens[0].token_type;
+        if matches!(first, TokenType::End | TokenType::Else) {
+            level = level.saturating_sub(1);
+        }
+
+        let indent = indentation(level, config);
+        let mut lines = match commented {
+            true => written_lines(&tokens, source, &indent),
+            false => statement_lines(&pieces(&tokens, source, config), &indent, config),
+        };
+        if let (Some(comment), Some(last)) = (trailing, lines.last_mut()) {
+            last.push(' ');
+            last.push_str(&comment);
+        }
+
+        for line in lines {
+            push_line(&mut output, &mut blank, "", &line);
+        }
+
+        if first == TokenType::Else || BLOCK_KEYWORDS.contains(&first) {
+            level += 1;
+        }
+    }
+
+    Ok(output)
+}
+
+fn push_line(output: &mut String, blank: &mut bool, indent: &str, line: &str) {
+    if std::mem::take(blank) {
+        output.push('\n');
+    }
+
+    output.push_str(indent);
+    output.push_str(line);
+    output.push('\n');
+}
+
+fn indentation(level: usize, config: &Config) -> String {
+    " ".repeat(level * config.indent)
+}
+
+/// Split the source into statements, comments and blank lines. A statement
+/// goes on over new lines inside brackets and after an operator or a comma.
+fn items(source: &str, tokens: Vec<Token>) -> Vec<Item> {
+    let mut items = Vec::new();
+    let mut statement = Vec::<Token>::new();
+    let mut commented = false;
+    let mut depth = 0usize;
+    let mut previous_end = 0;
+
+    let tokens = tokens
+        .into_iter()
+        .filter(|token| !matches!(token.token_type, TokenType::NewLine | TokenType::Eof));
+
+    for token in tokens {
+        let gap = Gap::new(
+            &source[previous_end..token.span.start],
+            !statement.is_empty(),
+        );
+        previous_end = token.span.end;
+
+        let continues = statement
+            .last()
+            .is_some_and(|last| depth > 0 || continues_line(last.token_type));
+
+        if !gap.new_line || continues {
+            commented |= gap.trailing.is_some()
+                || gap
+                    .lines
+                    .iter()
+                    .any(|line| matches!(line, Item::Comment(_)));
+        } else {
+            if !statement.is_empty() {
+                items.push(Item::Statement {
+                    tokens: std::mem::take(&mut statement),
+                    trailing: gap.trailing,
+                    commented: std::mem::take(&mut commented),
+                });
+            }
+            items.extend(gap.lines);
+        }
+
+        match token.token_type {
+            TokenType::LeftParen | TokenType::LeftBracket => depth += 1,
+            TokenType::RightParen | TokenType::RightBracket => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        statement.push(token);
+    }
+
+    let gap = Gap::new(&source[previous_end..], !statement.is_empty());
+    if !statement.is_empty() {
+        items.push(Item::Statement {
+            tokens: statement,
+            trailing: gap.trailing,
+            commented,
+        });
+    }
+    items.extend(gap.lines);
+
+    items
+}
+
+/// The text between two tokens: a comment ending the line of the first,
+/// then whole lines that are blank or comments
+struct Gap {
+    new_line: bool,
+    trailing: Option<String>,
+    lines: Vec<Item>,
+}
+
+impl Gap {
+    /// `after_token` is false for the text before the first token, whose
+    /// first line is a whole line
+    fn new(text: &str, after_token: bool) -> Self {
+        let new_line = text.contains('\n');
+        let mut segments = text.split('\n').collect::<Vec<&str>>();
+
+        let trailing = match after_token {
+            true => comment(segments.remove(0)).map(str::to_string),
+            false => None,
+        };
+
+        // the indentation of the next token, or a comment ending the file
+        let next = segments.pop();
+
+        let lines = segments
+            .into_iter()
+            .chain(next.filter(|next| comment(next).is_some()))
+            .map(|line| match comment(line) {
+                Some(comment) => Item::Comment(comment.to_string()),
+                None => Item::Blank,
+            })
+            .collect::<Vec<Item>>();
+
+        Self {
+            new_line,
+            trailing,
+            lines,
+        }
+    }
+}
+
+fn comment(line: &str) -> Option<&str> {
+    line.find('#').map(|start| line[start..].trim_end())
+}
+
+/// Whether a statement goes on after a new line following this token,
+/// where the parser skips new lines
+fn continues_line(token_type: TokenType) -> bool {
+    matches!(
+        token_type,
+        TokenType::ColonEqual
+            | TokenType::Comma
+            | TokenType::LeftParen
+            | TokenType::LeftBracket
+            | TokenType::Not
+    ) || is_binary_operator(token_type)
+}
+
+fn is_binary_operator(token_type: TokenType) -> bool {
+    BinaryOp::from_token(&simple_token(token_type)).is_ok()
+}
+
+fn is_unary_operator(token_type: TokenType) -> bool {
+    UnaryOp::from_token(&simple_token(token_type)).is_ok()
+}
+
+fn simple_token(token_type: TokenType) -> Token {
+    Token {
+        token_type,
+        lexeme: String::new(),
+        span: Default::default(),
+    }
+}
+
+/// A token with the text it is written as
+#[derive(Debug, Clone)]
+struct Piece {
+    token_type: TokenType,
+    text: String,
+}
+
+/// The pieces of a statement, with declarations written in the configured
+/// style
+fn pieces(tokens: &[Token], source: &str, config: &Config) -> Vec<Piece> {
+    let mut pieces = tokens
+        .iter()
+        .map(|token| Piece {
+            token_type: token.token_type,
+            text: source[token.span.start..token.span.end].to_string(),
+        })
+        .collect::<Vec<Piece>>();
+
+    let kinds = pieces
+        .iter()
+        .take(3)
+        .map(|piece| piece.token_type)
+        .collect::<Vec<TokenType>>();
+
+    let piece = |token_type: TokenType, text: &str| Piece {
+        token_type,
+        text: text.to_string(),
+    };
+
+    match (config.declarations, kinds.as_slice()) {
+        // `let name = value` or `let name := value`
+        (
+            Declarations::Short,
+            [
+                TokenType::Let,
+                TokenType::Identifier,
+                TokenType::Equal | TokenType::ColonEqual,
+            ],
+        ) => {
+            pieces.remove(0);
+            pieces[1] = piece(TokenType::ColonEqual, ":=");
+        }
+        (Declarations::Let, [TokenType::Identifier, TokenType::ColonEqual, ..]) => {
+            pieces.insert(0, piece(TokenType::Let, "let"));
+            pieces[2] = piece(TokenType::Equal, "=");
+        }
+        (Declarations::Let, [TokenType::Let, TokenType::Identifier, TokenType::ColonEqual]) => {
+            pieces[2] = piece(TokenType::Equal, "=");
+        }
+
+        _ => {}
+    }
+
+    pieces
+}
+
+/// The lines of a statement. When it is too long, the arguments of its
+/// first call go on lines of their own; the `)` stays on the last argument
+/// line since the parser does not skip a new line before it.
+fn statement_lines(pieces: &[Piece], indent: &str, config: &Config) -> Vec<String> {
+    let line = format!("{}{}", indent, join(pieces));
+    if line.len() <= config.max_width {
+        return vec![line];
+    }
+
+    let Some((open, close)) = first_call(pieces) else {
+        return vec![line];
+    };
+
+    let mut lines = vec![format!("{}{}", indent, join(&pieces[..=open]))];
+
+    let arguments = split_arguments(&pieces[open + 1..close]);
+    let last = arguments.len() - 1;
+    for (index, argument) in arguments.into_iter().enumerate() {
+        let mut line = format!("{}{}{}", indent, " ".repeat(config.indent), join(argument));
+
+        match index == last {
+            true => line.push_str(&join(&pieces[close..])),
+            false => line.push(','),
+        }
+        lines.push(line);
+    }
+
+    lines
+}
+
+/// The lines of a statement as written, only its first line is indented
+fn written_lines(tokens: &[Token], source: &str, indent: &str) -> Vec<String> {
+    let (Some(first), Some(last)) = (tokens.first(), tokens.last()) else {
+        return Vec::new();
+    };
+
+    source[first.span.start..last.span.end]
+        .split('\n')
+        .enumerate()
+        .map(|(index, line)| match index {
+            0 => format!("{}{}", indent, line.trim_end()),
+            _ => line.trim_end().to_string(),
+        })
+        .collect()
+}
+
+/// The positions of the parentheses of the first call with arguments that
+/// is not nested in brackets
+fn first_call(pieces: &[Piece]) -> Option<(usize, usize)> {
+    let mut depth = 0;
+
+    for (index, piece) in pieces.iter().enumerate() {
+        match piece.token_type {
+            TokenType::LeftParen if depth == 0 && index > 0 && is_callee(&pieces[index - 1]) => {
+                let close = matching(pieces, index)?;
+
+                if close > index + 1 {
+                    return Some((index, close));
+                }
+                depth += 1;
+            }
+            TokenType::LeftParen | TokenType::LeftBracket => depth += 1,
+            TokenType::RightParen | TokenType::RightBracket => depth -= 1,
+
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// The position of the bracket closing the one at `open`
+fn matching(pieces: &[Piece], open: usize) -> Option<usize> {
+    let mut depth = 0;
+
+    for (index, piece) in pieces.iter().enumerate().skip(open) {
+        match piece.token_type {
+            TokenType::LeftParen | TokenType::LeftBracket => depth += 1,
+            TokenType::RightParen | TokenType::RightBracket => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(index);
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Split the pieces between the parentheses of a call at its commas
+fn split_arguments(pieces: &[Piece]) -> Vec<&[Piece]> {
+    let mut arguments = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+
+    for (index, piece) in pieces.iter().enumerate() {
+        match piece.token_type {
+            TokenType::LeftParen | TokenType::LeftBracket => depth += 1,
+            TokenType::RightParen | TokenType::RightBracket => depth -= 1,
+            TokenType::Comma if depth == 0 => {
+                arguments.push(&pieces[start..index]);
+                start = index + 1;
+            }
+
+            _ => {}
+        }
+    }
+    arguments.push(&pieces[start..]);
+
+    arguments
+}
+
+/// Whether a `(` after this piece starts a call, or an `[` an index
+fn is_callee(piece: &Piece) -> bool {
+    matches!(
+        piece.token_type,
+        TokenType::Identifier | TokenType::RightParen | TokenType::RightBracket
+    )
+}
+
+/// Whether a value ends with this piece, so an operator after it is binary
+fn ends_operand(piece: &Piece) -> bool {
+    matches!(
+        piece.token_type,
+        TokenType::Identifier
+            | TokenType::Number
+            | TokenType::String
+            | TokenType::True
+            | TokenType::False
+            | TokenType::None
+            | TokenType::RightParen
+            | TokenType::RightBracket
+    )
+}
+
+/// Join pieces with single spaces, except inside brackets, before commas
+/// and colons, after unary operators and between a callee and its `(`
+fn join(pieces: &[Piece]) -> String {
+    let mut text = String::new();
+
+    for (index, piece) in pieces.iter().enumerate() {
+        if index > 0 && space_between(pieces, index) {
+            text.push(' ');
+        }
+        text.push_str(&piece.text);
+    }
+
+    text
+}
+
+fn space_between(pieces: &[Piece], index: usize) -> bool {
+    let previous = &pieces[index - 1];
+    let current = &pieces[index];
+
+    let unary_previous =
+        is_unary_operator(previous.token_type) && (index < 2 || !ends_operand(&pieces[index - 2]));
+
+    match (previous.token_type, current.token_type) {
+        (TokenType::LeftParen | TokenType::LeftBracket | TokenType::Dot, _) => false,
+        (
+            _,
+            TokenType::RightParen
+            | TokenType::RightBracket
+            | TokenType::Comma
+            | TokenType::Colon
+            | TokenType::Dot,
+        ) => false,
+        (_, TokenType::LeftParen | TokenType::LeftBracket) if is_callee(previous) => false,
+        _ if unary_previous => false,
+
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod formatter_tests {
+    use crate::{Config, Declarations, format};
+    use std::path::PathBuf;
+
+    const MESSY: &str = "\
+# header
+
+
+let a=1+2*-3   # trailing
+b:=a*(a-1)
+let c: int=wrapping_add( a ,b)
+while a>1
+print(a)
+    if b
+  # inside
+  print( - b)
+      end
+end
+d := wrapping_add(1 +
+  # interior
+  2, 3)
+print(\"a long string that pushes the line past the width\", wrapping_mul(a, b), !true)
+
+
+
+# final";
+
+    const FORMATTED: &str = "\
+# header
+
+a := 1 + 2 * -3 # trailing
+b := a * (a - 1)
+let c: int = wrapping_add(a, b)
+while a > 1
+    print(a)
+    if b
+        # inside
+        print(-b)
+    end
+end
+d := wrapping_add(1 +
+  # interior
+  2, 3)
+print(
+    \"a long string that pushes the line past the width\",
+    wrapping_mul(a, b),
+    !true)
+
+# final
+";
+
+    fn config() -> Config {
+        Config {
+            max_width: 60,
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(format(MESSY, &config()).unwrap(), FORMATTED);
+    }
+
+    /// The sample programs are not the formatter's, so they only have to
+    /// format the same way twice
+    #[test]
+    fn test_idempotence() {
+        let directory = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../test-code");
+        let mut sources = std::fs::read_dir(directory)
+            .unwrap()
+            .map(|entry| std::fs::read_to_string(entry.unwrap().path()).unwrap())
+            .collect::<Vec<String>>();
+        sources.push(MESSY.to_string());
+
+        for declarations in [Declarations::Short, Declarations::Let] {
+            let config = Config {
+                declarations,
+                ..config()
+            };
+
+            for source in &sources {
+                let once = format(source, &config).unwrap();
+                let twice = format(&once, &config).unwrap();
+
+                assert_eq!(once, twice, "formatting is not idempotent for\n{}", source);
+            }
+        }
+    }
+
+    #[test]
+    fn test_non_ascii_source() {
+        assert_eq!(
+            format("x:=\"café\"   # ü\nprint( x )\n", &config()).unwrap(),
+            "x := \"café\" # ü\nprint(x)\n"
+        );
+    }
+
+    #[test]
+    fn test_declaration_styles() {
+        let source = "let a = 1\nb := 2\nlet c := 3\nlet d: any = 4\nlet e: int\n";
+        let config = Config {
+            declarations: Declarations::Let,
+            ..Config::default()
+        };
+
+        assert_eq!(
+            format(source, &config).unwrap(),
+            "let a = 1\nlet b = 2\nlet c = 3\nlet d: any = 4\nlet e: int\n"
+        );
+        assert_eq!(
+            format(source, &Config::default()).unwrap(),
+            "a := 1\nb := 2\nc := 3\nlet d: any = 4\nlet e: int\n"
+        );
+    }
+
+    #[test]
+    fn test_comments_in_arguments() {
+        let source = "\
+if  a
+  print(a,   # first
+      # second
+      b)  # after
+end
+";
+        let expected = "\
+if a
+    print(a,   # first
+      # second
+      b) # after
+end
+";
+
+        assert_eq!(format(source, &Config::default()).unwrap(), expected);
+        assert_eq!(format(expected, &Config::default()).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_wrapped_calls_still_parse() {
+        let formatted = format(MESSY, &config()).unwrap();
+        let tokens = scanner::scan_tokens(&formatted).unwrap();
+        let (statements, _) = parser::parse_with_recovery(tokens, None);
+
+        // the block is one statement, the wrapped call another
+        assert!(matches!(
+            statements.last(),
+            Some(parser::statements::Statement::ExpressionStatement(
+                parser::expressions::Expression::Call(_)
+            ))
+        ));
+    }
+}
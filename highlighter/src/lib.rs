@@ -0,0 +1,325 @@
+pub mod textmate;
+
+use analyzer::TypeChecker;
+use analyzer::names::Resolution;
+use errors::KirinError;
+use scanner::{KEYWORDS, Token, TokenType};
+use std::collections::HashMap;
+use std::ops::Range;
+
+const RESET: &str = "\x1b[0m";
+
+/// What a part of the source is, for coloring it
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Kind {
+    Keyword,
+    /// a variable
+    Identifier,
+    /// the name of a called library function
+    Function,
+    /// a type annotation
+    Type,
+    Number,
+    String,
+    Comment,
+    Operator,
+}
+
+impl Kind {
+    pub const ALL: [Kind; 8] = [
+        Self::Keyword,
+        Self::Identifier,
+        Self::Function,
+        Self::Type,
+        Self::Number,
+        Self::String,
+        Self::Comment,
+        Self::Operator,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Keyword => "keyword",
+            Self::Identifier => "identifier",
+            Self::Function => "function",
+            Self::Type => "type",
+            Self::Number => "number",
+            Self::String => "string",
+            Self::Comment => "comment",
+            Self::Operator => "operator",
+        }
+    }
+
+    /// The TextMate scope, which editor themes color
+    pub fn scope(&self) -> &'static str {
+        match self {
+            Self::Keyword => "keyword.control.kirin",
+            Self::Identifier => "variable.other.kirin",
+            Self::Function => "entity.name.function.kirin",
+            Self::Type => "storage.type.kirin",
+            Self::Number => "constant.numeric.kirin",
+            Self::String => "string.quoted.double.kirin",
+            Self::Comment => "comment.line.number-sign.kirin",
+            Self::Operator => "keyword.operator.kirin",
+        }
+    }
+
+    /// Identifiers keep the color of the terminal
+    fn ansi(&self) -> Option<&'static str> {
+        match self {
+            Self::Keyword => Some("\x1b[35m"),
+            Self::Identifier => None,
+            Self::Function => Some("\x1b[34m"),
+            Self::Type => Some("\x1b[36m"),
+            Self::Number => Some("\x1b[33m"),
+            Self::String => Some("\x1b[32m"),
+            Self::Comment => Some("\x1b[90m"),
+            Self::Operator => Some("\x1b[1m"),
+        }
+    }
+}
+
+/// A classified part of the source
+#[derive(Debug, Clone, PartialEq)]
+pub struct Highlight {
+    pub kind: Kind,
+    /// bytes of the source
+    pub range: Range<usize>,
+}
+
+/// Classify the tokens and comments of `source`, in order. Names are
+/// classified by what the analyzer resolves them to, undefined names,
+/// parentheses, commas and other punctuation are not classified.
+pub fn highlight(source: &str) -> Result<Vec<Highlight>, KirinError> {
+    let tokens = scanner::scan_tokens(source)?;
+    let names = resolve(tokens.clone());
+
+    let mut highlights = Vec::new();
+    let mut previous_end = 0;
+
+    for token in &tokens {
+        if matches!(token.token_type, TokenType::NewLine | TokenType::Eof) {
+            continue;
+        }
+
+        let range = token.span.start..token.span.end;
+        comments(source, previous_end..range.start, &mut highlights);
+        previous_end = range.end;
+
+        if let Some(kind) = classify(token, &names) {
+            highlights.push(Highlight { kind, range });
+        }
+    }
+    comments(source, previous_end..source.len(), &mut highlights);
+
+    Ok(highlights)
+}
+
+/// What the names of the source resolve to, by where they start. Names in
+/// statements that do not parse, or after a type error in a statement, are
+/// not resolved.
+fn resolve(tokens: Vec<Token>) -> HashMap<usize, Resolution> {
+    let (statements, _) = parser::parse_with_recovery(tokens, None);
+
+    let mut checker = TypeChecker::new();
+    let _ = checker.infer_types(&statements);
+
+    checker
+        .names()
+        .into_iter()
+        .map(|name| (name.range.start, name.resolution))
+        .collect()
+}
+
+fn classify(token: &Token, names: &HashMap<usize, Resolution>) -> Option<Kind> {
+    match token.token_type {
+        TokenType::Identifier => match names.get(&token.span.start) {
+            Some(Resolution::Function) => Some(Kind::Function),
+            Some(Resolution::Type) => Some(Kind::Type),
+            Some(Resolution::Undefined) => None,
+            Some(Resolution::Variable) | None => Some(Kind::Identifier),
+        },
+        TokenType::Number => Some(Kind::Number),
+        TokenType::String => Some(Kind::String),
+        TokenType::Plus
+        | TokenType::Minus
+        | TokenType::Star
+        | TokenType::Slash
+        | TokenType::Percent
+        | TokenType::Caret
+        | TokenType::Not
+        | TokenType::Equal
+        | TokenType::EqualEqual
+        | TokenType::ColonEqual
+        | TokenType::NotEqual
+        | TokenType::GreaterEqual
+        | TokenType::LessEqual
+        | TokenType::Greater
+        | TokenType::Less => Some(Kind::Operator),
+        token_type if KEYWORDS.iter().any(|(_, keyword)| *keyword == token_type) => {
+            Some(Kind::Keyword)
+        }
+
+        _ => None,
+    }
+}
+
+/// The comments in the text between two tokens, which run from a `#` to
+/// the end of its line
+fn comments(source: &str, gap: Range<usize>, highlights: &mut Vec<Highlight>) {
+    let mut line_start = gap.start;
+
+    for line in source[gap].split_inclusive('\n') {
+        if let Some(start) = line.find('#') {
+            let end = line_start + line.trim_end().len();
+            highlights.push(Highlight {
+                kind: Kind::Comment,
+                range: line_start + start..end,
+            });
+        }
+        line_start += line.len();
+    }
+}
+
+/// The source with ANSI color codes around the highlights
+pub fn to_ansi(source: &str, highlights: &[Highlight]) -> String {
+    render(
+        source,
+        highlights,
+        str::to_string,
+        |kind, text| match kind.ansi() {
+            Some(color) => format!("{}{}{}", color, text, RESET),
+            None => text.to_string(),
+        },
+    )
+}
+
+/// The source as HTML, with the highlights in `<span class="kn-<kind>">`
+/// elements to be styled by a stylesheet. The caller wraps it, usually in
+/// `<pre>`.
+pub fn to_html(source: &str, highlights: &[Highlight]) -> String {
+    render(source, highlights, escape_html, |kind, text| {
+        format!(
+            "<span class=\"kn-{}\">{}</span>",
+            kind.name(),
+            escape_html(text)
+        )
+    })
+}
+
+fn render(
+    source: &str,
+    highlights: &[Highlight],
+    plain: impl Fn(&str) -> String,
+    highlighted: impl Fn(Kind, &str) -> String,
+) -> String {
+    let mut output = String::with_capacity(source.len());
+    let mut end = 0;
+
+    for highlight in highlights {
+        output.push_str(&plain(&source[end..highlight.range.start]));
+        output.push_str(&highlighted(
+            highlight.kind,
+            &source[highlight.range.clone()],
+        ));
+        end = highlight.range.end;
+    }
+    output.push_str(&plain(&source[end..]));
+
+    output
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod highlighter_tests {
+    use crate::{Kind, highlight, to_ansi, to_html};
+
+    const SOURCE: &str = "# sum\nlet a: int = 1 + 2 # two\nprint(\"<a>\" == a)\nf(a)\nint := a\nprint(int + b)\nwhile true\nend\n";
+
+    #[test]
+    fn test_highlight() {
+        let highlights = highlight(SOURCE).unwrap();
+
+        let classified = highlights
+            .iter()
+            .map(|highlight| (highlight.kind, &SOURCE[highlight.range.clone()]))
+            .collect::<Vec<(Kind, &str)>>();
+
+        assert_eq!(
+            classified,
+            [
+                (Kind::Comment, "# sum"),
+                (Kind::Keyword, "let"),
+                (Kind::Identifier, "a"),
+                (Kind::Type, "int"),
+                (Kind::Operator, "="),
+                (Kind::Number, "1"),
+                (Kind::Operator, "+"),
+                (Kind::Number, "2"),
+                (Kind::Comment, "# two"),
+                (Kind::Function, "print"),
+                (Kind::String, "\"<a>\""),
+                (Kind::Operator, "=="),
+                (Kind::Identifier, "a"),
+                // `f` is not defined
+                (Kind::Identifier, "a"),
+                (Kind::Identifier, "int"),
+                (Kind::Operator, ":="),
+                (Kind::Identifier, "a"),
+                (Kind::Function, "print"),
+                (Kind::Identifier, "int"),
+                (Kind::Operator, "+"),
+                // nor is `b`
+                (Kind::Keyword, "while"),
+                (Kind::Keyword, "true"),
+                (Kind::Keyword, "end"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_non_ascii_source() {
+        let source = "s := \"é\" # ü\nprint(s)\n";
+
+        let classified = highlight(source)
+            .unwrap()
+            .iter()
+            .map(|highlight| (highlight.kind, &source[highlight.range.clone()]))
+            .collect::<Vec<(Kind, &str)>>();
+
+        assert_eq!(
+            classified,
+            [
+                (Kind::Identifier, "s"),
+                (Kind::Operator, ":="),
+                (Kind::String, "\"é\""),
+                (Kind::Comment, "# ü"),
+                (Kind::Function, "print"),
+                (Kind::Identifier, "s"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_outputs() {
+        let source = "x := \"<b>\" # &\n";
+        let highlights = highlight(source).unwrap();
+
+        assert_eq!(
+            to_ansi(source, &highlights),
+            "x \x1b[1m:=\x1b[0m \x1b[32m\"<b>\"\x1b[0m \x1b[90m# &\x1b[0m\n"
+        );
+        assert_eq!(
+            to_html(source, &highlights),
+            "<span class=\"kn-identifier\">x</span> <span class=\"kn-operator\">:=</span> \
+             <span class=\"kn-string\">&quot;&lt;b&gt;&quot;</span> \
+             <span class=\"kn-comment\"># &amp;</span>\n"
+        );
+    }
+}
@@ -0,0 +1,108 @@
+use crate::Kind;
+use analyzer::builtins::builtins;
+use json::Json;
+use scanner::KEYWORDS;
+use types::KirinType;
+
+/// A TextMate grammar for .kn files, in JSON. Its scopes are those of the
+/// kinds of [`crate::highlight`], and the keywords, types and library
+/// functions it matches are those of the scanner and analyzer.
+pub fn grammar() -> String {
+    let keywords = KEYWORDS
+        .iter()
+        .map(|(keyword, _)| *keyword)
+        .collect::<Vec<&str>>();
+
+    // `none` is scanned as a keyword
+    let types = (0..=u8::MAX)
+        .map_while(KirinType::from_u8)
+        .map(|kind| kind.name())
+        .filter(|name| !keywords.contains(name))
+        .collect::<Vec<&str>>();
+
+    let functions = builtins()
+        .iter()
+        .map(|builtin| builtin.name)
+        .collect::<Vec<&str>>();
+
+    let patterns = vec![
+        matched(Kind::Comment, "#.*$"),
+        Json::object([
+            ("name", Json::from(Kind::String.scope())),
+            ("begin", Json::from("\"")),
+            ("end", Json::from("\"")),
+        ]),
+        matched(Kind::Number, r"\b[0-9]+(\.[0-9]+)?\b"),
+        Json::object([
+            (
+                "match",
+                Json::from(format!(r":\s*\b({})\b", types.join("|"))),
+            ),
+            (
+                "captures",
+                Json::object([(
+                    "1",
+                    Json::object([("name", Json::from(Kind::Type.scope()))]),
+                )]),
+            ),
+        ]),
+        matched(Kind::Keyword, &format!(r"\b({})\b", keywords.join("|"))),
+        matched(
+            Kind::Function,
+            &format!(r"\b({})\b(?=\()", functions.join("|")),
+        ),
+        matched(Kind::Operator, r":=|==|!=|<=|>=|[-+*/%^<>=!]"),
+        matched(Kind::Identifier, r"\b[A-Za-z_][A-Za-z0-9_]*\b"),
+    ];
+
+    let grammar = Json::object([
+        ("name", Json::from("Kirin")),
+        ("scopeName", Json::from("source.kirin")),
+        ("fileTypes", Json::from(vec![Json::from("kn")])),
+        ("patterns", Json::from(patterns)),
+    ]);
+
+    format!("{}\n", grammar)
+}
+
+fn matched(kind: Kind, pattern: &str) -> Json {
+    Json::object([
+        ("name", Json::from(kind.scope())),
+        ("match", Json::from(pattern)),
+    ])
+}
+
+#[cfg(test)]
+mod textmate_tests {
+    use crate::Kind;
+    use crate::textmate::grammar;
+    use json::Json;
+
+    #[test]
+    fn test_grammar() {
+        let grammar = Json::parse(&grammar()).unwrap();
+        let patterns = grammar.get("patterns").unwrap().as_array().unwrap();
+
+        let scope = |index: usize| {
+            let pattern = &patterns[index];
+            pattern
+                .get("name")
+                .or(pattern.at(&["captures", "1", "name"]))
+                .and_then(Json::as_str)
+                .unwrap()
+        };
+        let scopes = (0..patterns.len()).map(scope).collect::<Vec<&str>>();
+        for kind in Kind::ALL {
+            assert!(scopes.contains(&kind.scope()), "{:?} has no pattern", kind);
+        }
+
+        assert_eq!(
+            patterns[3].get("match").and_then(Json::as_str),
+            Some(r":\s*\b(void|any|string|int|float|bool)\b")
+        );
+        assert_eq!(
+            patterns[5].get("match").and_then(Json::as_str),
+            Some(r"\b(print|wrapping_add|wrapping_sub|wrapping_mul|wrapping_pow)\b(?=\()")
+        );
+    }
+}
@@ -1,5 +1,5 @@
-use crate::Instruction;
 use crate::constants::{DESTINATION_MASK, SIXTEEN_BIT_MASK, SOURCE_1_MASK, SOURCE_2_MASK};
+use crate::{Instruction, OpCode, OperandSlot};
 
 pub struct InstructionDecoder {}
 
@@ -10,32 +10,30 @@ impl InstructionDecoder {
 
         instruction as u8
     }
+    /// Decode the opcode, `None` for values that are not a known instruction
     #[inline(always)]
-    pub fn decode_destination(instruction: Instruction) -> Instruction {
-        let instruction = (instruction & DESTINATION_MASK) >> 16;
+    pub fn decode_known_opcode(instruction: Instruction) -> Option<OpCode> {
+        OpCode::try_from(Self::decode_opcode(instruction)).ok()
+    }
 
-        instruction
+    #[inline(always)]
+    pub fn decode_destination(instruction: Instruction) -> Instruction {
+        (instruction & DESTINATION_MASK) >> 16
     }
 
     #[inline(always)]
     pub fn decode_source_1(instruction: Instruction) -> Instruction {
-        let instruction = instruction & SOURCE_1_MASK;
-
-        instruction
+        instruction & SOURCE_1_MASK
     }
 
     #[inline(always)]
     pub fn decode_source_2(instruction: Instruction) -> Instruction {
-        let instruction = (instruction & SOURCE_2_MASK) >> 8;
-
-        instruction
+        (instruction & SOURCE_2_MASK) >> 8
     }
 
     #[inline(always)]
     pub fn decode_16bit_value(instruction: Instruction) -> Instruction {
-        let instruction = instruction & SIXTEEN_BIT_MASK;
-
-        instruction
+        instruction & SIXTEEN_BIT_MASK
     }
 
     #[inline(always)]
@@ -44,4 +42,47 @@ impl InstructionDecoder {
 
         value as i16
     }
+
+    /// Decode the raw value stored in an operand slot
+    #[inline(always)]
+    pub fn decode_operand(instruction: Instruction, slot: OperandSlot) -> Instruction {
+        match slot {
+            OperandSlot::Destination => Self::decode_destination(instruction),
+            OperandSlot::Source1 => Self::decode_source_1(instruction),
+            OperandSlot::Source2 => Self::decode_source_2(instruction),
+            OperandSlot::Immediate => Self::decode_16bit_value(instruction),
+        }
+    }
+
+    /// Decode an operand, combined with the high bits carried by the `Extend`
+    /// prefix preceding the instruction
+    #[inline(always)]
+    pub fn decode_extended_operand(
+        extension: Option<Instruction>,
+        instruction: Instruction,
+        slot: OperandSlot,
+    ) -> Instruction {
+        let low = Self::decode_operand(instruction, slot);
+
+        let Some(extension) = extension else {
+            return low;
+        };
+
+        let high = Self::decode_operand(extension, slot);
+        match slot {
+            OperandSlot::Immediate => (high << 16) | low,
+            _ => (high << 8) | low,
+        }
+    }
+
+    /// Decode the signed immediate, 16 bits wide or 32 bits with a prefix
+    #[inline(always)]
+    pub fn decode_extended_int(extension: Option<Instruction>, instruction: Instruction) -> i32 {
+        match extension {
+            Some(_) => {
+                Self::decode_extended_operand(extension, instruction, OperandSlot::Immediate) as i32
+            }
+            None => Self::decode_16bit_int(instruction) as i32,
+        }
+    }
 }
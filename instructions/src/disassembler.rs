@@ -0,0 +1,137 @@
+use crate::{Instruction, InstructionDecoder, OpCode, Operand, OperandKind, OperandSlot};
+use std::fmt::Write;
+use types::KirinType;
+
+/// Column at which annotations of an instruction start
+const ANNOTATION_COLUMN: usize = 32;
+
+/// Information about a program that is not part of the instruction stream,
+/// used to annotate the disassembly
+pub trait DisassemblyContext {
+    /// Rendering of the constant at `index` for `LOAD_CONST` instructions
+    fn constant(&self, _index: usize) -> Option<String> {
+        None
+    }
+
+    /// Source line that produced the instruction at `index`
+    fn source_line(&self, _index: usize) -> Option<String> {
+        None
+    }
+}
+
+/// Context for instruction streams without constants or debug info
+pub struct NoContext;
+
+impl DisassemblyContext for NoContext {}
+
+pub struct Disassembler {}
+
+impl Disassembler {
+    /// Render a single instruction, e.g. `ADD_INT r0, r0, r1`
+    pub fn instruction(instruction: Instruction) -> String {
+        Self::extended_instruction(None, instruction)
+    }
+
+    /// Render an instruction with the operands widened by its `Extend` prefix
+    pub fn extended_instruction(
+        extension: Option<Instruction>,
+        instruction: Instruction,
+    ) -> String {
+        let Some(opcode) = InstructionDecoder::decode_known_opcode(instruction) else {
+            return format!(".word {:#010x}", instruction);
+        };
+
+        let operands = opcode
+            .operands()
+            .iter()
+            .map(|operand| Self::operand(extension, instruction, operand))
+            .collect::<Vec<_>>();
+
+        if operands.is_empty() {
+            return opcode.mnemonic().to_string();
+        }
+
+        format!("{} {}", opcode.mnemonic(), operands.join(", "))
+    }
+
+    fn operand(
+        extension: Option<Instruction>,
+        instruction: Instruction,
+        operand: &Operand,
+    ) -> String {
+        let value =
+            InstructionDecoder::decode_extended_operand(extension, instruction, operand.slot);
+
+        match operand.kind {
+            OperandKind::Register | OperandKind::RegisterPair => format!("r{}", value),
+            OperandKind::Constant => format!("#{}", value),
+            OperandKind::Int => {
+                InstructionDecoder::decode_extended_int(extension, instruction).to_string()
+            }
+            OperandKind::Count | OperandKind::Address => value.to_string(),
+            OperandKind::Bool => (value != 0).to_string(),
+            OperandKind::Type => match KirinType::from_u8(value as u8) {
+                Some(kind) => kind.to_string(),
+                None => value.to_string(),
+            },
+            OperandKind::Char => format!("{:?}", value as u8 as char),
+        }
+    }
+
+    /// Render an instruction stream, one instruction per line prefixed with its
+    /// index. Source lines from the context are shown as comments above the
+    /// instructions they produced.
+    pub fn instructions(instructions: &[Instruction], context: &dyn DisassemblyContext) -> String {
+        let mut output = String::new();
+        let mut previous_line = None;
+
+        let mut extension = None;
+
+        for (index, &instruction) in instructions.iter().enumerate() {
+            let opcode = InstructionDecoder::decode_known_opcode(instruction);
+
+            // prefixes are shown as part of the instruction they extend
+            if opcode == Some(OpCode::Extend)
+                && extension.is_none()
+                && index + 1 < instructions.len()
+            {
+                extension = Some((index, instruction));
+                continue;
+            }
+
+            let (line_index, prefix) = match extension.take() {
+                Some((prefix_index, prefix)) => (prefix_index, Some(prefix)),
+                None => (index, None),
+            };
+
+            let source_line = context.source_line(line_index);
+            if source_line.is_some() && source_line != previous_line {
+                writeln!(output, "; {}", source_line.as_deref().unwrap_or_default()).unwrap();
+            }
+            previous_line = source_line;
+
+            let mut line = format!(
+                "{:04}  {}",
+                line_index,
+                Self::extended_instruction(prefix, instruction)
+            );
+
+            if opcode == Some(OpCode::LoadConst) {
+                let constant_index = InstructionDecoder::decode_extended_operand(
+                    prefix,
+                    instruction,
+                    OperandSlot::Immediate,
+                ) as usize;
+
+                if let Some(constant) = context.constant(constant_index) {
+                    let padding = ANNOTATION_COLUMN.saturating_sub(line.len()).max(1);
+                    write!(line, "{:padding$}; {}", "", constant).unwrap();
+                }
+            }
+
+            writeln!(output, "{}", line).unwrap();
+        }
+
+        output
+    }
+}
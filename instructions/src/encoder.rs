@@ -2,12 +2,18 @@ use crate::Instruction;
 use crate::constants::{
     DESTINATION_MASK, EIGHT_BIT_MASK, OPCODE_MASK, SIXTEEN_BIT_MASK, SOURCE_1_MASK, SOURCE_2_MASK,
 };
-use crate::opcodes::OpCode;
+use crate::opcodes::{OpCode, Operand, OperandKind, OperandSlot};
 
 pub struct InstructionBuilder {
     instruction: Instruction,
 }
 
+impl Default for InstructionBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl InstructionBuilder {
     pub fn new() -> Self {
         Self { instruction: 0 }
@@ -61,6 +67,63 @@ impl InstructionBuilder {
         self.instruction
     }
 
+    pub fn set_operand(self, slot: OperandSlot, value: Instruction) -> InstructionBuilder {
+        match slot {
+            OperandSlot::Destination => self.set_destination_register(value),
+            OperandSlot::Source1 => self.set_source1_register(value),
+            OperandSlot::Source2 => self.set_source2_register(value),
+            OperandSlot::Immediate => self.set_16bit_value(value),
+        }
+    }
+
+    /// Encode an instruction from its operand values, in the order of the
+    /// opcode's operand layout
+    pub fn with_operands(opcode: OpCode, values: &[Instruction]) -> Instruction {
+        let mut builder = InstructionBuilder::new().set_opcode(opcode);
+
+        for (operand, &value) in opcode.operands().iter().zip(values) {
+            builder = builder.set_operand(operand.slot, value);
+        }
+
+        builder.build()
+    }
+
+    /// Encode an instruction whose operands may not fit their slots. The high
+    /// bits of every operand are then carried by an `Extend` prefix, widening
+    /// registers to 16 bits and the immediate to 32 bits. Returns the prefix,
+    /// if needed, followed by the instruction.
+    pub fn extended(opcode: OpCode, values: &[Instruction]) -> Vec<Instruction> {
+        let operands = opcode.operands().iter().zip(values);
+
+        if operands
+            .clone()
+            .all(|(operand, &value)| fits(operand, value))
+        {
+            return vec![Self::with_operands(opcode, values)];
+        }
+
+        Self::prefixed(opcode, values).to_vec()
+    }
+
+    /// Encode an instruction with an `Extend` prefix even when its operands
+    /// fit their slots, so its size does not depend on their values
+    pub fn prefixed(opcode: OpCode, values: &[Instruction]) -> [Instruction; 2] {
+        let mut prefix = InstructionBuilder::new().set_opcode(OpCode::Extend);
+        let mut low_values = Vec::with_capacity(values.len());
+
+        for (operand, &value) in opcode.operands().iter().zip(values) {
+            let (high, low) = match operand.slot {
+                OperandSlot::Immediate => (value >> 16, value & SIXTEEN_BIT_MASK),
+                _ => (value >> 8, value & EIGHT_BIT_MASK),
+            };
+
+            prefix = prefix.set_operand(operand.slot, high);
+            low_values.push(low);
+        }
+
+        [prefix.build(), Self::with_operands(opcode, &low_values)]
+    }
+
     pub fn simple(opcode: OpCode) -> Instruction {
         InstructionBuilder::new().set_opcode(opcode).build()
     }
@@ -103,6 +166,29 @@ impl InstructionBuilder {
         Self::binary_operation(OpCode::AddInt, destination, source1, source2)
     }
 
+    pub fn load_constant(destination: Instruction, index: Instruction) -> Instruction {
+        InstructionBuilder::new()
+            .set_opcode(OpCode::LoadConst)
+            .set_destination_register(destination)
+            .set_16bit_value(index)
+            .build()
+    }
+
+    pub fn load_bool(destination: Instruction, value: bool) -> Instruction {
+        InstructionBuilder::new()
+            .set_opcode(OpCode::LoadBool)
+            .set_destination_register(destination)
+            .set_16bit_value(value as Instruction)
+            .build()
+    }
+
+    pub fn load_null(destination: Instruction) -> Instruction {
+        InstructionBuilder::new()
+            .set_opcode(OpCode::LoadNull)
+            .set_destination_register(destination)
+            .build()
+    }
+
     pub fn cast(opcode: OpCode, destination: Instruction, source: Instruction) -> Instruction {
         InstructionBuilder::new()
             .set_opcode(opcode)
@@ -111,6 +197,31 @@ impl InstructionBuilder {
             .build()
     }
 
+    /// Conversions that replace the value of a register, e.g. `IntToFloat`
+    pub fn convert(opcode: OpCode, register: Instruction) -> Instruction {
+        InstructionBuilder::new()
+            .set_opcode(opcode)
+            .set_source1_register(register)
+            .build()
+    }
+
+    pub fn type_of(destination: Instruction, source: Instruction) -> Instruction {
+        InstructionBuilder::new()
+            .set_opcode(OpCode::TypeOf)
+            .set_destination_register(destination)
+            .set_source1_register(source)
+            .build()
+    }
+
+    pub fn is_type(destination: Instruction, source: Instruction, tag: Instruction) -> Instruction {
+        InstructionBuilder::new()
+            .set_opcode(OpCode::IsType)
+            .set_destination_register(destination)
+            .set_source1_register(source)
+            .set_source2_register(tag)
+            .build()
+    }
+
     pub fn print_any(source: Instruction) -> Instruction {
         InstructionBuilder::new()
             .set_opcode(OpCode::PrintAny)
@@ -125,6 +236,28 @@ impl InstructionBuilder {
             .build()
     }
 
+    pub fn call(address: Instruction) -> Instruction {
+        InstructionBuilder::new()
+            .set_opcode(OpCode::Call)
+            .set_16bit_value(address)
+            .build()
+    }
+
+    pub fn jump(address: Instruction) -> Instruction {
+        InstructionBuilder::new()
+            .set_opcode(OpCode::Jump)
+            .set_16bit_value(address)
+            .build()
+    }
+
+    pub fn jump_if_false(condition: Instruction, address: Instruction) -> Instruction {
+        InstructionBuilder::new()
+            .set_opcode(OpCode::JumpIfFalse)
+            .set_destination_register(condition)
+            .set_16bit_value(address)
+            .build()
+    }
+
     pub fn allocate_registers(count: Instruction) -> Instruction {
         InstructionBuilder::new()
             .set_opcode(OpCode::AllocReg)
@@ -132,6 +265,14 @@ impl InstructionBuilder {
             .build()
     }
 
+    pub fn move_register(destination: Instruction, source: Instruction) -> Instruction {
+        InstructionBuilder::new()
+            .set_opcode(OpCode::Move)
+            .set_destination_register(destination)
+            .set_source1_register(source)
+            .build()
+    }
+
     pub fn deallocate_registers(count: Instruction) -> Instruction {
         InstructionBuilder::new()
             .set_opcode(OpCode::DeallocReg)
@@ -139,3 +280,12 @@ impl InstructionBuilder {
             .build()
     }
 }
+
+/// Whether the value can be encoded in the operand's slot without an extension
+fn fits(operand: &Operand, value: Instruction) -> bool {
+    match (operand.slot, operand.kind) {
+        (OperandSlot::Immediate, OperandKind::Int) => i16::try_from(value as i32).is_ok(),
+        (OperandSlot::Immediate, _) => value <= SIXTEEN_BIT_MASK,
+        _ => value <= EIGHT_BIT_MASK,
+    }
+}
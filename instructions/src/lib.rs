@@ -1,17 +1,23 @@
 mod constants;
 mod decoder;
+mod disassembler;
 mod encoder;
 mod opcodes;
 
 pub use decoder::InstructionDecoder;
+pub use disassembler::{Disassembler, DisassemblyContext, NoContext};
 pub use encoder::InstructionBuilder;
-pub use opcodes::OpCode;
+pub use opcodes::{OpCode, OpCodeInfo, Operand, OperandKind, OperandSlot};
 
 pub type Instruction = u32;
 
 #[cfg(test)]
 mod instruction_tests {
-    use crate::{InstructionBuilder, InstructionDecoder, OpCode};
+    use crate::{
+        Disassembler, DisassemblyContext, InstructionBuilder, InstructionDecoder, NoContext,
+        OpCode, OperandSlot,
+    };
+    use types::KirinType;
 
     #[test]
     fn test_opcode_encoding_decoding() {
@@ -37,6 +43,94 @@ mod instruction_tests {
         }
     }
 
+    #[test]
+    fn test_known_opcode_decoding() {
+        for value in 0..=u8::MAX {
+            let instruction = (value as u32) << 24;
+
+            match InstructionDecoder::decode_known_opcode(instruction) {
+                Some(opcode) => assert_eq!(opcode as u8, value),
+                None => assert!(value as usize >= OpCode::all().count()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_opcode_table() {
+        for (value, opcode) in OpCode::all().enumerate() {
+            assert_eq!(opcode as usize, value);
+            assert_eq!(OpCode::try_from(value as u8), Ok(opcode));
+            assert_eq!(OpCode::from_mnemonic(opcode.mnemonic()), Some(opcode));
+        }
+
+        assert_eq!(OpCode::try_from(u8::MAX), Err(u8::MAX));
+        assert_eq!(OpCode::LoadInt16.mnemonic(), "LOAD_INT16");
+    }
+
+    #[test]
+    fn test_encoding_from_operand_layout() {
+        assert_eq!(
+            InstructionBuilder::with_operands(OpCode::AddInt, &[0, 1, 2]),
+            InstructionBuilder::binary_operation(OpCode::AddInt, 0, 1, 2)
+        );
+        assert_eq!(
+            InstructionBuilder::with_operands(OpCode::LoadInt16, &[3, -5i16 as u16 as u32]),
+            InstructionBuilder::load_16bit_int(3, -5)
+        );
+        assert_eq!(
+            InstructionBuilder::with_operands(OpCode::IsType, &[1, 2, 3]),
+            InstructionBuilder::is_type(1, 2, 3)
+        );
+    }
+
+    #[test]
+    fn test_extended_encoding_decoding() {
+        assert_eq!(
+            InstructionBuilder::extended(OpCode::AddInt, &[0, 1, 2]),
+            vec![InstructionBuilder::binary_operation(
+                OpCode::AddInt,
+                0,
+                1,
+                2
+            )]
+        );
+
+        let encoded = InstructionBuilder::extended(OpCode::AddInt, &[300, 1, 0xFFFF]);
+        let [prefix, instruction] = encoded[..] else {
+            panic!("expected an extended instruction, got {:?}", encoded);
+        };
+        assert_eq!(
+            InstructionDecoder::decode_known_opcode(prefix),
+            Some(OpCode::Extend)
+        );
+
+        let decode =
+            |slot| InstructionDecoder::decode_extended_operand(Some(prefix), instruction, slot);
+        assert_eq!(decode(OperandSlot::Destination), 300);
+        assert_eq!(decode(OperandSlot::Source1), 1);
+        assert_eq!(decode(OperandSlot::Source2), 0xFFFF);
+
+        for value in [i16::MAX as i32 + 1, -100_000, i32::MIN, i32::MAX] {
+            let encoded = InstructionBuilder::extended(OpCode::LoadInt16, &[0, value as u32]);
+            assert_eq!(encoded.len(), 2);
+            assert_eq!(
+                InstructionDecoder::decode_extended_int(Some(encoded[0]), encoded[1]),
+                value
+            );
+        }
+
+        // negative values that fit 16 bits need no prefix
+        let encoded = InstructionBuilder::extended(OpCode::LoadInt16, &[0, -5i32 as u32]);
+        assert_eq!(encoded, vec![InstructionBuilder::load_16bit_int(0, -5)]);
+
+        // prefixed instructions keep their prefix when the operands fit
+        let [prefix, instruction] = InstructionBuilder::prefixed(OpCode::JumpIfFalse, &[3, 70_000]);
+        let decode =
+            |slot| InstructionDecoder::decode_extended_operand(Some(prefix), instruction, slot);
+        assert_eq!(decode(OperandSlot::Destination), 3);
+        assert_eq!(decode(OperandSlot::Immediate), 70_000);
+    }
+
     #[test]
     fn test_destination_encoding_decoding() {
         for value in 0..256u32 {
@@ -105,4 +199,98 @@ mod instruction_tests {
             assert_eq!(value, decoded_value);
         }
     }
+
+    #[test]
+    fn test_disassemble_instruction() {
+        let cases = [
+            (
+                InstructionBuilder::binary_operation(OpCode::AddInt, 0, 0, 1),
+                "ADD_INT r0, r0, r1",
+            ),
+            (
+                InstructionBuilder::load_16bit_int(1, 480),
+                "LOAD_INT16 r1, 480",
+            ),
+            (
+                InstructionBuilder::load_16bit_int(2, -7),
+                "LOAD_INT16 r2, -7",
+            ),
+            (
+                InstructionBuilder::load_constant(3, 12),
+                "LOAD_CONST r3, #12",
+            ),
+            (InstructionBuilder::load_bool(0, true), "LOAD_BOOL r0, true"),
+            (
+                InstructionBuilder::cast(OpCode::IntToAny, 2, 0),
+                "INT_TO_ANY r2, r0",
+            ),
+            (
+                InstructionBuilder::is_type(3, 0, KirinType::Null as u32),
+                "IS_TYPE r3, r0, none",
+            ),
+            (InstructionBuilder::allocate_registers(4), "ALLOC_REG 4"),
+            (InstructionBuilder::print_char('\n'), "PRINT_CHAR '\\n'"),
+            (InstructionBuilder::simple(OpCode::Halt), "HALT"),
+            (0xFF00_0000, ".word 0xff000000"),
+        ];
+
+        for (instruction, expected) in cases {
+            assert_eq!(Disassembler::instruction(instruction), expected);
+        }
+    }
+
+    #[test]
+    fn test_disassemble_with_context() {
+        struct Context;
+
+        impl DisassemblyContext for Context {
+            fn constant(&self, index: usize) -> Option<String> {
+                Some(format!("constant {}", index))
+            }
+
+            fn source_line(&self, index: usize) -> Option<String> {
+                (index > 0).then(|| "1 | x := 2.5".to_string())
+            }
+        }
+
+        let instructions = [
+            InstructionBuilder::allocate_registers(1),
+            InstructionBuilder::load_constant(0, 0),
+            InstructionBuilder::deallocate_registers(1),
+        ];
+
+        let expected = "\
+0000  ALLOC_REG 1
+; 1 | x := 2.5
+0001  LOAD_CONST r0, #0         ; constant 0
+0002  DEALLOC_REG 1
+";
+        assert_eq!(
+            Disassembler::instructions(&instructions, &Context),
+            expected
+        );
+
+        let plain = Disassembler::instructions(&instructions, &NoContext);
+        assert!(plain.contains("0001  LOAD_CONST r0, #0\n"));
+    }
+
+    #[test]
+    fn test_disassemble_extended_instruction() {
+        let mut instructions = InstructionBuilder::extended(OpCode::LoadConst, &[256, 70_000]);
+        instructions.extend(InstructionBuilder::extended(
+            OpCode::LoadInt16,
+            &[1, -40_000i32 as u32],
+        ));
+        instructions.push(InstructionBuilder::simple(OpCode::Return));
+
+        let expected = "\
+0000  LOAD_CONST r256, #70000
+0002  LOAD_INT16 r1, -40000
+0004  RETURN
+";
+        assert_eq!(
+            Disassembler::instructions(&instructions, &NoContext),
+            expected
+        );
+    }
 }
@@ -1,45 +1,219 @@
-#[repr(u8)]
+/// The opcode table, the single source of truth for every instruction.
+///
+/// Invokes `$callback!` with one entry per opcode in encoding order:
+/// `Name = "MNEMONIC", vm_handler, [slot: kind, ...];`
+///
+/// The handler is the name of the `VM` method executing the instruction and
+/// the operands describe where each operand is stored in the instruction and
+/// how it is interpreted. Adding an instruction only requires a new entry
+/// here and its VM handler.
+#[macro_export]
+macro_rules! opcode_table {
+    ($callback:ident) => {
+        $callback! {
+            None = "NONE", no_operation, [];
+
+            // Load Instructions
+            LoadConst = "LOAD_CONST", load_constant, [Destination: Register, Immediate: Constant];
+            LoadInt16 = "LOAD_INT16", load_int16, [Destination: Register, Immediate: Int];
+            LoadNull = "LOAD_NULL", load_null, [Destination: RegisterPair];
+            LoadBool = "LOAD_BOOL", load_bool, [Destination: Register, Immediate: Bool];
+
+            // Mathematical Instructions
+            AddInt = "ADD_INT", add_int, [Destination: Register, Source1: Register, Source2: Register];
+            AddFloat = "ADD_FLOAT", add_float, [Destination: Register, Source1: Register, Source2: Register];
+            SubInt = "SUB_INT", sub_int, [Destination: Register, Source1: Register, Source2: Register];
+            SubFloat = "SUB_FLOAT", sub_float, [Destination: Register, Source1: Register, Source2: Register];
+            MulInt = "MUL_INT", mul_int, [Destination: Register, Source1: Register, Source2: Register];
+            MulFloat = "MUL_FLOAT", mul_float, [Destination: Register, Source1: Register, Source2: Register];
+            DivInt = "DIV_INT", div_int, [Destination: Register, Source1: Register, Source2: Register];
+            DivFloat = "DIV_FLOAT", div_float, [Destination: Register, Source1: Register, Source2: Register];
+            ModInt = "MOD_INT", mod_int, [Destination: Register, Source1: Register, Source2: Register];
+            ModFloat = "MOD_FLOAT", mod_float, [Destination: Register, Source1: Register, Source2: Register];
+            PowInt = "POW_INT", pow_int, [Destination: Register, Source1: Register, Source2: Register];
+            PowFloat = "POW_FLOAT", pow_float, [Destination: Register, Source1: Register, Source2: Register];
+
+            // Modular integer arithmetic
+            WrappingAddInt = "WRAPPING_ADD_INT", wrapping_add_int, [Destination: Register, Source1: Register, Source2: Register];
+            WrappingSubInt = "WRAPPING_SUB_INT", wrapping_sub_int, [Destination: Register, Source1: Register, Source2: Register];
+            WrappingMulInt = "WRAPPING_MUL_INT", wrapping_mul_int, [Destination: Register, Source1: Register, Source2: Register];
+            WrappingPowInt = "WRAPPING_POW_INT", wrapping_pow_int, [Destination: Register, Source1: Register, Source2: Register];
+
+            // Comparison Instructions producing a bool
+            EqualInt = "EQUAL_INT", equal_int, [Destination: Register, Source1: Register, Source2: Register];
+            EqualFloat = "EQUAL_FLOAT", equal_float, [Destination: Register, Source1: Register, Source2: Register];
+            LessInt = "LESS_INT", less_int, [Destination: Register, Source1: Register, Source2: Register];
+            LessFloat = "LESS_FLOAT", less_float, [Destination: Register, Source1: Register, Source2: Register];
+            LessEqualInt = "LESS_EQUAL_INT", less_equal_int, [Destination: Register, Source1: Register, Source2: Register];
+            LessEqualFloat = "LESS_EQUAL_FLOAT", less_equal_float, [Destination: Register, Source1: Register, Source2: Register];
+
+            // Logical Instructions on bools
+            Not = "NOT", not, [Destination: Register, Source1: Register];
+            And = "AND", and, [Destination: Register, Source1: Register, Source2: Register];
+            Or = "OR", or, [Destination: Register, Source1: Register, Source2: Register];
+
+            // Dynamic Mathematical Instructions on Any register pairs
+            AddAny = "ADD_ANY", add_any, [Destination: RegisterPair, Source1: RegisterPair, Source2: RegisterPair];
+            SubAny = "SUB_ANY", sub_any, [Destination: RegisterPair, Source1: RegisterPair, Source2: RegisterPair];
+            MulAny = "MUL_ANY", mul_any, [Destination: RegisterPair, Source1: RegisterPair, Source2: RegisterPair];
+            DivAny = "DIV_ANY", div_any, [Destination: RegisterPair, Source1: RegisterPair, Source2: RegisterPair];
+            ModAny = "MOD_ANY", mod_any, [Destination: RegisterPair, Source1: RegisterPair, Source2: RegisterPair];
+            PowAny = "POW_ANY", pow_any, [Destination: RegisterPair, Source1: RegisterPair, Source2: RegisterPair];
+
+            // Casting, int/float conversions happen in place
+            IntToAny = "INT_TO_ANY", cast_int_to_any, [Destination: RegisterPair, Source1: Register];
+            FloatToAny = "FLOAT_TO_ANY", cast_float_to_any, [Destination: RegisterPair, Source1: Register];
+            StringToAny = "STRING_TO_ANY", cast_string_to_any, [Destination: RegisterPair, Source1: Register];
+            BoolToAny = "BOOL_TO_ANY", cast_bool_to_any, [Destination: RegisterPair, Source1: Register];
+            IntToFloat = "INT_TO_FLOAT", cast_int_to_float, [Source1: Register];
+            FloatToInt = "FLOAT_TO_INT", cast_float_to_int, [Source1: Register];
+
+            // Checked downcasts from an Any register pair
+            AnyToInt = "ANY_TO_INT", cast_any_to_int, [Destination: Register, Source1: RegisterPair];
+            AnyToFloat = "ANY_TO_FLOAT", cast_any_to_float, [Destination: Register, Source1: RegisterPair];
+            AnyToString = "ANY_TO_STRING", cast_any_to_string, [Destination: Register, Source1: RegisterPair];
+            AnyToBool = "ANY_TO_BOOL", cast_any_to_bool, [Destination: Register, Source1: RegisterPair];
+
+            // Type tags
+            TypeOf = "TYPE_OF", type_of, [Destination: Register, Source1: RegisterPair];
+            IsType = "IS_TYPE", is_type, [Destination: Register, Source1: RegisterPair, Source2: Type];
+
+            // Frames
+            InitFrame = "INIT_FRAME", init_frame, [];
+            DropFrame = "DROP_FRAME", drop_frame, [];
+            Call = "CALL", call, [Immediate: Address];
+            Return = "RETURN", do_return, [];
+
+            // Allocation
+            AllocReg = "ALLOC_REG", allocate_registers, [Immediate: Count];
+            DeallocReg = "DEALLOC_REG", deallocate_registers, [Immediate: Count];
+
+            // Registers
+            Move = "MOVE", copy_register, [Destination: Register, Source1: Register];
+
+            // Library
+            PrintAny = "PRINT_ANY", print_any, [Source1: RegisterPair];
+            PrintChar = "PRINT_CHAR", print_char, [Source1: Char];
+
+            // represents end of instructions
+            Halt = "HALT", halt, [];
+
+            // Prefix carrying the high bits of the next instruction's operands,
+            // stored in the same slots as the operands they extend
+            Extend = "EXTEND", extend, [];
+
+            // Control flow, the condition is a bool register
+            Jump = "JUMP", jump, [Immediate: Address];
+            JumpIfFalse = "JUMP_IF_FALSE", jump_if_false, [Destination: Register, Immediate: Address];
+        }
+    };
+}
+
+/// Part of the instruction an operand is stored in
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OperandSlot {
+    Destination,
+    Source1,
+    Source2,
+    /// the lower 16 bits, overlapping both sources
+    Immediate,
+}
+
+/// How the value of an operand is interpreted
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OperandKind {
+    Register,
+    /// the first of two registers holding an Any value
+    RegisterPair,
+    /// index into the constant pool
+    Constant,
+    /// signed integer, 16 bits wide or 32 bits with an `Extend` prefix
+    Int,
+    /// number of registers
+    Count,
+    Bool,
+    /// tag of a `KirinType`
+    Type,
+    Char,
+    /// instruction index
+    Address,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Operand {
+    pub slot: OperandSlot,
+    pub kind: OperandKind,
+}
+
+/// Name and operand layout of an opcode
 #[derive(Debug, Copy, Clone)]
-pub enum OpCode {
-    None,
-
-    // Load Instructions
-    LoadConst,
-    LoadInt16,
-
-    // Mathematical Instructions [OpCode dest src1 src2]
-    AddInt,
-    AddFloat,
-    SubInt,
-    SubFloat,
-    MulInt,
-    MulFloat,
-    DivInt,
-    DivFloat,
-    ModInt,
-    ModFloat,
-    PowInt,
-    PowFloat,
-
-    // Casting
-    IntToAny,
-    FloatToAny,
-    IntToFloat,
-    FloatToInt,
-
-    // Frames
-    InitFrame,
-    DropFrame,
-    Return,
-
-    // Allocation [OpCode <register-count>]
-    AllocReg,
-    DeallocReg,
-
-    // Library
-    PrintAny,
-    PrintChar,
-
-    // represents end of instructions
-    Halt,
+pub struct OpCodeInfo {
+    pub mnemonic: &'static str,
+    pub operands: &'static [Operand],
+}
+
+macro_rules! define_opcodes {
+    ($($name:ident = $mnemonic:literal, $handler:ident, [$($slot:ident: $kind:ident),*];)*) => {
+        #[repr(u8)]
+        #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+        pub enum OpCode {
+            $($name,)*
+        }
+
+        /// Every opcode with its metadata, indexed by its encoded value
+        const OPCODES: &[(OpCode, OpCodeInfo)] = &[
+            $((
+                OpCode::$name,
+                OpCodeInfo {
+                    mnemonic: $mnemonic,
+                    operands: &[$(Operand {
+                        slot: OperandSlot::$slot,
+                        kind: OperandKind::$kind,
+                    }),*],
+                },
+            ),)*
+        ];
+    };
+}
+
+opcode_table!(define_opcodes);
+
+impl TryFrom<u8> for OpCode {
+    /// the value that is not a known opcode
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        OPCODES
+            .get(value as usize)
+            .map(|(opcode, _)| *opcode)
+            .ok_or(value)
+    }
+}
+
+impl OpCode {
+    pub fn info(self) -> &'static OpCodeInfo {
+        &OPCODES[self as usize].1
+    }
+
+    /// Name of the opcode in assembly, e.g. `ADD_INT`
+    pub fn mnemonic(self) -> &'static str {
+        self.info().mnemonic
+    }
+
+    pub fn operands(self) -> &'static [Operand] {
+        self.info().operands
+    }
+
+    /// Look up an opcode by its mnemonic, ignoring case
+    pub fn from_mnemonic(mnemonic: &str) -> Option<Self> {
+        OPCODES
+            .iter()
+            .find(|(_, info)| info.mnemonic.eq_ignore_ascii_case(mnemonic))
+            .map(|(opcode, _)| *opcode)
+    }
+
+    /// Every opcode in encoding order
+    pub fn all() -> impl Iterator<Item = OpCode> {
+        OPCODES.iter().map(|(opcode, _)| *opcode)
+    }
 }
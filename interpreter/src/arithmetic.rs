@@ -0,0 +1,181 @@
+use crate::{Value, runtime_error};
+use errors::{KirinError, codes};
+use parser::expressions::BinaryOp;
+use parser::span::AstSpan;
+
+/// Whether `operator` computes a number from its operands
+pub(crate) fn is_arithmetic(operator: BinaryOp) -> bool {
+    matches!(
+        operator,
+        BinaryOp::Add
+            | BinaryOp::Subtract
+            | BinaryOp::Multiply
+            | BinaryOp::Divide
+            | BinaryOp::Modulus
+            | BinaryOp::Power
+    )
+}
+
+/// The operator of a `wrapping_*` library function
+pub(crate) fn library_operator(name: &str) -> Option<BinaryOp> {
+    let operator = match name {
+        "wrapping_add" => BinaryOp::Add,
+        "wrapping_sub" => BinaryOp::Subtract,
+        "wrapping_mul" => BinaryOp::Multiply,
+        "wrapping_pow" => BinaryOp::Power,
+
+        _ => return None,
+    };
+
+    Some(operator)
+}
+
+/// Ints stay ints and report overflow, other numbers are computed as
+/// floats. Operands of `any` arithmetic are checked here.
+pub(crate) fn arithmetic(
+    operator: BinaryOp,
+    left: Value,
+    right: Value,
+    span: &AstSpan,
+) -> Result<Value, KirinError> {
+    match (&left, &right) {
+        (Value::Int(first), Value::Int(second)) => {
+            checked_int(operator, *first, *second, span).map(Value::Int)
+        }
+
+        _ => match (left.as_float(), right.as_float()) {
+            (Some(first), Some(second)) => Ok(Value::Float(float(operator, first, second))),
+            _ => Err(runtime_error(
+                span,
+                codes::UNSUPPORTED_OPERANDS,
+                format!(
+                    "unsupported operand types for `{}`: `{}` and `{}`",
+                    operator.symbol(),
+                    left.kind(),
+                    right.kind()
+                ),
+            )),
+        },
+    }
+}
+
+/// Modular arithmetic for the `wrapping_*` library functions
+pub(crate) fn wrapping_int(
+    operator: BinaryOp,
+    first: i64,
+    second: i64,
+    span: &AstSpan,
+) -> Result<i64, KirinError> {
+    match operator {
+        BinaryOp::Add => Ok(first.wrapping_add(second)),
+        BinaryOp::Subtract => Ok(first.wrapping_sub(second)),
+        BinaryOp::Multiply => Ok(first.wrapping_mul(second)),
+        BinaryOp::Power => wrapping_pow(first, second, span),
+
+        operator => checked_int(operator, first, second, span),
+    }
+}
+
+fn checked_int(
+    operator: BinaryOp,
+    first: i64,
+    second: i64,
+    span: &AstSpan,
+) -> Result<i64, KirinError> {
+    let result = match operator {
+        BinaryOp::Divide | BinaryOp::Modulus if second == 0 => {
+            return Err(runtime_error(
+                span,
+                codes::DIVISION_BY_ZERO,
+                format!("division by zero: `{} {} 0`", first, operator.symbol()),
+            ));
+        }
+        BinaryOp::Add => first.checked_add(second),
+        BinaryOp::Subtract => first.checked_sub(second),
+        BinaryOp::Multiply => first.checked_mul(second),
+        BinaryOp::Divide => first.checked_div(second),
+        BinaryOp::Modulus => first.checked_rem(second),
+        BinaryOp::Power => first.checked_pow(exponent(first, second, span)?),
+
+        operator => {
+            return Err(runtime_error(
+                span,
+                codes::RUNTIME,
+                format!("`{}` is not arithmetic", operator.symbol()),
+            ));
+        }
+    };
+
+    result.ok_or_else(|| {
+        runtime_error(
+            span,
+            codes::INTEGER_OVERFLOW,
+            format!(
+                "integer overflow: `{} {} {}`",
+                first,
+                operator.symbol(),
+                second
+            ),
+        )
+    })
+}
+
+fn float(operator: BinaryOp, first: f64, second: f64) -> f64 {
+    match operator {
+        BinaryOp::Add => first + second,
+        BinaryOp::Subtract => first - second,
+        BinaryOp::Multiply => first * second,
+        BinaryOp::Divide => first / second,
+        BinaryOp::Modulus => first % second,
+        BinaryOp::Power => first.powf(second),
+
+        _ => f64::NAN,
+    }
+}
+
+/// `base ^ exponent` modulo 2^64 for `wrapping_pow`, which takes any
+/// exponent that is not negative
+fn wrapping_pow(base: i64, exponent: i64, span: &AstSpan) -> Result<i64, KirinError> {
+    if exponent < 0 {
+        return Err(negative_exponent(base, exponent, span));
+    }
+
+    // square and multiply for every bit of the exponent
+    let (mut base, mut exponent, mut result) = (base, exponent as u64, 1i64);
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = result.wrapping_mul(base);
+        }
+
+        base = base.wrapping_mul(base);
+        exponent >>= 1;
+    }
+
+    Ok(result)
+}
+
+/// Integer powers take exponents from 0 to `u32::MAX`
+fn exponent(base: i64, exponent: i64, span: &AstSpan) -> Result<u32, KirinError> {
+    if exponent < 0 {
+        return Err(negative_exponent(base, exponent, span));
+    }
+
+    u32::try_from(exponent).map_err(|_| {
+        runtime_error(
+            span,
+            codes::INTEGER_OVERFLOW,
+            format!("integer overflow: `{} ^ {}`", base, exponent),
+        )
+    })
+}
+
+fn negative_exponent(base: i64, exponent: i64, span: &AstSpan) -> KirinError {
+    runtime_error(
+        span,
+        codes::NEGATIVE_EXPONENT,
+        format!(
+            "negative exponent in integer power: `{} ^ {}`",
+            base, exponent
+        ),
+    )
+}
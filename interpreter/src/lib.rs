@@ -0,0 +1,612 @@
+mod arithmetic;
+mod value;
+
+use errors::{KirinError, codes};
+use parser::expressions::{
+    Assign, Binary, BinaryOp, Call, Expression, Grouping, Literal, Unary, UnaryOp, Variable,
+};
+use parser::span::AstSpan;
+use parser::statements::{If, Statement, VariableDeclaration, While};
+use parser::value::ParsedValue;
+use parser::visitor::{ExpressionVisitor, StatementVisitor};
+use std::collections::HashMap;
+use types::KirinType;
+
+pub use value::Value;
+
+/// Executes the analyzed AST directly. Conversions, arithmetic and runtime
+/// errors follow the code the compiler emits for the same statements, so
+/// programs print the same output as on the VM. The arithmetic is its own
+/// rather than the VM's, so comparing both checks them against each other.
+pub struct Interpreter {
+    /// the declared type and current value of each variable, per scope
+    variables: Vec<HashMap<String, (KirinType, Value)>>,
+    /// printed text is collected here instead of stdout when set
+    output: Option<String>,
+    /// whether a `return` ended the statements of the current call
+    returned: bool,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Self {
+            variables: vec![HashMap::new()],
+            output: None,
+            returned: false,
+        }
+    }
+
+    /// Collect printed text instead of writing it to stdout
+    pub fn capture_output(&mut self) {
+        self.output = Some(String::new());
+    }
+
+    /// The text printed since the last call, empty unless output is captured
+    pub fn take_output(&mut self) -> String {
+        self.output.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    /// Execute type checked statements until a `return`. Variables stay
+    /// defined for the statements of later calls.
+    pub fn interpret(&mut self, statements: &[Statement]) -> Result<(), KirinError> {
+        self.returned = false;
+        self.execute(statements)
+    }
+
+    fn execute(&mut self, statements: &[Statement]) -> Result<(), KirinError> {
+        for statement in statements {
+            if self.returned {
+                break;
+            }
+
+            statement.accept(self)?;
+        }
+
+        Ok(())
+    }
+
+    /// Execute the statements of a block in a scope of their own
+    fn block(&mut self, statements: &[Statement]) -> Result<(), KirinError> {
+        self.variables.push(HashMap::new());
+        let result = self.execute(statements);
+        self.variables.pop();
+
+        result
+    }
+
+    /// The value of an `if` or `while` condition
+    fn condition(&mut self, condition: &Expression, span: &AstSpan) -> Result<bool, KirinError> {
+        let kind = Self::expression_type(condition, span)?;
+        let value = self.evaluate(condition)?;
+
+        match Self::convert(value, kind, KirinType::Bool, span)? {
+            Value::Bool(value) => Ok(value),
+            value => Err(runtime_error(
+                span,
+                codes::RUNTIME,
+                format!("condition of type `{}` is not a bool", value.kind()),
+            )),
+        }
+    }
+
+    /// The type and value of a variable, in the innermost scope declaring it
+    fn variable(&mut self, name: &str) -> Option<&mut (KirinType, Value)> {
+        self.variables
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(name))
+    }
+
+    /// The value of a type checked expression, assignments and printed text
+    /// take effect like in a statement
+    pub fn evaluate(&mut self, expression: &Expression) -> Result<Value, KirinError> {
+        expression.accept(self)
+    }
+
+    /// Define a variable for the following statements, replacing one of the
+    /// same name
+    pub fn define(&mut self, name: &str, kind: KirinType, value: Value) {
+        if let Some(scope) = self.variables.last_mut() {
+            scope.insert(name.to_string(), (kind, value));
+        }
+    }
+
+    fn write_output(&mut self, text: &str) {
+        match &mut self.output {
+            Some(output) => output.push_str(text),
+            None => print!("{}", text),
+        }
+    }
+
+    fn expression_type(expression: &Expression, span: &AstSpan) -> Result<KirinType, KirinError> {
+        expression.inferred_type().ok_or_else(|| {
+            runtime_error(
+                span,
+                codes::RUNTIME,
+                "expression has not been type checked".to_string(),
+            )
+        })
+    }
+
+    /// Convert a value of the static type `from` to `to`, boxing into `any`
+    /// or checking the type of the value when unboxing
+    fn convert(
+        value: Value,
+        from: KirinType,
+        to: KirinType,
+        span: &AstSpan,
+    ) -> Result<Value, KirinError> {
+        match (from, to, value) {
+            (from, to, value) if from == to => Ok(value),
+            (KirinType::Int, KirinType::Float, Value::Int(value)) => Ok(Value::Float(value as f64)),
+            (
+                KirinType::Int
+                | KirinType::Float
+                | KirinType::String
+                | KirinType::Bool
+                | KirinType::Null,
+                KirinType::Any,
+                value,
+            ) => Ok(value),
+            (
+                KirinType::Any,
+                KirinType::Int | KirinType::Float | KirinType::String | KirinType::Bool,
+                value,
+            ) => {
+                if value.kind() != to {
+                    return Err(runtime_error(
+                        span,
+                        codes::FAILED_DOWNCAST,
+                        format!(
+                            "cannot downcast value of type `{}` to `{}`",
+                            value.kind(),
+                            to
+                        ),
+                    ));
+                }
+
+                Ok(value)
+            }
+
+            _ => Err(runtime_error(
+                span,
+                codes::RUNTIME,
+                format!("cannot convert `{}` to `{}`", from, to),
+            )),
+        }
+    }
+
+    /// The type both operands of a binary operation are converted to
+    fn operand_type(
+        operator: BinaryOp,
+        left_type: KirinType,
+        right_type: KirinType,
+        kind: KirinType,
+    ) -> KirinType {
+        match operator {
+            BinaryOp::Equal
+            | BinaryOp::NotEqual
+            | BinaryOp::Greater
+            | BinaryOp::GreaterEqual
+            | BinaryOp::Less
+            | BinaryOp::LessEqual
+                if left_type != right_type =>
+            {
+                KirinType::Float
+            }
+            BinaryOp::Equal
+            | BinaryOp::NotEqual
+            | BinaryOp::Greater
+            | BinaryOp::GreaterEqual
+            | BinaryOp::Less
+            | BinaryOp::LessEqual => left_type,
+            BinaryOp::And | BinaryOp::Or => KirinType::Bool,
+
+            _ => kind,
+        }
+    }
+
+    fn comparison(operator: BinaryOp, left: Value, right: Value) -> Option<Value> {
+        let result = match (operator, left, right) {
+            (BinaryOp::And, Value::Bool(left), Value::Bool(right)) => left && right,
+            (BinaryOp::Or, Value::Bool(left), Value::Bool(right)) => left || right,
+            (BinaryOp::Equal, Value::Bool(left), Value::Bool(right)) => left == right,
+            (BinaryOp::NotEqual, Value::Bool(left), Value::Bool(right)) => left != right,
+            (operator, Value::Int(left), Value::Int(right)) => compare(operator, left, right)?,
+            (operator, Value::Float(left), Value::Float(right)) => compare(operator, left, right)?,
+
+            _ => return None,
+        };
+
+        Some(Value::Bool(result))
+    }
+}
+
+fn compare<T: PartialOrd>(operator: BinaryOp, left: T, right: T) -> Option<bool> {
+    let result = match operator {
+        BinaryOp::Equal => left == right,
+        BinaryOp::NotEqual => left != right,
+        BinaryOp::Greater => left > right,
+        BinaryOp::GreaterEqual => left >= right,
+        BinaryOp::Less => left < right,
+        BinaryOp::LessEqual => left <= right,
+
+        _ => return None,
+    };
+
+    Some(result)
+}
+
+fn runtime_error(span: &AstSpan, code: &'static str, message: String) -> KirinError {
+    KirinError::Runtime(span.error(message).with_code(code))
+}
+
+impl StatementVisitor for Interpreter {
+    type Output = Result<(), KirinError>;
+
+    fn visit_none(&mut self) -> Self::Output {
+        Ok(())
+    }
+
+    fn visit_var_declaration(&mut self, var_declaration: &VariableDeclaration) -> Self::Output {
+        let span = &var_declaration.span;
+        let Some(kind) = var_declaration.inferred_type else {
+            return Err(runtime_error(
+                span,
+                codes::RUNTIME,
+                format!(
+                    "variable `{}` has not been type checked",
+                    var_declaration.name
+                ),
+            ));
+        };
+
+        let value = match &var_declaration.initializer {
+            Some(initializer) => {
+                let value_type = Self::expression_type(initializer, span)?;
+                let value = self.evaluate(initializer)?;
+
+                Self::convert(value, value_type, kind, span)?
+            }
+            None => match kind {
+                KirinType::Int => Value::Int(0),
+                KirinType::Float => Value::Float(0.0),
+                KirinType::Bool => Value::Bool(false),
+                KirinType::String => Value::String(String::new()),
+                _ => Value::Null,
+            },
+        };
+
+        self.define(&var_declaration.name, kind, value);
+
+        Ok(())
+    }
+
+    fn visit_expression_statement(&mut self, expression_statement: &Expression) -> Self::Output {
+        self.evaluate(expression_statement)?;
+
+        Ok(())
+    }
+
+    fn visit_if(&mut self, if_statement: &If) -> Self::Output {
+        if self.condition(&if_statement.condition, &if_statement.span)? {
+            return self.block(&if_statement.then_branch);
+        }
+
+        match &if_statement.else_branch {
+            Some(else_branch) => self.block(else_branch),
+            None => Ok(()),
+        }
+    }
+
+    fn visit_while(&mut self, while_statement: &While) -> Self::Output {
+        while !self.returned && self.condition(&while_statement.condition, &while_statement.span)? {
+            self.block(&while_statement.body)?;
+        }
+
+        Ok(())
+    }
+
+    fn visit_return(&mut self, _span: &AstSpan) -> Self::Output {
+        self.returned = true;
+
+        Ok(())
+    }
+}
+
+impl ExpressionVisitor for Interpreter {
+    type Output = Result<Value, KirinError>;
+
+    fn visit_binary(&mut self, binary: &Binary) -> Self::Output {
+        let span = &binary.span;
+        let kind = binary.inferred_type.ok_or_else(|| {
+            runtime_error(
+                span,
+                codes::RUNTIME,
+                "expression has not been type checked".to_string(),
+            )
+        })?;
+
+        let left_type = Self::expression_type(&binary.left, span)?;
+        let right_type = Self::expression_type(&binary.right, span)?;
+        let operand_type = Self::operand_type(binary.operator, left_type, right_type, kind);
+
+        let left = self.evaluate(&binary.left)?;
+        let left = Self::convert(left, left_type, operand_type, span)?;
+        let right = self.evaluate(&binary.right)?;
+        let right = Self::convert(right, right_type, operand_type, span)?;
+
+        if arithmetic::is_arithmetic(binary.operator)
+            && matches!(kind, KirinType::Int | KirinType::Float | KirinType::Any)
+        {
+            return arithmetic::arithmetic(binary.operator, left, right, span);
+        }
+
+        Self::comparison(binary.operator, left, right).ok_or_else(|| {
+            runtime_error(
+                span,
+                codes::RUNTIME,
+                format!(
+                    "binary operator `{}` not implemented for `{}`",
+                    binary.operator.symbol(),
+                    operand_type
+                ),
+            )
+        })
+    }
+
+    fn visit_unary(&mut self, unary: &Unary) -> Self::Output {
+        let span = &unary.span;
+        let kind = Self::expression_type(&unary.right, span)?;
+        let right = self.evaluate(&unary.right)?;
+
+        match unary.operator {
+            // negation is `0 - value` like in the compiled code
+            UnaryOp::Negate => match (kind, right) {
+                (KirinType::Float, Value::Float(value)) => Ok(Value::Float(0.0 - value)),
+                (KirinType::Int | KirinType::Any, right) => {
+                    arithmetic::arithmetic(BinaryOp::Subtract, Value::Int(0), right, span)
+                }
+
+                _ => Err(runtime_error(
+                    span,
+                    codes::RUNTIME,
+                    format!("cannot negate value of type `{}`", kind),
+                )),
+            },
+            UnaryOp::Not => match Self::convert(right, kind, KirinType::Bool, span)? {
+                Value::Bool(value) => Ok(Value::Bool(!value)),
+                value => Err(runtime_error(
+                    span,
+                    codes::RUNTIME,
+                    format!("cannot negate value of type `{}`", value.kind()),
+                )),
+            },
+        }
+    }
+
+    fn visit_grouping(&mut self, grouping: &Grouping) -> Self::Output {
+        self.evaluate(&grouping.expression)
+    }
+
+    fn visit_literal(&mut self, literal: &Literal) -> Self::Output {
+        match &literal.value {
+            ParsedValue::Int(value) => Ok(Value::Int(*value)),
+            ParsedValue::Float(value) => Ok(Value::Float(*value)),
+            ParsedValue::Bool(value) => Ok(Value::Bool(*value)),
+            ParsedValue::String(value) => Ok(Value::String(value.clone())),
+            ParsedValue::Null => Ok(Value::Null),
+
+            value => Err(runtime_error(
+                &literal.span,
+                codes::RUNTIME,
+                format!("literal `{:?}` not yet supported", value),
+            )),
+        }
+    }
+
+    fn visit_call(&mut self, callable: &Call) -> Self::Output {
+        let span = &callable.span;
+
+        let Expression::Variable(callee) = &callable.callee else {
+            return Err(runtime_error(
+                span,
+                codes::RUNTIME,
+                "only library functions can be called".to_string(),
+            ));
+        };
+
+        if callee.name == "print" {
+            let [argument] = &callable.arguments[..] else {
+                return Err(runtime_error(
+                    span,
+                    codes::RUNTIME,
+                    "`print` expects 1 argument".to_string(),
+                ));
+            };
+
+            let argument_type = Self::expression_type(argument, span)?;
+            let value = self.evaluate(argument)?;
+            let value = Self::convert(value, argument_type, KirinType::Any, span)?;
+
+            self.write_output(&format!("{}\n", value));
+
+            return Ok(value);
+        }
+
+        let Some(operator) = arithmetic::library_operator(&callee.name) else {
+            return Err(runtime_error(
+                span,
+                codes::RUNTIME,
+                format!("undefined function `{}`", callee.name),
+            ));
+        };
+
+        let mut operands = Vec::with_capacity(callable.arguments.len());
+        for argument in &callable.arguments {
+            let argument_type = Self::expression_type(argument, span)?;
+            let operand = self.evaluate(argument)?;
+            operands.push(Self::convert(operand, argument_type, KirinType::Int, span)?);
+        }
+
+        let [Value::Int(first), Value::Int(second)] = operands[..] else {
+            return Err(runtime_error(
+                span,
+                codes::RUNTIME,
+                format!("`{}` expects 2 arguments", callee.name),
+            ));
+        };
+
+        arithmetic::wrapping_int(operator, first, second, span).map(Value::Int)
+    }
+
+    fn visit_variable(&mut self, variable: &Variable) -> Self::Output {
+        match self.variable(&variable.name) {
+            Some((_, value)) => Ok(value.clone()),
+            None => Err(runtime_error(
+                &variable.span,
+                codes::RUNTIME,
+                format!("undefined variable `{}`", variable.name),
+            )),
+        }
+    }
+
+    fn visit_assign(&mut self, assign: &Assign) -> Self::Output {
+        let span = &assign.span;
+
+        let Some(&mut (target, _)) = self.variable(&assign.name) else {
+            return Err(runtime_error(
+                span,
+                codes::RUNTIME,
+                format!("assignment to undefined variable `{}`", assign.name),
+            ));
+        };
+
+        let value_type = Self::expression_type(&assign.value, span)?;
+        let value = self.evaluate(&assign.value)?;
+        let value = Self::convert(value, value_type, target, span)?;
+
+        if let Some(variable) = self.variable(&assign.name) {
+            *variable = (target, value.clone());
+        }
+
+        Ok(value)
+    }
+
+    fn visit_error(&mut self, span: &AstSpan) -> Self::Output {
+        Err(runtime_error(
+            span,
+            codes::RUNTIME,
+            "cannot run source that failed to parse".to_string(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod interpreter_tests {
+    use crate::{Interpreter, Value};
+    use errors::KirinError;
+
+    fn interpret(source: &str) -> (Interpreter, Result<(), KirinError>) {
+        let tokens = scanner::scan_tokens(source).unwrap();
+        let ast = parser::parse_ast(tokens, None).unwrap();
+        let analyzed_ast = analyzer::TypeChecker::new().infer_types(&ast).unwrap();
+
+        let mut interpreter = Interpreter::new();
+        interpreter.capture_output();
+        let result = interpreter.interpret(&analyzed_ast);
+
+        (interpreter, result)
+    }
+
+    #[test]
+    fn test_interpret_arithmetic() {
+        let (interpreter, result) =
+            interpret("first := 40 - 9\nsecond := first * 300\nthird := first / 2.5\n");
+
+        assert!(result.is_ok());
+        assert_eq!(interpreter.variables[0]["second"].1, Value::Int(9300));
+        assert_eq!(interpreter.variables[0]["third"].1, Value::Float(12.4));
+    }
+
+    #[test]
+    fn test_print_values() {
+        let (mut interpreter, result) = interpret(
+            "let a: any = 10\na = a * 2.5\nprint(a)\nprint(3 > 2.5)\nprint(none)\nprint(\"text\")\n",
+        );
+
+        assert!(result.is_ok());
+        assert_eq!(interpreter.take_output(), "25\ntrue\nnone\ntext\n");
+    }
+
+    #[test]
+    fn test_runtime_errors_match_the_vm() {
+        let (_, result) = interpret("a := 9223372036854775807\n\nb := a + 1\n");
+        match result {
+            Err(KirinError::Runtime(error)) => {
+                assert_eq!((error.line, error.column), (3, 8));
+                assert_eq!(error.message, "integer overflow: `9223372036854775807 + 1`");
+            }
+            result => panic!("expected runtime error, got {:?}", result),
+        }
+
+        let failures = [
+            (
+                "let a: any = \"text\"\nlet b: int = a\n",
+                "cannot downcast value of type `string` to `int`",
+            ),
+            ("print(7 % (1 - 1))\n", "division by zero: `7 % 0`"),
+            (
+                "print(2 ^ -1)\n",
+                "negative exponent in integer power: `2 ^ -1`",
+            ),
+            (
+                "print(wrapping_pow(2, -1))\n",
+                "negative exponent in integer power: `2 ^ -1`",
+            ),
+            (
+                "let a: any = true\nprint(a + 1)\n",
+                "unsupported operand types for `+`: `bool` and `int`",
+            ),
+        ];
+        for (source, message) in failures {
+            match interpret(source).1 {
+                Err(KirinError::Runtime(error)) => assert_eq!(error.message, message),
+                result => panic!("expected runtime error, got {:?}", result),
+            }
+        }
+
+        let (mut interpreter, result) = interpret("print(wrapping_mul(9223372036854775807, 2))\n");
+        assert!(result.is_ok());
+        assert_eq!(interpreter.take_output(), "-2\n");
+
+        // exponents beyond `u32::MAX` wrap too
+        let (mut interpreter, result) =
+            interpret("print(wrapping_pow(3, 4294967296))\nprint(wrapping_pow(-1, 4294967297))\n");
+        assert!(result.is_ok());
+        assert_eq!(interpreter.take_output(), "2491309678558969857\n-1\n");
+    }
+
+    #[test]
+    fn test_variables_persist_between_calls() {
+        let mut interpreter = Interpreter::new();
+        interpreter.capture_output();
+
+        let mut checker = analyzer::TypeChecker::new();
+        for source in ["a := 2\n", "print(a * 21)\n"] {
+            let tokens = scanner::scan_tokens(source).unwrap();
+            let ast = parser::parse_ast(tokens, None).unwrap();
+            let analyzed_ast = checker.infer_types(&ast).unwrap();
+
+            interpreter.interpret(&analyzed_ast).unwrap();
+        }
+
+        assert_eq!(interpreter.take_output(), "42\n");
+    }
+}
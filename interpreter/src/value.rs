@@ -0,0 +1,48 @@
+use std::fmt::{Display, Formatter};
+use types::KirinType;
+
+/// A runtime value. Variables of type `any` hold any of them, their type is
+/// the one of the value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    String(String),
+    Null,
+}
+
+impl Value {
+    pub fn kind(&self) -> KirinType {
+        match self {
+            Value::Int(_) => KirinType::Int,
+            Value::Float(_) => KirinType::Float,
+            Value::Bool(_) => KirinType::Bool,
+            Value::String(_) => KirinType::String,
+            Value::Null => KirinType::Null,
+        }
+    }
+
+    /// Numbers as floats, ints are converted like `INT_TO_FLOAT`
+    pub(crate) fn as_float(&self) -> Option<f64> {
+        match self {
+            Value::Int(value) => Some(*value as f64),
+            Value::Float(value) => Some(*value),
+
+            _ => None,
+        }
+    }
+}
+
+/// Formatted like `PRINT_ANY` formats the register pair of the value
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Int(value) => write!(f, "{}", value),
+            Value::Float(value) => write!(f, "{}", value),
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::String(value) => write!(f, "{}", value),
+            Value::Null => write!(f, "none"),
+        }
+    }
+}
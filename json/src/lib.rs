@@ -0,0 +1,363 @@
+pub mod rpc;
+
+use std::fmt::{Display, Formatter};
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// A JSON value, objects keep the order of their fields
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn object<'a>(fields: impl IntoIterator<Item = (&'a str, Json)>) -> Self {
+        Self::Object(
+            fields
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        )
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut parser = Parser {
+            chars: text.chars().peekable(),
+        };
+
+        let value = parser.value()?;
+        parser.whitespace();
+
+        match parser.chars.next() {
+            None => Ok(value),
+            Some(character) => Err(format!("unexpected `{}` after the value", character)),
+        }
+    }
+
+    /// The field `key` of an object, `None` for other values
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Self::Object(fields) => fields
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// Follow the fields of nested objects
+    pub fn at(&self, path: &[&str]) -> Option<&Json> {
+        path.iter().try_fold(self, |value, key| value.get(key))
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    pub fn as_usize(&self) -> Option<usize> {
+        match self {
+            Self::Number(number) if *number >= 0.0 && number.fract() == 0.0 => {
+                Some(*number as usize)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Self::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+impl From<&str> for Json {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(value: usize) -> Self {
+        Self::Number(value as f64)
+    }
+}
+
+impl From<bool> for Json {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<Vec<Json>> for Json {
+    fn from(values: Vec<Json>) -> Self {
+        Self::Array(values)
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(value: Option<T>) -> Self {
+        value.map(Into::into).unwrap_or(Self::Null)
+    }
+}
+
+/// Compact JSON without whitespace
+impl Display for Json {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Null => write!(f, "null"),
+            Self::Bool(value) => write!(f, "{}", value),
+            // integers are written without a fraction
+            Self::Number(number) if number.fract() == 0.0 && number.abs() < 1e15 => {
+                write!(f, "{}", *number as i64)
+            }
+            Self::Number(number) => write!(f, "{}", number),
+            Self::String(string) => write_string(f, string),
+            Self::Array(values) => {
+                write!(f, "[")?;
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            }
+            Self::Object(fields) => {
+                write!(f, "{{")?;
+                for (index, (key, value)) in fields.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut Formatter<'_>, string: &str) -> std::fmt::Result {
+    write!(f, "\"")?;
+    for character in string.chars() {
+        match character {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            character if character.is_control() => write!(f, "\\u{:04x}", character as u32)?,
+            character => write!(f, "{}", character)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl Parser<'_> {
+    fn value(&mut self) -> Result<Json, String> {
+        self.whitespace();
+
+        match self.chars.peek() {
+            Some('n') => self.keyword("null", Json::Null),
+            Some('t') => self.keyword("true", Json::Bool(true)),
+            Some('f') => self.keyword("false", Json::Bool(false)),
+            Some('"') => self.string().map(Json::String),
+            Some('[') => self.array(),
+            Some('{') => self.object(),
+            Some(character) if *character == '-' || character.is_ascii_digit() => self.number(),
+            Some(character) => Err(format!("unexpected `{}`", character)),
+            None => Err("unexpected end of input".to_string()),
+        }
+    }
+
+    fn whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_ascii_whitespace()).is_some() {}
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.whitespace();
+
+        match self.chars.next() {
+            Some(character) if character == expected => Ok(()),
+            Some(character) => Err(format!("expected `{}` but found `{}`", expected, character)),
+            None => Err(format!("expected `{}` at the end of input", expected)),
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str, value: Json) -> Result<Json, String> {
+        for expected in keyword.chars() {
+            if self.chars.next() != Some(expected) {
+                return Err(format!("invalid literal, expected `{}`", keyword));
+            }
+        }
+
+        Ok(value)
+    }
+
+    fn number(&mut self) -> Result<Json, String> {
+        let mut text = String::new();
+        while let Some(character) = self
+            .chars
+            .next_if(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))
+        {
+            text.push(character);
+        }
+
+        text.parse()
+            .map(Json::Number)
+            .map_err(|_| format!("invalid number `{}`", text))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+
+        let mut string = String::new();
+        loop {
+            match self.chars.next() {
+                Some('"') => return Ok(string),
+                Some('\\') => string.push(self.escape()?),
+                Some(character) => string.push(character),
+                None => return Err("unterminated string".to_string()),
+            }
+        }
+    }
+
+    fn escape(&mut self) -> Result<char, String> {
+        match self.chars.next() {
+            Some('"') => Ok('"'),
+            Some('\\') => Ok('\\'),
+            Some('/') => Ok('/'),
+            Some('b') => Ok('\u{8}'),
+            Some('f') => Ok('\u{c}'),
+            Some('n') => Ok('\n'),
+            Some('r') => Ok('\r'),
+            Some('t') => Ok('\t'),
+            Some('u') => {
+                let high = self.code_unit()?;
+                if !(0xd800..0xdc00).contains(&high) {
+                    return char::from_u32(high).ok_or("invalid escape".to_string());
+                }
+
+                // characters outside the basic plane are escaped as a
+                // surrogate pair
+                if self.chars.next() != Some('\\') || self.chars.next() != Some('u') {
+                    return Err("unpaired surrogate".to_string());
+                }
+                let low = self.code_unit()?;
+
+                char::from_u32(0x10000 + ((high - 0xd800) << 10) + (low.wrapping_sub(0xdc00)))
+                    .ok_or("invalid surrogate pair".to_string())
+            }
+            _ => Err("invalid escape".to_string()),
+        }
+    }
+
+    fn code_unit(&mut self) -> Result<u32, String> {
+        let digits = (0..4).filter_map(|_| self.chars.next()).collect::<String>();
+
+        u32::from_str_radix(&digits, 16).map_err(|_| format!("invalid escape `\\u{}`", digits))
+    }
+
+    fn array(&mut self) -> Result<Json, String> {
+        self.expect('[')?;
+        self.whitespace();
+
+        let mut values = Vec::new();
+        if self.chars.next_if_eq(&']').is_some() {
+            return Ok(Json::Array(values));
+        }
+
+        loop {
+            values.push(self.value()?);
+            self.whitespace();
+
+            match self.chars.next() {
+                Some(',') => continue,
+                Some(']') => return Ok(Json::Array(values)),
+                _ => return Err("expected `,` or `]` in array".to_string()),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, String> {
+        self.expect('{')?;
+        self.whitespace();
+
+        let mut fields = Vec::new();
+        if self.chars.next_if_eq(&'}').is_some() {
+            return Ok(Json::Object(fields));
+        }
+
+        loop {
+            self.whitespace();
+            let key = self.string()?;
+            self.expect(':')?;
+            fields.push((key, self.value()?));
+            self.whitespace();
+
+            match self.chars.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(Json::Object(fields)),
+                _ => return Err("expected `,` or `}` in object".to_string()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod json_tests {
+    use crate::Json;
+
+    #[test]
+    fn test_round_trip() {
+        let text = r#"{"id":1,"params":{"text":"a := \"b\"\n","items":[true,false,null,-2.5]}}"#;
+        let json = Json::parse(text).unwrap();
+
+        assert_eq!(json.to_string(), text);
+        assert_eq!(
+            json.at(&["params", "text"]).unwrap().as_str(),
+            Some("a := \"b\"\n")
+        );
+        assert_eq!(json.get("id").unwrap().as_usize(), Some(1));
+    }
+
+    #[test]
+    fn test_escapes() {
+        let json = Json::parse(r#" [ "\u00e9\ud83d\ude00\t" ] "#).unwrap();
+
+        assert_eq!(json, Json::Array(vec![Json::from("é😀\t")]));
+    }
+
+    #[test]
+    fn test_invalid() {
+        for text in ["", "{", "[1,]", r#"{"a" 1}"#, "tru", "1 2", r#""\x""#] {
+            assert!(Json::parse(text).is_err(), "`{}` parsed", text);
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use crate::Json;
+use std::io::{BufRead, Error, ErrorKind, Write};
+
+/// Read the next message, `None` when the input is closed. Messages are a
+/// `Content-Length` header, an empty line and that many bytes of JSON.
+pub fn read_message(reader: &mut impl BufRead) -> std::io::Result<Option<Result<Json, String>>> {
+    let mut length = None;
+
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+
+    let Some(length) = length else {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "message without a Content-Length header",
+        ));
+    };
+
+    let mut content = vec![0; length];
+    reader.read_exact(&mut content)?;
+
+    Ok(Some(match String::from_utf8(content) {
+        Ok(content) => Json::parse(&content),
+        Err(_) => Err("message is not UTF-8".to_string()),
+    }))
+}
+
+pub fn write_message(writer: &mut impl Write, message: &Json) -> std::io::Result<()> {
+    let content = message.to_string();
+
+    write!(
+        writer,
+        "Content-Length: {}\r\n\r\n{}",
+        content.len(),
+        content
+    )?;
+    writer.flush()
+}
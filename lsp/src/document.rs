@@ -0,0 +1,530 @@
+use analyzer::TypeChecker;
+use analyzer::builtins::lookup_builtin;
+use errors::Diagnostic;
+use parser::expressions::{Assign, Binary, Call, Expression, Grouping, Literal, Unary, Variable};
+use parser::span::AstSpan;
+use parser::statements::{If, Statement, VariableDeclaration, While};
+use parser::visitor::{ExpressionVisitor, StatementVisitor};
+use std::collections::HashMap;
+use std::ops::Range;
+use types::KirinType;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SymbolKind {
+    Variable,
+    /// a library function
+    Function,
+}
+
+/// A declared variable or a called library function, and where it is used
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// `None` for library functions, which are not declared in the source
+    pub definition: Option<Range<usize>>,
+    pub references: Vec<Range<usize>>,
+    pub inferred_type: Option<KirinType>,
+}
+
+/// An open file and what the scanner, parser and analyzer know about it.
+/// Offsets are bytes of the text.
+pub struct Document {
+    pub text: String,
+    pub diagnostics: Vec<Diagnostic>,
+    pub symbols: Vec<Symbol>,
+    /// the expressions the analyzer could type
+    types: Vec<(Range<usize>, KirinType)>,
+}
+
+impl Document {
+    pub fn new(text: String) -> Self {
+        let mut document = Self {
+            text,
+            diagnostics: Vec::new(),
+            symbols: Vec::new(),
+            types: Vec::new(),
+        };
+
+        let tokens = match scanner::scan_tokens(&document.text) {
+            Ok(tokens) => tokens,
+            Err(error) => {
+                document.diagnostics.push(error.diagnostic());
+                return document;
+            }
+        };
+
+        let (ast, errors) = parser::parse_with_recovery(tokens, None);
+        document
+            .diagnostics
+            .extend(errors.iter().map(|error| error.diagnostic()));
+
+        // statements are checked one at a time to keep the types of those
+        // that check when others do not
+        let mut checker = TypeChecker::new();
+        checker.allow_pragmas(&document.text);
+
+        let mut typed = Nodes::default();
+        for statement in &ast {
+            match checker.infer_types(&vec![statement.clone()]) {
+                Ok(statements) => statements.iter().for_each(|s| s.accept(&mut typed)),
+                Err(errors) => document
+                    .diagnostics
+                    .extend(errors.iter().map(|error| error.diagnostic())),
+            }
+        }
+        document.diagnostics.extend(
+            checker
+                .warnings()
+                .iter()
+                .map(|warning| warning.diagnostic()),
+        );
+
+        document.types = typed
+            .0
+            .iter()
+            .filter_map(|node| Some((node.range.clone(), node.inferred_type?)))
+            .collect();
+
+        let mut parsed = Nodes::default();
+        ast.iter()
+            .for_each(|statement| statement.accept(&mut parsed));
+        document.resolve(parsed.0);
+
+        document
+    }
+
+    /// Link the uses of names to their declarations, a declaration of a
+    /// name that is already declared starts a new symbol
+    fn resolve(&mut self, nodes: Vec<Node>) {
+        // the symbol of each name declared in the open blocks
+        let mut scopes = vec![HashMap::<String, usize>::new()];
+        let mut functions = HashMap::<String, usize>::new();
+
+        for node in nodes {
+            match node.kind {
+                NodeKind::Declaration => {
+                    if let Some(scope) = scopes.last_mut() {
+                        scope.insert(node.name.clone(), self.symbols.len());
+                    }
+                    self.symbols.push(Symbol {
+                        inferred_type: self.type_of(&node.range),
+                        name: node.name,
+                        kind: SymbolKind::Variable,
+                        definition: Some(node.range),
+                        references: Vec::new(),
+                    });
+                }
+                NodeKind::Use => {
+                    if let Some(index) = scopes
+                        .iter()
+                        .rev()
+                        .find_map(|scope| scope.get(&node.name).copied())
+                    {
+                        self.symbols[index].references.push(node.range);
+                    }
+                }
+                NodeKind::BlockStart => scopes.push(HashMap::new()),
+                NodeKind::BlockEnd => {
+                    scopes.pop();
+                }
+                NodeKind::Function if lookup_builtin(&node.name).is_some() => {
+                    let index = *functions.entry(node.name.clone()).or_insert_with(|| {
+                        self.symbols.push(Symbol {
+                            name: node.name.clone(),
+                            kind: SymbolKind::Function,
+                            definition: None,
+                            references: Vec::new(),
+                            inferred_type: lookup_builtin(&node.name).map(|f| f.return_type),
+                        });
+                        self.symbols.len() - 1
+                    });
+                    self.symbols[index].references.push(node.range);
+                }
+                NodeKind::Function | NodeKind::Expression => {}
+            }
+        }
+    }
+
+    fn type_of(&self, range: &Range<usize>) -> Option<KirinType> {
+        self.types
+            .iter()
+            .find(|(typed, _)| typed == range)
+            .map(|(_, kind)| *kind)
+    }
+
+    /// The symbol declared or used at `offset`, a cursor right after a name
+    /// is on it
+    pub fn symbol_at(&self, offset: usize) -> Option<&Symbol> {
+        self.symbols.iter().find(|symbol| {
+            symbol
+                .definition
+                .iter()
+                .chain(&symbol.references)
+                .any(|range| touches(range, offset))
+        })
+    }
+
+    /// The type of the expression at `offset` and its range
+    pub fn type_at(&self, offset: usize) -> Option<(Range<usize>, KirinType)> {
+        self.types
+            .iter()
+            .find(|(range, _)| touches(range, offset))
+            .cloned()
+    }
+
+    /// The symbols that can be used at `offset`, the last declaration of
+    /// each name before it
+    pub fn visible_symbols(&self, offset: usize) -> Vec<&Symbol> {
+        let mut visible = HashMap::new();
+
+        for symbol in &self.symbols {
+            if let Some(definition) = &symbol.definition
+                && definition.end <= offset
+            {
+                visible.insert(symbol.name.as_str(), symbol);
+            }
+        }
+
+        let mut visible = visible.into_values().collect::<Vec<&Symbol>>();
+        visible.sort_by(|a, b| a.name.cmp(&b.name));
+        visible
+    }
+
+    /// The range a diagnostic points at, diagnostics that only know their
+    /// position point at the character there
+    pub fn diagnostic_range(&self, diagnostic: &Diagnostic) -> Range<usize> {
+        if !diagnostic.range.is_empty() || diagnostic.line == 0 {
+            return diagnostic.range.clone();
+        }
+
+        let start = self
+            .text
+            .split_inclusive('\n')
+            .take(diagnostic.line - 1)
+            .map(str::len)
+            .sum::<usize>()
+            + diagnostic.column.saturating_sub(1);
+        let start = start.min(self.text.len());
+
+        match self.text.get(start..).and_then(|rest| rest.chars().next()) {
+            Some(character) if character != '\n' => start..start + character.len_utf8(),
+            _ => start..start,
+        }
+    }
+
+    /// The zero based line and UTF-16 column of a byte offset
+    pub fn position(&self, offset: usize) -> (usize, usize) {
+        let before = floor(&self.text, offset);
+        let line_start = before.rfind('\n').map(|index| index + 1).unwrap_or(0);
+
+        (
+            before.matches('\n').count(),
+            before[line_start..].encode_utf16().count(),
+        )
+    }
+
+    /// The byte offset of a zero based line and UTF-16 column, clamped to
+    /// the end of the line
+    pub fn offset(&self, line: usize, character: usize) -> usize {
+        let Some(line_start) = (match line {
+            0 => Some(0),
+            _ => self
+                .text
+                .match_indices('\n')
+                .nth(line - 1)
+                .map(|(index, _)| index + 1),
+        }) else {
+            return self.text.len();
+        };
+
+        let mut units = 0;
+        for (index, c) in self.text[line_start..].char_indices() {
+            if units >= character || c == '\n' {
+                return line_start + index;
+            }
+            units += c.len_utf16();
+        }
+
+        self.text.len()
+    }
+}
+
+fn touches(range: &Range<usize>, offset: usize) -> bool {
+    range.start <= offset && offset <= range.end
+}
+
+/// The text before `offset`, which may be in the middle of a character
+fn floor(text: &str, offset: usize) -> &str {
+    let mut offset = offset.min(text.len());
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+
+    &text[..offset]
+}
+
+#[derive(Debug)]
+enum NodeKind {
+    Declaration,
+    /// a read of or an assignment to a variable
+    Use,
+    /// the name of a called function
+    Function,
+    Expression,
+    /// the bounds of the statements of an `if`, `else` or `while`
+    BlockStart,
+    BlockEnd,
+}
+
+#[derive(Debug)]
+struct Node {
+    kind: NodeKind,
+    name: String,
+    range: Range<usize>,
+    inferred_type: Option<KirinType>,
+}
+
+/// The nodes of a tree in the order they are evaluated
+#[derive(Default)]
+struct Nodes(Vec<Node>);
+
+impl Nodes {
+    fn push(&mut self, kind: NodeKind, name: &str, span: &AstSpan, kind_type: Option<KirinType>) {
+        self.0.push(Node {
+            kind,
+            name: name.to_string(),
+            range: span.range(),
+            inferred_type: kind_type,
+        });
+    }
+
+    fn block(&mut self, statements: &[Statement]) {
+        let bound = |kind| Node {
+            kind,
+            name: String::new(),
+            range: 0..0,
+            inferred_type: None,
+        };
+
+        self.0.push(bound(NodeKind::BlockStart));
+        statements
+            .iter()
+            .for_each(|statement| statement.accept(self));
+        self.0.push(bound(NodeKind::BlockEnd));
+    }
+}
+
+impl StatementVisitor for Nodes {
+    type Output = ();
+
+    fn visit_none(&mut self) -> Self::Output {}
+
+    fn visit_var_declaration(&mut self, var_declaration: &VariableDeclaration) -> Self::Output {
+        // the initializer cannot use the variable it declares
+        if let Some(initializer) = &var_declaration.initializer {
+            initializer.accept(self);
+        }
+
+        self.push(
+            NodeKind::Declaration,
+            &var_declaration.name,
+            &var_declaration.span,
+            var_declaration.inferred_type,
+        );
+    }
+
+    fn visit_expression_statement(&mut self, expression_statement: &Expression) -> Self::Output {
+        expression_statement.accept(self);
+    }
+
+    fn visit_if(&mut self, if_statement: &If) -> Self::Output {
+        if_statement.condition.accept(self);
+        self.block(&if_statement.then_branch);
+
+        if let Some(else_branch) = &if_statement.else_branch {
+            self.block(else_branch);
+        }
+    }
+
+    fn visit_while(&mut self, while_statement: &While) -> Self::Output {
+        while_statement.condition.accept(self);
+        self.block(&while_statement.body);
+    }
+
+    fn visit_return(&mut self, _span: &AstSpan) -> Self::Output {}
+}
+
+impl ExpressionVisitor for Nodes {
+    type Output = ();
+
+    fn visit_binary(&mut self, binary: &Binary) -> Self::Output {
+        binary.left.accept(self);
+        binary.right.accept(self);
+        self.push(NodeKind::Expression, "", &binary.span, binary.inferred_type);
+    }
+
+    fn visit_unary(&mut self, unary: &Unary) -> Self::Output {
+        unary.right.accept(self);
+        self.push(NodeKind::Expression, "", &unary.span, unary.inferred_type);
+    }
+
+    fn visit_grouping(&mut self, grouping: &Grouping) -> Self::Output {
+        grouping.expression.accept(self);
+        self.push(
+            NodeKind::Expression,
+            "",
+            &grouping.span,
+            grouping.inferred_type,
+        );
+    }
+
+    fn visit_literal(&mut self, literal: &Literal) -> Self::Output {
+        self.push(
+            NodeKind::Expression,
+            "",
+            &literal.span,
+            literal.inferred_type,
+        );
+    }
+
+    fn visit_call(&mut self, callable: &Call) -> Self::Output {
+        match &callable.callee {
+            Expression::Variable(callee) => {
+                self.push(NodeKind::Function, &callee.name, &callee.span, None)
+            }
+            callee => callee.accept(self),
+        }
+
+        callable
+            .arguments
+            .iter()
+            .for_each(|argument| argument.accept(self));
+        self.push(
+            NodeKind::Expression,
+            "",
+            &callable.span,
+            callable.inferred_type,
+        );
+    }
+
+    fn visit_variable(&mut self, variable: &Variable) -> Self::Output {
+        self.push(
+            NodeKind::Use,
+            &variable.name,
+            &variable.span,
+            variable.inferred_type,
+        );
+    }
+
+    fn visit_assign(&mut self, assign: &Assign) -> Self::Output {
+        assign.value.accept(self);
+        self.push(
+            NodeKind::Use,
+            &assign.name,
+            &assign.target,
+            assign.inferred_type,
+        );
+    }
+
+    fn visit_error(&mut self, _span: &AstSpan) -> Self::Output {}
+}
+
+#[cfg(test)]
+mod document_tests {
+    use crate::document::{Document, SymbolKind};
+    use types::KirinType;
+
+    const SOURCE: &str = "a := 1\nb := a + 2\na = b\nprint(a)\na := \"x\"\nprint(a)\n";
+
+    fn codes(document: &Document) -> Vec<&str> {
+        document
+            .diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.code)
+            .collect()
+    }
+
+    fn offset_of(text: &str, needle: &str, occurrence: usize) -> usize {
+        text.match_indices(needle).nth(occurrence).unwrap().0
+    }
+
+    #[test]
+    fn test_symbols() {
+        let document = Document::new(SOURCE.to_string());
+
+        assert_eq!(codes(&document), ["W0004"]);
+
+        let names = document
+            .symbols
+            .iter()
+            .map(|symbol| (symbol.name.as_str(), symbol.kind, symbol.references.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                ("a", SymbolKind::Variable, 3),
+                ("b", SymbolKind::Variable, 1),
+                ("print", SymbolKind::Function, 2),
+                ("a", SymbolKind::Variable, 1),
+            ]
+        );
+
+        // the second declaration shadows the first
+        let last_use = offset_of(SOURCE, "print(a)", 1) + "print(".len();
+        let symbol = document.symbol_at(last_use).unwrap();
+        assert_eq!(symbol.inferred_type, Some(KirinType::String));
+        assert_eq!(symbol.definition, Some(33..34));
+    }
+
+    #[test]
+    fn test_block_scopes() {
+        let source = "a := 1\nif a > 0\n  a := 2\n  print(a)\nend\nprint(a)\n";
+        let document = Document::new(source.to_string());
+
+        let references = document
+            .symbols
+            .iter()
+            .filter(|symbol| symbol.kind == SymbolKind::Variable)
+            .map(|symbol| (symbol.definition.clone(), symbol.references.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(references, [(Some(0..1), 2), (Some(18..19), 1)]);
+
+        // the use after the block is the outer `a`
+        let last_use = offset_of(source, "print(a)", 1) + "print(".len();
+        assert_eq!(document.symbol_at(last_use).unwrap().definition, Some(0..1));
+    }
+
+    #[test]
+    fn test_types_survive_errors() {
+        let document = Document::new("a := 1\nb := a + \"x\"\nc := a * 2\n".to_string());
+
+        assert_eq!(codes(&document), ["E0202", "W0002"]);
+        assert_eq!(
+            document.type_at(offset_of(&document.text, "*", 0)),
+            Some((27..28, KirinType::Int))
+        );
+    }
+
+    #[test]
+    fn test_non_ascii_text() {
+        let document = Document::new("a := 1\ns := \"é\"\nprint(s)\n".to_string());
+
+        // only the unused `a`
+        assert_eq!(codes(&document), ["W0002"]);
+
+        // the closing quote after the two byte `é`, at UTF-16 column 7
+        let offset = document.offset(1, 7);
+        assert_eq!(offset, document.text.rfind('"').unwrap());
+        assert_eq!(document.position(offset), (1, 7));
+        assert_eq!(document.type_at(offset), Some((12..16, KirinType::String)));
+
+        let symbol = document.symbol_at(document.offset(2, 6)).unwrap();
+        assert_eq!(symbol.name, "s");
+        assert_eq!(symbol.definition, Some(7..8));
+        assert_eq!(symbol.inferred_type, Some(KirinType::String));
+
+        assert_eq!(document.offset(1, 100), 16);
+        assert_eq!(document.offset(5, 0), document.text.len());
+    }
+}
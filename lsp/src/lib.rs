@@ -0,0 +1,736 @@
+pub mod document;
+
+use analyzer::builtins::{Builtin, builtins, lookup_builtin};
+use document::{Document, Symbol, SymbolKind};
+use errors::{Diagnostic, Severity};
+use json::Json;
+use json::rpc;
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::ops::Range;
+
+/// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// LSP kinds of completion items and symbols
+const FUNCTION_COMPLETION: usize = 3;
+const VARIABLE_COMPLETION: usize = 6;
+const KEYWORD_COMPLETION: usize = 14;
+const VARIABLE_SYMBOL: usize = 13;
+
+/// Full documents are sent on every change
+const FULL_SYNC: usize = 1;
+
+struct ResponseError {
+    code: i64,
+    message: String,
+}
+
+impl ResponseError {
+    fn invalid_params(message: &str) -> Self {
+        Self {
+            code: INVALID_PARAMS,
+            message: message.to_string(),
+        }
+    }
+}
+
+/// A language server for the open .kn documents of one client. Documents
+/// are analyzed again on every change and their diagnostics published.
+#[derive(Default)]
+pub struct Server {
+    documents: HashMap<String, Document>,
+    shutting_down: bool,
+    exited: bool,
+}
+
+/// Serve the messages of `input` until the client sends `exit` or closes
+/// it, returning the exit code: 0 when the client asked for a shutdown
+/// first
+pub fn run(mut input: impl BufRead, mut output: impl Write) -> std::io::Result<i32> {
+    let mut server = Server::new();
+
+    while let Some(message) = rpc::read_message(&mut input)? {
+        let replies = match message {
+            Ok(message) => server.handle(&message),
+            Err(error) => vec![error_response(
+                Json::Null,
+                ResponseError {
+                    code: PARSE_ERROR,
+                    message: error,
+                },
+            )],
+        };
+
+        for reply in replies {
+            rpc::write_message(&mut output, &reply)?;
+        }
+
+        if server.exited {
+            break;
+        }
+    }
+
+    Ok(if server.shutting_down { 0 } else { 1 })
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handle a request or notification, returning the messages to send
+    /// back
+    pub fn handle(&mut self, message: &Json) -> Vec<Json> {
+        let Some(method) = message.get("method").and_then(Json::as_str) else {
+            // responses to requests of the server, which sends none
+            return Vec::new();
+        };
+        let params = message.get("params").unwrap_or(&Json::Null);
+
+        let Some(id) = message.get("id").cloned() else {
+            return self.notify(method, params);
+        };
+
+        let result = match method {
+            _ if self.shutting_down => Err(ResponseError {
+                code: INVALID_REQUEST,
+                message: "the server is shutting down".to_string(),
+            }),
+            "initialize" => Ok(capabilities()),
+            "shutdown" => {
+                self.shutting_down = true;
+                Ok(Json::Null)
+            }
+            "textDocument/hover" => self.hover(params),
+            "textDocument/definition" => self.definition(params),
+            "textDocument/references" => self.references(params),
+            "textDocument/documentSymbol" => self.document_symbols(params),
+            "textDocument/completion" => self.completion(params),
+            _ => Err(ResponseError {
+                code: METHOD_NOT_FOUND,
+                message: format!("unknown method `{}`", method),
+            }),
+        };
+
+        vec![match result {
+            Ok(result) => Json::object([
+                ("jsonrpc", Json::from("2.0")),
+                ("id", id),
+                ("result", result),
+            ]),
+            Err(error) => error_response(id, error),
+        }]
+    }
+
+    fn notify(&mut self, method: &str, params: &Json) -> Vec<Json> {
+        let uri = params
+            .at(&["textDocument", "uri"])
+            .and_then(Json::as_str)
+            .map(str::to_string);
+
+        let text = match method {
+            "exit" => {
+                self.exited = true;
+                return Vec::new();
+            }
+            "textDocument/didOpen" => params.at(&["textDocument", "text"]),
+            // with full sync the last change is the whole document
+            "textDocument/didChange" => params
+                .get("contentChanges")
+                .and_then(Json::as_array)
+                .and_then(|changes| changes.last())
+                .and_then(|change| change.get("text")),
+            "textDocument/didClose" => {
+                let Some(uri) = uri else {
+                    return Vec::new();
+                };
+                self.documents.remove(&uri);
+                return vec![publish_diagnostics(&uri, Vec::new())];
+            }
+            _ => return Vec::new(),
+        };
+
+        let (Some(uri), Some(text)) = (uri, text.and_then(Json::as_str)) else {
+            return Vec::new();
+        };
+
+        let document = Document::new(text.to_string());
+        let diagnostics = document
+            .diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic_json(&uri, &document, diagnostic))
+            .collect();
+        self.documents.insert(uri.clone(), document);
+
+        vec![publish_diagnostics(&uri, diagnostics)]
+    }
+
+    /// The document and offset of `textDocument` and `position` parameters
+    fn document<'a>(
+        &'a self,
+        params: &'a Json,
+    ) -> Result<(&'a str, &'a Document, usize), ResponseError> {
+        let uri = params
+            .at(&["textDocument", "uri"])
+            .and_then(Json::as_str)
+            .ok_or(ResponseError::invalid_params("missing textDocument.uri"))?;
+
+        let document = self
+            .documents
+            .get(uri)
+            .ok_or(ResponseError::invalid_params("the document is not open"))?;
+
+        let offset = match params.get("position") {
+            Some(position) => {
+                let line = position.get("line").and_then(Json::as_usize);
+                let character = position.get("character").and_then(Json::as_usize);
+                let (Some(line), Some(character)) = (line, character) else {
+                    return Err(ResponseError::invalid_params("invalid position"));
+                };
+
+                document.offset(line, character)
+            }
+            None => 0,
+        };
+
+        Ok((uri, document, offset))
+    }
+
+    fn hover(&self, params: &Json) -> Result<Json, ResponseError> {
+        let (_, document, offset) = self.document(params)?;
+        let typed = document.type_at(offset);
+
+        let (range, code) = match document.symbol_at(offset) {
+            Some(symbol) if symbol.kind == SymbolKind::Function => {
+                let range = occurrence(symbol, offset);
+                let signature = lookup_builtin(&symbol.name).map(signature);
+                (range, signature.unwrap_or(symbol.name.clone()))
+            }
+            Some(symbol) => {
+                let inferred_type = typed
+                    .map(|(_, kind)| kind)
+                    .or(symbol.inferred_type)
+                    .map(|kind| format!("{}: {}", symbol.name, kind));
+                (
+                    occurrence(symbol, offset),
+                    inferred_type.unwrap_or(symbol.name.clone()),
+                )
+            }
+            None => match typed {
+                Some((range, kind)) => (range, kind.to_string()),
+                None => return Ok(Json::Null),
+            },
+        };
+
+        Ok(Json::object([
+            (
+                "contents",
+                Json::object([
+                    ("kind", Json::from("markdown")),
+                    ("value", Json::from(format!("```kirin\n{}\n```", code))),
+                ]),
+            ),
+            ("range", range_json(document, &range)),
+        ]))
+    }
+
+    fn definition(&self, params: &Json) -> Result<Json, ResponseError> {
+        let (uri, document, offset) = self.document(params)?;
+
+        Ok(document
+            .symbol_at(offset)
+            .and_then(|symbol| symbol.definition.as_ref())
+            .map(|definition| location_json(uri, document, definition))
+            .unwrap_or(Json::Null))
+    }
+
+    fn references(&self, params: &Json) -> Result<Json, ResponseError> {
+        let (uri, document, offset) = self.document(params)?;
+        let include_declaration = params
+            .at(&["context", "includeDeclaration"])
+            .and_then(Json::as_bool)
+            .unwrap_or(false);
+
+        let Some(symbol) = document.symbol_at(offset) else {
+            return Ok(Json::Null);
+        };
+
+        let declaration = symbol.definition.iter().filter(|_| include_declaration);
+
+        Ok(Json::from(
+            declaration
+                .chain(&symbol.references)
+                .map(|range| location_json(uri, document, range))
+                .collect::<Vec<Json>>(),
+        ))
+    }
+
+    fn document_symbols(&self, params: &Json) -> Result<Json, ResponseError> {
+        let (_, document, _) = self.document(params)?;
+
+        let symbols = document
+            .symbols
+            .iter()
+            .filter_map(|symbol| {
+                let definition = symbol.definition.as_ref()?;
+                let detail = symbol.inferred_type.map(|kind| kind.to_string());
+
+                Some(Json::object([
+                    ("name", Json::from(symbol.name.as_str())),
+                    ("detail", Json::from(detail)),
+                    ("kind", Json::from(VARIABLE_SYMBOL)),
+                    ("range", range_json(document, definition)),
+                    ("selectionRange", range_json(document, definition)),
+                ]))
+            })
+            .collect::<Vec<Json>>();
+
+        Ok(Json::from(symbols))
+    }
+
+    fn completion(&self, params: &Json) -> Result<Json, ResponseError> {
+        let (_, document, offset) = self.document(params)?;
+
+        let keywords = scanner::KEYWORDS
+            .iter()
+            .map(|(keyword, _)| completion_item(keyword, KEYWORD_COMPLETION, None));
+        let functions = builtins().iter().map(|builtin| {
+            completion_item(builtin.name, FUNCTION_COMPLETION, Some(signature(builtin)))
+        });
+        let variables = document.visible_symbols(offset).into_iter().map(|symbol| {
+            let detail = symbol.inferred_type.map(|kind| kind.to_string());
+            completion_item(&symbol.name, VARIABLE_COMPLETION, detail)
+        });
+
+        Ok(Json::from(
+            variables
+                .chain(functions)
+                .chain(keywords)
+                .collect::<Vec<Json>>(),
+        ))
+    }
+}
+
+fn capabilities() -> Json {
+    Json::object([
+        (
+            "capabilities",
+            Json::object([
+                ("textDocumentSync", Json::from(FULL_SYNC)),
+                ("hoverProvider", Json::from(true)),
+                ("definitionProvider", Json::from(true)),
+                ("referencesProvider", Json::from(true)),
+                ("documentSymbolProvider", Json::from(true)),
+                ("completionProvider", Json::object([])),
+            ]),
+        ),
+        (
+            "serverInfo",
+            Json::object([
+                ("name", Json::from("kirin")),
+                ("version", Json::from(env!("CARGO_PKG_VERSION"))),
+            ]),
+        ),
+    ])
+}
+
+fn error_response(id: Json, error: ResponseError) -> Json {
+    Json::object([
+        ("jsonrpc", Json::from("2.0")),
+        ("id", id),
+        (
+            "error",
+            Json::object([
+                ("code", Json::Number(error.code as f64)),
+                ("message", Json::from(error.message)),
+            ]),
+        ),
+    ])
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Vec<Json>) -> Json {
+    Json::object([
+        ("jsonrpc", Json::from("2.0")),
+        ("method", Json::from("textDocument/publishDiagnostics")),
+        (
+            "params",
+            Json::object([
+                ("uri", Json::from(uri)),
+                ("diagnostics", Json::from(diagnostics)),
+            ]),
+        ),
+    ])
+}
+
+/// The diagnostic with its notes and help appended to the message and its
+/// labels as related information
+fn diagnostic_json(uri: &str, document: &Document, diagnostic: &Diagnostic) -> Json {
+    let mut message = diagnostic.message.clone();
+    for note in &diagnostic.notes {
+        message.push_str(&format!("\nnote: {}", note));
+    }
+    if let Some(help) = &diagnostic.help {
+        message.push_str(&format!("\nhelp: {}", help));
+    }
+
+    let related = diagnostic
+        .labels
+        .iter()
+        .map(|label| {
+            Json::object([
+                ("location", location_json(uri, document, &label.range)),
+                ("message", Json::from(label.message.as_str())),
+            ])
+        })
+        .collect::<Vec<Json>>();
+
+    let severity: usize = match diagnostic.severity {
+        Severity::Error => 1,
+        Severity::Warning => 2,
+    };
+
+    Json::object([
+        (
+            "range",
+            range_json(document, &document.diagnostic_range(diagnostic)),
+        ),
+        ("severity", Json::from(severity)),
+        ("code", Json::from(diagnostic.code)),
+        ("source", Json::from("kirin")),
+        ("message", Json::from(message)),
+        ("relatedInformation", Json::from(related)),
+    ])
+}
+
+fn range_json(document: &Document, range: &Range<usize>) -> Json {
+    let position = |offset| {
+        let (line, character) = document.position(offset);
+        Json::object([
+            ("line", Json::from(line)),
+            ("character", Json::from(character)),
+        ])
+    };
+
+    Json::object([
+        ("start", position(range.start)),
+        ("end", position(range.end)),
+    ])
+}
+
+fn location_json(uri: &str, document: &Document, range: &Range<usize>) -> Json {
+    Json::object([
+        ("uri", Json::from(uri)),
+        ("range", range_json(document, range)),
+    ])
+}
+
+fn completion_item(label: &str, kind: usize, detail: Option<String>) -> Json {
+    Json::object([
+        ("label", Json::from(label)),
+        ("kind", Json::from(kind)),
+        ("detail", Json::from(detail)),
+    ])
+}
+
+/// `fn wrapping_add(int, int) -> int`
+fn signature(builtin: &Builtin) -> String {
+    let parameters = builtin
+        .parameters
+        .iter()
+        .map(|parameter| parameter.to_string())
+        .collect::<Vec<String>>();
+
+    format!(
+        "fn {}({}) -> {}",
+        builtin.name,
+        parameters.join(", "),
+        builtin.return_type
+    )
+}
+
+/// The range of the use or declaration of `symbol` at `offset`
+fn occurrence(symbol: &Symbol, offset: usize) -> Range<usize> {
+    symbol
+        .definition
+        .iter()
+        .chain(&symbol.references)
+        .find(|range| range.start <= offset && offset <= range.end)
+        .cloned()
+        .unwrap_or(offset..offset)
+}
+
+#[cfg(test)]
+mod server_tests {
+    use crate::{rpc, run};
+    use json::Json;
+    use std::io::Write;
+
+    const URI: &str = "file:///main.kn";
+    const SOURCE: &str = "a := 1\nb := a + 2\nprint(b)\nprint(a)\n";
+
+    /// Feed the messages to the server as a client would over stdio and
+    /// collect what it writes back
+    fn session(messages: &[Json]) -> (i32, Vec<Json>) {
+        let mut input = Vec::new();
+        for message in messages {
+            rpc::write_message(&mut input, message).unwrap();
+        }
+
+        let mut output = Vec::new();
+        let code = run(input.as_slice(), &mut output).unwrap();
+
+        let mut reader = output.as_slice();
+        let mut replies = Vec::new();
+        while let Some(reply) = rpc::read_message(&mut reader).unwrap() {
+            replies.push(reply.unwrap());
+        }
+
+        (code, replies)
+    }
+
+    fn request(id: usize, method: &str, params: Json) -> Json {
+        Json::object([
+            ("jsonrpc", Json::from("2.0")),
+            ("id", Json::from(id)),
+            ("method", Json::from(method)),
+            ("params", params),
+        ])
+    }
+
+    fn notification(method: &str, params: Json) -> Json {
+        Json::object([
+            ("jsonrpc", Json::from("2.0")),
+            ("method", Json::from(method)),
+            ("params", params),
+        ])
+    }
+
+    fn open(text: &str) -> Json {
+        notification(
+            "textDocument/didOpen",
+            Json::object([(
+                "textDocument",
+                Json::object([
+                    ("uri", Json::from(URI)),
+                    ("languageId", Json::from("kirin")),
+                    ("version", Json::from(1)),
+                    ("text", Json::from(text)),
+                ]),
+            )]),
+        )
+    }
+
+    fn at(id: usize, method: &str, line: usize, character: usize) -> Json {
+        request(
+            id,
+            method,
+            Json::object([
+                ("textDocument", Json::object([("uri", Json::from(URI))])),
+                (
+                    "position",
+                    Json::object([
+                        ("line", Json::from(line)),
+                        ("character", Json::from(character)),
+                    ]),
+                ),
+                (
+                    "context",
+                    Json::object([("includeDeclaration", Json::from(true))]),
+                ),
+            ]),
+        )
+    }
+
+    /// The result of the response to request `id`
+    fn result(replies: &[Json], id: usize) -> &Json {
+        replies
+            .iter()
+            .find(|reply| reply.get("id") == Some(&Json::from(id)))
+            .and_then(|reply| reply.get("result"))
+            .unwrap_or_else(|| panic!("no result for request {}", id))
+    }
+
+    fn ranges(locations: &Json) -> Vec<String> {
+        locations
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|location| location.get("range").unwrap().to_string())
+            .collect()
+    }
+
+    fn range(line: usize, start: usize, end: usize) -> String {
+        format!(
+            r#"{{"start":{{"line":{},"character":{}}},"end":{{"line":{},"character":{}}}}}"#,
+            line, start, line, end
+        )
+    }
+
+    #[test]
+    fn test_lifecycle() {
+        let (code, replies) = session(&[
+            request(1, "initialize", Json::object([])),
+            notification("initialized", Json::object([])),
+            request(2, "shutdown", Json::Null),
+            request(3, "textDocument/hover", Json::Null),
+            notification("exit", Json::Null),
+        ]);
+
+        assert_eq!(code, 0);
+        assert_eq!(
+            result(&replies, 1)
+                .at(&["capabilities", "hoverProvider"])
+                .and_then(Json::as_bool),
+            Some(true)
+        );
+        assert_eq!(result(&replies, 2), &Json::Null);
+        assert_eq!(
+            replies[2].at(&["error", "code"]),
+            Some(&Json::Number(-32600.0))
+        );
+
+        // exiting without a shutdown is an error
+        let (code, _) = session(&[notification("exit", Json::Null)]);
+        assert_eq!(code, 1);
+    }
+
+    #[test]
+    fn test_diagnostics_on_change() {
+        let change = notification(
+            "textDocument/didChange",
+            Json::object([
+                (
+                    "textDocument",
+                    Json::object([("uri", Json::from(URI)), ("version", Json::from(2))]),
+                ),
+                (
+                    "contentChanges",
+                    Json::Array(vec![Json::object([("text", Json::from(SOURCE))])]),
+                ),
+            ]),
+        );
+        let (_, replies) = session(&[open("a := 1\nb := c\n"), change]);
+
+        let diagnostics = replies
+            .iter()
+            .map(|reply| {
+                reply
+                    .at(&["params", "diagnostics"])
+                    .unwrap()
+                    .as_array()
+                    .unwrap()
+            })
+            .collect::<Vec<&[Json]>>();
+
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(
+            diagnostics[0]
+                .iter()
+                .map(|diagnostic| diagnostic.get("code").unwrap().as_str().unwrap())
+                .collect::<Vec<&str>>(),
+            ["E0203", "W0002"]
+        );
+        assert_eq!(
+            diagnostics[0][0].get("range").unwrap().to_string(),
+            range(1, 5, 6)
+        );
+        assert!(diagnostics[1].is_empty());
+    }
+
+    #[test]
+    fn test_navigation() {
+        let (_, replies) = session(&[
+            open(SOURCE),
+            at(1, "textDocument/hover", 1, 5),
+            at(2, "textDocument/hover", 2, 2),
+            at(3, "textDocument/definition", 3, 6),
+            at(4, "textDocument/references", 0, 0),
+            at(5, "textDocument/references", 3, 0),
+            at(6, "textDocument/definition", 2, 0),
+        ]);
+
+        assert_eq!(
+            result(&replies, 1).at(&["contents", "value"]),
+            Some(&Json::from("```kirin\na: int\n```"))
+        );
+        assert_eq!(
+            result(&replies, 2).at(&["contents", "value"]),
+            Some(&Json::from("```kirin\nfn print(any) -> void\n```"))
+        );
+        assert_eq!(
+            result(&replies, 3).get("range").unwrap().to_string(),
+            range(0, 0, 1)
+        );
+        assert_eq!(
+            ranges(result(&replies, 4)),
+            [range(0, 0, 1), range(1, 5, 6), range(3, 6, 7)]
+        );
+        assert_eq!(
+            ranges(result(&replies, 5)),
+            [range(2, 0, 5), range(3, 0, 5)]
+        );
+        // library functions are not declared in the source
+        assert_eq!(result(&replies, 6), &Json::Null);
+    }
+
+    #[test]
+    fn test_symbols_and_completion() {
+        let (_, replies) = session(&[
+            open(SOURCE),
+            at(1, "textDocument/documentSymbol", 0, 0),
+            at(2, "textDocument/completion", 1, 0),
+        ]);
+
+        let symbols = result(&replies, 1)
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|symbol| symbol.get("name").unwrap().as_str().unwrap())
+            .collect::<Vec<&str>>();
+        assert_eq!(symbols, ["a", "b"]);
+
+        let labels = result(&replies, 2)
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item.get("label").unwrap().as_str().unwrap())
+            .collect::<Vec<&str>>();
+        // `b` is declared after the cursor
+        assert!(labels.contains(&"a"));
+        assert!(!labels.contains(&"b"));
+        assert!(labels.contains(&"wrapping_add"));
+        assert!(labels.contains(&"while"));
+    }
+
+    #[test]
+    fn test_invalid_messages() {
+        let mut input = Vec::new();
+        let garbage = "{not json";
+        write!(
+            input,
+            "Content-Length: {}\r\n\r\n{}",
+            garbage.len(),
+            garbage
+        )
+        .unwrap();
+        rpc::write_message(&mut input, &request(1, "unknown/method", Json::Null)).unwrap();
+        rpc::write_message(&mut input, &at(2, "textDocument/hover", 0, 0)).unwrap();
+
+        let mut output = Vec::new();
+        run(input.as_slice(), &mut output).unwrap();
+
+        let mut reader = output.as_slice();
+        let codes = std::iter::from_fn(|| rpc::read_message(&mut reader).unwrap())
+            .map(|reply| reply.unwrap().at(&["error", "code"]).cloned())
+            .collect::<Vec<Option<Json>>>();
+
+        assert_eq!(
+            codes,
+            [-32700.0, -32601.0, -32602.0].map(|code| Some(Json::Number(code)))
+        );
+    }
+}
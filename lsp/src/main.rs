@@ -0,0 +1,13 @@
+/// A language server for .kn files, speaking JSON-RPC over stdin and
+/// stdout
+fn main() {
+    let code = match lsp::run(std::io::stdin().lock(), std::io::stdout().lock()) {
+        Ok(code) => code,
+        Err(error) => {
+            eprintln!("language server failed: {}", error);
+            1
+        }
+    };
+
+    std::process::exit(code);
+}
@@ -6,7 +6,9 @@ pub use crate::expressions::literal::Literal;
 pub use crate::expressions::unary::{Unary, UnaryOp};
 pub use crate::expressions::variable::Variable;
 
+use crate::span::AstSpan;
 use crate::visitor::ExpressionVisitor;
+use types::KirinType;
 
 mod assignment;
 mod binary;
@@ -26,6 +28,9 @@ pub enum Expression {
     Variable(Box<Variable>),
     Assign(Box<Assign>),
     Call(Box<Call>),
+    /// source that could not be parsed, kept so the later stages can check
+    /// the rest of the program
+    Error(AstSpan),
 }
 
 impl Expression {
@@ -38,6 +43,35 @@ impl Expression {
             Self::Variable(variable_expression) => variable_expression.accept(visitor),
             Self::Assign(assign) => assign.accept(visitor),
             Self::Call(callable) => callable.accept(visitor),
+            Self::Error(span) => visitor.visit_error(span),
+        }
+    }
+
+    pub fn span(&self) -> &AstSpan {
+        match self {
+            Self::Binary(binary) => &binary.span,
+            Self::Unary(unary) => &unary.span,
+            Self::Grouping(grouping) => &grouping.span,
+            Self::Literal(literal) => &literal.span,
+            Self::Variable(variable) => &variable.span,
+            Self::Assign(assign) => &assign.span,
+            Self::Call(callable) => &callable.span,
+            Self::Error(span) => span,
+        }
+    }
+
+    /// Error nodes are `any`, which the type checks accept, so they do not
+    /// cause more errors
+    pub fn inferred_type(&self) -> Option<KirinType> {
+        match self {
+            Self::Binary(binary) => binary.inferred_type,
+            Self::Unary(unary) => unary.inferred_type,
+            Self::Grouping(grouping) => grouping.inferred_type,
+            Self::Literal(literal) => literal.inferred_type,
+            Self::Variable(variable) => variable.inferred_type,
+            Self::Assign(assign) => assign.inferred_type,
+            Self::Call(callable) => callable.inferred_type,
+            Self::Error(_) => Some(KirinType::Any),
         }
     }
 }
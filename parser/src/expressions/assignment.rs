@@ -6,15 +6,18 @@ use types::KirinType;
 #[derive(Debug, Clone)]
 pub struct Assign {
     pub name: String,
+    /// the assigned name, `span` is the `=`
+    pub target: AstSpan,
     pub span: AstSpan,
     pub value: Expression,
     pub inferred_type: Option<KirinType>,
 }
 
 impl Assign {
-    pub fn new(name: String, value: Expression, span: AstSpan) -> Self {
+    pub fn new(name: String, target: AstSpan, value: Expression, span: AstSpan) -> Self {
         Self {
             name,
+            target,
             value,
             span,
             inferred_type: None,
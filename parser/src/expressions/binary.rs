@@ -49,6 +49,25 @@ pub enum BinaryOp {
 }
 
 impl BinaryOp {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Subtract => "-",
+            BinaryOp::Multiply => "*",
+            BinaryOp::Divide => "/",
+            BinaryOp::Power => "^",
+            BinaryOp::Or => "||",
+            BinaryOp::And => "&&",
+            BinaryOp::Equal => "==",
+            BinaryOp::NotEqual => "!=",
+            BinaryOp::Greater => ">",
+            BinaryOp::GreaterEqual => ">=",
+            BinaryOp::Less => "<",
+            BinaryOp::LessEqual => "<=",
+            BinaryOp::Modulus => "%",
+        }
+    }
+
     pub fn from_token(token: &Token) -> Result<BinaryOp, KirinError> {
         match token.token_type {
             TokenType::Plus => Ok(BinaryOp::Add),
@@ -64,12 +83,17 @@ impl BinaryOp {
             TokenType::Equal => Ok(BinaryOp::Equal),
             TokenType::NotEqual => Ok(BinaryOp::NotEqual),
             TokenType::EqualEqual => Ok(BinaryOp::Equal),
+            TokenType::And => Ok(BinaryOp::And),
+            TokenType::Or => Ok(BinaryOp::Or),
 
-            _ => Err(KirinError::Parse(SpannedError {
-                message: format!("token `{:?}` is not a binary operation", token.token_type),
-                line: token.span.line,
-                column: token.span.column,
-            })),
+            _ => Err(KirinError::Parse(
+                SpannedError::new(
+                    format!("token `{:?}` is not a binary operation", token.token_type),
+                    token.span.line,
+                    token.span.column,
+                )
+                .with_range(token.span.start..token.span.end),
+            )),
         }
     }
 }
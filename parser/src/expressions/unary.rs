@@ -35,16 +35,26 @@ pub enum UnaryOp {
 }
 
 impl UnaryOp {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            UnaryOp::Negate => "-",
+            UnaryOp::Not => "!",
+        }
+    }
+
     pub fn from_token(token: &Token) -> Result<UnaryOp, KirinError> {
         match token.token_type {
             TokenType::Minus => Ok(UnaryOp::Negate),
             TokenType::Not => Ok(UnaryOp::Not),
 
-            _ => Err(KirinError::Parse(SpannedError {
-                message: format!("token `{:?}` is not a unary operator", token.token_type),
-                line: token.span.line,
-                column: token.span.column,
-            })),
+            _ => Err(KirinError::Parse(
+                SpannedError::new(
+                    format!("token `{:?}` is not a unary operator", token.token_type),
+                    token.span.line,
+                    token.span.column,
+                )
+                .with_range(token.span.start..token.span.end),
+            )),
         }
     }
 }
@@ -1,6 +1,5 @@
 use crate::span::AstSpan;
 use crate::visitor::ExpressionVisitor;
-use scanner::Token;
 use types::KirinType;
 
 #[derive(Debug, Clone)]
@@ -8,27 +8,56 @@ use expressions::{
     Assign, Binary, BinaryOp, Call, Expression, Grouping, Literal, Unary, UnaryOp, Variable,
 };
 
-use errors::{KirinError, SpannedError};
+use errors::{KirinError, codes};
 use scanner::{Token, TokenSpan, TokenType};
 use span::AstSpan;
-use statements::{Statement, VariableDeclaration};
+use statements::{If, Statement, VariableDeclaration, While};
+use types::KirinType;
 use value::ParsedValue;
 
 const MAX_PARAMETERS: usize = 8;
 
+/// Keywords of blocks closed by `end`
+const BLOCK_KEYWORDS: [TokenType; 6] = [
+    TokenType::Fn,
+    TokenType::Class,
+    TokenType::Block,
+    TokenType::If,
+    TokenType::For,
+    TokenType::While,
+];
+
 pub struct Parser {
     tokens: Vec<Token>,
     filename: Option<String>,
     current: usize,
+    errors: Vec<KirinError>,
 }
 
 pub fn parse_ast(
     tokens: Vec<Token>,
     filename: Option<String>,
 ) -> Result<Vec<Statement>, Vec<KirinError>> {
+    let (statements, errors) = parse_with_recovery(tokens, filename);
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    Ok(statements)
+}
+
+/// Parse as much as possible. Statements and expressions that could not be
+/// parsed are kept as `Expression::Error` nodes, so the later stages can
+/// still check the rest of the program.
+pub fn parse_with_recovery(
+    tokens: Vec<Token>,
+    filename: Option<String>,
+) -> (Vec<Statement>, Vec<KirinError>) {
     let mut parser = Parser::new(tokens, filename);
+    let statements = parser.parse_all();
 
-    parser.parse_all()
+    (statements, parser.errors)
 }
 
 impl Parser {
@@ -37,39 +66,42 @@ impl Parser {
             tokens,
             filename,
             current: 0,
+            errors: Vec::new(),
         }
     }
 
-    fn parse_all(&mut self) -> Result<Vec<Statement>, Vec<KirinError>> {
+    fn parse_all(&mut self) -> Vec<Statement> {
         let mut statements = Vec::new();
-        let mut errors = Vec::new();
 
         while !self.is_at_end() {
-            let result = self.declaration();
-
-            match result {
-                Ok(stmt) => statements.push(stmt),
-                Err(error) => {
-                    errors.push(error);
-                    self.synchronize();
-                }
-            }
+            statements.extend(self.next_statement());
         }
 
-        if errors.len() > 0 {
-            return Err(errors);
+        statements
+    }
+
+    /// Parse the statement starting at the current token, `None` for an
+    /// empty line. A statement that cannot be parsed is reported and kept as
+    /// an error node.
+    fn next_statement(&mut self) -> Option<Statement> {
+        if self.match_tokens(&[TokenType::NewLine]) {
+            return None;
         }
 
-        Ok(statements)
+        let start = self.current;
+        match self.declaration() {
+            Ok(statement) => Some(statement),
+            Err(error) => {
+                self.errors.push(error);
+
+                let span = self.synchronize(start);
+                Some(Statement::ExpressionStatement(Expression::Error(span)))
+            }
+        }
     }
 
     fn declaration(&mut self) -> Result<Statement, KirinError> {
-        if self.match_tokens(&[TokenType::NewLine]) {
-            // skip trailing new line tokens
-            self.declaration()
-        } else if self.match_tokens(&[TokenType::Let]) {
-            self.var_declaration()
-        } else if self.check_next(TokenType::ColonEqual) {
+        if self.match_tokens(&[TokenType::Let]) || self.check_next(TokenType::ColonEqual) {
             self.var_declaration()
         } else {
             self.statement()
@@ -79,6 +111,33 @@ impl Parser {
     fn var_declaration(&mut self) -> Result<Statement, KirinError> {
         let name = self.consume(TokenType::Identifier)?.clone();
 
+        let mut annotation = None;
+        if self.match_tokens(&[TokenType::Colon]) {
+            annotation = Some(self.type_annotation()?);
+        }
+
+        // an initializer that cannot be parsed still declares the variable,
+        // so its uses are not reported as undefined
+        let start = self.current;
+        let initializer = match self.initializer() {
+            Ok(initializer) => initializer,
+            Err(error) => {
+                self.errors.push(error);
+                Some(Expression::Error(self.synchronize(start)))
+            }
+        };
+
+        let span = AstSpan::from_token_span(name.span, self.filename.clone());
+        let mut declaration = VariableDeclaration::new(name.lexeme.clone(), initializer, span);
+        if let Some((type_annotation, span)) = annotation {
+            declaration.type_annotation = Some(type_annotation);
+            declaration.annotation_span = Some(span);
+        }
+
+        Ok(Statement::VarDeclaration(declaration))
+    }
+
+    fn initializer(&mut self) -> Result<Option<Expression>, KirinError> {
         let mut initializer = None;
 
         if self.match_tokens(&[TokenType::Equal, TokenType::ColonEqual]) {
@@ -86,17 +145,173 @@ impl Parser {
         }
 
         self.consume(TokenType::NewLine)?;
+        Ok(initializer)
+    }
 
-        let span = AstSpan::from_token_span(name.span, self.filename.clone());
-        Ok(Statement::VarDeclaration(VariableDeclaration {
-            name: name.lexeme.clone(),
-            initializer,
-            span,
-        }))
+    /// The annotated type and where it is written. Unknown types are
+    /// reported and replaced by `any`, which every value can be assigned to
+    fn type_annotation(&mut self) -> Result<(KirinType, AstSpan), KirinError> {
+        let token = self.consume(TokenType::Identifier)?.clone();
+        let span = AstSpan::from_token_span(token.span, self.filename.clone());
+
+        match KirinType::from_name(&token.lexeme) {
+            Some(kind) => Ok((kind, span)),
+            None => {
+                let error = self.error_from_token_span(
+                    token.span,
+                    codes::UNKNOWN_TYPE,
+                    &format!("unknown type `{}`", token.lexeme),
+                );
+                self.errors.push(error);
+
+                Ok((KirinType::Any, span))
+            }
+        }
     }
 
     fn statement(&mut self) -> Result<Statement, KirinError> {
-        self.expression_statement()
+        match self.peek().token_type {
+            TokenType::If => Ok(self.if_statement()),
+            TokenType::While => Ok(self.while_statement()),
+            TokenType::Return => self.return_statement(),
+            token_type if BLOCK_KEYWORDS.contains(&token_type) => Err(self.block()),
+
+            _ => self.expression_statement(),
+        }
+    }
+
+    /// `if condition`, the statements up to `else` or `end`, then the
+    /// statements after the `else` up to `end`
+    fn if_statement(&mut self) -> Statement {
+        let keyword = self.advance().clone();
+        let span = AstSpan::from_token_span(keyword.span, self.filename.clone());
+
+        let condition = self.condition();
+        let then_branch = self.block_statements(&[TokenType::Else, TokenType::End]);
+
+        let mut else_branch = None;
+        if self.match_tokens(&[TokenType::Else]) {
+            self.end_of_line();
+            else_branch = Some(self.block_statements(&[TokenType::End]));
+        }
+        self.end(&keyword, "if");
+
+        Statement::If(Box::new(If::new(condition, then_branch, else_branch, span)))
+    }
+
+    /// `while condition` and the statements up to `end`
+    fn while_statement(&mut self) -> Statement {
+        let keyword = self.advance().clone();
+        let span = AstSpan::from_token_span(keyword.span, self.filename.clone());
+
+        let condition = self.condition();
+        let body = self.block_statements(&[TokenType::End]);
+        self.end(&keyword, "while");
+
+        Statement::While(Box::new(While::new(condition, body, span)))
+    }
+
+    /// `return` ends the program. It has no value, as there are no
+    /// functions to return it to.
+    fn return_statement(&mut self) -> Result<Statement, KirinError> {
+        let keyword = self.advance().clone();
+        let span = AstSpan::from_token_span(keyword.span, self.filename.clone());
+
+        self.consume(TokenType::NewLine)?;
+        Ok(Statement::Return(span))
+    }
+
+    /// The condition on the line of an `if` or `while`. A condition that
+    /// cannot be parsed is reported and kept as an error node, so the
+    /// statements of its block are still parsed.
+    fn condition(&mut self) -> Expression {
+        let start = self.current;
+
+        match self.expression() {
+            Ok(condition) => {
+                self.end_of_line();
+                condition
+            }
+            Err(error) => {
+                self.errors.push(error);
+                Expression::Error(self.synchronize(start))
+            }
+        }
+    }
+
+    /// The statements of a block, up to one of `terminators` or the end of
+    /// the source
+    fn block_statements(&mut self, terminators: &[TokenType]) -> Vec<Statement> {
+        let mut statements = Vec::new();
+
+        while !self.is_at_end() && !terminators.contains(&self.peek().token_type) {
+            statements.extend(self.next_statement());
+        }
+
+        statements
+    }
+
+    /// Consume the `end` of the block opened by `keyword`, reporting a
+    /// missing one
+    fn end(&mut self, keyword: &Token, name: &str) {
+        match self.consume(TokenType::End) {
+            Ok(_) => self.end_of_line(),
+            Err(KirinError::Parse(error)) => self.errors.push(KirinError::Parse(error.with_label(
+                keyword.span.start..keyword.span.end,
+                format!("unclosed `{}`", name),
+            ))),
+            Err(error) => self.errors.push(error),
+        }
+    }
+
+    /// Consume the new line after a block keyword, reporting and skipping
+    /// anything else on its line
+    fn end_of_line(&mut self) {
+        let start = self.current;
+
+        if let Err(error) = self.consume(TokenType::NewLine) {
+            self.errors.push(error);
+            self.synchronize(start);
+        }
+    }
+
+    /// Blocks other than `if` and `while` are not part of the language yet.
+    /// Such a block is reported once and skipped up to its `end`, so its body
+    /// does not add more errors.
+    fn block(&mut self) -> KirinError {
+        let keyword = self.advance().clone();
+        let span = AstSpan::from_token_span(keyword.span, self.filename.clone());
+
+        let mut error = span
+            .error(format!("unsupported `{:?}` block", keyword.token_type))
+            .with_code(codes::UNSUPPORTED_BLOCK);
+
+        if !self.skip_block() {
+            error = error.with_note("the block is not closed by an `end`".to_string());
+        }
+
+        KirinError::Parse(error)
+    }
+
+    /// Skip past the `end` of the block whose keyword was just consumed,
+    /// counting nested blocks. Returns `false` when the source ends first.
+    fn skip_block(&mut self) -> bool {
+        let mut depth = 1;
+
+        while !self.is_at_end() {
+            match self.advance().token_type {
+                TokenType::End => depth -= 1,
+                token_type if BLOCK_KEYWORDS.contains(&token_type) => depth += 1,
+
+                _ => {}
+            }
+
+            if depth == 0 {
+                return true;
+            }
+        }
+
+        false
     }
 
     fn expression_statement(&mut self) -> Result<Statement, KirinError> {
@@ -122,19 +337,26 @@ impl Parser {
 
             if let Expression::Variable(variable) = &expression {
                 let name = variable.name.clone();
-                return Ok(Expression::Assign(Box::new(Assign::new(name, value, span))));
+                let target = variable.span.clone();
+                return Ok(Expression::Assign(Box::new(Assign::new(
+                    name, target, value, span,
+                ))));
             }
 
-            return Err(self.error_from_token_span(equals.span, "invalid assignment target"));
+            return Err(self.error_from_token_span(
+                equals.span,
+                codes::INVALID_ASSIGNMENT_TARGET,
+                "invalid assignment target",
+            ));
         }
 
         Ok(expression)
     }
 
     fn or(&mut self) -> Result<Expression, KirinError> {
-        let expression = self.and()?;
+        let mut expression = self.and()?;
 
-        if self.match_tokens(&[TokenType::Or]) {
+        while self.match_tokens(&[TokenType::Or]) {
             let operator_token = self.previous();
 
             let operator = BinaryOp::from_token(operator_token)?;
@@ -142,18 +364,17 @@ impl Parser {
 
             let right = self.and()?;
 
-            let binary =
-                Expression::Binary(Box::new(Binary::new(expression, right, operator, span)));
-            return Ok(binary);
+            expression =
+                Expression::Binary(Box::new(Binary::new(expression, right, operator, span)))
         }
 
         Ok(expression)
     }
 
     fn and(&mut self) -> Result<Expression, KirinError> {
-        let expression = self.equality()?;
+        let mut expression = self.equality()?;
 
-        if self.match_tokens(&[TokenType::And]) {
+        while self.match_tokens(&[TokenType::And]) {
             let operator_token = self.previous();
 
             let operator = BinaryOp::from_token(operator_token)?;
@@ -161,9 +382,8 @@ impl Parser {
 
             let right = self.equality()?;
 
-            let binary =
-                Expression::Binary(Box::new(Binary::new(expression, right, operator, span)));
-            return Ok(binary);
+            expression =
+                Expression::Binary(Box::new(Binary::new(expression, right, operator, span)))
         }
 
         Ok(expression)
@@ -287,7 +507,8 @@ impl Parser {
         let expression = self.primary()?;
 
         if self.match_tokens(&[TokenType::LeftParen]) {
-            return self.finish_call(expression);
+            let paren = self.previous().clone();
+            return self.finish_call(expression, &paren);
         }
 
         Ok(expression)
@@ -299,10 +520,16 @@ impl Parser {
             loop {
                 if arguments.len() > MAX_PARAMETERS {
                     let previous = self.previous().clone();
-                    return Err(self.error_from_token_span(previous.span, "Too many arguments"));
+                    return Err(self.error_from_token_span(
+                        previous.span,
+                        codes::TOO_MANY_ARGUMENTS,
+                        "Too many arguments",
+                    ));
                 }
 
-                arguments.push(self.expression()?);
+                arguments.push(
+                    self.recover(&[TokenType::Comma, TokenType::RightParen], Self::expression)?,
+                );
                 if !self.match_tokens(&[TokenType::Comma]) {
                     break;
                 }
@@ -312,10 +539,10 @@ impl Parser {
         Ok(arguments)
     }
 
-    fn finish_call(&mut self, callee: Expression) -> Result<Expression, KirinError> {
+    fn finish_call(&mut self, callee: Expression, open: &Token) -> Result<Expression, KirinError> {
         let arguments = self.get_arguments()?;
 
-        let paren = self.consume(TokenType::RightParen)?;
+        let paren = self.close(open)?;
 
         let span = AstSpan::from_token_span(paren.span, self.filename.clone());
         Ok(Expression::Call(Box::new(Call::new(
@@ -353,8 +580,9 @@ impl Parser {
         }
 
         if self.match_tokens(&[TokenType::LeftParen]) {
-            let expression = self.expression()?;
-            let token = self.consume(TokenType::RightParen)?;
+            let open = self.previous().clone();
+            let expression = self.recover(&[TokenType::RightParen], Self::expression)?;
+            let token = self.close(&open)?;
 
             return Ok(Expression::Grouping(Box::new(Grouping::new(
                 expression,
@@ -368,33 +596,136 @@ impl Parser {
 
         let current = self.peek().clone();
         Err(self.error_from_token_span(
-            current.span,
+            self.found_span(),
+            codes::EXPECTED_EXPRESSION,
             &format!("expected Expression but found `{:?}`", current.token_type),
         ))
     }
 
-    fn synchronize(&mut self) {
-        self.advance();
+    /// Parse with `parse`, and when it fails skip to one of `delimiters` if
+    /// that does not leave the enclosing brackets or the line. The error is
+    /// then recorded and an error node takes the place of the expression,
+    /// otherwise the error is returned for the statement to recover from.
+    fn recover(
+        &mut self,
+        delimiters: &[TokenType],
+        parse: impl FnOnce(&mut Self) -> Result<Expression, KirinError>,
+    ) -> Result<Expression, KirinError> {
+        let start = self.current;
+        let error = match parse(self) {
+            Ok(expression) => return Ok(expression),
+            Err(error) => error,
+        };
+
+        let mut depth = self.open_brackets(start);
+        let mut position = self.current;
+        loop {
+            let token_type = self.tokens[position].token_type;
+
+            match token_type {
+                _ if depth == 0 && delimiters.contains(&token_type) => break,
+                TokenType::LeftParen | TokenType::LeftBracket => depth += 1,
+                TokenType::RightParen | TokenType::RightBracket if depth > 0 => depth -= 1,
+                TokenType::NewLine if self.continues_line(position) => {}
+
+                TokenType::RightParen
+                | TokenType::RightBracket
+                | TokenType::NewLine
+                | TokenType::Eof => return Err(error),
 
-        while !self.is_at_end() {
-            if self.previous().token_type == TokenType::NewLine {
-                return;
+                _ => {}
             }
 
+            position += 1;
+        }
+
+        self.errors.push(error);
+        self.current = position;
+
+        Ok(Expression::Error(self.span_between(start, position)))
+    }
+
+    /// Skip the rest of a statement that failed to parse: up to the end of
+    /// the line, or of the brackets opened in it when the line continues
+    /// inside them. Returns the span from `start` to the skipped tokens.
+    fn synchronize(&mut self, start: usize) -> AstSpan {
+        if self.current == start && !self.check(TokenType::NewLine) {
+            self.advance();
+        }
+
+        let mut depth = self.open_brackets(start);
+        while !self.is_at_end() {
             match self.peek().token_type {
-                TokenType::Class
-                | TokenType::Fn
-                | TokenType::Let
-                | TokenType::For
-                | TokenType::If
-                | TokenType::While
-                | TokenType::Return => return,
+                TokenType::LeftParen | TokenType::LeftBracket => depth += 1,
+                TokenType::RightParen | TokenType::RightBracket => depth = depth.saturating_sub(1),
+                TokenType::NewLine if depth == 0 || !self.continues_line(self.current) => break,
 
                 _ => {}
             }
 
             self.advance();
         }
+
+        let span = self.span_between(start, self.current);
+        self.match_tokens(&[TokenType::NewLine]);
+
+        span
+    }
+
+    /// The number of brackets opened but not closed from the token at
+    /// `start` up to the current one
+    fn open_brackets(&self, start: usize) -> usize {
+        self.tokens[start..self.current]
+            .iter()
+            .fold(0, |depth, token| match token.token_type {
+                TokenType::LeftParen | TokenType::LeftBracket => depth + 1,
+                TokenType::RightParen | TokenType::RightBracket => depth.saturating_sub(1),
+                _ => depth,
+            })
+    }
+
+    /// Whether the expression goes on after the new line at `position`:
+    /// after an operator, a comma or an opening bracket the parser skips
+    /// new lines
+    fn continues_line(&self, position: usize) -> bool {
+        let Some(before) = position.checked_sub(1).map(|index| &self.tokens[index]) else {
+            return false;
+        };
+
+        matches!(
+            before.token_type,
+            TokenType::Plus
+                | TokenType::Minus
+                | TokenType::Star
+                | TokenType::Slash
+                | TokenType::Percent
+                | TokenType::Caret
+                | TokenType::And
+                | TokenType::Or
+                | TokenType::Not
+                | TokenType::Equal
+                | TokenType::ColonEqual
+                | TokenType::EqualEqual
+                | TokenType::NotEqual
+                | TokenType::Greater
+                | TokenType::GreaterEqual
+                | TokenType::Less
+                | TokenType::LessEqual
+                | TokenType::Comma
+                | TokenType::LeftParen
+                | TokenType::LeftBracket
+        )
+    }
+
+    /// The span from the token at `start` to the token before `end`, or of
+    /// the token at `start` when nothing was skipped
+    fn span_between(&self, start: usize, end: usize) -> AstSpan {
+        let last = self.tokens[end.saturating_sub(1).max(start)].span;
+
+        let mut span = AstSpan::from_token_span(self.tokens[start].span, self.filename.clone());
+        span.end = last.end.max(span.start);
+
+        span
     }
 
     fn match_tokens(&mut self, token_types: &[TokenType]) -> bool {
@@ -421,13 +752,29 @@ impl Parser {
             return Ok(self.advance());
         }
 
-        let previous = self.previous().clone();
+        let found = self.peek().clone();
         Err(self.error_from_token_span(
-            previous.span,
-            &format!("expected {:?}, got {:?}", token_type, previous.token_type),
+            self.found_span(),
+            codes::EXPECTED_TOKEN,
+            &format!(
+                "expected `{:?}` but found `{:?}`",
+                token_type, found.token_type
+            ),
         ))
     }
 
+    /// Consume the `)` closing the `open` parenthesis, pointing at it when
+    /// the `)` is missing
+    fn close(&mut self, open: &Token) -> Result<Token, KirinError> {
+        match self.consume(TokenType::RightParen) {
+            Ok(paren) => Ok(paren.clone()),
+            Err(KirinError::Parse(error)) => Err(KirinError::Parse(
+                error.with_label(open.span.start..open.span.end, "unclosed `(`".to_string()),
+            )),
+            Err(error) => Err(error),
+        }
+    }
+
     /// Check if the current token is of the given type
     fn check(&self, token_type: TokenType) -> bool {
         if self.is_at_end() {
@@ -464,11 +811,171 @@ impl Parser {
         &self.tokens[self.current - 1]
     }
 
-    fn error_from_token_span(&mut self, span: TokenSpan, message: &str) -> KirinError {
-        KirinError::Parse(SpannedError {
-            line: span.line,
-            column: span.column,
-            message: message.to_string(),
-        })
+    /// Where the current token is when reporting that it is unexpected. A
+    /// line or the source ending too early is shown right after the last
+    /// token of the line, not at the start of the next one.
+    fn found_span(&self) -> TokenSpan {
+        let found = self.peek();
+        if !matches!(found.token_type, TokenType::NewLine | TokenType::Eof) || self.current == 0 {
+            return found.span;
+        }
+
+        let last = self.previous().span;
+        TokenSpan {
+            line: last.line,
+            column: last.column + (last.end - last.start),
+            start: last.end,
+            end: last.end,
+        }
+    }
+
+    fn error_from_token_span(
+        &mut self,
+        span: TokenSpan,
+        code: &'static str,
+        message: &str,
+    ) -> KirinError {
+        KirinError::Parse(
+            AstSpan::from_token_span(span, self.filename.clone())
+                .error(message.to_string())
+                .with_code(code),
+        )
+    }
+}
+
+#[cfg(test)]
+mod parser_tests {
+    use crate::expressions::Expression;
+    use crate::parse_with_recovery;
+    use crate::statements::Statement;
+    use errors::KirinError;
+
+    fn parse(source: &str) -> (Vec<Statement>, Vec<KirinError>) {
+        let tokens = scanner::scan_tokens(source).unwrap();
+
+        parse_with_recovery(tokens, None)
+    }
+
+    /// Malformed sources with the number of errors each should report, one
+    /// per mistake and none following from another
+    const MALFORMED: [(&str, usize); 20] = [
+        ("print(1 +, 2)\nb := 3\n", 1),
+        ("print((1, 2))\n", 1),
+        ("print(1, 2\nb := 3\nprint(b)\n", 1),
+        ("print(a b c)\nprint(a)\n", 1),
+        ("print(1,\n  2 +\n  , 3)\n", 1),
+        ("a := print(1 +, ) + 2\n", 2),
+        ("a := (1 + 2\nprint(a)\n", 1),
+        ("a := 1 2\nprint(a)\n", 1),
+        ("a := )\nb := a\nc := (\n", 2),
+        ("let a: strng = 1\nprint(a)\n", 1),
+        ("1 = 2\nprint(1 +)\n", 2),
+        (
+            "while a\n  b := 1\n  if b\n    print(b +)\n  end\nend\nc := 2\n",
+            1,
+        ),
+        ("fn f\n  print(1)\n", 1),
+        ("end\nprint(1)\nend\n", 2),
+        ("a := [1, 2\nprint(a)\n", 1),
+        ("print(1))\nprint(2)\n", 1),
+        ("if a\n  print(1)\n", 1),
+        ("if a b\n  print(a +)\nend\n", 2),
+        (
+            "if a\n  b := 1\nelse c\n  print(b)\nend\nwhile a\nelse\nend\n",
+            2,
+        ),
+        ("return 1\nprint(2)\n", 1),
+    ];
+
+    #[test]
+    fn test_malformed_sources() {
+        for (source, expected) in MALFORMED {
+            let (_, errors) = parse(source);
+
+            assert_eq!(
+                errors.len(),
+                expected,
+                "{:?} reported {:#?}",
+                source,
+                errors
+            );
+        }
+    }
+
+    #[test]
+    fn test_expected_token_reports_found_token() {
+        let (_, errors) = parse("print(a b)\n");
+
+        assert_eq!(
+            errors[0].spanned().unwrap().message,
+            "expected `RightParen` but found `Identifier`"
+        );
+        assert_eq!(errors[0].spanned().unwrap().column, 9);
+
+        // a missing token at the end of a line is reported after its last token
+        let (_, errors) = parse("a := (1 + 2\n");
+        let error = errors[0].diagnostic();
+        assert_eq!(error.message, "expected `RightParen` but found `NewLine`");
+        assert_eq!((error.line, error.column), (1, 12));
+        assert_eq!(error.labels[0].message, "unclosed `(`");
+    }
+
+    #[test]
+    fn test_control_flow() {
+        let (statements, errors) = parse(
+            "if a > 1\n  print(a)\nelse\n  b := 2\n\n  c := 3\nend\nwhile a\n  return\nend\n",
+        );
+        assert!(errors.is_empty(), "{:#?}", errors);
+        assert_eq!(statements.len(), 2);
+
+        let Statement::If(if_statement) = &statements[0] else {
+            panic!("expected an if, got {:?}", statements[0]);
+        };
+        assert!(matches!(if_statement.condition, Expression::Binary(_)));
+        assert_eq!(if_statement.then_branch.len(), 1);
+        assert_eq!(if_statement.else_branch.as_ref().map(Vec::len), Some(2));
+
+        let Statement::While(while_statement) = &statements[1] else {
+            panic!("expected a while, got {:?}", statements[1]);
+        };
+        assert!(matches!(while_statement.body[..], [Statement::Return(_)]));
+
+        // an unclosed block keeps its statements and points at its keyword
+        let (statements, errors) = parse("x := 1\nwhile x\n  print(x)\n");
+        assert!(matches!(&statements[1], Statement::While(body) if body.body.len() == 1));
+        let error = errors[0].diagnostic();
+        assert_eq!(error.message, "expected `End` but found `Eof`");
+        assert_eq!(error.labels[0].message, "unclosed `while`");
+        assert_eq!(error.labels[0].range, 7..12);
+    }
+
+    #[test]
+    fn test_error_nodes() {
+        let (statements, errors) = parse("a := 1 +)\nprint(1 +, b)\n* 2\nc := 3\n");
+
+        assert_eq!(errors.len(), 3);
+        assert_eq!(statements.len(), 4);
+
+        // the variable is still declared
+        let Statement::VarDeclaration(declaration) = &statements[0] else {
+            panic!("expected a declaration, got {:?}", statements[0]);
+        };
+        assert!(matches!(
+            declaration.initializer,
+            Some(Expression::Error(_))
+        ));
+
+        // the call keeps the arguments that could be parsed
+        let Statement::ExpressionStatement(Expression::Call(call)) = &statements[1] else {
+            panic!("expected a call, got {:?}", statements[1]);
+        };
+        assert!(matches!(call.arguments[0], Expression::Error(_)));
+        assert!(matches!(call.arguments[1], Expression::Variable(_)));
+
+        let Statement::ExpressionStatement(Expression::Error(span)) = &statements[2] else {
+            panic!("expected an error node, got {:?}", statements[2]);
+        };
+        assert_eq!((span.line, span.column), (3, 1));
+        assert!(matches!(statements[3], Statement::VarDeclaration(_)));
     }
 }
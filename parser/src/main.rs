@@ -1,26 +1,57 @@
+use errors::{ErrorFormat, KirinError, use_color};
 use parser::parse_ast;
 use scanner::scan_tokens;
 
 fn main() {
     let args = std::env::args().collect::<Vec<String>>();
 
+    let format = args
+        .iter()
+        .rev()
+        .find_map(|arg| ErrorFormat::from_flag(arg))
+        .unwrap_or_default();
+
     if args.len() < 2 {
         repl()
     } else {
-        run_file(args.get(1).unwrap())
+        run_file(args.get(1).unwrap(), format)
     }
 }
 
-fn run_file(path: &str) {
-    let contents = std::fs::read_to_string(path).unwrap();
-
-    let tokens = scan_tokens(contents.as_str()).unwrap();
+fn run_file(path: &str, format: ErrorFormat) {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(error) => {
+            let error = KirinError::General(format!("failed to read {}: {}", path, error));
+            eprint!("{}", error.render_as(format, None, false));
+            std::process::exit(1);
+        }
+    };
 
-    let ast = parse_ast(tokens, Some(path.to_string())).unwrap();
+    let result = scan_tokens(contents.as_str())
+        .map_err(|error| vec![error])
+        .and_then(|tokens| parse_ast(tokens, Some(path.to_string())));
 
-    ast.iter().for_each(|item| println!("{:#?}", item));
+    match result {
+        Ok(ast) => ast.iter().for_each(|item| println!("{:#?}", item)),
+        Err(errors) => {
+            let color = use_color(&std::io::stderr());
+            for error in errors {
+                eprint!(
+                    "{}",
+                    error
+                        .in_file(path)
+                        .render_as(format, Some(&contents), color)
+                );
+            }
+            std::process::exit(1);
+        }
+    }
 }
 
+/// The REPL needs the analyzer, compiler and VM, which depend on this
+/// crate, so it is part of the compiler driver
 fn repl() {
-    println!("Usage: cargo run --bin parser -- <file>");
+    println!("Usage: cargo run --bin parser -- <file> [--error-format=human|json]");
+    println!("Start the REPL with: cargo run --bin compiler");
 }
@@ -1,9 +1,14 @@
+use errors::SpannedError;
 use scanner::TokenSpan;
+use std::ops::Range;
 
 #[derive(Clone, Debug)]
 pub struct AstSpan {
     pub line: usize,
     pub column: usize,
+    /// byte offsets of the token in the source
+    pub start: usize,
+    pub end: usize,
     pub filename: Option<String>,
 }
 
@@ -12,14 +17,27 @@ impl AstSpan {
         let TokenSpan {
             line,
             column,
-            start: _start,
-            end: _end,
+            start,
+            end,
         } = span;
 
         Self {
             line,
             column,
+            start,
+            end,
             filename,
         }
     }
+
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    /// An error pointing at this span
+    pub fn error(&self, message: String) -> SpannedError {
+        SpannedError::new(message, self.line, self.column)
+            .with_file(self.filename.clone())
+            .with_range(self.range())
+    }
 }
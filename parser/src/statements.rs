@@ -1,8 +1,11 @@
+mod control_flow;
 mod declaration;
 
 use crate::expressions::Expression;
+use crate::span::AstSpan;
 
 use crate::visitor::StatementVisitor;
+pub use control_flow::{If, While};
 pub use declaration::VariableDeclaration;
 
 #[derive(Debug, Clone)]
@@ -10,6 +13,10 @@ pub enum Statement {
     None,
     ExpressionStatement(Expression),
     VarDeclaration(VariableDeclaration),
+    If(Box<If>),
+    While(Box<While>),
+    /// `return`, which ends the program
+    Return(AstSpan),
 }
 
 impl Statement {
@@ -22,6 +29,22 @@ impl Statement {
             Statement::VarDeclaration(var_declaration) => {
                 visitor.visit_var_declaration(var_declaration)
             }
+            Statement::If(if_statement) => visitor.visit_if(if_statement),
+            Statement::While(while_statement) => visitor.visit_while(while_statement),
+            Statement::Return(span) => visitor.visit_return(span),
+        }
+    }
+
+    /// Where the statement is reported: its expression, the declared name
+    /// or its keyword
+    pub fn span(&self) -> Option<&AstSpan> {
+        match self {
+            Statement::None => None,
+            Statement::ExpressionStatement(expression) => Some(expression.span()),
+            Statement::VarDeclaration(var_declaration) => Some(&var_declaration.span),
+            Statement::If(if_statement) => Some(&if_statement.span),
+            Statement::While(while_statement) => Some(&while_statement.span),
+            Statement::Return(span) => Some(span),
         }
     }
 }
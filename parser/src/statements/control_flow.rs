@@ -0,0 +1,48 @@
+use crate::expressions::Expression;
+use crate::span::AstSpan;
+use crate::statements::Statement;
+
+/// `if condition`, its statements, then those after an `else` up to `end`
+#[derive(Debug, Clone)]
+pub struct If {
+    pub condition: Expression,
+    pub then_branch: Vec<Statement>,
+    pub else_branch: Option<Vec<Statement>>,
+    /// the `if`
+    pub span: AstSpan,
+}
+
+impl If {
+    pub fn new(
+        condition: Expression,
+        then_branch: Vec<Statement>,
+        else_branch: Option<Vec<Statement>>,
+        span: AstSpan,
+    ) -> Self {
+        Self {
+            condition,
+            then_branch,
+            else_branch,
+            span,
+        }
+    }
+}
+
+/// `while condition`, and the statements repeated up to `end`
+#[derive(Debug, Clone)]
+pub struct While {
+    pub condition: Expression,
+    pub body: Vec<Statement>,
+    /// the `while`
+    pub span: AstSpan,
+}
+
+impl While {
+    pub fn new(condition: Expression, body: Vec<Statement>, span: AstSpan) -> Self {
+        Self {
+            condition,
+            body,
+            span,
+        }
+    }
+}
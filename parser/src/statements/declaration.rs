@@ -1,10 +1,15 @@
 use crate::expressions::Expression;
 use crate::span::AstSpan;
+use types::KirinType;
 
 #[derive(Debug, Clone)]
 pub struct VariableDeclaration {
     pub name: String,
     pub initializer: Option<Expression>,
+    pub type_annotation: Option<KirinType>,
+    /// where the type annotation is written
+    pub annotation_span: Option<AstSpan>,
+    pub inferred_type: Option<KirinType>,
     pub span: AstSpan,
 }
 
@@ -13,6 +18,9 @@ impl VariableDeclaration {
         Self {
             name,
             initializer,
+            type_annotation: None,
+            annotation_span: None,
+            inferred_type: None,
             span,
         }
     }
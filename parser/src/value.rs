@@ -1,4 +1,4 @@
-use errors::{KirinError, SpannedError};
+use errors::{KirinError, SpannedError, codes};
 use scanner::{Token, TokenSpan, TokenType};
 use types::KirinType;
 
@@ -22,14 +22,17 @@ impl ParsedValue {
             TokenType::String => Ok(ParsedValue::String(token.lexeme.clone())),
             TokenType::Number => parse_number(&token.lexeme, token.span),
 
-            _ => Err(KirinError::Parse(SpannedError {
-                message: format!(
-                    "cannot parse Token: `{:?}` into a literal value",
-                    token.token_type
-                ),
-                line: token.span.line,
-                column: token.span.column,
-            })),
+            _ => Err(KirinError::Parse(
+                SpannedError::new(
+                    format!(
+                        "cannot parse Token: `{:?}` into a literal value",
+                        token.token_type
+                    ),
+                    token.span.line,
+                    token.span.column,
+                )
+                .with_range(token.span.start..token.span.end),
+            )),
         }
     }
 
@@ -46,18 +49,23 @@ impl ParsedValue {
     }
 }
 
+/// Numbers written with a `.` or an exponent are floats, even when they
+/// have no fraction
 fn parse_number(number: &str, span: TokenSpan) -> Result<ParsedValue, KirinError> {
     let split = number.split("E").collect::<Vec<&str>>();
 
+    if split.len() == 1 && !number.contains('.') {
+        return match number.parse::<i64>() {
+            Ok(n) => Ok(ParsedValue::Int(n)),
+            Err(err) => Err(number_error(err.to_string(), span)),
+        };
+    }
+
     let base_result = split[0].parse::<f64>();
 
     match base_result {
         Ok(n) => {
             if split.len() == 1 {
-                if n.fract() == 0.0 {
-                    return Ok(ParsedValue::Int(n as i64));
-                }
-
                 return Ok(ParsedValue::Float(n));
             }
 
@@ -65,24 +73,24 @@ fn parse_number(number: &str, span: TokenSpan) -> Result<ParsedValue, KirinError
             match exponent_result {
                 Ok(e) => Ok(ParsedValue::Float(n * 10f64.powi(e as i32))),
 
-                Err(error) => Err(KirinError::Parse(SpannedError {
-                    line: span.line,
-                    column: span.column,
-                    message: format!(
-                        "failed to parse exponent of number: `{}`",
-                        error.to_string()
-                    ),
-                })),
+                Err(error) => Err(number_error(
+                    format!("failed to parse exponent of number: `{}`", error),
+                    span,
+                )),
             }
         }
-        Err(err) => Err(KirinError::Parse(SpannedError {
-            line: span.line,
-            column: span.column,
-            message: err.to_string(),
-        })),
+        Err(err) => Err(number_error(err.to_string(), span)),
     }
 }
 
+fn number_error(message: String, span: TokenSpan) -> KirinError {
+    KirinError::Parse(
+        SpannedError::new(message, span.line, span.column)
+            .with_range(span.start..span.end)
+            .with_code(codes::INVALID_NUMBER),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use crate::value::{ParsedValue, parse_number};
@@ -90,7 +98,7 @@ mod tests {
 
     #[test]
     fn test_parse_number() {
-        let src = vec!["20.9", "10E5", "2E-3", "1000"];
+        let src = ["20.9", "10E5", "2E-3", "1000", "2.0", "9007199254740993"];
 
         let calculated = src
             .iter()
@@ -101,6 +109,8 @@ mod tests {
             ParsedValue::Float(10E5),
             ParsedValue::Float(2E-3),
             ParsedValue::Int(1000),
+            ParsedValue::Float(2.0),
+            ParsedValue::Int(9007199254740993),
         ];
 
         assert_eq!(calculated, expected);
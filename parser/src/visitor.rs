@@ -1,5 +1,6 @@
 use crate::expressions::{Assign, Binary, Call, Expression, Grouping, Literal, Unary, Variable};
-use crate::statements::VariableDeclaration;
+use crate::span::AstSpan;
+use crate::statements::{If, VariableDeclaration, While};
 
 pub trait ExpressionVisitor {
     type Output;
@@ -10,6 +11,7 @@ pub trait ExpressionVisitor {
     fn visit_call(&mut self, callable: &Call) -> Self::Output;
     fn visit_variable(&mut self, variable: &Variable) -> Self::Output;
     fn visit_assign(&mut self, assign: &Assign) -> Self::Output;
+    fn visit_error(&mut self, span: &AstSpan) -> Self::Output;
 }
 
 pub trait StatementVisitor {
@@ -18,4 +20,7 @@ pub trait StatementVisitor {
     fn visit_none(&mut self) -> Self::Output;
     fn visit_var_declaration(&mut self, var_declaration: &VariableDeclaration) -> Self::Output;
     fn visit_expression_statement(&mut self, expression_statement: &Expression) -> Self::Output;
+    fn visit_if(&mut self, if_statement: &If) -> Self::Output;
+    fn visit_while(&mut self, while_statement: &While) -> Self::Output;
+    fn visit_return(&mut self, span: &AstSpan) -> Self::Output;
 }
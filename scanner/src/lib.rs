@@ -1,9 +1,30 @@
 mod span;
 mod token;
 
-use errors::{KirinError, SpannedError};
+use errors::{KirinError, SpannedError, codes};
 pub use span::TokenSpan;
-pub use token::{Token, TokenType};
+pub use token::{Token, TokenType, debug_print_tokens};
+
+/// The reserved words and the tokens they are scanned as
+pub const KEYWORDS: [(&str, TokenType); 17] = [
+    ("for", TokenType::For),
+    ("if", TokenType::If),
+    ("else", TokenType::Else),
+    ("while", TokenType::While),
+    ("fn", TokenType::Fn),
+    ("end", TokenType::End),
+    ("return", TokenType::Return),
+    ("true", TokenType::True),
+    ("false", TokenType::False),
+    ("and", TokenType::And),
+    ("or", TokenType::Or),
+    ("class", TokenType::Class),
+    ("let", TokenType::Let),
+    ("block", TokenType::Block),
+    ("delete", TokenType::Delete),
+    ("none", TokenType::None),
+    ("include", TokenType::Include),
+];
 
 fn simple_token(token_type: TokenType, span: TokenSpan) -> Token {
     Token {
@@ -45,7 +66,7 @@ impl Scanner {
             start: 0,
             current: 0,
             line: 1,
-            line_start: 1,
+            line_start: 0,
         }
     }
 
@@ -72,11 +93,11 @@ impl Scanner {
             tokens.push(token);
         }
 
-        if let Some(last) = tokens.last() {
-            if last.token_type != TokenType::NewLine {
-                let token = simple_token(TokenType::NewLine, self.get_span());
-                tokens.push(token);
-            }
+        if let Some(last) = tokens.last()
+            && last.token_type != TokenType::NewLine
+        {
+            let token = simple_token(TokenType::NewLine, self.get_span());
+            tokens.push(token);
         }
 
         tokens.push(Token {
@@ -88,12 +109,14 @@ impl Scanner {
         Ok(tokens)
     }
 
-    fn generate_error(&self, message: String) -> KirinError {
-        KirinError::Scan(SpannedError {
-            message,
-            line: self.line,
-            column: self.start,
-        })
+    fn generate_error(&self, code: &'static str, message: String) -> KirinError {
+        let span = self.get_span();
+
+        KirinError::Scan(
+            SpannedError::new(message, span.line, span.column)
+                .with_range(span.start..span.end)
+                .with_code(code),
+        )
     }
 
     fn scan_token(&mut self) -> Result<Token, KirinError> {
@@ -136,13 +159,19 @@ impl Scanner {
                     return Ok(simple_token(TokenType::And, self.get_span()));
                 }
 
-                Err(self.generate_error(format!("Unexpected character {}", current_character)))
+                Err(self.generate_error(
+                    codes::UNEXPECTED_CHARACTER,
+                    format!("Unexpected character {}", current_character),
+                ))
             }
 
             '|' => {
                 let next = self.advance();
                 if next != '|' {
-                    return Err(self.generate_error("Unknown character '|' ".to_string()));
+                    return Err(self.generate_error(
+                        codes::UNEXPECTED_CHARACTER,
+                        "Unknown character '|' ".to_string(),
+                    ));
                 }
 
                 Ok(simple_token(TokenType::Or, self.get_span()))
@@ -184,7 +213,10 @@ impl Scanner {
             x if x.is_ascii_digit() => self.scan_number(),
             x if is_identifier_start(x) => self.scan_identifier(),
 
-            _ => Err(self.generate_error(format!("Unknown character {}", current_character))),
+            _ => Err(self.generate_error(
+                codes::UNEXPECTED_CHARACTER,
+                format!("Unknown character {}", current_character),
+            )),
         }
     }
 
@@ -290,30 +322,9 @@ impl Scanner {
 
         let segment = &self.source[self.start..self.current];
 
-        match segment {
-            "for" => Ok(simple_token(TokenType::For, self.get_span())),
-            "if" => Ok(simple_token(TokenType::If, self.get_span())),
-            "else" => Ok(simple_token(TokenType::Else, self.get_span())),
-            "while" => Ok(simple_token(TokenType::While, self.get_span())),
-            "fn" => Ok(simple_token(TokenType::Fn, self.get_span())),
-            "end" => Ok(simple_token(TokenType::End, self.get_span())),
-            "return" => Ok(simple_token(TokenType::Return, self.get_span())),
-            "true" => Ok(simple_token(TokenType::True, self.get_span())),
-            "false" => Ok(simple_token(TokenType::False, self.get_span())),
-            "and" => Ok(simple_token(TokenType::And, self.get_span())),
-            "or" => Ok(simple_token(TokenType::Or, self.get_span())),
-            "class" => Ok(simple_token(TokenType::Class, self.get_span())),
-            "let" => Ok(simple_token(TokenType::Let, self.get_span())),
-            "block" => Ok(simple_token(TokenType::Block, self.get_span())),
-            "delete" => Ok(simple_token(TokenType::Delete, self.get_span())),
-            "none" => Ok(simple_token(TokenType::None, self.get_span())),
-            "include" => Ok(simple_token(TokenType::Include, self.get_span())),
-
-            _ => Ok(Token {
-                token_type: TokenType::Identifier,
-                lexeme: segment.to_string(),
-                span: self.get_span(),
-            }),
+        match KEYWORDS.iter().find(|(keyword, _)| *keyword == segment) {
+            Some(&(_, token_type)) => Ok(simple_token(token_type, self.get_span())),
+            None => Ok(self.emit_token(TokenType::Identifier, segment.to_string())),
         }
     }
 
@@ -334,21 +345,19 @@ impl Scanner {
         })
     }
 
+    /// Offsets are in bytes, so the character at `current` is decoded from
+    /// the rest of the source
     fn peek(&self) -> char {
-        if self.is_at_end() {
-            return '\0';
-        }
-
-        self.source.chars().nth(self.current).unwrap()
+        self.source[self.current..].chars().next().unwrap_or('\0')
     }
 
     fn advance(&mut self) -> char {
-        if self.is_at_end() {
-            return '\0';
+        let character = self.peek();
+        if character != '\0' {
+            self.current += character.len_utf8();
         }
 
-        self.current += 1;
-        self.source.chars().nth(self.current - 1).unwrap()
+        character
     }
 
     fn is_at_end(&self) -> bool {
@@ -361,7 +370,7 @@ impl Scanner {
             return Ok(());
         }
 
-        Err(self.generate_error(message.to_string()))
+        Err(self.generate_error(codes::UNTERMINATED_LITERAL, message.to_string()))
     }
 }
 
@@ -381,16 +390,12 @@ mod scanner_tests {
     fn assert_scanned_tokens(left: Vec<Token>, right: Vec<Token>) {
         let mapped_left = left
             .iter()
-            .map(|v| {
-                return (v.token_type, &v.lexeme);
-            })
+            .map(|v| (v.token_type, &v.lexeme))
             .collect::<Vec<(TokenType, &String)>>();
 
         let mapped_right = right
             .iter()
-            .map(|v| {
-                return (v.token_type, &v.lexeme);
-            })
+            .map(|v| (v.token_type, &v.lexeme))
             .collect::<Vec<(TokenType, &String)>>();
 
         assert_eq!(mapped_left, mapped_right);
@@ -637,4 +642,30 @@ mod scanner_tests {
             ],
         )
     }
+
+    #[test]
+    fn test_scanner_error_location() {
+        let error = Scanner::new().scan_tokens("a := 1 $ 2").unwrap_err();
+        let spanned = error.spanned().unwrap();
+
+        assert_eq!(error.code(), errors::codes::UNEXPECTED_CHARACTER);
+        assert_eq!((spanned.line, spanned.column), (1, 8));
+        assert_eq!(spanned.range, 7..8);
+    }
+
+    #[test]
+    fn test_scanner_non_ascii() {
+        let tokens = Scanner::new().scan_tokens("x := \"café\"\ný := 1").unwrap();
+
+        assert_eq!(tokens[2].lexeme, "café");
+        assert_eq!((tokens[2].span.start, tokens[2].span.end), (5, 12));
+        assert_eq!(tokens[4].lexeme, "ý");
+        assert_eq!((tokens[4].span.line, tokens[4].span.column), (2, 1));
+        assert_eq!((tokens[4].span.start, tokens[4].span.end), (13, 15));
+        assert_eq!(tokens[6].lexeme, "1");
+
+        // columns count bytes, like the offsets of the ranges
+        let error = Scanner::new().scan_tokens("\"é\" $").unwrap_err();
+        assert_eq!(error.spanned().unwrap().column, 6);
+    }
 }
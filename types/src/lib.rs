@@ -1,5 +1,7 @@
+use std::fmt::{Display, Formatter};
+
 #[repr(u8)]
-#[derive(Debug, PartialOrd, PartialEq, Clone)]
+#[derive(Debug, PartialOrd, PartialEq, Eq, Hash, Copy, Clone)]
 pub enum KirinType {
     Void,
     Any,
@@ -24,4 +26,41 @@ impl KirinType {
             _ => None,
         }
     }
+
+    /// Resolve a type name as written in source annotations, e.g. `let x: any = 1`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "void" => Some(Self::Void),
+            "any" => Some(Self::Any),
+            "none" => Some(Self::Null),
+            "string" => Some(Self::String),
+            "int" => Some(Self::Int),
+            "float" => Some(Self::Float),
+            "bool" => Some(Self::Bool),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Void => "void",
+            Self::Any => "any",
+            Self::Null => "none",
+            Self::String => "string",
+            Self::Int => "int",
+            Self::Float => "float",
+            Self::Bool => "bool",
+            Self::Variable => "variable",
+        }
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        matches!(self, Self::Int | Self::Float)
+    }
+}
+
+impl Display for KirinType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
 }
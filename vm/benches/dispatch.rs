@@ -0,0 +1,133 @@
+//! Benchmarks of the interpreter loop, run with `cargo bench -p vm`.
+//!
+//! Every program runs to completion in a fresh VM, once with the handler
+//! table and once with the match loop it replaced. The fastest of several
+//! runs is reported to reduce the noise of other processes.
+
+use errors::KirinError;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use vm::{Program, VM, assemble};
+
+const RUNS: usize = 10;
+
+/// One million iterations of integer arithmetic
+const ARITHMETIC_LOOP: &str = "
+        ALLOC_REG 8
+        LOAD_INT16 r0, 0            ; counter
+        LOAD_INT16 r1, 1000000      ; iterations
+        LOAD_INT16 r2, 1
+        LOAD_INT16 r3, 3
+        LOAD_INT16 r4, 7
+        LOAD_INT16 r5, 0            ; sum
+loop:   ADD_INT r0, r0, r2
+        MUL_INT r6, r0, r3
+        MOD_INT r6, r6, r4
+        ADD_INT r5, r5, r6
+        LESS_EQUAL_INT r7, r1, r0
+        JUMP_IF_FALSE r7, loop
+        DEALLOC_REG 8
+        RETURN
+        HALT
+";
+
+/// Twenty thousand calls computing the 90th Fibonacci number iteratively
+const FIB: &str = "
+        ALLOC_REG 4
+        LOAD_INT16 r0, 0            ; counter
+        LOAD_INT16 r1, 20000        ; calls
+        LOAD_INT16 r2, 1
+loop:   CALL fib
+        ADD_INT r0, r0, r2
+        LESS_EQUAL_INT r3, r1, r0
+        JUMP_IF_FALSE r3, loop
+        DEALLOC_REG 4
+        RETURN
+
+fib:    ALLOC_REG 6
+        LOAD_INT16 r0, 0
+        LOAD_INT16 r1, 1
+        LOAD_INT16 r2, 0            ; counter
+        LOAD_INT16 r3, 1
+        LOAD_INT16 r4, 90
+next:   ADD_INT r5, r0, r1
+        MOVE r0, r1
+        MOVE r1, r5
+        ADD_INT r2, r2, r3
+        LESS_EQUAL_INT r5, r4, r2
+        JUMP_IF_FALSE r5, next
+        DEALLOC_REG 6
+        RETURN
+        HALT
+";
+
+/// Builds a comma separated list of one hundred thousand numbers
+const STRING_BUILDING: &str = "
+        ALLOC_REG 6
+        LOAD_INT16 r0, 0            ; counter
+        LOAD_INT16 r1, 100000       ; numbers
+        LOAD_INT16 r2, 1
+loop:   INT_TO_ANY r3, r0
+        PRINT_ANY r3
+        PRINT_CHAR ','
+        ADD_INT r0, r0, r2
+        LESS_EQUAL_INT r5, r1, r0
+        JUMP_IF_FALSE r5, loop
+        DEALLOC_REG 6
+        RETURN
+        HALT
+";
+
+/// Starts a loaded VM
+type Start = fn(&mut VM) -> Result<(), KirinError>;
+
+fn main() {
+    let benchmarks = [
+        ("arithmetic loop", ARITHMETIC_LOOP),
+        ("fib", FIB),
+        ("string building", STRING_BUILDING),
+    ];
+    let loops: [(&str, Start); 2] = [
+        ("match loop", |vm| vm.start_with_match_loop(0)),
+        ("handler table", |vm| vm.start_with_offset(0)),
+    ];
+
+    for (name, source) in benchmarks {
+        let program = assemble(source).expect("benchmark programs are valid assembly");
+
+        let mut fastest = Vec::new();
+        for (dispatch, start) in loops {
+            let times = (0..RUNS).map(|_| run(&program, start)).collect::<Vec<_>>();
+            let best = times.iter().min().copied().unwrap_or_default();
+            let mean = times.iter().sum::<Duration>() / RUNS as u32;
+            fastest.push(best);
+
+            println!(
+                "{:<16} {:<14} fastest {:>9.3} ms    mean {:>9.3} ms",
+                name,
+                dispatch,
+                best.as_secs_f64() * 1000.0,
+                mean.as_secs_f64() * 1000.0
+            );
+        }
+
+        println!(
+            "{:<16} speedup {:.2}x\n",
+            name,
+            fastest[0].as_secs_f64() / fastest[1].as_secs_f64()
+        );
+    }
+}
+
+fn run(program: &Program, start: Start) -> Duration {
+    let mut vm = VM::new();
+    vm.capture_output();
+    vm.load_program(program.clone()).unwrap();
+
+    let begin = Instant::now();
+    start(&mut vm).unwrap();
+    let elapsed = begin.elapsed();
+
+    black_box(vm.take_output());
+    elapsed
+}
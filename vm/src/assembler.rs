@@ -0,0 +1,838 @@
+//! Text assembly for Kirin bytecode, the inverse of `Program::disassemble`.
+//!
+//! ```text
+//! ; comments run to the end of the line
+//! .const greeting "hello"     ; constants are referenced by name or `#index`
+//! .const big 5i64             ; `i64` forces a 64 bit integer constant
+//! .register total r2          ; names for registers
+//!
+//! main:                       ; labels are used as call targets
+//!     ALLOC_REG 4
+//!     LOAD_CONST total, greeting
+//!     CALL main
+//! ```
+//!
+//! Lines may start with an instruction index as printed by the disassembler,
+//! it is ignored.
+
+use crate::{DebugInfo, Program, ProgramConstant};
+use errors::{KirinError, SpannedError};
+use instructions::{Instruction, InstructionBuilder, OpCode, OperandKind};
+use std::collections::HashMap;
+use types::KirinType;
+
+/// Assemble the text into a program. Each instruction records its line in
+/// the debug info so runtime errors point back into the assembly.
+pub fn assemble(source: &str) -> Result<Program, KirinError> {
+    let mut assembler = Assembler::default();
+
+    for (index, line) in source.lines().enumerate() {
+        assembler.read_line(line, index + 1)?;
+    }
+
+    assembler.finish()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    String(String),
+    Char(char),
+    Comma,
+    Colon,
+}
+
+#[derive(Debug, Clone)]
+struct Lexeme {
+    token: Token,
+    column: usize,
+}
+
+struct PendingInstruction {
+    opcode: OpCode,
+    operands: Vec<Lexeme>,
+    line: usize,
+    column: usize,
+}
+
+#[derive(Default)]
+struct Assembler {
+    constants: Vec<ProgramConstant>,
+    constant_names: HashMap<String, usize>,
+    registers: HashMap<String, Instruction>,
+    labels: HashMap<String, usize>,
+    pending: Vec<PendingInstruction>,
+}
+
+impl Assembler {
+    fn read_line(&mut self, line: &str, line_number: usize) -> Result<(), KirinError> {
+        let tokens = tokenize(line, line_number)?;
+        let mut position = 0;
+
+        // labels, possibly followed by an instruction on the same line
+        while let (
+            Some(Lexeme {
+                token: Token::Word(name),
+                column,
+            }),
+            Some(colon),
+        ) = (tokens.get(position), tokens.get(position + 1))
+            && colon.token == Token::Colon
+        {
+            if !is_identifier(name) {
+                return Err(error(
+                    format!("invalid label `{}`", name),
+                    line_number,
+                    *column,
+                ));
+            }
+
+            if self
+                .labels
+                .insert(name.clone(), self.pending.len())
+                .is_some()
+            {
+                return Err(error(
+                    format!("duplicate label `{}`", name),
+                    line_number,
+                    *column,
+                ));
+            }
+
+            position += 2;
+        }
+
+        // instruction index printed by the disassembler
+        if let (
+            Some(Lexeme {
+                token: Token::Word(word),
+                ..
+            }),
+            Some(_),
+        ) = (tokens.get(position), tokens.get(position + 1))
+            && word.chars().all(|c| c.is_ascii_digit())
+        {
+            position += 1;
+        }
+
+        let Some(first) = tokens.get(position) else {
+            return Ok(());
+        };
+        let rest = &tokens[position + 1..];
+
+        match &first.token {
+            Token::Word(word) if word.starts_with('.') => {
+                self.directive(word, rest, line_number, first.column)
+            }
+
+            Token::Word(word) => {
+                let opcode = match OpCode::from_mnemonic(word) {
+                    Some(OpCode::Extend) => {
+                        return Err(error(
+                            "`EXTEND` prefixes are inserted for operands that need them"
+                                .to_string(),
+                            line_number,
+                            first.column,
+                        ));
+                    }
+                    Some(opcode) => opcode,
+                    None => {
+                        return Err(error(
+                            format!("unknown instruction `{}`", word),
+                            line_number,
+                            first.column,
+                        ));
+                    }
+                };
+
+                let operands = split_operands(rest, line_number)?;
+
+                self.pending.push(PendingInstruction {
+                    opcode,
+                    operands,
+                    line: line_number,
+                    column: first.column,
+                });
+
+                Ok(())
+            }
+
+            _ => Err(error(
+                "expected an instruction, label or directive".to_string(),
+                line_number,
+                first.column,
+            )),
+        }
+    }
+
+    fn directive(
+        &mut self,
+        directive: &str,
+        arguments: &[Lexeme],
+        line: usize,
+        column: usize,
+    ) -> Result<(), KirinError> {
+        let [name, value] = arguments else {
+            return Err(error(
+                format!("`{}` expects a name and a value", directive),
+                line,
+                column,
+            ));
+        };
+
+        let name_text = match &name.token {
+            Token::Word(word) if is_identifier(word) => word.clone(),
+            _ => return Err(error("expected a name".to_string(), line, name.column)),
+        };
+
+        match directive {
+            ".const" => {
+                let constant = match &value.token {
+                    Token::String(string) => ProgramConstant::String(string.clone()),
+                    Token::Word(word) => parse_constant(word).ok_or_else(|| {
+                        error(format!("invalid constant `{}`", word), line, value.column)
+                    })?,
+                    _ => {
+                        return Err(error(
+                            "expected a number or string".to_string(),
+                            line,
+                            value.column,
+                        ));
+                    }
+                };
+
+                if self.constant_names.contains_key(&name_text) {
+                    return Err(error(
+                        format!("duplicate constant `{}`", name_text),
+                        line,
+                        name.column,
+                    ));
+                }
+
+                self.constant_names.insert(name_text, self.constants.len());
+                self.constants.push(constant);
+            }
+
+            ".register" => {
+                let register = match &value.token {
+                    Token::Word(word) => parse_register(word),
+                    _ => None,
+                }
+                .ok_or_else(|| error("expected a register".to_string(), line, value.column))?;
+
+                self.registers.insert(name_text, register);
+            }
+
+            _ => {
+                return Err(error(
+                    format!("unknown directive `{}`", directive),
+                    line,
+                    column,
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Program, KirinError> {
+        // extended instructions take two slots, so the address of a label
+        // depends on the encoding of the instructions before it
+        let mut sizes = vec![1; self.pending.len()];
+
+        let encoded = loop {
+            let mut addresses = Vec::with_capacity(sizes.len() + 1);
+            let mut address = 0;
+            for size in &sizes {
+                addresses.push(address);
+                address += size;
+            }
+            addresses.push(address);
+
+            let encoded = self
+                .pending
+                .iter()
+                .map(|pending| self.encode(pending, &addresses))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let encoded_sizes = encoded.iter().map(Vec::len).collect::<Vec<_>>();
+            if encoded_sizes == sizes {
+                break encoded;
+            }
+
+            sizes = encoded_sizes;
+        };
+
+        let mut instructions = Vec::with_capacity(self.pending.len());
+        let mut debug_info = DebugInfo::new();
+
+        for (pending, encoded) in self.pending.iter().zip(encoded) {
+            debug_info.push(instructions.len(), None, pending.line, pending.column);
+            instructions.extend(encoded);
+        }
+
+        Ok(Program::new(instructions, self.constants).with_debug_info(debug_info))
+    }
+
+    fn encode(
+        &self,
+        pending: &PendingInstruction,
+        addresses: &[usize],
+    ) -> Result<Vec<Instruction>, KirinError> {
+        let layout = pending.opcode.operands();
+
+        if layout.len() != pending.operands.len() {
+            return Err(error(
+                format!(
+                    "`{}` expects {} operands, found {}",
+                    pending.opcode.mnemonic(),
+                    layout.len(),
+                    pending.operands.len()
+                ),
+                pending.line,
+                pending.column,
+            ));
+        }
+
+        let mut values = Vec::with_capacity(layout.len());
+        for (operand, lexeme) in layout.iter().zip(&pending.operands) {
+            let value = match operand.kind {
+                OperandKind::Register | OperandKind::RegisterPair => {
+                    self.register(lexeme, pending)?
+                }
+                OperandKind::Constant => self.constant(lexeme, pending)?,
+                OperandKind::Int => self.number::<i32>(lexeme, pending)? as Instruction,
+                OperandKind::Count => self.number::<u32>(lexeme, pending)?,
+                OperandKind::Bool => self.boolean(lexeme, pending)? as Instruction,
+                OperandKind::Type => self.type_tag(lexeme, pending)?,
+                OperandKind::Char => self.character(lexeme, pending)? as Instruction,
+                OperandKind::Address => self.target(lexeme, pending, addresses)?,
+            };
+
+            values.push(value);
+        }
+
+        Ok(InstructionBuilder::extended(pending.opcode, &values))
+    }
+
+    fn register(
+        &self,
+        operand: &Lexeme,
+        pending: &PendingInstruction,
+    ) -> Result<Instruction, KirinError> {
+        let register = match &operand.token {
+            Token::Word(word) => parse_register(word).or_else(|| self.registers.get(word).copied()),
+            _ => None,
+        };
+
+        register.ok_or_else(|| invalid_operand("a register", operand, pending))
+    }
+
+    fn constant(
+        &self,
+        operand: &Lexeme,
+        pending: &PendingInstruction,
+    ) -> Result<Instruction, KirinError> {
+        let index = match &operand.token {
+            Token::Word(word) => match word.strip_prefix('#') {
+                Some(index) => index.parse::<Instruction>().ok(),
+                None => self
+                    .constant_names
+                    .get(word)
+                    .map(|&index| index as Instruction),
+            },
+            _ => None,
+        };
+
+        index.ok_or_else(|| invalid_operand("a constant", operand, pending))
+    }
+
+    fn number<T: std::str::FromStr>(
+        &self,
+        operand: &Lexeme,
+        pending: &PendingInstruction,
+    ) -> Result<T, KirinError> {
+        match &operand.token {
+            Token::Word(word) => word.parse::<T>().ok(),
+            _ => None,
+        }
+        .ok_or_else(|| invalid_operand("a number in range", operand, pending))
+    }
+
+    fn boolean(&self, operand: &Lexeme, pending: &PendingInstruction) -> Result<bool, KirinError> {
+        match &operand.token {
+            Token::Word(word) if word == "true" => Ok(true),
+            Token::Word(word) if word == "false" => Ok(false),
+            _ => Err(invalid_operand("`true` or `false`", operand, pending)),
+        }
+    }
+
+    fn type_tag(
+        &self,
+        operand: &Lexeme,
+        pending: &PendingInstruction,
+    ) -> Result<Instruction, KirinError> {
+        match &operand.token {
+            Token::Word(word) => KirinType::from_name(word)
+                .map(|kind| kind as u8)
+                .or_else(|| word.parse::<u8>().ok()),
+            _ => None,
+        }
+        .map(|tag| tag as Instruction)
+        .ok_or_else(|| invalid_operand("a type", operand, pending))
+    }
+
+    fn target(
+        &self,
+        operand: &Lexeme,
+        pending: &PendingInstruction,
+        addresses: &[usize],
+    ) -> Result<Instruction, KirinError> {
+        match &operand.token {
+            Token::Word(word) => self
+                .labels
+                .get(word)
+                .map(|&label| addresses[label] as Instruction)
+                .or_else(|| word.parse::<Instruction>().ok()),
+            _ => None,
+        }
+        .ok_or_else(|| invalid_operand("a label or address", operand, pending))
+    }
+
+    fn character(
+        &self,
+        operand: &Lexeme,
+        pending: &PendingInstruction,
+    ) -> Result<char, KirinError> {
+        match &operand.token {
+            Token::Char(character) if (*character as u32) <= u8::MAX as u32 => Some(*character),
+            Token::Word(word) => word.parse::<u8>().ok().map(|value| value as char),
+            _ => None,
+        }
+        .ok_or_else(|| invalid_operand("a character", operand, pending))
+    }
+}
+
+fn tokenize(line: &str, line_number: usize) -> Result<Vec<Lexeme>, KirinError> {
+    let characters = line.chars().collect::<Vec<_>>();
+    let mut tokens = Vec::new();
+    let mut position = 0;
+
+    while position < characters.len() {
+        let character = characters[position];
+        let column = position + 1;
+
+        let token = match character {
+            ';' => break,
+            c if c.is_whitespace() => {
+                position += 1;
+                continue;
+            }
+            ',' => {
+                position += 1;
+                Token::Comma
+            }
+            ':' => {
+                position += 1;
+                Token::Colon
+            }
+            '"' => {
+                position += 1;
+                let mut string = String::new();
+
+                loop {
+                    match characters.get(position) {
+                        Some('"') => break,
+                        Some('\\') => {
+                            let (escaped, length) =
+                                unescape(&characters[position + 1..], line_number, position + 2)?;
+                            string.push(escaped);
+                            position += length + 1;
+                        }
+                        Some(&c) => {
+                            string.push(c);
+                            position += 1;
+                        }
+                        None => {
+                            return Err(error(
+                                "unterminated string".to_string(),
+                                line_number,
+                                column,
+                            ));
+                        }
+                    }
+                }
+
+                position += 1;
+                Token::String(string)
+            }
+            '\'' => {
+                let (value, length) = match characters.get(position + 1) {
+                    Some('\\') => {
+                        let (escaped, length) =
+                            unescape(&characters[position + 2..], line_number, position + 3)?;
+                        (escaped, length + 1)
+                    }
+                    Some(&c) if c != '\'' => (c, 1),
+                    _ => {
+                        return Err(error("empty character".to_string(), line_number, column));
+                    }
+                };
+
+                position += length + 1;
+                if characters.get(position) != Some(&'\'') {
+                    return Err(error(
+                        "unterminated character".to_string(),
+                        line_number,
+                        column,
+                    ));
+                }
+
+                position += 1;
+                Token::Char(value)
+            }
+            _ => {
+                let start = position;
+                while position < characters.len()
+                    && !characters[position].is_whitespace()
+                    && !",:;\"'".contains(characters[position])
+                {
+                    position += 1;
+                }
+
+                Token::Word(characters[start..position].iter().collect())
+            }
+        };
+
+        tokens.push(Lexeme { token, column });
+    }
+
+    Ok(tokens)
+}
+
+/// Decode the escape sequence following a backslash, returning the character
+/// and the number of characters consumed
+fn unescape(characters: &[char], line: usize, column: usize) -> Result<(char, usize), KirinError> {
+    let escaped = match characters.first() {
+        Some('n') => '\n',
+        Some('t') => '\t',
+        Some('r') => '\r',
+        Some('0') => '\0',
+        Some('\\') => '\\',
+        Some('"') => '"',
+        Some('\'') => '\'',
+        Some('u') if characters.get(1) == Some(&'{') => {
+            let end = characters
+                .iter()
+                .position(|&c| c == '}')
+                .ok_or_else(|| error("unterminated unicode escape".to_string(), line, column))?;
+            let digits = characters[2..end].iter().collect::<String>();
+
+            let value = u32::from_str_radix(&digits, 16)
+                .ok()
+                .and_then(char::from_u32)
+                .ok_or_else(|| error("invalid unicode escape".to_string(), line, column))?;
+
+            return Ok((value, end + 1));
+        }
+        _ => return Err(error("invalid escape sequence".to_string(), line, column)),
+    };
+
+    Ok((escaped, 1))
+}
+
+fn split_operands(tokens: &[Lexeme], line: usize) -> Result<Vec<Lexeme>, KirinError> {
+    let mut operands = Vec::new();
+
+    for (index, operand) in tokens.iter().enumerate() {
+        let expects_operand = index % 2 == 0;
+        let is_comma = operand.token == Token::Comma;
+
+        if expects_operand == is_comma || operand.token == Token::Colon {
+            return Err(error(
+                "operands must be separated by commas".to_string(),
+                line,
+                operand.column,
+            ));
+        }
+
+        if expects_operand {
+            operands.push(operand.clone());
+        }
+    }
+
+    if let Some(last) = tokens.last()
+        && last.token == Token::Comma
+    {
+        return Err(error("expected an operand".to_string(), line, last.column));
+    }
+
+    Ok(operands)
+}
+
+fn parse_register(word: &str) -> Option<Instruction> {
+    let index = word.strip_prefix('r')?.parse::<u16>().ok()?;
+
+    Some(index as Instruction)
+}
+
+fn parse_constant(word: &str) -> Option<ProgramConstant> {
+    if let Some(digits) = word.strip_suffix("i64") {
+        return digits.parse::<i64>().ok().map(ProgramConstant::Int64);
+    }
+
+    if let Ok(value) = word.parse::<i32>() {
+        return Some(ProgramConstant::Int32(value));
+    }
+
+    if let Ok(value) = word.parse::<i64>() {
+        return Some(ProgramConstant::Int64(value));
+    }
+
+    word.parse::<f64>().ok().map(ProgramConstant::Float)
+}
+
+fn is_identifier(word: &str) -> bool {
+    let mut characters = word.chars();
+
+    matches!(characters.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && characters.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn invalid_operand(expected: &str, operand: &Lexeme, pending: &PendingInstruction) -> KirinError {
+    let found = match &operand.token {
+        Token::Word(word) => format!("`{}`", word),
+        Token::String(string) => format!("{:?}", string),
+        Token::Char(character) => format!("{:?}", character),
+        Token::Comma => "`,`".to_string(),
+        Token::Colon => "`:`".to_string(),
+    };
+
+    error(
+        format!(
+            "`{}` expects {}, found {}",
+            pending.opcode.mnemonic(),
+            expected,
+            found
+        ),
+        pending.line,
+        operand.column,
+    )
+}
+
+fn error(message: String, line: usize, column: usize) -> KirinError {
+    KirinError::Parse(SpannedError::new(message, line, column))
+}
+
+#[cfg(test)]
+mod assembler_tests {
+    use crate::{Program, ProgramConstant, VM, assemble};
+    use errors::KirinError;
+    use instructions::{InstructionBuilder, OpCode};
+    use types::KirinType;
+
+    fn error_position(source: &str) -> (String, usize, usize) {
+        match assemble(source) {
+            Err(KirinError::Parse(error)) => (error.message, error.line, error.column),
+            result => panic!("expected assembly error, got {:?}", result.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_assemble_program() {
+        let program = assemble(
+            "
+            .const half 0.5         ; a float constant
+            .const greeting \"hi\\n\"
+            .register total r2
+
+            entry:  ALLOC_REG 4
+                    LOAD_CONST r0, half
+                    LOAD_CONST r1, #1
+                    LOAD_INT16 total, -12
+                    IS_TYPE r3, r0, none
+                    CALL function
+                    DEALLOC_REG 4
+                    RETURN
+                    HALT
+            function:
+                    PRINT_CHAR ';'
+                    RETURN
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(
+            program.instructions,
+            vec![
+                InstructionBuilder::allocate_registers(4),
+                InstructionBuilder::load_constant(0, 0),
+                InstructionBuilder::load_constant(1, 1),
+                InstructionBuilder::load_16bit_int(2, -12),
+                InstructionBuilder::is_type(3, 0, KirinType::Null as u32),
+                InstructionBuilder::call(9),
+                InstructionBuilder::deallocate_registers(4),
+                InstructionBuilder::simple(OpCode::Return),
+                InstructionBuilder::simple(OpCode::Halt),
+                InstructionBuilder::print_char(';'),
+                InstructionBuilder::simple(OpCode::Return),
+            ]
+        );
+        assert_eq!(
+            program.constants,
+            vec![
+                ProgramConstant::Float(0.5),
+                ProgramConstant::String("hi\n".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_round_trip_through_disassembly() {
+        let constants = vec![
+            ProgramConstant::Int32(-4),
+            ProgramConstant::Int64(3),
+            ProgramConstant::Float(f64::INFINITY),
+            ProgramConstant::String("tab\t\"quoted\" \u{1b}".to_string()),
+        ];
+        let instructions = vec![
+            InstructionBuilder::allocate_registers(8),
+            InstructionBuilder::load_constant(0, 3),
+            InstructionBuilder::load_16bit_int(1, i16::MIN),
+            InstructionBuilder::load_bool(2, false),
+            InstructionBuilder::load_null(3),
+            InstructionBuilder::binary_operation(OpCode::PowAny, 0, 2, 4),
+            InstructionBuilder::cast(OpCode::AnyToString, 5, 3),
+            InstructionBuilder::convert(OpCode::IntToFloat, 6),
+            InstructionBuilder::is_type(7, 3, KirinType::Bool as u32),
+            InstructionBuilder::print_any(3),
+            InstructionBuilder::print_char('\n'),
+            InstructionBuilder::simple(OpCode::InitFrame),
+            InstructionBuilder::simple(OpCode::DropFrame),
+            InstructionBuilder::call(0),
+            InstructionBuilder::deallocate_registers(8),
+            InstructionBuilder::simple(OpCode::Return),
+            InstructionBuilder::simple(OpCode::Halt),
+        ];
+        let program = Program::new(instructions, constants);
+
+        let mut source = String::new();
+        for (index, constant) in program.constants.iter().enumerate() {
+            let value = match constant {
+                ProgramConstant::Int32(value) => value.to_string(),
+                ProgramConstant::Int64(value) => format!("{}i64", value),
+                ProgramConstant::Float(value) => format!("{:?}", value),
+                ProgramConstant::String(value) => format!("{:?}", value),
+            };
+            source.push_str(&format!(".const c{} {}\n", index, value));
+        }
+        source.push_str(&program.disassemble(None));
+
+        let assembled = assemble(&source).unwrap();
+
+        assert_eq!(assembled.instructions, program.instructions);
+        assert_eq!(assembled.constants, program.constants);
+    }
+
+    #[test]
+    fn test_wide_operands_are_extended() {
+        let program = assemble(
+            "ALLOC_REG 300
+             LOAD_INT16 r299, -100000
+             CALL function
+             DEALLOC_REG 300
+             RETURN
+             HALT
+             function: RETURN",
+        )
+        .unwrap();
+
+        let mut expected = vec![InstructionBuilder::allocate_registers(300)];
+        expected.extend(InstructionBuilder::extended(
+            OpCode::LoadInt16,
+            &[299, -100_000i32 as u32],
+        ));
+        // the label moved past the prefix
+        expected.push(InstructionBuilder::call(7));
+        expected.extend([
+            InstructionBuilder::deallocate_registers(300),
+            InstructionBuilder::simple(OpCode::Return),
+            InstructionBuilder::simple(OpCode::Halt),
+            InstructionBuilder::simple(OpCode::Return),
+        ]);
+
+        assert_eq!(program.instructions, expected);
+        assert_eq!(program.location(2).map(|location| location.line), Some(2));
+        assert_eq!(program.location(3).map(|location| location.line), Some(3));
+    }
+
+    #[test]
+    fn test_runtime_errors_point_into_assembly() {
+        let program = assemble(
+            "ALLOC_REG 2
+             LOAD_INT16 r0, 1
+             LOAD_INT16 r1, 0
+             DIV_INT r0, r0, r1
+             DEALLOC_REG 2
+             RETURN
+             HALT",
+        )
+        .unwrap();
+
+        let mut vm = VM::new();
+        vm.load_program(program).unwrap();
+
+        match vm.start_with_offset(0) {
+            Err(KirinError::Runtime(error)) => assert_eq!((error.line, error.column), (4, 14)),
+            result => panic!("expected runtime error, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_assembly_errors() {
+        assert_eq!(
+            error_position("HALT\n  BRANCH 4"),
+            ("unknown instruction `BRANCH`".to_string(), 2, 3)
+        );
+        assert_eq!(
+            error_position("CALL nowhere"),
+            (
+                "`CALL` expects a label or address, found `nowhere`".to_string(),
+                1,
+                6
+            )
+        );
+        assert_eq!(
+            error_position("MOVE r0, r65536"),
+            (
+                "`MOVE` expects a register, found `r65536`".to_string(),
+                1,
+                10
+            )
+        );
+        assert_eq!(
+            error_position("EXTEND"),
+            (
+                "`EXTEND` prefixes are inserted for operands that need them".to_string(),
+                1,
+                1
+            )
+        );
+        assert_eq!(
+            error_position("ADD_INT r0, r1"),
+            ("`ADD_INT` expects 3 operands, found 2".to_string(), 1, 1)
+        );
+        assert_eq!(
+            error_position("MOVE r0 r1"),
+            ("operands must be separated by commas".to_string(), 1, 9)
+        );
+        assert_eq!(
+            error_position(".const text \"open"),
+            ("unterminated string".to_string(), 1, 13)
+        );
+        assert_eq!(
+            error_position("a: HALT\na: HALT"),
+            ("duplicate label `a`".to_string(), 2, 1)
+        );
+    }
+}
@@ -0,0 +1,88 @@
+//! The interpreter loop the handler table replaced, kept so the dispatch
+//! benchmarks can measure one against the other
+
+use crate::{VM, VmStatus};
+use errors::KirinError;
+use instructions::{Instruction, InstructionDecoder, OpCode};
+
+impl VM {
+    /// Like `start_with_offset`, but matches on the status and bounds checks
+    /// the fetch for every instruction, then matches on its opcode
+    #[doc(hidden)]
+    pub fn start_with_match_loop(&mut self, offset: usize) -> Result<(), KirinError> {
+        self.instruction_pointer += offset;
+        self.status = VmStatus::Running;
+
+        let result = loop {
+            match self.status {
+                VmStatus::Running => {
+                    self.instruction_pointer += 1;
+                    let instruction = self.instructions[self.instruction_pointer - 1];
+                    self.execute_matched(instruction);
+                }
+
+                VmStatus::Halted => break Ok(()),
+                VmStatus::Error => break Err(self.failure()),
+            }
+        };
+        self.signaled = false;
+
+        result
+    }
+}
+
+/// Generates `VM::execute_matched` from the opcode table
+macro_rules! match_dispatch {
+    ($($name:ident = $mnemonic:literal, $handler:ident, [$($operands:tt)*];)*) => {
+        impl VM {
+            fn execute_matched(&mut self, instruction: Instruction) {
+                match OpCode::try_from(InstructionDecoder::decode_opcode(instruction)) {
+                    $(Ok(OpCode::$name) => self.$handler(instruction),)*
+                    Err(_) => self.unknown_instruction(instruction),
+                }
+            }
+        }
+    };
+}
+
+instructions::opcode_table!(match_dispatch);
+
+#[cfg(test)]
+mod baseline_tests {
+    use crate::{VM, assemble};
+
+    #[test]
+    fn test_match_loop_matches_handler_table() {
+        let program = assemble(
+            "
+            ALLOC_REG 3
+            LOAD_INT16 r0, 0
+            LOAD_INT16 r1, 1
+            loop: INT_TO_ANY r1, r0
+            PRINT_ANY r1
+            LOAD_INT16 r1, 1
+            ADD_INT r0, r0, r1
+            LOAD_INT16 r2, 3
+            LESS_EQUAL_INT r2, r2, r0
+            JUMP_IF_FALSE r2, loop
+            DEALLOC_REG 3
+            RETURN
+            HALT
+            ",
+        )
+        .unwrap();
+
+        let mut matching = VM::new();
+        matching.capture_output();
+        matching.load_program(program.clone()).unwrap();
+        matching.start_with_match_loop(0).unwrap();
+
+        let mut vm = VM::new();
+        vm.capture_output();
+        vm.load_program(program).unwrap();
+        vm.start_with_offset(0).unwrap();
+
+        assert_eq!(matching.take_output(), "012");
+        assert_eq!(vm.take_output(), "012");
+    }
+}
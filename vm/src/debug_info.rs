@@ -0,0 +1,278 @@
+use types::KirinType;
+
+/// Maps instruction indices to source locations. Consecutive instructions
+/// that share a location are stored as a single entry.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DebugInfo {
+    files: Vec<String>,
+    entries: Vec<DebugEntry>,
+    variables: Vec<DebugVariable>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct DebugEntry {
+    /// index of the first instruction covered by this entry
+    pub instruction: usize,
+    pub file: Option<usize>,
+    /// `0` marks instructions without a source location
+    pub line: usize,
+    pub column: usize,
+}
+
+/// A variable of the source and the register holding it
+#[derive(Debug, Clone, PartialEq)]
+pub struct DebugVariable {
+    pub name: String,
+    /// relative to the register base of the frame, the first of the pair
+    /// for `any`
+    pub register: usize,
+    pub kind: KirinType,
+    /// index of the first instruction after the declaration
+    pub instruction: usize,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SourceLocation<'a> {
+    pub file: Option<&'a str>,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl DebugInfo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_parts(files: Vec<String>, entries: Vec<DebugEntry>) -> Self {
+        Self {
+            files,
+            entries,
+            variables: Vec::new(),
+        }
+    }
+
+    pub fn with_variables(mut self, variables: Vec<DebugVariable>) -> Self {
+        self.variables = variables;
+        self
+    }
+
+    pub fn files(&self) -> &[String] {
+        &self.files
+    }
+
+    pub fn entries(&self) -> &[DebugEntry] {
+        &self.entries
+    }
+
+    pub fn variables(&self) -> &[DebugVariable] {
+        &self.variables
+    }
+
+    /// The variables declared before `instruction`, without the ones
+    /// shadowed by a later declaration of the same name
+    pub fn variables_at(&self, instruction: usize) -> Vec<&DebugVariable> {
+        let mut visible: Vec<&DebugVariable> = Vec::new();
+
+        for variable in &self.variables {
+            if variable.instruction > instruction {
+                continue;
+            }
+
+            match visible
+                .iter_mut()
+                .find(|existing| existing.name == variable.name)
+            {
+                Some(existing) if existing.instruction <= variable.instruction => {
+                    *existing = variable
+                }
+                Some(_) => {}
+                None => visible.push(variable),
+            }
+        }
+
+        visible
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Record the location of `instruction`, which must not be lower than
+    /// previously recorded instructions
+    pub fn push(&mut self, instruction: usize, file: Option<&str>, line: usize, column: usize) {
+        let file = file.map(|file| self.intern_file(file));
+
+        if let Some(last) = self.entries.last()
+            && last.file == file
+            && last.line == line
+            && last.column == column
+        {
+            return;
+        }
+
+        if let Some(last) = self.entries.last_mut()
+            && last.instruction == instruction
+        {
+            *last = DebugEntry {
+                instruction,
+                file,
+                line,
+                column,
+            };
+            return;
+        }
+
+        self.entries.push(DebugEntry {
+            instruction,
+            file,
+            line,
+            column,
+        });
+    }
+
+    /// Record that the variable `name` is held by `register` from
+    /// `instruction` on
+    pub fn declare(&mut self, name: &str, register: usize, kind: KirinType, instruction: usize) {
+        self.variables.push(DebugVariable {
+            name: name.to_string(),
+            register,
+            kind,
+            instruction,
+        });
+    }
+
+    /// Mark `instruction` and the instructions following it as having no source location
+    pub fn push_unknown(&mut self, instruction: usize) {
+        self.push(instruction, None, 0, 0);
+    }
+
+    /// Append the entries and variables of `other`, shifting its
+    /// instruction indices by `offset`
+    pub fn append(&mut self, other: &DebugInfo, offset: usize) {
+        for variable in &other.variables {
+            self.variables.push(DebugVariable {
+                instruction: variable.instruction + offset,
+                ..variable.clone()
+            });
+        }
+
+        for entry in &other.entries {
+            let file = entry.file.and_then(|file| other.files.get(file));
+
+            self.push(
+                entry.instruction + offset,
+                file.map(|file| file.as_str()),
+                entry.line,
+                entry.column,
+            );
+        }
+    }
+
+    pub fn lookup(&self, instruction: usize) -> Option<SourceLocation<'_>> {
+        let index = self
+            .entries
+            .partition_point(|entry| entry.instruction <= instruction);
+        let entry = self.entries.get(index.checked_sub(1)?)?;
+
+        if entry.line == 0 {
+            return None;
+        }
+
+        Some(SourceLocation {
+            file: entry
+                .file
+                .and_then(|file| self.files.get(file))
+                .map(|file| file.as_str()),
+            line: entry.line,
+            column: entry.column,
+        })
+    }
+
+    fn intern_file(&mut self, file: &str) -> usize {
+        match self.files.iter().position(|existing| existing == file) {
+            Some(index) => index,
+            None => {
+                self.files.push(file.to_string());
+                self.files.len() - 1
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for SourceLocation<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}",
+            self.file.unwrap_or("<unknown>"),
+            self.line,
+            self.column
+        )
+    }
+}
+
+#[cfg(test)]
+mod debug_info_tests {
+    use crate::DebugInfo;
+    use types::KirinType;
+
+    #[test]
+    fn test_consecutive_locations_are_merged() {
+        let mut debug_info = DebugInfo::new();
+        debug_info.push_unknown(0);
+        debug_info.push(1, Some("main.kn"), 1, 4);
+        debug_info.push(2, Some("main.kn"), 1, 4);
+        debug_info.push(3, Some("main.kn"), 2, 1);
+        debug_info.push_unknown(5);
+
+        assert_eq!(debug_info.entries().len(), 4);
+        assert_eq!(debug_info.files().len(), 1);
+
+        assert!(debug_info.lookup(0).is_none());
+        assert_eq!(
+            debug_info.lookup(2).map(|l| (l.line, l.column)),
+            Some((1, 4))
+        );
+        assert_eq!(
+            debug_info.lookup(4).map(|l| (l.line, l.column)),
+            Some((2, 1))
+        );
+        assert!(debug_info.lookup(7).is_none());
+    }
+
+    #[test]
+    fn test_append_with_offset() {
+        let mut first = DebugInfo::new();
+        first.push(0, Some("a.kn"), 1, 1);
+
+        let mut second = DebugInfo::new();
+        second.push(0, Some("b.kn"), 3, 2);
+
+        first.append(&second, 10);
+
+        let location = first.lookup(12).unwrap();
+        assert_eq!(location.file, Some("b.kn"));
+        assert_eq!(location.line, 3);
+        assert_eq!(first.lookup(9).unwrap().file, Some("a.kn"));
+    }
+
+    #[test]
+    fn test_shadowed_variables_are_hidden() {
+        let mut debug_info = DebugInfo::new();
+        debug_info.declare("a", 0, KirinType::Int, 2);
+        debug_info.declare("b", 1, KirinType::Any, 4);
+        debug_info.declare("a", 3, KirinType::String, 6);
+
+        let names = |instruction| {
+            debug_info
+                .variables_at(instruction)
+                .iter()
+                .map(|variable| (variable.name.as_str(), variable.register))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(names(1), []);
+        assert_eq!(names(4), [("a", 0), ("b", 1)]);
+        assert_eq!(names(6), [("a", 3), ("b", 1)]);
+    }
+}
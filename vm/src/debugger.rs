@@ -0,0 +1,624 @@
+mod prompt;
+
+use crate::{DebugVariable, Frame, Program, ProgramConstant, SourceLocation, VM, VmStatus};
+use errors::KirinError;
+use instructions::{
+    Disassembler, Instruction, InstructionDecoder, OpCode, OperandKind, OperandSlot,
+};
+use std::fmt::{Display, Formatter};
+use types::KirinType;
+
+pub use prompt::Prompt;
+
+/// Where execution stops when it reaches it
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Breakpoint {
+    /// the first instructions of a source line
+    Line(usize),
+    Instruction(usize),
+}
+
+impl Display for Breakpoint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Line(line) => write!(f, "line {}", line),
+            Self::Instruction(index) => write!(f, "instruction {}", index),
+        }
+    }
+}
+
+/// Why execution stopped
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Stop {
+    /// the step is done
+    Step,
+    /// at the breakpoint with this number
+    Breakpoint(usize),
+    /// the program returned from its top level
+    Finished,
+}
+
+/// What is known about the value of a register, from the instruction that
+/// last wrote it. Registers are untyped in the VM.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Content {
+    Unknown,
+    /// an int, float, bool or string register
+    Value(KirinType),
+    /// the type tag of an Any register pair
+    Tag,
+    /// the value of an Any register pair, after its tag
+    Payload,
+    /// a type tag read by `TYPE_OF`
+    Type,
+}
+
+/// Runs a program one instruction at a time, stopping at breakpoints. The
+/// output of the program is captured, see [`Debugger::take_output`].
+pub struct Debugger {
+    vm: VM,
+    /// each breakpoint with the instructions it stops at, removed ones are
+    /// `None` so the numbers of the others do not change
+    breakpoints: Vec<Option<(Breakpoint, Vec<usize>)>>,
+    /// indexed by absolute register
+    contents: Vec<Content>,
+    finished: bool,
+}
+
+impl Debugger {
+    pub fn new(program: Program) -> Result<Self, KirinError> {
+        let mut vm = VM::new();
+        vm.capture_output();
+        vm.load_program(program)?;
+        vm.status = VmStatus::Running;
+
+        let finished = vm.instructions.is_empty();
+
+        Ok(Self {
+            vm,
+            breakpoints: Vec::new(),
+            contents: Vec::new(),
+            finished,
+        })
+    }
+
+    /// Add a breakpoint and return its number, breakpoints on lines without
+    /// instructions or past the program are rejected
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) -> Result<usize, String> {
+        let addresses = match breakpoint {
+            // a line has an entry for each column, only the first one
+            // of the instructions of a statement stops
+            Breakpoint::Line(line) => {
+                let entries = self.vm.debug_info.entries();
+
+                entries
+                    .iter()
+                    .enumerate()
+                    .filter(|&(index, entry)| {
+                        entry.line == line && (index == 0 || entries[index - 1].line != line)
+                    })
+                    .map(|(_, entry)| entry.instruction)
+                    .collect()
+            }
+            Breakpoint::Instruction(index) if index < self.vm.instructions.len() => vec![index],
+            Breakpoint::Instruction(index) => {
+                return Err(format!(
+                    "the program has {} instructions, {} is past its end",
+                    self.vm.instructions.len(),
+                    index
+                ));
+            }
+        };
+
+        if addresses.is_empty() {
+            return Err(format!("no instructions on {}", breakpoint));
+        }
+
+        self.breakpoints.push(Some((breakpoint, addresses)));
+        Ok(self.breakpoints.len())
+    }
+
+    /// Remove the breakpoint with the number returned by `add_breakpoint`
+    pub fn remove_breakpoint(&mut self, number: usize) -> Option<Breakpoint> {
+        let slot = self.breakpoints.get_mut(number.checked_sub(1)?)?;
+        slot.take().map(|(breakpoint, _)| breakpoint)
+    }
+
+    /// The breakpoints with their numbers
+    pub fn breakpoints(&self) -> Vec<(usize, Breakpoint)> {
+        self.breakpoints
+            .iter()
+            .enumerate()
+            .filter_map(|(index, slot)| Some((index + 1, slot.as_ref()?.0)))
+            .collect()
+    }
+
+    fn breakpoint_at(&self, address: usize) -> Option<usize> {
+        self.breakpoints.iter().position(|slot| {
+            slot.as_ref()
+                .is_some_and(|(_, addresses)| addresses.contains(&address))
+        })
+    }
+
+    /// Execute the next instruction. Errors stop the program like
+    /// returning from it does.
+    pub fn step(&mut self) -> Result<Stop, KirinError> {
+        let Some((extension, instruction)) = self.next_instruction() else {
+            self.finished = true;
+            return Ok(Stop::Finished);
+        };
+        let register_offset = self.vm.register_offset;
+
+        let result = self.vm.step();
+        self.track(extension, instruction, register_offset);
+
+        match result {
+            Ok(true) => Ok(Stop::Step),
+            Ok(false) => {
+                self.finished = true;
+                Ok(Stop::Finished)
+            }
+            Err(error) => {
+                self.finished = true;
+                Err(error)
+            }
+        }
+    }
+
+    /// Execute the next instruction, a `CALL` runs until its function
+    /// returns
+    pub fn step_over(&mut self) -> Result<Stop, KirinError> {
+        let Some((_, instruction)) = self.next_instruction() else {
+            self.finished = true;
+            return Ok(Stop::Finished);
+        };
+        let depth = self.vm.frames.len();
+
+        if InstructionDecoder::decode_known_opcode(instruction) != Some(OpCode::Call) {
+            return self.step();
+        }
+
+        self.run_while(|vm| vm.frames.len() > depth)
+    }
+
+    /// Run until the current frame is left, or the program returns when
+    /// no frame is open
+    pub fn step_out(&mut self) -> Result<Stop, KirinError> {
+        let depth = self.vm.frames.len();
+
+        self.run_while(|vm| vm.frames.len() >= depth)
+    }
+
+    /// Run until a breakpoint or the end of the program
+    pub fn resume(&mut self) -> Result<Stop, KirinError> {
+        self.run_while(|_| true)
+    }
+
+    /// Step at least once and while `running` holds, stopping early at
+    /// breakpoints
+    fn run_while(&mut self, running: impl Fn(&VM) -> bool) -> Result<Stop, KirinError> {
+        loop {
+            let stop = self.step()?;
+            if stop == Stop::Finished {
+                return Ok(stop);
+            }
+
+            if let Some(index) = self.breakpoint_at(self.vm.instruction_pointer) {
+                return Ok(Stop::Breakpoint(index + 1));
+            }
+
+            if !running(&self.vm) {
+                return Ok(stop);
+            }
+        }
+    }
+
+    /// The instruction at the instruction pointer and its `Extend` prefix,
+    /// `None` once the program has finished
+    fn next_instruction(&self) -> Option<(Option<Instruction>, Instruction)> {
+        if self.finished {
+            return None;
+        }
+
+        let address = self.vm.instruction_pointer;
+        let instruction = *self.vm.instructions.get(address)?;
+
+        match InstructionDecoder::decode_known_opcode(instruction) {
+            Some(OpCode::Extend) => {
+                Some((Some(instruction), *self.vm.instructions.get(address + 1)?))
+            }
+            _ => Some((None, instruction)),
+        }
+    }
+
+    /// Record what the executed instruction wrote into its destination
+    fn track(
+        &mut self,
+        extension: Option<Instruction>,
+        instruction: Instruction,
+        register_offset: usize,
+    ) {
+        self.contents
+            .resize(self.vm.registers.len(), Content::Unknown);
+
+        let Some(opcode) = InstructionDecoder::decode_known_opcode(instruction) else {
+            return;
+        };
+        let register = |slot| {
+            InstructionDecoder::decode_extended_operand(extension, instruction, slot) as usize
+                + register_offset
+        };
+
+        let written = match opcode {
+            OpCode::Move => {
+                let source = self.contents.get(register(OperandSlot::Source1));
+                vec![(
+                    register(OperandSlot::Destination),
+                    source.copied().unwrap_or(Content::Unknown),
+                )]
+            }
+            // conversions in place
+            OpCode::IntToFloat => vec![(
+                register(OperandSlot::Source1),
+                Content::Value(KirinType::Float),
+            )],
+            OpCode::FloatToInt => vec![(
+                register(OperandSlot::Source1),
+                Content::Value(KirinType::Int),
+            )],
+            _ => {
+                let destination = opcode
+                    .operands()
+                    .iter()
+                    .find(|operand| operand.slot == OperandSlot::Destination);
+                let destination_register = register(OperandSlot::Destination);
+
+                match destination.map(|operand| operand.kind) {
+                    Some(OperandKind::RegisterPair) => vec![
+                        (destination_register, Content::Tag),
+                        (destination_register + 1, Content::Payload),
+                    ],
+                    Some(OperandKind::Register) => self
+                        .result(opcode, extension, instruction)
+                        .map(|content| vec![(destination_register, content)])
+                        .unwrap_or_default(),
+                    _ => Vec::new(),
+                }
+            }
+        };
+
+        for (register, content) in written {
+            if let Some(slot) = self.contents.get_mut(register) {
+                *slot = content;
+            }
+        }
+    }
+
+    /// What an instruction writes into its destination register, `None`
+    /// for instructions that only read it
+    fn result(
+        &self,
+        opcode: OpCode,
+        extension: Option<Instruction>,
+        instruction: Instruction,
+    ) -> Option<Content> {
+        let kind = match opcode {
+            OpCode::LoadInt16
+            | OpCode::AddInt
+            | OpCode::SubInt
+            | OpCode::MulInt
+            | OpCode::DivInt
+            | OpCode::ModInt
+            | OpCode::PowInt
+            | OpCode::WrappingAddInt
+            | OpCode::WrappingSubInt
+            | OpCode::WrappingMulInt
+            | OpCode::WrappingPowInt
+            | OpCode::AnyToInt => KirinType::Int,
+            OpCode::AddFloat
+            | OpCode::SubFloat
+            | OpCode::MulFloat
+            | OpCode::DivFloat
+            | OpCode::ModFloat
+            | OpCode::PowFloat
+            | OpCode::AnyToFloat => KirinType::Float,
+            OpCode::LoadBool
+            | OpCode::EqualInt
+            | OpCode::EqualFloat
+            | OpCode::LessInt
+            | OpCode::LessFloat
+            | OpCode::LessEqualInt
+            | OpCode::LessEqualFloat
+            | OpCode::Not
+            | OpCode::And
+            | OpCode::Or
+            | OpCode::AnyToBool
+            | OpCode::IsType => KirinType::Bool,
+            OpCode::AnyToString => KirinType::String,
+            OpCode::TypeOf => return Some(Content::Type),
+            OpCode::LoadConst => {
+                let index = InstructionDecoder::decode_extended_operand(
+                    extension,
+                    instruction,
+                    OperandSlot::Immediate,
+                );
+
+                match self.vm.constants.get(index as usize)? {
+                    ProgramConstant::Int32(_) | ProgramConstant::Int64(_) => KirinType::Int,
+                    ProgramConstant::Float(_) => KirinType::Float,
+                    ProgramConstant::String(_) => KirinType::String,
+                }
+            }
+
+            _ => return None,
+        };
+
+        Some(Content::Value(kind))
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn instruction_pointer(&self) -> usize {
+        self.vm.instruction_pointer
+    }
+
+    /// The source location of the next instruction
+    pub fn location(&self) -> Option<SourceLocation<'_>> {
+        self.vm.debug_info.lookup(self.vm.instruction_pointer)
+    }
+
+    /// The source location of the instruction at `address`
+    pub fn location_of(&self, address: usize) -> Option<SourceLocation<'_>> {
+        self.vm.debug_info.lookup(address)
+    }
+
+    /// The open frames, innermost last
+    pub fn frames(&self) -> &[Frame] {
+        &self.vm.frames
+    }
+
+    /// The first register of the current frame, `r0` of its instructions
+    pub fn register_base(&self) -> usize {
+        self.vm.register_offset
+    }
+
+    /// The variables of the source declared before the next instruction
+    pub fn variables(&self) -> Vec<&DebugVariable> {
+        self.vm.debug_info.variables_at(self.vm.instruction_pointer)
+    }
+
+    /// The raw content of register `index` of the current frame
+    pub fn register_bits(&self, index: usize) -> Option<u64> {
+        self.vm
+            .registers
+            .get(self.vm.register_offset + index)
+            .copied()
+    }
+
+    /// The number of registers of the current frame
+    pub fn register_count(&self) -> usize {
+        self.vm
+            .registers
+            .len()
+            .saturating_sub(self.vm.register_offset)
+    }
+
+    pub fn constants(&self) -> &[ProgramConstant] {
+        &self.vm.constants
+    }
+
+    pub fn instruction_count(&self) -> usize {
+        self.vm.instructions.len()
+    }
+
+    /// The instruction at `address` as assembly, with the operands of its
+    /// prefix
+    pub fn disassemble(&self, address: usize) -> Option<String> {
+        let instruction = *self.vm.instructions.get(address)?;
+
+        Some(match InstructionDecoder::decode_known_opcode(instruction) {
+            Some(OpCode::Extend) => match self.vm.instructions.get(address + 1) {
+                Some(&next) => Disassembler::extended_instruction(Some(instruction), next),
+                None => Disassembler::instruction(instruction),
+            },
+            _ => Disassembler::instruction(instruction),
+        })
+    }
+
+    /// Register `index` of the current frame, decoded as `kind` or by what
+    /// the instruction that wrote it stored. `Any` decodes the register
+    /// pair starting at `index`.
+    pub fn register(&self, index: usize, kind: Option<KirinType>) -> Option<String> {
+        let register = self.vm.register_offset + index;
+        let value = *self.vm.registers.get(register)?;
+
+        let content = match kind {
+            Some(KirinType::Any) => Content::Tag,
+            Some(kind) => Content::Value(kind),
+            None => self
+                .contents
+                .get(register)
+                .copied()
+                .unwrap_or(Content::Unknown),
+        };
+
+        Some(match content {
+            Content::Value(kind) => format!("{} {}", kind, self.format_value(kind, value)),
+            Content::Tag => match self.vm.registers.get(register + 1) {
+                Some(&payload) => match KirinType::from_u8(value as u8) {
+                    Some(KirinType::Null) => "any none".to_string(),
+                    Some(kind) => format!("any {} {}", kind, self.format_value(kind, payload)),
+                    None => format!("any <invalid tag {}>", value),
+                },
+                None => format!("any <missing value register r{}>", index + 1),
+            },
+            Content::Payload => format!("value of the any in r{}", index.wrapping_sub(1)),
+            Content::Type => match KirinType::from_u8(value as u8) {
+                Some(kind) => format!("type {}", kind),
+                None => format!("type <invalid tag {}>", value),
+            },
+            Content::Unknown => format!("? {} ({:#x})", value as i64, value),
+        })
+    }
+
+    fn format_value(&self, kind: KirinType, value: u64) -> String {
+        match kind {
+            KirinType::Int => (value as i64).to_string(),
+            KirinType::Float => format!("{:?}", f64::from_bits(value)),
+            KirinType::Bool => (value != 0).to_string(),
+            KirinType::String => match self.vm.constants.get(value as usize) {
+                Some(ProgramConstant::String(string)) => format!("{:?}", string),
+                _ => format!("<invalid string #{}>", value),
+            },
+            _ => format!("{:#x}", value),
+        }
+    }
+
+    /// Take the output the program printed so far
+    pub fn take_output(&mut self) -> String {
+        self.vm.take_output()
+    }
+}
+
+#[cfg(test)]
+mod debugger_tests {
+    use crate::{Breakpoint, Debugger, Program, Stop, assemble};
+
+    const PROGRAM: &str = "
+        .const half 0.5
+        .const greeting \"hi\"
+        ALLOC_REG 6
+        LOAD_INT16 r0, 7
+        LOAD_CONST r1, half
+        INT_TO_ANY r2, r0
+        CALL function
+        LOAD_CONST r4, greeting
+        MOVE r5, r0
+        DEALLOC_REG 6
+        RETURN
+        function:
+        ALLOC_REG 2
+        LOAD_BOOL r0, true
+        DEALLOC_REG 2
+        RETURN
+        HALT
+        ";
+
+    fn debugger() -> Debugger {
+        Debugger::new(assemble(PROGRAM).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_breakpoints() {
+        let mut debugger = debugger();
+
+        assert_eq!(debugger.add_breakpoint(Breakpoint::Line(9)), Ok(1));
+        assert_eq!(debugger.add_breakpoint(Breakpoint::Instruction(10)), Ok(2));
+        assert!(debugger.add_breakpoint(Breakpoint::Line(1)).is_err());
+        assert!(
+            debugger
+                .add_breakpoint(Breakpoint::Instruction(99))
+                .is_err()
+        );
+
+        assert_eq!(debugger.resume().unwrap(), Stop::Breakpoint(2));
+        assert_eq!(debugger.instruction_pointer(), 10);
+        assert_eq!(debugger.frames().len(), 1);
+        assert_eq!(debugger.register_base(), 6);
+
+        assert_eq!(
+            debugger.remove_breakpoint(2),
+            Some(Breakpoint::Instruction(10))
+        );
+        assert_eq!(debugger.resume().unwrap(), Stop::Breakpoint(1));
+        assert_eq!(debugger.location().map(|location| location.line), Some(9));
+
+        assert_eq!(debugger.resume().unwrap(), Stop::Finished);
+        assert!(debugger.is_finished());
+    }
+
+    #[test]
+    fn test_stepping() {
+        let mut debugger = debugger();
+
+        // to the call
+        for _ in 0..4 {
+            assert_eq!(debugger.step().unwrap(), Stop::Step);
+        }
+        assert_eq!(debugger.disassemble(4).as_deref(), Some("CALL 9"));
+
+        assert_eq!(debugger.step_over().unwrap(), Stop::Step);
+        assert_eq!(debugger.instruction_pointer(), 5);
+        assert!(debugger.frames().is_empty());
+
+        let mut debugger = self::debugger();
+        for _ in 0..5 {
+            debugger.step().unwrap();
+        }
+        assert_eq!(debugger.instruction_pointer(), 9);
+        assert_eq!(debugger.step_out().unwrap(), Stop::Step);
+        assert_eq!(debugger.instruction_pointer(), 5);
+    }
+
+    #[test]
+    fn test_registers_are_decoded() {
+        let mut debugger = debugger();
+        debugger.add_breakpoint(Breakpoint::Instruction(7)).unwrap();
+        debugger.resume().unwrap();
+
+        let registers = (0..debugger.register_count())
+            .map(|index| debugger.register(index, None).unwrap())
+            .collect::<Vec<String>>();
+
+        assert_eq!(
+            registers,
+            [
+                "int 7",
+                "float 0.5",
+                "any int 7",
+                "value of the any in r2",
+                "string \"hi\"",
+                "int 7",
+            ]
+        );
+        assert_eq!(
+            debugger.register(1, Some(types::KirinType::Int)).as_deref(),
+            Some("int 4602678819172646912")
+        );
+    }
+
+    #[test]
+    fn test_runtime_errors_finish() {
+        let mut debugger = Debugger::new(
+            assemble(
+                "ALLOC_REG 2\nLOAD_INT16 r1, 0\nDIV_INT r0, r1, r1\nDEALLOC_REG 2\nRETURN\nHALT",
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        assert!(debugger.resume().is_err());
+        assert!(debugger.is_finished());
+        assert_eq!(debugger.step().unwrap(), Stop::Finished);
+    }
+
+    #[test]
+    fn test_stepping_past_the_end() {
+        let mut returned = debugger();
+        assert_eq!(returned.resume().unwrap(), Stop::Finished);
+        assert_eq!(returned.step_over().unwrap(), Stop::Finished);
+
+        let mut halted = Debugger::new(assemble("HALT").unwrap()).unwrap();
+        assert!(halted.step().is_err());
+        assert_eq!(halted.step_over().unwrap(), Stop::Finished);
+        assert_eq!(halted.step().unwrap(), Stop::Finished);
+        assert_eq!(halted.step_out().unwrap(), Stop::Finished);
+
+        let mut empty = Debugger::new(Program::new(Vec::new(), Vec::new())).unwrap();
+        assert!(empty.is_finished());
+        assert_eq!(empty.step_over().unwrap(), Stop::Finished);
+        assert_eq!(empty.resume().unwrap(), Stop::Finished);
+    }
+}
@@ -0,0 +1,344 @@
+use crate::{Breakpoint, Debugger, ProgramConstant, Stop};
+use errors::KirinError;
+use std::fmt::Write as _;
+use std::io::{BufRead, Write};
+use types::KirinType;
+
+const HELP: &str = "\
+break <line>       stop at the first instructions of a source line
+break @<index>     stop at an instruction
+delete <n>         remove breakpoint <n>
+breakpoints        list the breakpoints
+step, s            execute one instruction
+next, n            execute one instruction, running calls to their return
+finish, f          run until the current frame returns
+continue, c        run until a breakpoint or the end of the program
+registers, r       show the registers of the current frame
+print r<n> [type]  show a register as int, float, bool, string or any
+frames, bt         show the open frames
+constants, k       show the constant pool
+list, l            show the instructions around the next one
+quit, q            leave the debugger
+an empty line repeats the last command
+";
+
+/// Instructions shown before and after the next one by `list`
+const LIST_CONTEXT: usize = 4;
+
+/// A command line for a [`Debugger`]
+pub struct Prompt {
+    debugger: Debugger,
+    /// the source of the program, for showing the lines it stops at
+    source: Option<String>,
+    last_command: String,
+}
+
+impl Prompt {
+    pub fn new(debugger: Debugger, source: Option<String>) -> Self {
+        Self {
+            debugger,
+            source,
+            last_command: String::new(),
+        }
+    }
+
+    /// Handle one line of input and return the text to show for it
+    pub fn input(&mut self, line: &str) -> String {
+        let line = match line.trim() {
+            "" => std::mem::take(&mut self.last_command),
+            line => line.to_string(),
+        };
+        if line.is_empty() {
+            return String::new();
+        }
+
+        let (command, argument) = line.split_once(' ').unwrap_or((&line, ""));
+        let argument = argument.trim();
+
+        let output = match command {
+            "break" | "b" => self.add_breakpoint(argument),
+            "delete" | "d" => match argument.parse::<usize>() {
+                Ok(number) => match self.debugger.remove_breakpoint(number) {
+                    Some(breakpoint) => {
+                        format!("deleted breakpoint {} at {}\n", number, breakpoint)
+                    }
+                    None => format!("no breakpoint {}\n", number),
+                },
+                Err(_) => "usage: delete <n>\n".to_string(),
+            },
+            "breakpoints" => self
+                .debugger
+                .breakpoints()
+                .iter()
+                .map(|(number, breakpoint)| format!("{:>3}  {}\n", number, breakpoint))
+                .collect(),
+            "step" | "s" => self.execute(Debugger::step),
+            "next" | "n" => self.execute(Debugger::step_over),
+            "finish" | "f" => self.execute(Debugger::step_out),
+            "continue" | "c" => self.execute(Debugger::resume),
+            "registers" | "r" => self.registers(),
+            "print" | "p" => self.print(argument),
+            "frames" | "bt" => self.frames(),
+            "constants" | "k" => self.constants(),
+            "list" | "l" => self.list(),
+            "help" => HELP.to_string(),
+
+            _ => format!("unknown command `{}`, try `help`\n", command),
+        };
+
+        self.last_command = line;
+        output
+    }
+
+    fn add_breakpoint(&mut self, argument: &str) -> String {
+        let breakpoint = match argument.strip_prefix('@') {
+            Some(index) => index.parse().map(Breakpoint::Instruction),
+            None => argument.parse().map(Breakpoint::Line),
+        };
+        let Ok(breakpoint) = breakpoint else {
+            return "usage: break <line> or break @<index>\n".to_string();
+        };
+
+        match self.debugger.add_breakpoint(breakpoint) {
+            Ok(number) => format!("breakpoint {} at {}\n", number, breakpoint),
+            Err(message) => format!("{}\n", message),
+        }
+    }
+
+    /// Run the debugger and report where it stopped, after the output of
+    /// the program
+    fn execute(&mut self, run: fn(&mut Debugger) -> Result<Stop, KirinError>) -> String {
+        let stop = run(&mut self.debugger);
+        let mut output = self.debugger.take_output();
+
+        match stop {
+            Ok(Stop::Finished) => output.push_str("the program finished\n"),
+            Ok(stop) => {
+                if let Stop::Breakpoint(number) = stop {
+                    writeln!(output, "breakpoint {}", number).ok();
+                }
+                output.push_str(&self.current());
+            }
+            Err(error) => {
+                let diagnostic = error.diagnostic();
+                writeln!(output, "the program failed: {}", diagnostic.message).ok();
+            }
+        }
+
+        output
+    }
+
+    /// The next instruction and its source line
+    fn current(&self) -> String {
+        let address = self.debugger.instruction_pointer();
+        let mut output = format!(
+            "=> {:>4}  {}\n",
+            address,
+            self.debugger.disassemble(address).unwrap_or_default()
+        );
+
+        if let Some(location) = self.debugger.location() {
+            let line = self
+                .source
+                .as_ref()
+                .and_then(|source| source.lines().nth(location.line - 1));
+
+            writeln!(
+                output,
+                "   {}:{}:{}{}",
+                location.file.unwrap_or("<input>"),
+                location.line,
+                location.column,
+                line.map(|line| format!("  {}", line.trim()))
+                    .unwrap_or_default()
+            )
+            .ok();
+        }
+
+        output
+    }
+
+    fn registers(&self) -> String {
+        let mut output = format!("register base {}\n", self.debugger.register_base());
+
+        for index in 0..self.debugger.register_count() {
+            if let Some(register) = self.debugger.register(index, None) {
+                writeln!(output, "{:>5}  {}", format!("r{}", index), register).ok();
+            }
+        }
+
+        output
+    }
+
+    fn print(&self, argument: &str) -> String {
+        let (register, kind) = argument.split_once(' ').unwrap_or((argument, ""));
+
+        let Some(index) = register
+            .strip_prefix('r')
+            .and_then(|index| index.parse::<usize>().ok())
+        else {
+            return "usage: print r<n> [int|float|bool|string|any]\n".to_string();
+        };
+
+        let kind = match kind.trim() {
+            "" => None,
+            name => match KirinType::from_name(name) {
+                Some(kind) => Some(kind),
+                None => return format!("unknown type `{}`\n", name),
+            },
+        };
+
+        match self.debugger.register(index, kind) {
+            Some(value) => format!("r{} = {}\n", index, value),
+            None => format!(
+                "the frame has {} registers\n",
+                self.debugger.register_count()
+            ),
+        }
+    }
+
+    fn frames(&self) -> String {
+        let mut output = String::new();
+
+        for (depth, frame) in self.debugger.frames().iter().enumerate().rev() {
+            let called_from = match frame.return_address {
+                Some(address) => {
+                    let call = address - 1;
+                    let line = self
+                        .debugger
+                        .location_of(call)
+                        .map(|location| format!(", line {}", location.line))
+                        .unwrap_or_default();
+
+                    format!("called from {}{}", call, line)
+                }
+                None => "no return address".to_string(),
+            };
+
+            writeln!(
+                output,
+                "#{}  register base {}, {}",
+                depth, frame.register_base, called_from
+            )
+            .ok();
+        }
+
+        if output.is_empty() {
+            output.push_str("no frames, at the top level\n");
+        }
+
+        output
+    }
+
+    fn constants(&self) -> String {
+        self.debugger
+            .constants()
+            .iter()
+            .enumerate()
+            .map(|(index, constant)| {
+                let constant = match constant {
+                    ProgramConstant::Int32(value) => format!("int {}", value),
+                    ProgramConstant::Int64(value) => format!("int {}", value),
+                    ProgramConstant::Float(value) => format!("float {:?}", value),
+                    ProgramConstant::String(value) => format!("string {:?}", value),
+                };
+
+                format!("{:>5}  {}\n", format!("#{}", index), constant)
+            })
+            .collect()
+    }
+
+    fn list(&self) -> String {
+        let current = self.debugger.instruction_pointer();
+        let start = current.saturating_sub(LIST_CONTEXT);
+        let end = (current + LIST_CONTEXT + 1).min(self.debugger.instruction_count());
+
+        (start..end)
+            .map(|address| {
+                let marker = if address == current { "=>" } else { "  " };
+
+                format!(
+                    "{} {:>4}  {}\n",
+                    marker,
+                    address,
+                    self.debugger.disassemble(address).unwrap_or_default()
+                )
+            })
+            .collect()
+    }
+
+    /// Read commands from stdin until `quit` or the end of the input
+    pub fn run(&mut self) {
+        println!("kirin debugger, `help` lists the commands");
+        print!("{}", self.current());
+
+        let stdin = std::io::stdin();
+        let mut lines = stdin.lock().lines();
+        loop {
+            print!("(kdb) ");
+            std::io::stdout().flush().ok();
+
+            let Some(Ok(line)) = lines.next() else {
+                println!();
+                break;
+            };
+
+            if matches!(line.trim(), "quit" | "q") {
+                break;
+            }
+
+            print!("{}", self.input(&line));
+        }
+    }
+}
+
+#[cfg(test)]
+mod prompt_tests {
+    use crate::{Debugger, Prompt, assemble};
+
+    const PROGRAM: &str = "
+        .const greeting \"hi\"
+        ALLOC_REG 4
+        LOAD_CONST r0, greeting
+        STRING_TO_ANY r2, r0
+        PRINT_ANY r2
+        LOAD_INT16 r1, 3
+        DEALLOC_REG 4
+        RETURN
+        HALT
+        ";
+
+    fn run(lines: &[&str]) -> String {
+        let debugger = Debugger::new(assemble(PROGRAM).unwrap()).unwrap();
+        let mut prompt = Prompt::new(debugger, Some(PROGRAM.to_string()));
+
+        lines.iter().map(|line| prompt.input(line)).collect()
+    }
+
+    #[test]
+    fn test_commands() {
+        assert_eq!(
+            run(&["break 7", "continue", "registers"]),
+            concat!(
+                "breakpoint 1 at line 7\n",
+                "hibreakpoint 1\n",
+                "=>    4  LOAD_INT16 r1, 3\n",
+                "   <input>:7:9  LOAD_INT16 r1, 3\n",
+                "register base 0\n",
+                "   r0  string \"hi\"\n",
+                "   r1  ? 0 (0x0)\n",
+                "   r2  any string \"hi\"\n",
+                "   r3  value of the any in r2\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_empty_line_repeats() {
+        let output = run(&["s", "", "print r0 int", "c", "q"]);
+
+        assert!(output.contains("=>    2  STRING_TO_ANY r2, r0\n"));
+        assert!(output.contains("r0 = int 0\n"));
+        assert!(output.ends_with("hithe program finished\nunknown command `q`, try `help`\n"));
+    }
+}
@@ -1,5 +1,8 @@
+/// A register window opened by `CALL` or `INIT_FRAME`
 #[derive(Debug, Copy, Clone)]
 pub struct Frame {
+    /// where `RETURN` continues, `None` for frames of `INIT_FRAME`
     pub return_address: Option<usize>,
+    /// index of the first register of the window
     pub register_base: usize,
 }
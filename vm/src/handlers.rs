@@ -1,6 +1,8 @@
 mod arithmetic;
 mod control;
 mod conversions;
+mod dynamic;
 mod library;
 mod load;
+mod logic;
 mod registers;
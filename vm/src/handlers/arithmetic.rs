@@ -1,96 +1,207 @@
 use crate::VM;
-use instructions::{Instruction, InstructionDecoder};
+use errors::codes;
+use instructions::Instruction;
+
+#[derive(Debug, Copy, Clone)]
+pub(crate) enum ArithmeticOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Pow,
+}
 
-impl VM {
-    #[inline]
-    pub(crate) fn add_int(&mut self, instruction: Instruction) {
-        let destination = InstructionDecoder::decode_destination(instruction);
-        let source1 = InstructionDecoder::decode_source_1(instruction);
-        let source2 = InstructionDecoder::decode_source_2(instruction);
+/// A failed integer operation
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ArithmeticError {
+    pub(crate) code: &'static str,
+    pub(crate) message: String,
+}
 
-        let first = self.get_register(source1) as i64;
-        let second = self.get_register(source2) as i64;
+impl ArithmeticError {
+    fn new(code: &'static str, message: String) -> Self {
+        Self { code, message }
+    }
+}
 
-        let result = first + second;
+impl ArithmeticOp {
+    pub(crate) fn symbol(&self) -> &'static str {
+        match self {
+            Self::Add => "+",
+            Self::Sub => "-",
+            Self::Mul => "*",
+            Self::Div => "/",
+            Self::Mod => "%",
+            Self::Pow => "^",
+        }
+    }
 
-        self.set_int_in_register(destination, result);
+    /// Integer arithmetic that reports overflow, division by zero and negative
+    /// exponents instead of panicking or silently wrapping
+    pub(crate) fn checked_int(&self, first: i64, second: i64) -> Result<i64, ArithmeticError> {
+        let result = match self {
+            Self::Div | Self::Mod if second == 0 => {
+                return Err(ArithmeticError::new(
+                    codes::DIVISION_BY_ZERO,
+                    format!("division by zero: `{} {} 0`", first, self.symbol()),
+                ));
+            }
+            Self::Add => first.checked_add(second),
+            Self::Sub => first.checked_sub(second),
+            Self::Mul => first.checked_mul(second),
+            Self::Div => first.checked_div(second),
+            Self::Mod => first.checked_rem(second),
+            Self::Pow => {
+                let exponent = Self::exponent(first, second)?;
+                first.checked_pow(exponent)
+            }
+        };
+
+        result.ok_or_else(|| {
+            ArithmeticError::new(
+                codes::INTEGER_OVERFLOW,
+                format!("integer overflow: `{} {} {}`", first, self.symbol(), second),
+            )
+        })
     }
 
-    #[inline]
-    pub(crate) fn sub_int(&mut self, instruction: Instruction) {
-        let destination = InstructionDecoder::decode_destination(instruction);
-        let source1 = InstructionDecoder::decode_source_1(instruction);
-        let source2 = InstructionDecoder::decode_source_2(instruction);
+    pub(crate) fn float(&self, first: f64, second: f64) -> f64 {
+        match self {
+            Self::Add => first + second,
+            Self::Sub => first - second,
+            Self::Mul => first * second,
+            Self::Div => first / second,
+            Self::Mod => first % second,
+            Self::Pow => first.powf(second),
+        }
+    }
 
-        let first = self.get_register(source1) as i64;
-        let second = self.get_register(source2) as i64;
+    fn exponent(base: i64, exponent: i64) -> Result<u32, ArithmeticError> {
+        if exponent < 0 {
+            return Err(negative_exponent(base, exponent));
+        }
+
+        u32::try_from(exponent).map_err(|_| {
+            ArithmeticError::new(
+                codes::INTEGER_OVERFLOW,
+                format!("integer overflow: `{} ^ {}`", base, exponent),
+            )
+        })
+    }
+}
 
-        let result = first - second;
+fn negative_exponent(base: i64, exponent: i64) -> ArithmeticError {
+    ArithmeticError::new(
+        codes::NEGATIVE_EXPONENT,
+        format!(
+            "negative exponent in integer power: `{} ^ {}`",
+            base, exponent
+        ),
+    )
+}
 
-        self.set_int_in_register(destination, result);
+/// `base ^ exponent` modulo 2^64 for `wrapping_pow`, which takes any
+/// exponent that is not negative
+fn wrapping_pow(base: i64, exponent: i64) -> Result<i64, ArithmeticError> {
+    if exponent < 0 {
+        return Err(negative_exponent(base, exponent));
     }
 
-    #[inline]
-    pub(crate) fn mul_int(&mut self, instruction: Instruction) {
-        let destination = InstructionDecoder::decode_destination(instruction);
-        let source1 = InstructionDecoder::decode_source_1(instruction);
-        let source2 = InstructionDecoder::decode_source_2(instruction);
+    // square and multiply for every bit of the exponent
+    let (mut base, mut exponent, mut result) = (base, exponent as u64, 1i64);
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = result.wrapping_mul(base);
+        }
 
-        let first = self.get_register(source1) as i64;
-        let second = self.get_register(source2) as i64;
+        base = base.wrapping_mul(base);
+        exponent >>= 1;
+    }
 
-        let result = first * second;
+    Ok(result)
+}
 
-        self.set_int_in_register(destination, result);
+impl VM {
+    #[inline]
+    pub(crate) fn add_int(&mut self, instruction: Instruction) {
+        self.int_operation(instruction, ArithmeticOp::Add);
     }
 
     #[inline]
-    pub(crate) fn div_int(&mut self, instruction: Instruction) {
-        let destination = InstructionDecoder::decode_destination(instruction);
-        let source1 = InstructionDecoder::decode_source_1(instruction);
-        let source2 = InstructionDecoder::decode_source_2(instruction);
-
-        let first = self.get_register(source1) as i64;
-        let second = self.get_register(source2) as i64;
+    pub(crate) fn sub_int(&mut self, instruction: Instruction) {
+        self.int_operation(instruction, ArithmeticOp::Sub);
+    }
 
-        let result = first / second;
+    #[inline]
+    pub(crate) fn mul_int(&mut self, instruction: Instruction) {
+        self.int_operation(instruction, ArithmeticOp::Mul);
+    }
 
-        self.set_int_in_register(destination, result);
+    #[inline]
+    pub(crate) fn div_int(&mut self, instruction: Instruction) {
+        self.int_operation(instruction, ArithmeticOp::Div);
     }
 
     #[inline]
     pub(crate) fn mod_int(&mut self, instruction: Instruction) {
-        let destination = InstructionDecoder::decode_destination(instruction);
-        let source1 = InstructionDecoder::decode_source_1(instruction);
-        let source2 = InstructionDecoder::decode_source_2(instruction);
+        self.int_operation(instruction, ArithmeticOp::Mod);
+    }
+
+    #[inline]
+    pub(crate) fn pow_int(&mut self, instruction: Instruction) {
+        self.int_operation(instruction, ArithmeticOp::Pow);
+    }
 
-        let first = self.get_register(source1) as i64;
-        let second = self.get_register(source2) as i64;
+    #[inline]
+    pub(crate) fn wrapping_add_int(&mut self, instruction: Instruction) {
+        self.wrapping_int_operation(instruction, |first, second| Ok(first.wrapping_add(second)));
+    }
 
-        let result = first % second;
+    #[inline]
+    pub(crate) fn wrapping_sub_int(&mut self, instruction: Instruction) {
+        self.wrapping_int_operation(instruction, |first, second| Ok(first.wrapping_sub(second)));
+    }
 
-        self.set_int_in_register(destination, result);
+    #[inline]
+    pub(crate) fn wrapping_mul_int(&mut self, instruction: Instruction) {
+        self.wrapping_int_operation(instruction, |first, second| Ok(first.wrapping_mul(second)));
     }
 
     #[inline]
-    pub(crate) fn pow_int(&mut self, instruction: Instruction) {
-        let destination = InstructionDecoder::decode_destination(instruction);
-        let source1 = InstructionDecoder::decode_source_1(instruction);
-        let source2 = InstructionDecoder::decode_source_2(instruction);
+    pub(crate) fn wrapping_pow_int(&mut self, instruction: Instruction) {
+        self.wrapping_int_operation(instruction, wrapping_pow);
+    }
 
-        let first = self.get_register(source1) as i64;
-        let second = self.get_register(source2) as i64;
+    #[inline(always)]
+    fn int_operation(&mut self, instruction: Instruction, operation: ArithmeticOp) {
+        let (destination, first, second) = self.decode_int_operands(instruction);
 
-        let result = first.pow(second as u32);
+        match operation.checked_int(first, second) {
+            Ok(result) => self.set_int_in_register(destination, result),
+            Err(error) => self.runtime_error(error.code, error.message),
+        }
+    }
 
-        self.set_int_in_register(destination, result);
+    #[inline(always)]
+    fn wrapping_int_operation(
+        &mut self,
+        instruction: Instruction,
+        operation: fn(i64, i64) -> Result<i64, ArithmeticError>,
+    ) {
+        let (destination, first, second) = self.decode_int_operands(instruction);
+
+        match operation(first, second) {
+            Ok(result) => self.set_int_in_register(destination, result),
+            Err(error) => self.runtime_error(error.code, error.message),
+        }
     }
 
     #[inline]
     pub(crate) fn add_float(&mut self, instruction: Instruction) {
-        let destination = InstructionDecoder::decode_destination(instruction);
-        let source1 = InstructionDecoder::decode_source_1(instruction);
-        let source2 = InstructionDecoder::decode_source_2(instruction);
+        let destination = self.decode_destination(instruction);
+        let source1 = self.decode_source_1(instruction);
+        let source2 = self.decode_source_2(instruction);
 
         let first = f64::from_bits(self.get_register(source1));
         let second = f64::from_bits(self.get_register(source2));
@@ -102,9 +213,9 @@ impl VM {
 
     #[inline]
     pub(crate) fn sub_float(&mut self, instruction: Instruction) {
-        let destination = InstructionDecoder::decode_destination(instruction);
-        let source1 = InstructionDecoder::decode_source_1(instruction);
-        let source2 = InstructionDecoder::decode_source_2(instruction);
+        let destination = self.decode_destination(instruction);
+        let source1 = self.decode_source_1(instruction);
+        let source2 = self.decode_source_2(instruction);
 
         let first = f64::from_bits(self.get_register(source1));
         let second = f64::from_bits(self.get_register(source2));
@@ -116,9 +227,9 @@ impl VM {
 
     #[inline]
     pub(crate) fn mul_float(&mut self, instruction: Instruction) {
-        let destination = InstructionDecoder::decode_destination(instruction);
-        let source1 = InstructionDecoder::decode_source_1(instruction);
-        let source2 = InstructionDecoder::decode_source_2(instruction);
+        let destination = self.decode_destination(instruction);
+        let source1 = self.decode_source_1(instruction);
+        let source2 = self.decode_source_2(instruction);
 
         let first = f64::from_bits(self.get_register(source1));
         let second = f64::from_bits(self.get_register(source2));
@@ -130,9 +241,9 @@ impl VM {
 
     #[inline]
     pub(crate) fn div_float(&mut self, instruction: Instruction) {
-        let destination = InstructionDecoder::decode_destination(instruction);
-        let source1 = InstructionDecoder::decode_source_1(instruction);
-        let source2 = InstructionDecoder::decode_source_2(instruction);
+        let destination = self.decode_destination(instruction);
+        let source1 = self.decode_source_1(instruction);
+        let source2 = self.decode_source_2(instruction);
 
         let first = f64::from_bits(self.get_register(source1));
         let second = f64::from_bits(self.get_register(source2));
@@ -144,9 +255,9 @@ impl VM {
 
     #[inline]
     pub(crate) fn mod_float(&mut self, instruction: Instruction) {
-        let destination = InstructionDecoder::decode_destination(instruction);
-        let source1 = InstructionDecoder::decode_source_1(instruction);
-        let source2 = InstructionDecoder::decode_source_2(instruction);
+        let destination = self.decode_destination(instruction);
+        let source1 = self.decode_source_1(instruction);
+        let source2 = self.decode_source_2(instruction);
 
         let first = f64::from_bits(self.get_register(source1));
         let second = f64::from_bits(self.get_register(source2));
@@ -158,9 +269,9 @@ impl VM {
 
     #[inline]
     pub(crate) fn pow_float(&mut self, instruction: Instruction) {
-        let destination = InstructionDecoder::decode_destination(instruction);
-        let source1 = InstructionDecoder::decode_source_1(instruction);
-        let source2 = InstructionDecoder::decode_source_2(instruction);
+        let destination = self.decode_destination(instruction);
+        let source1 = self.decode_source_1(instruction);
+        let source2 = self.decode_source_2(instruction);
 
         let first = f64::from_bits(self.get_register(source1));
         let second = f64::from_bits(self.get_register(source2));
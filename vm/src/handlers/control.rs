@@ -1,16 +1,97 @@
+use crate::frame::Frame;
 use crate::{VM, VmStatus};
+use errors::codes;
 use instructions::Instruction;
 
 impl VM {
+    #[inline]
+    pub(crate) fn no_operation(&mut self, _instruction: Instruction) {}
+
+    /// Programs end with `Return`, reaching `Halt` means execution ran past it
+    #[inline]
+    pub(crate) fn halt(&mut self, _instruction: Instruction) {
+        self.runtime_error(codes::RUNTIME, "halt instruction encountered".to_string());
+    }
+
     #[inline]
     pub(crate) fn do_return(&mut self, _instruction: Instruction) {
+        if let Some(frame) = self.frames.pop()
+            && let Some(return_address) = frame.return_address
+        {
+            self.instruction_pointer = return_address;
+            self.register_offset = self
+                .frames
+                .last()
+                .map(|frame| frame.register_base)
+                .unwrap_or(0);
+            return;
+        }
+
+        self.signal(VmStatus::Halted)
+    }
+
+    /// Execute the next instruction with its operands widened by this prefix
+    #[inline]
+    pub(crate) fn extend(&mut self, instruction: Instruction) {
+        self.extension = Some(instruction);
+
+        let next = self.get_next_instruction();
+        self.execute_instruction(next);
+
+        self.extension = None;
+    }
+
+    /// Jump to a function, its registers start above the caller's registers
+    #[inline]
+    pub(crate) fn call(&mut self, instruction: Instruction) {
+        let address = self.decode_immediate(instruction) as usize;
+        let register_base = self.registers.len();
+
+        self.frames.push(Frame {
+            return_address: Some(self.instruction_pointer),
+            register_base,
+        });
+        self.register_offset = register_base;
+        self.instruction_pointer = address;
+    }
+
+    #[inline]
+    pub(crate) fn jump(&mut self, instruction: Instruction) {
+        self.instruction_pointer = self.decode_immediate(instruction) as usize;
+    }
+
+    #[inline]
+    pub(crate) fn jump_if_false(&mut self, instruction: Instruction) {
+        let condition = self.decode_destination(instruction);
+
+        if self.get_register(condition) == 0 {
+            self.instruction_pointer = self.decode_immediate(instruction) as usize;
+        }
+    }
+
+    /// Start a new register window above the currently allocated registers
+    #[inline]
+    pub(crate) fn init_frame(&mut self, _instruction: Instruction) {
+        let register_base = self.registers.len();
+
+        self.frames.push(Frame {
+            return_address: None,
+            register_base,
+        });
+        self.register_offset = register_base;
+    }
+
+    /// Release the current register window and restore the previous one
+    #[inline]
+    pub(crate) fn drop_frame(&mut self, _instruction: Instruction) {
         if let Some(frame) = self.frames.pop() {
-            if let Some(return_address) = frame.return_address {
-                self.instruction_pointer = return_address;
-                return;
-            }
+            self.registers.truncate(frame.register_base);
         }
 
-        self.status = VmStatus::Halted
+        self.register_offset = self
+            .frames
+            .last()
+            .map(|frame| frame.register_base)
+            .unwrap_or(0);
     }
 }
@@ -1,12 +1,13 @@
 use crate::VM;
-use instructions::{Instruction, InstructionDecoder};
+use errors::codes;
+use instructions::Instruction;
 use types::KirinType;
 
 impl VM {
     #[inline]
     pub(crate) fn cast_int_to_any(&mut self, instruction: Instruction) {
-        let destination = InstructionDecoder::decode_destination(instruction);
-        let source = InstructionDecoder::decode_source_1(instruction);
+        let destination = self.decode_destination(instruction);
+        let source = self.decode_source_1(instruction);
 
         self.set_register(destination, KirinType::Int as u64); // set type to int
         self.move_register(destination + 1, source);
@@ -14,16 +15,34 @@ impl VM {
 
     #[inline]
     pub(crate) fn cast_float_to_any(&mut self, instruction: Instruction) {
-        let destination = InstructionDecoder::decode_destination(instruction);
-        let source = InstructionDecoder::decode_source_1(instruction);
+        let destination = self.decode_destination(instruction);
+        let source = self.decode_source_1(instruction);
 
-        self.set_register(destination, KirinType::Float as u64); // set type to int
+        self.set_register(destination, KirinType::Float as u64); // set type to float
+        self.move_register(destination + 1, source);
+    }
+
+    #[inline]
+    pub(crate) fn cast_string_to_any(&mut self, instruction: Instruction) {
+        let destination = self.decode_destination(instruction);
+        let source = self.decode_source_1(instruction);
+
+        self.set_register(destination, KirinType::String as u64); // set type to string
+        self.move_register(destination + 1, source);
+    }
+
+    #[inline]
+    pub(crate) fn cast_bool_to_any(&mut self, instruction: Instruction) {
+        let destination = self.decode_destination(instruction);
+        let source = self.decode_source_1(instruction);
+
+        self.set_register(destination, KirinType::Bool as u64); // set type to bool
         self.move_register(destination + 1, source);
     }
 
     #[inline]
     pub(crate) fn cast_int_to_float(&mut self, instruction: Instruction) {
-        let source = InstructionDecoder::decode_source_1(instruction);
+        let source = self.decode_source_1(instruction);
         let value = self.get_register(source) as i64 as f64;
 
         self.set_float_in_register(source, value);
@@ -31,9 +50,58 @@ impl VM {
 
     #[inline]
     pub(crate) fn cast_float_to_int(&mut self, instruction: Instruction) {
-        let source = InstructionDecoder::decode_source_1(instruction);
+        let source = self.decode_source_1(instruction);
         let value = f64::from_bits(self.get_register(source));
 
         self.set_int_in_register(source, value as i64)
     }
+
+    #[inline]
+    pub(crate) fn cast_any_to_int(&mut self, instruction: Instruction) {
+        self.checked_downcast(instruction, KirinType::Int);
+    }
+
+    #[inline]
+    pub(crate) fn cast_any_to_float(&mut self, instruction: Instruction) {
+        self.checked_downcast(instruction, KirinType::Float);
+    }
+
+    #[inline]
+    pub(crate) fn cast_any_to_string(&mut self, instruction: Instruction) {
+        self.checked_downcast(instruction, KirinType::String);
+    }
+
+    #[inline]
+    pub(crate) fn cast_any_to_bool(&mut self, instruction: Instruction) {
+        self.checked_downcast(instruction, KirinType::Bool);
+    }
+
+    /// Move the value out of an Any register pair, failing if the type tag
+    /// does not match the expected type
+    fn checked_downcast(&mut self, instruction: Instruction, expected: KirinType) {
+        let destination = self.decode_destination(instruction);
+        let source = self.decode_source_1(instruction);
+
+        let tag = self.get_register(source);
+        if tag != expected as u64 {
+            let found = Self::type_name(tag);
+            self.runtime_error(
+                codes::FAILED_DOWNCAST,
+                format!(
+                    "cannot downcast value of type `{}` to `{}`",
+                    found, expected
+                ),
+            );
+            return;
+        }
+
+        self.move_register(destination, source + 1);
+    }
+
+    pub(crate) fn type_name(tag: u64) -> String {
+        match KirinType::from_u8(tag as u8) {
+            Some(kind) => kind.to_string(),
+            None => format!("unknown({:x})", tag),
+        }
+    }
 }
@@ -0,0 +1,115 @@
+use crate::VM;
+use crate::handlers::arithmetic::ArithmeticOp;
+use errors::codes;
+use instructions::Instruction;
+use types::KirinType;
+
+impl VM {
+    #[inline]
+    pub(crate) fn add_any(&mut self, instruction: Instruction) {
+        self.binary_any(instruction, ArithmeticOp::Add);
+    }
+
+    #[inline]
+    pub(crate) fn sub_any(&mut self, instruction: Instruction) {
+        self.binary_any(instruction, ArithmeticOp::Sub);
+    }
+
+    #[inline]
+    pub(crate) fn mul_any(&mut self, instruction: Instruction) {
+        self.binary_any(instruction, ArithmeticOp::Mul);
+    }
+
+    #[inline]
+    pub(crate) fn div_any(&mut self, instruction: Instruction) {
+        self.binary_any(instruction, ArithmeticOp::Div);
+    }
+
+    #[inline]
+    pub(crate) fn mod_any(&mut self, instruction: Instruction) {
+        self.binary_any(instruction, ArithmeticOp::Mod);
+    }
+
+    #[inline]
+    pub(crate) fn pow_any(&mut self, instruction: Instruction) {
+        self.binary_any(instruction, ArithmeticOp::Pow);
+    }
+
+    #[inline]
+    pub(crate) fn type_of(&mut self, instruction: Instruction) {
+        let destination = self.decode_destination(instruction);
+        let source = self.decode_source_1(instruction);
+
+        self.move_register(destination, source);
+    }
+
+    #[inline]
+    pub(crate) fn is_type(&mut self, instruction: Instruction) {
+        let destination = self.decode_destination(instruction);
+        let source = self.decode_source_1(instruction);
+        let tag = self.decode_source_2(instruction);
+
+        let matches = self.get_register(source) == tag as u64;
+
+        self.set_register(destination, matches as u64);
+    }
+
+    /// Dispatch arithmetic on the runtime type tags of two Any register pairs.
+    /// Two ints produce an int, any other numeric combination produces a float.
+    fn binary_any(&mut self, instruction: Instruction, operation: ArithmeticOp) {
+        let destination = self.decode_destination(instruction);
+        let source1 = self.decode_source_1(instruction);
+        let source2 = self.decode_source_2(instruction);
+
+        let first_tag = self.get_register(source1);
+        let first = self.get_register(source1 + 1);
+        let second_tag = self.get_register(source2);
+        let second = self.get_register(source2 + 1);
+
+        let first_type = KirinType::from_u8(first_tag as u8);
+        let second_type = KirinType::from_u8(second_tag as u8);
+
+        let (tag, value) = match (first_type, second_type) {
+            (Some(KirinType::Int), Some(KirinType::Int)) => {
+                match operation.checked_int(first as i64, second as i64) {
+                    Ok(result) => (KirinType::Int, result as u64),
+                    Err(error) => {
+                        self.runtime_error(error.code, error.message);
+                        return;
+                    }
+                }
+            }
+
+            (Some(left), Some(right)) if left.is_numeric() && right.is_numeric() => {
+                let first = Self::any_as_float(left, first);
+                let second = Self::any_as_float(right, second);
+
+                let result = operation.float(first, second);
+                (KirinType::Float, result.to_bits())
+            }
+
+            _ => {
+                self.runtime_error(
+                    codes::UNSUPPORTED_OPERANDS,
+                    format!(
+                        "unsupported operand types for `{}`: `{}` and `{}`",
+                        operation.symbol(),
+                        Self::type_name(first_tag),
+                        Self::type_name(second_tag)
+                    ),
+                );
+                return;
+            }
+        };
+
+        self.set_register(destination, tag as u64);
+        self.set_register(destination + 1, value);
+    }
+
+    fn any_as_float(kind: KirinType, value: u64) -> f64 {
+        match kind {
+            KirinType::Int => value as i64 as f64,
+            _ => f64::from_bits(value),
+        }
+    }
+}
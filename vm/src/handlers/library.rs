@@ -1,30 +1,50 @@
-use crate::VM;
-use instructions::{Instruction, InstructionDecoder};
+use crate::{ProgramConstant, Register, VM};
+use instructions::Instruction;
 use types::KirinType;
 
 impl VM {
     pub(crate) fn print_any(&mut self, instruction: Instruction) {
-        let source = InstructionDecoder::decode_source_1(instruction);
+        let source = self.decode_source_1(instruction);
 
         let type_tag = self.get_register(source);
         let value = self.get_register(source + 1);
 
+        let text = self.format_any(type_tag, value);
+        self.write_output(&text);
+    }
+
+    pub(crate) fn print_char(&mut self, instruction: Instruction) {
+        let value = self.decode_source_1(instruction);
+
+        let mut buffer = [0; 4];
+        self.write_output((value as u8 as char).encode_utf8(&mut buffer));
+    }
+
+    fn write_output(&mut self, text: &str) {
+        match &mut self.output {
+            Some(output) => output.push_str(text),
+            None => print!("{}", text),
+        }
+    }
+
+    /// Render the value of an Any register pair
+    pub(crate) fn format_any(&self, type_tag: Register, value: Register) -> String {
         let type_val = KirinType::from_u8(type_tag as u8);
 
         if let Some(kind) = type_val {
             match kind {
-                KirinType::Int => print!("{}", value as i64),
-                KirinType::Float => print!("{}", f64::from_bits(value)),
-                _ => print!("Unsupported type: {:x}", value),
+                KirinType::Int => format!("{}", value as i64),
+                KirinType::Float => format!("{}", f64::from_bits(value)),
+                KirinType::Bool => format!("{}", value != 0),
+                KirinType::Null => "none".to_string(),
+                KirinType::String => match self.constants.get(value as usize) {
+                    Some(ProgramConstant::String(string)) => string.clone(),
+                    _ => format!("Invalid string reference: {:x}", value),
+                },
+                _ => format!("Unsupported type: {:x}", value),
             }
         } else {
-            print!("Unsupported type: {:x} {:x}", type_tag, value)
+            format!("Unsupported type: {:x} {:x}", type_tag, value)
         }
     }
-
-    pub(crate) fn print_char(&mut self, instruction: Instruction) {
-        let value = InstructionDecoder::decode_source_1(instruction);
-
-        print!("{}", value as u8 as char);
-    }
 }
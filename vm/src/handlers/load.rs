@@ -1,12 +1,60 @@
-use crate::VM;
-use instructions::{Instruction, InstructionDecoder};
+use crate::{ProgramConstant, VM};
+use errors::codes;
+use instructions::Instruction;
+use types::KirinType;
 
 impl VM {
     #[inline]
     pub(crate) fn load_int16(&mut self, instruction: Instruction) {
-        let destination = InstructionDecoder::decode_destination(instruction);
-        let value = InstructionDecoder::decode_16bit_int(instruction);
+        let destination = self.decode_destination(instruction);
+        let value = self.decode_int_immediate(instruction);
 
         self.set_int_in_register(destination, value as i64);
     }
+
+    /// Bools are stored as `0` or `1` in a single register
+    #[inline]
+    pub(crate) fn load_bool(&mut self, instruction: Instruction) {
+        let destination = self.decode_destination(instruction);
+        let value = self.decode_immediate(instruction) != 0;
+
+        self.set_bool_in_register(destination, value);
+    }
+
+    /// Strings are not copied into registers, the register holds the index of
+    /// the string in the constant pool instead
+    #[inline]
+    pub(crate) fn load_constant(&mut self, instruction: Instruction) {
+        let destination = self.decode_destination(instruction);
+        let index = self.decode_immediate(instruction) as usize;
+
+        match self.constants.get(index) {
+            Some(ProgramConstant::Int32(value)) => {
+                let value = *value as i64;
+                self.set_int_in_register(destination, value)
+            }
+            Some(ProgramConstant::Int64(value)) => {
+                let value = *value;
+                self.set_int_in_register(destination, value)
+            }
+            Some(ProgramConstant::Float(value)) => {
+                let value = *value;
+                self.set_float_in_register(destination, value)
+            }
+            Some(ProgramConstant::String(_)) => self.set_register(destination, index as u64),
+            None => self.runtime_error(
+                codes::RUNTIME,
+                format!("constant index {} out of bounds", index),
+            ),
+        }
+    }
+
+    /// Load `none` into an Any register pair
+    #[inline]
+    pub(crate) fn load_null(&mut self, instruction: Instruction) {
+        let destination = self.decode_destination(instruction);
+
+        self.set_register(destination, KirinType::Null as u64);
+        self.set_register(destination + 1, 0);
+    }
 }
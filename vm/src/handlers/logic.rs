@@ -0,0 +1,70 @@
+use crate::VM;
+use instructions::Instruction;
+
+impl VM {
+    #[inline]
+    pub(crate) fn equal_int(&mut self, instruction: Instruction) {
+        let (destination, first, second) = self.decode_int_operands(instruction);
+
+        self.set_bool_in_register(destination, first == second);
+    }
+
+    #[inline]
+    pub(crate) fn equal_float(&mut self, instruction: Instruction) {
+        let (destination, first, second) = self.decode_float_operands(instruction);
+
+        self.set_bool_in_register(destination, first == second);
+    }
+
+    #[inline]
+    pub(crate) fn less_int(&mut self, instruction: Instruction) {
+        let (destination, first, second) = self.decode_int_operands(instruction);
+
+        self.set_bool_in_register(destination, first < second);
+    }
+
+    #[inline]
+    pub(crate) fn less_float(&mut self, instruction: Instruction) {
+        let (destination, first, second) = self.decode_float_operands(instruction);
+
+        self.set_bool_in_register(destination, first < second);
+    }
+
+    #[inline]
+    pub(crate) fn less_equal_int(&mut self, instruction: Instruction) {
+        let (destination, first, second) = self.decode_int_operands(instruction);
+
+        self.set_bool_in_register(destination, first <= second);
+    }
+
+    #[inline]
+    pub(crate) fn less_equal_float(&mut self, instruction: Instruction) {
+        let (destination, first, second) = self.decode_float_operands(instruction);
+
+        self.set_bool_in_register(destination, first <= second);
+    }
+
+    #[inline]
+    pub(crate) fn not(&mut self, instruction: Instruction) {
+        let destination = self.decode_destination(instruction);
+        let source = self.decode_source_1(instruction);
+
+        let value = self.get_register(source) != 0;
+
+        self.set_bool_in_register(destination, !value);
+    }
+
+    #[inline]
+    pub(crate) fn and(&mut self, instruction: Instruction) {
+        let (destination, first, second) = self.decode_int_operands(instruction);
+
+        self.set_bool_in_register(destination, first != 0 && second != 0);
+    }
+
+    #[inline]
+    pub(crate) fn or(&mut self, instruction: Instruction) {
+        let (destination, first, second) = self.decode_int_operands(instruction);
+
+        self.set_bool_in_register(destination, first != 0 || second != 0);
+    }
+}
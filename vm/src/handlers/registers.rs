@@ -1,7 +1,48 @@
 use crate::{Register, VM};
-use instructions::{Instruction, InstructionDecoder};
+use instructions::{Instruction, InstructionDecoder, OperandSlot};
 
 impl VM {
+    #[inline(always)]
+    pub(crate) fn decode_destination(&self, instruction: Instruction) -> Instruction {
+        InstructionDecoder::decode_extended_operand(
+            self.extension,
+            instruction,
+            OperandSlot::Destination,
+        )
+    }
+
+    #[inline(always)]
+    pub(crate) fn decode_source_1(&self, instruction: Instruction) -> Instruction {
+        InstructionDecoder::decode_extended_operand(
+            self.extension,
+            instruction,
+            OperandSlot::Source1,
+        )
+    }
+
+    #[inline(always)]
+    pub(crate) fn decode_source_2(&self, instruction: Instruction) -> Instruction {
+        InstructionDecoder::decode_extended_operand(
+            self.extension,
+            instruction,
+            OperandSlot::Source2,
+        )
+    }
+
+    #[inline(always)]
+    pub(crate) fn decode_immediate(&self, instruction: Instruction) -> Instruction {
+        InstructionDecoder::decode_extended_operand(
+            self.extension,
+            instruction,
+            OperandSlot::Immediate,
+        )
+    }
+
+    #[inline(always)]
+    pub(crate) fn decode_int_immediate(&self, instruction: Instruction) -> i32 {
+        InstructionDecoder::decode_extended_int(self.extension, instruction)
+    }
+
     #[inline(always)]
     pub(crate) fn set_register(&mut self, destination: Instruction, value: Register) {
         self.registers[destination as usize + self.register_offset] = value
@@ -21,6 +62,11 @@ impl VM {
         self.set_register(destination, value)
     }
 
+    #[inline(always)]
+    pub(crate) fn set_bool_in_register(&mut self, destination: Instruction, value: bool) {
+        self.set_register(destination, value as Register)
+    }
+
     #[inline(always)]
     pub(crate) fn get_register(&mut self, source: Instruction) -> Register {
         self.registers[source as usize + self.register_offset]
@@ -28,7 +74,7 @@ impl VM {
 
     #[inline(always)]
     pub(crate) fn allocate_registers(&mut self, instruction: Instruction) {
-        let count = InstructionDecoder::decode_16bit_value(instruction) as usize;
+        let count = self.decode_immediate(instruction) as usize;
         let new_len = self.registers.len() + count;
 
         self.registers.resize(new_len, 0);
@@ -36,7 +82,7 @@ impl VM {
 
     #[inline(always)]
     pub(crate) fn deallocate_registers(&mut self, instruction: Instruction) {
-        let count = InstructionDecoder::decode_16bit_value(instruction) as usize;
+        let count = self.decode_immediate(instruction) as usize;
 
         let new_len = self.registers.len() - count;
         self.registers.resize(new_len, 0);
@@ -48,4 +94,42 @@ impl VM {
 
         self.set_register(destination, value);
     }
+
+    #[inline(always)]
+    pub(crate) fn copy_register(&mut self, instruction: Instruction) {
+        let destination = self.decode_destination(instruction);
+        let source = self.decode_source_1(instruction);
+
+        self.move_register(destination, source);
+    }
+
+    #[inline(always)]
+    pub(crate) fn decode_int_operands(
+        &mut self,
+        instruction: Instruction,
+    ) -> (Instruction, i64, i64) {
+        let destination = self.decode_destination(instruction);
+        let source1 = self.decode_source_1(instruction);
+        let source2 = self.decode_source_2(instruction);
+
+        let first = self.get_register(source1) as i64;
+        let second = self.get_register(source2) as i64;
+
+        (destination, first, second)
+    }
+
+    #[inline(always)]
+    pub(crate) fn decode_float_operands(
+        &mut self,
+        instruction: Instruction,
+    ) -> (Instruction, f64, f64) {
+        let destination = self.decode_destination(instruction);
+        let source1 = self.decode_source_1(instruction);
+        let source2 = self.decode_source_2(instruction);
+
+        let first = f64::from_bits(self.get_register(source1));
+        let second = f64::from_bits(self.get_register(source2));
+
+        (destination, first, second)
+    }
 }
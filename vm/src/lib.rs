@@ -1,15 +1,29 @@
+mod assembler;
+#[cfg(feature = "match-loop")]
+mod baseline;
+mod debug_info;
+mod debugger;
 mod frame;
 mod handlers;
-mod opcodes;
+mod profile;
 mod program;
 mod register;
+mod trace;
+mod verifier;
 
-use errors::KirinError;
-use instructions::{Instruction, InstructionDecoder};
-use opcodes::*;
+use errors::{KirinError, SpannedError, codes};
+use instructions::{Disassembler, Instruction, InstructionDecoder, OpCode};
+use std::io::Write;
 
-use crate::frame::Frame;
-pub use program::{Program, ProgramConstant, ProgramMetadata};
+use crate::profile::Profiler;
+use crate::trace::{Snapshot, Tracer};
+use crate::verifier::Verifier;
+pub use assembler::assemble;
+pub use debug_info::{DebugEntry, DebugInfo, DebugVariable, SourceLocation};
+pub use debugger::{Breakpoint, Debugger, Prompt, Stop};
+pub use frame::Frame;
+pub use profile::{FunctionSamples, LineSamples, Profile};
+pub use program::{FORMAT_VERSION, PROGRAM_MAGIC, Program, ProgramConstant, ProgramMetadata};
 pub use register::Register;
 
 #[repr(u8)]
@@ -22,13 +36,32 @@ enum VmStatus {
 pub struct VM {
     instructions: Vec<Instruction>,
     constants: Vec<ProgramConstant>,
+    debug_info: DebugInfo,
     registers: Vec<Register>,
     frames: Vec<Frame>,
     instruction_pointer: usize,
+    #[allow(dead_code)] // reserved for function return values
     return_register: Register,
     register_offset: usize,
+    /// `Extend` prefix of the instruction being executed
+    extension: Option<Instruction>,
     status: VmStatus,
-    error: Option<String>,
+    /// set with the status to stop the dispatch loop
+    signaled: bool,
+    /// code and message of the error that stopped execution
+    error: Option<(&'static str, String)>,
+    /// output of the print instructions, written to stdout unless captured
+    output: Option<String>,
+    /// set when a tracer or profiler observes the executed instructions
+    observed: bool,
+    tracer: Option<Tracer>,
+    profiler: Option<Profiler>,
+}
+
+impl Default for VM {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl VM {
@@ -36,35 +69,103 @@ impl VM {
         Self {
             instructions: Vec::new(),
             constants: Vec::new(),
+            debug_info: DebugInfo::new(),
             registers: Vec::new(),
             instruction_pointer: 0,
             return_register: 0,
             status: VmStatus::Halted,
+            signaled: false,
             error: None,
             register_offset: 0,
+            extension: None,
             frames: Vec::new(),
+            output: None,
+            observed: false,
+            tracer: None,
+            profiler: None,
         }
     }
 
+    /// Collect the output of print instructions instead of writing it to stdout
+    pub fn capture_output(&mut self) {
+        self.output = Some(String::new());
+    }
+
+    /// Output collected since the last call, empty unless output is captured
+    pub fn take_output(&mut self) -> String {
+        self.output.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    /// Write each executed instruction and the registers it changed to
+    /// `writer`
+    pub fn trace(&mut self, writer: impl Write + 'static) {
+        self.tracer = Some(Tracer::new(Box::new(writer)));
+        self.observed = true;
+    }
+
+    /// Sample every `interval`-th executed instruction, see `take_profile`
+    pub fn profile(&mut self, interval: usize) {
+        self.profiler = Some(Profiler::new(interval));
+        self.observed = true;
+    }
+
+    /// The samples taken since `profile` was called, which ends profiling
+    pub fn take_profile(&mut self) -> Option<Profile> {
+        let profiler = self.profiler.take()?;
+        self.observed = self.tracer.is_some();
+
+        Some(profiler.finish(&self.debug_info))
+    }
+
     pub fn load_program(&mut self, program: Program) -> Result<(), KirinError> {
-        if program.instructions.len() == 0 {
+        if program.instructions.is_empty() {
             return Ok(());
         }
 
-        let last_instruction = program.instructions[program.instructions.len() - 1];
-        if InstructionDecoder::decode_opcode(last_instruction) != OP_HALT {
-            return Err(KirinError::General(
+        Self::check_halt(&program)?;
+        Verifier::new(&program, self.instructions.len(), self.constants.len()).verify()?;
+        self.append_program(program);
+
+        Ok(())
+    }
+
+    /// Run a program that continues the top level frame of the programs run
+    /// before it, e.g. the next input of a REPL. Registers keep their values
+    /// between programs, each one only allocates the registers it adds and
+    /// returns without releasing them.
+    pub fn resume(&mut self, program: Program) -> Result<(), KirinError> {
+        let start = self.instructions.len();
+
+        Self::check_halt(&program)?;
+        Verifier::new(&program, start, self.constants.len())
+            .resuming(self.registers.len())
+            .verify()?;
+        self.append_program(program);
+
+        self.instruction_pointer = start;
+        self.start_with_offset(0)
+    }
+
+    fn check_halt(program: &Program) -> Result<(), KirinError> {
+        match program.instructions.last() {
+            Some(&instruction)
+                if InstructionDecoder::decode_opcode(instruction) == OpCode::Halt as u8 =>
+            {
+                Ok(())
+            }
+            _ => Err(KirinError::General(
                 "program does not end with halt instruction".to_string(),
-            ));
+            )),
         }
+    }
 
-        let mut instructions = program.instructions.clone();
-        let mut constants = program.constants.clone();
-
-        self.instructions.append(&mut instructions);
-        self.constants.append(&mut constants);
+    fn append_program(&mut self, mut program: Program) {
+        let offset = self.instructions.len();
+        self.debug_info.push_unknown(offset);
+        self.debug_info.append(&program.debug_info, offset);
 
-        Ok(())
+        self.instructions.append(&mut program.instructions);
+        self.constants.append(&mut program.constants);
     }
 
     pub fn start_with_offset(&mut self, offset: usize) -> Result<(), KirinError> {
@@ -76,84 +177,594 @@ impl VM {
         Ok(())
     }
 
+    /// The dispatch loop. Handlers report a change of status through `signal`,
+    /// so the loop only fetches and dispatches until one of them does.
     fn start_execution(&mut self) -> Result<(), KirinError> {
-        loop {
-            match self.status {
-                VmStatus::Running => {
-                    let instruction = self.get_next_instruction();
-                    self.execute_instruction(instruction);
-                }
+        if self.instruction_pointer >= self.instructions.len() {
+            return Err(KirinError::General(format!(
+                "cannot start execution at instruction {} of {}",
+                self.instruction_pointer,
+                self.instructions.len()
+            )));
+        }
 
-                VmStatus::Halted => {
-                    break;
-                }
+        while !self.signaled {
+            let instruction = self.get_next_instruction();
+            self.execute_instruction(instruction);
+        }
+        self.signaled = false;
+
+        if let Some(tracer) = &mut self.tracer {
+            tracer.flush();
+        }
+
+        match self.status {
+            VmStatus::Error => Err(self.failure()),
+            VmStatus::Running | VmStatus::Halted => Ok(()),
+        }
+    }
 
-                VmStatus::Error => {
-                    let error = self
-                        .error
-                        .clone()
-                        .unwrap_or("error flag was set".to_string());
-                    return Err(KirinError::General(error));
+    /// Execute only the next instruction, with its prefix if it has one.
+    /// `Ok(false)` once the program has returned from its top level.
+    pub(crate) fn step(&mut self) -> Result<bool, KirinError> {
+        let instruction = self.get_next_instruction();
+        self.execute_instruction(instruction);
+
+        if !self.signaled {
+            return Ok(true);
+        }
+        self.signaled = false;
+
+        match self.status {
+            VmStatus::Error => Err(self.failure()),
+            VmStatus::Running => Ok(true),
+            VmStatus::Halted => Ok(false),
+        }
+    }
+
+    /// The error of a failed execution
+    fn failure(&self) -> KirinError {
+        let (code, message) = self
+            .error
+            .clone()
+            .unwrap_or((codes::RUNTIME, "error flag was set".to_string()));
+
+        self.runtime_failure(code, message)
+    }
+
+    /// Build the error for a failed execution, located at the faulting
+    /// instruction and followed by the call sites of the active frames
+    fn runtime_failure(&self, code: &'static str, message: String) -> KirinError {
+        // the instruction pointer has already moved past the faulting instruction
+        let address = self.instruction_pointer.saturating_sub(1);
+
+        let call_sites = self
+            .frames
+            .iter()
+            .rev()
+            .filter_map(|frame| frame.return_address)
+            .map(|return_address| return_address.saturating_sub(1));
+        let trace = std::iter::once(address)
+            .chain(call_sites)
+            .collect::<Vec<_>>();
+
+        let mut message = message;
+        if trace.len() > 1 {
+            message.push_str("\nstack trace:");
+            for (depth, address) in trace.iter().enumerate() {
+                match self.debug_info.lookup(*address) {
+                    Some(location) => message.push_str(&format!("\n    {}: {}", depth, location)),
+                    None => {
+                        message.push_str(&format!("\n    {}: <instruction {}>", depth, address))
+                    }
                 }
             }
         }
 
-        Ok(())
+        let location = self.debug_info.lookup(address);
+        let (line, column) = location
+            .map(|location| (location.line, location.column))
+            .unwrap_or((0, 0));
+        let file = location.and_then(|location| location.file.map(str::to_string));
+
+        KirinError::Runtime(
+            SpannedError::new(message, line, column)
+                .with_file(file)
+                .with_code(code),
+        )
+    }
+
+    /// Stop execution and record the reason, surfaced by `start_execution`
+    pub(crate) fn runtime_error(&mut self, code: &'static str, message: String) {
+        self.error = Some((code, message));
+        self.signal(VmStatus::Error);
+    }
+
+    /// Change the status and interrupt the dispatch loop to act on it
+    pub(crate) fn signal(&mut self, status: VmStatus) {
+        self.status = status;
+        self.signaled = true;
     }
 
+    #[inline(always)]
+    fn get_next_instruction(&mut self) -> Instruction {
+        // verified programs cannot leave their instructions: every program
+        // ends with `Halt`, jump and call targets are in bounds and an
+        // `Extend` prefix is always followed by its instruction. The fetch is
+        // still bounds checked, like the registers, so a gap in the verifier
+        // panics instead of reading past the instructions.
+        let instruction = self.instructions[self.instruction_pointer];
+        self.instruction_pointer += 1;
+
+        instruction
+    }
+
+    #[inline(always)]
     fn execute_instruction(&mut self, instruction: Instruction) {
-        let opcode = InstructionDecoder::decode_opcode(instruction);
-
-        match opcode {
-            OP_NONE => {}
-
-            // Loading
-            OP_LOAD_INT16 => self.load_int16(instruction),
-
-            // Arithmetic
-            OP_ADD_INT => self.add_int(instruction),
-            OP_SUB_INT => self.sub_int(instruction),
-            OP_MUL_INT => self.mul_int(instruction),
-            OP_DIV_INT => self.div_int(instruction),
-            OP_MOD_INT => self.mod_int(instruction),
-            OP_POW_INT => self.pow_int(instruction),
-            OP_ADD_FLOAT => self.add_float(instruction),
-            OP_SUB_FLOAT => self.sub_float(instruction),
-            OP_MUL_FLOAT => self.mul_float(instruction),
-            OP_DIV_FLOAT => self.div_float(instruction),
-            OP_MOD_FLOAT => self.mod_float(instruction),
-            OP_POW_FLOAT => self.pow_float(instruction),
-
-            // Allocations
-            OP_ALLOC_REG => self.allocate_registers(instruction),
-            OP_DEALLOC_REG => self.deallocate_registers(instruction),
-
-            // Casting
-            OP_INT_TO_ANY => self.cast_int_to_any(instruction),
-            OP_FLOAT_TO_ANY => self.cast_float_to_any(instruction),
-            OP_INT_TO_FLOAT => self.cast_int_to_float(instruction),
-            OP_FLOAT_TO_INT => self.cast_float_to_int(instruction),
-
-            // Control flow
-            OP_RETURN => self.do_return(instruction),
-            OP_HALT => {
-                self.status = VmStatus::Error;
-                self.error = Some("halt instruction encountered".to_string())
+        if self.observed {
+            return self.execute_observed(instruction);
+        }
+
+        HANDLERS[InstructionDecoder::decode_opcode(instruction) as usize](self, instruction)
+    }
+
+    /// Execute an instruction for the tracer and profiler, an `Extend`
+    /// prefix is observed together with its instruction
+    #[inline(never)]
+    fn execute_observed(&mut self, instruction: Instruction) {
+        let handler = HANDLERS[InstructionDecoder::decode_opcode(instruction) as usize];
+        if self.extension.is_some() {
+            return handler(self, instruction);
+        }
+
+        let address = self.instruction_pointer - 1;
+        let (extension, executed) = match InstructionDecoder::decode_known_opcode(instruction) {
+            Some(OpCode::Extend) => (Some(instruction), self.instructions[address + 1]),
+            _ => (None, instruction),
+        };
+
+        if let Some(profiler) = &mut self.profiler {
+            profiler.record(address, InstructionDecoder::decode_opcode(executed));
+        }
+        let before = self.tracer.as_ref().map(|_| Snapshot {
+            registers: self.registers.clone(),
+            register_offset: self.register_offset,
+        });
+
+        handler(self, instruction);
+
+        if let Some(profiler) = &mut self.profiler {
+            profiler.follow_frames(self.frames.len(), self.instruction_pointer);
+        }
+        if let (Some(tracer), Some(before)) = (&mut self.tracer, before) {
+            tracer.record(
+                address,
+                &Disassembler::extended_instruction(extension, executed),
+                &self.debug_info,
+                &before,
+                &self.registers,
+            );
+        }
+    }
+
+    /// Handler of the opcodes missing from the opcode table
+    fn unknown_instruction(&mut self, instruction: Instruction) {
+        self.runtime_error(
+            codes::RUNTIME,
+            format!(
+                "unknown instruction encountered: {:?}",
+                InstructionDecoder::decode_opcode(instruction)
+            ),
+        )
+    }
+}
+
+/// Executes an instruction, handlers are `VM` methods
+type Handler = fn(&mut VM, Instruction);
+
+/// Generates the handler table from the opcode table
+macro_rules! dispatch {
+    ($($name:ident = $mnemonic:literal, $handler:ident, [$($operands:tt)*];)*) => {
+        /// Handler of every opcode, indexed by its encoded value
+        const HANDLERS: [Handler; 256] = {
+            let mut handlers = [VM::unknown_instruction as Handler; 256];
+            $(handlers[OpCode::$name as usize] = VM::$handler;)*
+            handlers
+        };
+    };
+}
+
+instructions::opcode_table!(dispatch);
+
+#[cfg(test)]
+mod vm_tests {
+    use crate::{DebugInfo, Program, ProgramConstant, VM, VmStatus};
+    use errors::KirinError;
+    use instructions::{Instruction, InstructionBuilder, InstructionDecoder, OpCode};
+    use types::KirinType;
+
+    /// Run the instructions in a register window that is released at the end,
+    /// stopping before the release so the registers can be inspected
+    fn run(
+        mut instructions: Vec<Instruction>,
+        constants: Vec<ProgramConstant>,
+    ) -> (VM, Result<(), KirinError>) {
+        let allocated = instructions
+            .iter()
+            .filter(|&&instruction| {
+                InstructionDecoder::decode_opcode(instruction) == OpCode::AllocReg as u8
+            })
+            .map(|&instruction| InstructionDecoder::decode_16bit_value(instruction))
+            .sum();
+
+        let end = instructions.len();
+        instructions.push(InstructionBuilder::deallocate_registers(allocated));
+        instructions.push(InstructionBuilder::simple(OpCode::Return));
+        instructions.push(InstructionBuilder::simple(OpCode::Halt));
+
+        let mut vm = VM::new();
+        vm.load_program(Program::new(instructions, constants))
+            .unwrap();
+
+        vm.status = VmStatus::Running;
+        while vm.instruction_pointer < end && matches!(vm.status, VmStatus::Running) {
+            let instruction = vm.get_next_instruction();
+            vm.execute_instruction(instruction);
+        }
+
+        let result = match vm.status {
+            VmStatus::Error => {
+                let (code, message) = vm.error.clone().unwrap_or_default();
+                Err(vm.runtime_failure(code, message))
             }
+            _ => Ok(()),
+        };
+
+        (vm, result)
+    }
+
+    #[test]
+    fn test_extended_operands() {
+        let mut instructions = vec![InstructionBuilder::allocate_registers(300)];
+        instructions.extend(InstructionBuilder::extended(
+            OpCode::LoadInt16,
+            &[299, -100_000i32 as Instruction],
+        ));
+        instructions.extend(InstructionBuilder::extended(
+            OpCode::LoadConst,
+            &[280, 70_000],
+        ));
+        instructions.extend(InstructionBuilder::extended(
+            OpCode::AddInt,
+            &[290, 299, 280],
+        ));
+
+        let constants = (0..=70_000).map(ProgramConstant::Int32).collect();
+        let (vm, result) = run(instructions, constants);
+
+        assert!(result.is_ok());
+        assert_eq!(vm.registers[299] as i64, -100_000);
+        assert_eq!(vm.registers[280] as i64, 70_000);
+        assert_eq!(vm.registers[290] as i64, -30_000);
+    }
+
+    #[test]
+    fn test_dynamic_arithmetic_promotes_to_float() {
+        let (vm, result) = run(
+            vec![
+                InstructionBuilder::allocate_registers(8),
+                InstructionBuilder::load_16bit_int(0, 7),
+                InstructionBuilder::cast(OpCode::IntToAny, 2, 0),
+                InstructionBuilder::load_constant(1, 0),
+                InstructionBuilder::cast(OpCode::FloatToAny, 4, 1),
+                InstructionBuilder::binary_operation(OpCode::MulAny, 6, 2, 4),
+            ],
+            vec![ProgramConstant::Float(0.5)],
+        );
+
+        assert!(result.is_ok());
+        assert_eq!(vm.registers[6], KirinType::Float as u64);
+        assert_eq!(f64::from_bits(vm.registers[7]), 3.5);
+    }
+
+    #[test]
+    fn test_dynamic_arithmetic_keeps_int() {
+        let (vm, result) = run(
+            vec![
+                InstructionBuilder::allocate_registers(6),
+                InstructionBuilder::load_16bit_int(0, 7),
+                InstructionBuilder::cast(OpCode::IntToAny, 2, 0),
+                InstructionBuilder::binary_operation(OpCode::SubAny, 4, 2, 2),
+            ],
+            Vec::new(),
+        );
+
+        assert!(result.is_ok());
+        assert_eq!(vm.registers[4], KirinType::Int as u64);
+        assert_eq!(vm.registers[5] as i64, 0);
+    }
+
+    #[test]
+    fn test_dynamic_arithmetic_rejects_strings() {
+        let (_, result) = run(
+            vec![
+                InstructionBuilder::allocate_registers(6),
+                InstructionBuilder::load_constant(0, 0),
+                InstructionBuilder::cast(OpCode::StringToAny, 2, 0),
+                InstructionBuilder::binary_operation(OpCode::AddAny, 4, 2, 2),
+            ],
+            vec![ProgramConstant::String("text".to_string())],
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_checked_downcast() {
+        let (vm, result) = run(
+            vec![
+                InstructionBuilder::allocate_registers(4),
+                InstructionBuilder::load_16bit_int(0, -12),
+                InstructionBuilder::cast(OpCode::IntToAny, 1, 0),
+                InstructionBuilder::cast(OpCode::AnyToInt, 3, 1),
+            ],
+            Vec::new(),
+        );
+
+        assert!(result.is_ok());
+        assert_eq!(vm.registers[3] as i64, -12);
+
+        let (_, result) = run(
+            vec![
+                InstructionBuilder::allocate_registers(4),
+                InstructionBuilder::load_16bit_int(0, -12),
+                InstructionBuilder::cast(OpCode::IntToAny, 1, 0),
+                InstructionBuilder::cast(OpCode::AnyToFloat, 3, 1),
+            ],
+            Vec::new(),
+        );
+
+        assert!(result.is_err());
+    }
 
-            // Library
-            OP_PRINT_ANY => self.print_any(instruction),
-            OP_PRINT_CHAR => self.print_char(instruction),
+    #[test]
+    fn test_type_tags() {
+        let (vm, result) = run(
+            vec![
+                InstructionBuilder::allocate_registers(6),
+                InstructionBuilder::load_null(0),
+                InstructionBuilder::type_of(2, 0),
+                InstructionBuilder::is_type(3, 0, KirinType::Null as u32),
+                InstructionBuilder::is_type(4, 0, KirinType::Int as u32),
+            ],
+            Vec::new(),
+        );
 
-            _ => {
-                self.status = VmStatus::Error;
-                self.error = Some(format!("unknown instruction encountered: {:?}", opcode));
+        assert!(result.is_ok());
+        assert_eq!(vm.registers[2], KirinType::Null as u64);
+        assert_eq!(vm.registers[3], 1);
+        assert_eq!(vm.registers[4], 0);
+    }
+
+    #[test]
+    fn test_bool_operations() {
+        let (vm, result) = run(
+            vec![
+                InstructionBuilder::allocate_registers(9),
+                InstructionBuilder::load_16bit_int(0, -3),
+                InstructionBuilder::load_16bit_int(1, 2),
+                InstructionBuilder::binary_operation(OpCode::LessInt, 2, 0, 1),
+                InstructionBuilder::cast(OpCode::Not, 3, 2),
+                InstructionBuilder::load_bool(4, true),
+                InstructionBuilder::binary_operation(OpCode::And, 5, 3, 4),
+                InstructionBuilder::cast(OpCode::BoolToAny, 6, 2),
+                InstructionBuilder::cast(OpCode::AnyToBool, 8, 6),
+            ],
+            Vec::new(),
+        );
+
+        assert!(result.is_ok());
+        assert_eq!(vm.registers[2], 1);
+        assert_eq!(vm.registers[3], 0);
+        assert_eq!(vm.registers[5], 0);
+        assert_eq!(vm.registers[6], KirinType::Bool as u64);
+        assert_eq!(vm.registers[8], 1);
+    }
+
+    #[test]
+    fn test_checked_integer_arithmetic() {
+        let faulting = [
+            (OpCode::DivInt, 5, 0),
+            (OpCode::ModInt, 5, 0),
+            (OpCode::PowInt, 2, -1),
+            (OpCode::PowInt, 2, 64),
+            (OpCode::MulInt, i64::MAX, 2),
+            (OpCode::AddInt, i64::MAX, 1),
+            (OpCode::SubInt, i64::MIN, 1),
+            (OpCode::DivInt, i64::MIN, -1),
+        ];
+
+        for (opcode, first, second) in faulting {
+            let (_, result) = run(
+                vec![
+                    InstructionBuilder::allocate_registers(3),
+                    InstructionBuilder::load_constant(0, 0),
+                    InstructionBuilder::load_constant(1, 1),
+                    InstructionBuilder::binary_operation(opcode, 2, 0, 1),
+                ],
+                vec![
+                    ProgramConstant::Int64(first),
+                    ProgramConstant::Int64(second),
+                ],
+            );
+
+            assert!(
+                matches!(result, Err(KirinError::Runtime(_))),
+                "{:?} {} {}",
+                opcode,
+                first,
+                second
+            );
+        }
+    }
+
+    #[test]
+    fn test_wrapping_integer_arithmetic() {
+        let (vm, result) = run(
+            vec![
+                InstructionBuilder::allocate_registers(3),
+                InstructionBuilder::load_16bit_int(0, 2),
+                InstructionBuilder::load_16bit_int(1, 64),
+                InstructionBuilder::binary_operation(OpCode::WrappingPowInt, 2, 0, 1),
+                InstructionBuilder::binary_operation(OpCode::WrappingSubInt, 2, 2, 0),
+            ],
+            Vec::new(),
+        );
+
+        assert!(result.is_ok());
+        assert_eq!(vm.registers[2] as i64, -2);
+
+        // exponents beyond `u32::MAX` wrap too
+        let (vm, result) = run(
+            vec![
+                InstructionBuilder::allocate_registers(4),
+                InstructionBuilder::load_16bit_int(0, 3),
+                InstructionBuilder::load_constant(1, 0),
+                InstructionBuilder::binary_operation(OpCode::WrappingPowInt, 2, 0, 1),
+                InstructionBuilder::load_16bit_int(0, -1),
+                InstructionBuilder::load_constant(1, 1),
+                InstructionBuilder::binary_operation(OpCode::WrappingPowInt, 3, 0, 1),
+            ],
+            vec![
+                ProgramConstant::Int64(4_294_967_296),
+                ProgramConstant::Int64(1_099_511_627_781),
+            ],
+        );
+
+        assert!(result.is_ok());
+        assert_eq!(vm.registers[2] as i64, 2_491_309_678_558_969_857);
+        assert_eq!(vm.registers[3] as i64, -1);
+    }
+
+    #[test]
+    fn test_runtime_error_stack_trace() {
+        let instructions = vec![
+            InstructionBuilder::allocate_registers(1),
+            InstructionBuilder::call(5),
+            InstructionBuilder::deallocate_registers(1),
+            InstructionBuilder::simple(OpCode::Return),
+            InstructionBuilder::simple(OpCode::Halt),
+            // function body
+            InstructionBuilder::allocate_registers(2),
+            InstructionBuilder::load_16bit_int(0, 1),
+            InstructionBuilder::load_16bit_int(1, 0),
+            InstructionBuilder::binary_operation(OpCode::DivInt, 0, 0, 1),
+            InstructionBuilder::deallocate_registers(2),
+            InstructionBuilder::simple(OpCode::Return),
+            InstructionBuilder::simple(OpCode::Halt),
+        ];
+
+        let mut debug_info = DebugInfo::new();
+        debug_info.push(1, Some("main.kn"), 10, 1);
+        debug_info.push_unknown(2);
+        debug_info.push(8, Some("lib.kn"), 3, 7);
+        debug_info.push_unknown(9);
+
+        let program = Program::new(instructions, Vec::new()).with_debug_info(debug_info);
+
+        let mut vm = VM::new();
+        vm.load_program(program).unwrap();
+
+        match vm.start_with_offset(0) {
+            Err(KirinError::Runtime(error)) => {
+                assert_eq!((error.line, error.column), (3, 7));
+                assert!(error.message.contains("0: lib.kn:3:7"));
+                assert!(error.message.contains("1: main.kn:10:1"));
             }
+            result => panic!("expected runtime error, got {:?}", result),
         }
     }
 
-    fn get_next_instruction(&mut self) -> Instruction {
-        self.instruction_pointer += 1;
-        self.instructions[self.instruction_pointer - 1]
+    #[test]
+    fn test_resumed_programs_keep_registers() {
+        let first = vec![
+            InstructionBuilder::allocate_registers(1),
+            InstructionBuilder::load_16bit_int(0, 21),
+            InstructionBuilder::simple(OpCode::Return),
+            InstructionBuilder::simple(OpCode::Halt),
+        ];
+        let second = vec![
+            InstructionBuilder::allocate_registers(1),
+            InstructionBuilder::add_int(1, 0, 0),
+            InstructionBuilder::simple(OpCode::Return),
+            InstructionBuilder::simple(OpCode::Halt),
+        ];
+
+        let mut vm = VM::new();
+        vm.resume(Program::new(first, Vec::new())).unwrap();
+        vm.resume(Program::new(second.clone(), Vec::new())).unwrap();
+
+        assert_eq!(vm.registers, [21, 42]);
+
+        // a program that is not resumed cannot use registers it did not allocate
+        assert!(
+            VM::new()
+                .load_program(Program::new(second, Vec::new()))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_jumps_and_captured_output() {
+        let instructions = vec![
+            InstructionBuilder::allocate_registers(5),
+            InstructionBuilder::load_16bit_int(0, 0),
+            InstructionBuilder::load_16bit_int(1, 3),
+            InstructionBuilder::load_16bit_int(2, 1),
+            // loop body
+            InstructionBuilder::cast(OpCode::IntToAny, 3, 0),
+            InstructionBuilder::print_any(3),
+            InstructionBuilder::binary_operation(OpCode::AddInt, 0, 0, 2),
+            InstructionBuilder::binary_operation(OpCode::LessInt, 2, 0, 1),
+            InstructionBuilder::jump_if_false(2, 11),
+            InstructionBuilder::load_16bit_int(2, 1),
+            InstructionBuilder::jump(4),
+            // loop exit
+            InstructionBuilder::deallocate_registers(5),
+            InstructionBuilder::simple(OpCode::Return),
+            InstructionBuilder::simple(OpCode::Halt),
+        ];
+
+        let mut vm = VM::new();
+        vm.capture_output();
+        vm.load_program(Program::new(instructions, Vec::new()))
+            .unwrap();
+
+        assert!(vm.start_with_offset(0).is_ok());
+        assert_eq!(vm.take_output(), "012");
+        assert_eq!(vm.take_output(), "");
+    }
+
+    #[test]
+    fn test_start_outside_of_instructions() {
+        let mut vm = VM::new();
+
+        assert!(matches!(
+            vm.start_with_offset(0),
+            Err(KirinError::General(_))
+        ));
+    }
+
+    #[test]
+    fn test_format_any() {
+        let mut vm = VM::new();
+        vm.constants
+            .push(ProgramConstant::String("kirin".to_string()));
+
+        assert_eq!(vm.format_any(KirinType::Bool as u64, 1), "true");
+        assert_eq!(vm.format_any(KirinType::Bool as u64, 0), "false");
+        assert_eq!(vm.format_any(KirinType::Null as u64, 0), "none");
+        assert_eq!(vm.format_any(KirinType::String as u64, 0), "kirin");
+        assert_eq!(vm.format_any(KirinType::Int as u64, -4i64 as u64), "-4");
     }
 }
@@ -1,30 +1,134 @@
-use instructions::{InstructionBuilder, OpCode};
-use vm::{Program, VM};
+use errors::{ErrorFormat, KirinError, use_color};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use vm::{Debugger, Program, Prompt, VM};
 
 fn main() {
-    let program = get_program();
+    let args = std::env::args().collect::<Vec<String>>();
+
+    let format = args
+        .iter()
+        .rev()
+        .find_map(|arg| ErrorFormat::from_flag(arg))
+        .unwrap_or_default();
+
+    let path = args.get(1).filter(|arg| !arg.starts_with("--"));
+    let program = match path {
+        Some(path) => match read_program(path) {
+            Ok(program) => program,
+            Err(error) => report(error, format),
+        },
+        None => get_program(),
+    };
+
+    if args.iter().any(|arg| arg == "--debug") {
+        debug(program, path.map(String::as_str), format);
+    }
+
+    let interval = args.iter().find_map(|arg| match arg.as_str() {
+        "--profile" => Some(1),
+        arg => arg.strip_prefix("--profile=")?.parse::<usize>().ok(),
+    });
+    let folded = args.iter().find_map(|arg| arg.strip_prefix("--folded="));
+    let source = source(&program, path.map(String::as_str));
+
     let mut vm = VM::new();
 
-    vm.load_program(program).unwrap();
-    vm.start_with_offset(0).unwrap();
+    if args.iter().any(|arg| arg == "--trace") {
+        vm.trace(BufWriter::new(std::io::stderr()));
+    }
+    if interval.is_some() || folded.is_some() {
+        vm.profile(interval.unwrap_or(1));
+    }
+
+    if let Err(error) = vm.load_program(program) {
+        report(error, format);
+    }
+
+    let result = vm.start_with_offset(0);
+
+    // also for failed runs, which stop at the error
+    if let Some(profile) = vm.take_profile() {
+        eprint!("{}", profile.report(source.as_deref()));
+
+        if let Some(folded) = folded
+            && let Err(error) = std::fs::write(folded, profile.folded())
+        {
+            eprintln!("failed to write {}: {}", folded, error);
+        }
+    }
+
+    if let Err(error) = result {
+        report(error, format);
+    }
+}
+
+/// The source the debug info of the program points into, or the assembly
+/// it was read from
+fn source(program: &Program, path: Option<&str>) -> Option<String> {
+    program
+        .debug_info
+        .files()
+        .first()
+        .map(String::as_str)
+        .or(path.filter(|path| path.ends_with(".kasm")))
+        .and_then(|file| std::fs::read_to_string(file).ok())
+}
+
+/// Run the program under the debugger prompt, showing the lines of its
+/// source
+fn debug(program: Program, path: Option<&str>, format: ErrorFormat) -> ! {
+    let source = source(&program, path);
+
+    match Debugger::new(program) {
+        Ok(debugger) => Prompt::new(debugger, source).run(),
+        Err(error) => report(error, format),
+    }
+    std::process::exit(0);
+}
+
+/// Show the error, with a snippet when the file it points into can still be
+/// read, or as JSON, and exit
+fn report(error: KirinError, format: ErrorFormat) -> ! {
+    let source = error
+        .diagnostic()
+        .file
+        .and_then(|file| std::fs::read_to_string(file).ok());
+
+    eprint!(
+        "{}",
+        error.render_as(format, source.as_deref(), use_color(&std::io::stderr()))
+    );
+    std::process::exit(1);
+}
+
+fn read_program(path: &str) -> Result<Program, KirinError> {
+    let file = File::open(path)
+        .map_err(|error| KirinError::General(format!("failed to open {}: {}", path, error)))?;
+
+    if path.ends_with(".kasm") {
+        let source = std::io::read_to_string(file)
+            .map_err(|error| KirinError::General(format!("failed to read {}: {}", path, error)))?;
+
+        return vm::assemble(&source).map_err(|error| error.in_file(path));
+    }
+
+    Program::read_from(&mut BufReader::new(file))
 }
 
 fn get_program() -> Program {
-    let instructions = vec![
-        InstructionBuilder::new()
-            .set_opcode(OpCode::AllocReg)
-            .set_16bit_value(4)
-            .build(),
-        InstructionBuilder::load_16bit_int(0, -2800),
-        InstructionBuilder::load_16bit_int(1, 480),
-        InstructionBuilder::binary_operation(OpCode::AddInt, 0, 0, 1),
-        InstructionBuilder::cast(OpCode::IntToAny, 2, 0),
-        InstructionBuilder::print_any(2),
-        InstructionBuilder::print_char('\n'),
-        InstructionBuilder::deallocate_registers(4),
-        InstructionBuilder::simple(OpCode::Return),
-        InstructionBuilder::simple(OpCode::Halt),
-    ];
-
-    Program::new(instructions, Vec::new())
+    let source = "\
+        ALLOC_REG 4
+        LOAD_INT16 r0, -2800
+        LOAD_INT16 r1, 480
+        ADD_INT r0, r0, r1
+        INT_TO_ANY r2, r0
+        PRINT_ANY r2
+        PRINT_CHAR '\\n'
+        DEALLOC_REG 4
+        RETURN
+        HALT
+    ";
+
+    vm::assemble(source).expect("the demo program is valid assembly")
 }
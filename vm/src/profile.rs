@@ -0,0 +1,331 @@
+use crate::DebugInfo;
+use instructions::OpCode;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Lines of the source shown by the report
+const REPORT_LINES: usize = 20;
+
+/// Samples every `interval`-th executed instruction, an interval of 1
+/// counts all of them
+pub(crate) struct Profiler {
+    interval: usize,
+    /// instructions until the next sample
+    countdown: usize,
+    executed: u64,
+    /// entry addresses of the functions of the open frames
+    stack: Vec<usize>,
+    /// indexed by the encoded opcode
+    opcodes: [u64; 256],
+    addresses: HashMap<usize, u64>,
+    stacks: HashMap<Vec<usize>, u64>,
+}
+
+impl Profiler {
+    pub(crate) fn new(interval: usize) -> Self {
+        let interval = interval.max(1);
+
+        Self {
+            interval,
+            countdown: interval,
+            executed: 0,
+            stack: Vec::new(),
+            opcodes: [0; 256],
+            addresses: HashMap::new(),
+            stacks: HashMap::new(),
+        }
+    }
+
+    /// Count an instruction about to run in the innermost open function
+    pub(crate) fn record(&mut self, address: usize, opcode: u8) {
+        self.executed += 1;
+        self.countdown -= 1;
+        if self.countdown > 0 {
+            return;
+        }
+        self.countdown = self.interval;
+
+        self.opcodes[opcode as usize] += 1;
+        *self.addresses.entry(address).or_default() += 1;
+        match self.stacks.get_mut(&self.stack) {
+            Some(samples) => *samples += 1,
+            None => {
+                self.stacks.insert(self.stack.clone(), 1);
+            }
+        }
+    }
+
+    /// Follow the frames opened and closed by the executed instruction, the
+    /// function of a new frame starts at the instruction pointer
+    pub(crate) fn follow_frames(&mut self, depth: usize, instruction_pointer: usize) {
+        self.stack.truncate(depth);
+        self.stack.resize(depth, instruction_pointer);
+    }
+
+    pub(crate) fn finish(self, debug_info: &DebugInfo) -> Profile {
+        let samples = self.opcodes.iter().sum();
+
+        let mut opcodes = self
+            .opcodes
+            .iter()
+            .enumerate()
+            .filter(|&(_, &count)| count > 0)
+            .filter_map(|(opcode, &count)| Some((OpCode::try_from(opcode as u8).ok()?, count)))
+            .collect::<Vec<(OpCode, u64)>>();
+        opcodes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.mnemonic().cmp(b.0.mnemonic())));
+
+        let mut lines = HashMap::<(Option<String>, usize), u64>::new();
+        for (&address, &count) in &self.addresses {
+            let location = debug_info
+                .lookup(address)
+                .map(|location| (location.file.map(str::to_string), location.line))
+                .unwrap_or((None, 0));
+
+            *lines.entry(location).or_default() += count;
+        }
+        let mut lines = lines
+            .into_iter()
+            .map(|((file, line), samples)| LineSamples {
+                file,
+                line,
+                samples,
+            })
+            .collect::<Vec<LineSamples>>();
+        lines.sort_by(|a, b| {
+            b.samples
+                .cmp(&a.samples)
+                .then(a.file.cmp(&b.file))
+                .then(a.line.cmp(&b.line))
+        });
+
+        let mut functions = HashMap::<String, FunctionSamples>::new();
+        let mut stacks = Vec::new();
+        for (stack, &count) in &self.stacks {
+            let names = std::iter::once("main".to_string())
+                .chain(stack.iter().map(|entry| format!("fn@{}", entry)))
+                .collect::<Vec<String>>();
+
+            for (depth, name) in names.iter().enumerate() {
+                let function = functions
+                    .entry(name.clone())
+                    .or_insert_with(|| FunctionSamples {
+                        name: name.clone(),
+                        own_samples: 0,
+                        total_samples: 0,
+                    });
+
+                // recursion counts a function once per sample
+                if !names[..depth].contains(name) {
+                    function.total_samples += count;
+                }
+                if depth == names.len() - 1 {
+                    function.own_samples += count;
+                }
+            }
+
+            stacks.push((names.join(";"), count));
+        }
+        stacks.sort();
+
+        let mut functions = functions.into_values().collect::<Vec<FunctionSamples>>();
+        functions.sort_by(|a, b| {
+            b.total_samples
+                .cmp(&a.total_samples)
+                .then(a.name.cmp(&b.name))
+        });
+
+        Profile {
+            interval: self.interval,
+            executed: self.executed,
+            samples,
+            opcodes,
+            functions,
+            lines,
+            stacks,
+        }
+    }
+}
+
+/// The samples of a function, the top level is `main` and the others are
+/// named by their first instruction
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionSamples {
+    pub name: String,
+    /// samples of its own instructions
+    pub own_samples: u64,
+    /// samples of its instructions and those of the functions it called
+    pub total_samples: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineSamples {
+    pub file: Option<String>,
+    /// `0` for instructions without a source location
+    pub line: usize,
+    pub samples: u64,
+}
+
+/// What a profiled run executed, see [`crate::VM::profile`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+    /// executed instructions per sample
+    pub interval: usize,
+    pub executed: u64,
+    pub samples: u64,
+    /// the most sampled first, like the other lists
+    pub opcodes: Vec<(OpCode, u64)>,
+    pub functions: Vec<FunctionSamples>,
+    pub lines: Vec<LineSamples>,
+    /// call stacks from `main` joined by `;`
+    stacks: Vec<(String, u64)>,
+}
+
+impl Profile {
+    /// One line per sampled call stack followed by its samples, the input
+    /// of flamegraph tools
+    pub fn folded(&self) -> String {
+        self.stacks
+            .iter()
+            .map(|(stack, samples)| format!("{} {}\n", stack, samples))
+            .collect()
+    }
+
+    /// The samples by opcode, function and source line. Lines of `source`
+    /// are shown next to their samples.
+    pub fn report(&self, source: Option<&str>) -> String {
+        let percent = |samples: u64| samples as f64 * 100.0 / self.samples.max(1) as f64;
+
+        let mut report = format!(
+            "executed {} instructions, {} samples of every {}\n",
+            self.executed,
+            self.samples,
+            match self.interval {
+                1 => "instruction".to_string(),
+                interval => format!("{} instructions", interval),
+            }
+        );
+
+        writeln!(report, "\n{:<20} {:>10} {:>7}", "opcode", "samples", "%").ok();
+        for (opcode, samples) in &self.opcodes {
+            writeln!(
+                report,
+                "{:<20} {:>10} {:>6.1}%",
+                opcode.mnemonic(),
+                samples,
+                percent(*samples)
+            )
+            .ok();
+        }
+
+        writeln!(report, "\n{:<20} {:>10} {:>10}", "function", "own", "total").ok();
+        for function in &self.functions {
+            writeln!(
+                report,
+                "{:<20} {:>10} {:>10}",
+                function.name, function.own_samples, function.total_samples
+            )
+            .ok();
+        }
+
+        writeln!(report, "\n{:<20} {:>10} {:>7}", "line", "samples", "%").ok();
+        for line in self.lines.iter().take(REPORT_LINES) {
+            let (location, text) = match line.line {
+                0 => ("<no location>".to_string(), None),
+                number => (
+                    format!("{}:{}", line.file.as_deref().unwrap_or("<input>"), number),
+                    source.and_then(|source| source.lines().nth(number - 1)),
+                ),
+            };
+
+            write!(
+                report,
+                "{:<20} {:>10} {:>6.1}%",
+                location,
+                line.samples,
+                percent(line.samples)
+            )
+            .ok();
+            match text {
+                Some(text) => writeln!(report, "  {}", text.trim()).ok(),
+                None => writeln!(report).ok(),
+            };
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod profile_tests {
+    use crate::{OpCode, VM, assemble};
+
+    const PROGRAM: &str = "
+        ALLOC_REG 1
+        LOAD_INT16 r0, 1
+        CALL function
+        CALL function
+        DEALLOC_REG 1
+        RETURN
+        function:
+        ALLOC_REG 1
+        LOAD_INT16 r0, 2
+        DEALLOC_REG 1
+        RETURN
+        HALT
+        ";
+
+    fn profile(interval: usize) -> crate::Profile {
+        let mut vm = VM::new();
+        vm.profile(interval);
+        vm.load_program(assemble(PROGRAM).unwrap()).unwrap();
+        vm.start_with_offset(0).unwrap();
+        vm.take_profile().unwrap()
+    }
+
+    #[test]
+    fn test_counts_every_instruction() {
+        let profile = profile(1);
+
+        assert_eq!(profile.executed, 14);
+        assert_eq!(profile.samples, 14);
+        assert_eq!(profile.opcodes[0], (OpCode::AllocReg, 3));
+        assert!(profile.opcodes.contains(&(OpCode::Call, 2)));
+        assert_eq!(profile.folded(), "main 6\nmain;fn@6 8\n");
+
+        let names = profile
+            .functions
+            .iter()
+            .map(|function| {
+                (
+                    function.name.as_str(),
+                    function.own_samples,
+                    function.total_samples,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(names, [("main", 6, 14), ("fn@6", 8, 8)]);
+
+        // the function body, which starts below its label
+        assert_eq!(profile.lines[0].line, 9);
+        assert_eq!(profile.lines[0].samples, 2);
+        assert_eq!(
+            profile.lines.iter().map(|line| line.samples).sum::<u64>(),
+            14
+        );
+    }
+
+    #[test]
+    fn test_samples_by_interval() {
+        let profile = profile(5);
+
+        assert_eq!(profile.executed, 14);
+        assert_eq!(profile.samples, 2);
+        assert_eq!(profile.folded(), "main;fn@6 2\n");
+
+        let report = profile.report(None);
+        assert!(
+            report.starts_with("executed 14 instructions, 2 samples of every 5 instructions\n")
+        );
+        assert!(report.contains("\nfn@6                          2          2\n"));
+    }
+}
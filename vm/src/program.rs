@@ -1,28 +1,27 @@
+mod disassembly;
+mod serialization;
+
+use crate::debug_info::{DebugInfo, SourceLocation};
 use instructions::Instruction;
 
+pub use serialization::PROGRAM_MAGIC;
+
+/// Version of the layout of compiled programs as `(major, minor)`, stored in
+/// them. Programs of another major version are rejected, so it changes with
+/// every change to the layout. Version 0.1 had no debug variables.
+pub const FORMAT_VERSION: (usize, usize) = (1, 0);
+
 #[derive(Debug, Clone)]
 pub struct Program {
     metadata: ProgramMetadata,
     pub instructions: Vec<Instruction>,
     pub constants: Vec<ProgramConstant>,
+    pub debug_info: DebugInfo,
 }
 
 impl Program {
     pub fn new(instructions: Vec<Instruction>, constants: Vec<ProgramConstant>) -> Self {
-        let version = env!("CARGO_PKG_VERSION");
-
-        let mut version_parts = version.split('.');
-
-        let major = version_parts
-            .next()
-            .unwrap_or("0")
-            .parse::<usize>()
-            .unwrap_or(0);
-        let minor = version_parts
-            .next()
-            .unwrap_or("0")
-            .parse::<usize>()
-            .unwrap_or(0);
+        let (major, minor) = FORMAT_VERSION;
 
         let metadata = ProgramMetadata {
             instruction_count: instructions.len(),
@@ -35,15 +34,26 @@ impl Program {
             metadata,
             instructions,
             constants,
+            debug_info: DebugInfo::new(),
         }
     }
 
+    /// Attach the source locations of the instructions, used to report runtime errors
+    pub fn with_debug_info(mut self, debug_info: DebugInfo) -> Self {
+        self.debug_info = debug_info;
+        self
+    }
+
+    pub fn location(&self, instruction_index: usize) -> Option<SourceLocation<'_>> {
+        self.debug_info.lookup(instruction_index)
+    }
+
     pub fn metadata(&self) -> ProgramMetadata {
         self.metadata
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ProgramConstant {
     Int32(i32),
     Int64(i64),
@@ -51,7 +61,7 @@ pub enum ProgramConstant {
     String(String),
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct ProgramMetadata {
     pub version_major: usize,
     pub version_minor: usize,
@@ -0,0 +1,55 @@
+use crate::program::{Program, ProgramConstant};
+use instructions::{Disassembler, DisassemblyContext};
+
+impl Program {
+    /// Render the program as assembly text. When the source code is given,
+    /// instructions are grouped under the source lines that produced them.
+    pub fn disassemble(&self, source: Option<&str>) -> String {
+        let context = ProgramContext {
+            program: self,
+            source_lines: source.map(|source| source.lines().collect()),
+        };
+
+        Disassembler::instructions(&self.instructions, &context)
+    }
+}
+
+struct ProgramContext<'a> {
+    program: &'a Program,
+    source_lines: Option<Vec<&'a str>>,
+}
+
+impl DisassemblyContext for ProgramContext<'_> {
+    fn constant(&self, index: usize) -> Option<String> {
+        let constant = match self.program.constants.get(index)? {
+            ProgramConstant::Int32(value) => value.to_string(),
+            ProgramConstant::Int64(value) => value.to_string(),
+            ProgramConstant::Float(value) => format!("{:?}", value),
+            ProgramConstant::String(value) => format!("{:?}", value),
+        };
+
+        Some(constant)
+    }
+
+    fn source_line(&self, index: usize) -> Option<String> {
+        let location = self.program.location(index)?;
+        if location.line == 0 {
+            return None;
+        }
+
+        let prefix = match location.file {
+            Some(file) => format!("{}:{}", file, location.line),
+            None => location.line.to_string(),
+        };
+
+        let text = self
+            .source_lines
+            .as_ref()
+            .and_then(|lines| lines.get(location.line - 1));
+
+        match text {
+            Some(text) => Some(format!("{} | {}", prefix, text.trim())),
+            None => Some(prefix),
+        }
+    }
+}
@@ -0,0 +1,439 @@
+//! On-disk format of compiled programs (`.knc` files), version
+//! [`FORMAT_VERSION`]. All integers are little endian.
+//!
+//! ```text
+//! magic              4 bytes  "KNC\0"
+//! version_major      u16
+//! version_minor      u16
+//! instruction_count  u32
+//! constant_count     u32
+//! instructions       instruction_count * u32
+//! constants          constant_count * (u8 tag, payload)
+//! debug files        u32 count, each (u32 length, utf-8 bytes)
+//! debug entries      u32 count, each (u32 instruction, u32 file, u32 line, u32 column)
+//! debug variables    u32 count, each (u32 length, utf-8 name, u32 register, u8 type,
+//!                    u32 instruction)
+//! checksum           u32 CRC-32 of all preceding bytes
+//! ```
+
+use crate::debug_info::{DebugEntry, DebugInfo, DebugVariable};
+use crate::program::{FORMAT_VERSION, Program, ProgramConstant, ProgramMetadata};
+use errors::KirinError;
+use std::io::{Read, Write};
+use types::KirinType;
+
+pub const PROGRAM_MAGIC: [u8; 4] = *b"KNC\0";
+
+const CONSTANT_INT32: u8 = 0;
+const CONSTANT_INT64: u8 = 1;
+const CONSTANT_FLOAT: u8 = 2;
+const CONSTANT_STRING: u8 = 3;
+
+/// Stored in place of a file index for debug entries without a file
+const NO_FILE: u32 = u32::MAX;
+
+impl Program {
+    pub fn write_to(&self, writer: &mut impl Write) -> Result<(), KirinError> {
+        let mut bytes = Vec::new();
+
+        bytes.extend_from_slice(&PROGRAM_MAGIC);
+        write_u16(&mut bytes, self.metadata.version_major, "major version")?;
+        write_u16(&mut bytes, self.metadata.version_minor, "minor version")?;
+        write_u32(&mut bytes, self.instructions.len(), "instruction count")?;
+        write_u32(&mut bytes, self.constants.len(), "constant count")?;
+
+        for instruction in &self.instructions {
+            bytes.extend_from_slice(&instruction.to_le_bytes());
+        }
+
+        for constant in &self.constants {
+            match constant {
+                ProgramConstant::Int32(value) => {
+                    bytes.push(CONSTANT_INT32);
+                    bytes.extend_from_slice(&value.to_le_bytes());
+                }
+                ProgramConstant::Int64(value) => {
+                    bytes.push(CONSTANT_INT64);
+                    bytes.extend_from_slice(&value.to_le_bytes());
+                }
+                ProgramConstant::Float(value) => {
+                    bytes.push(CONSTANT_FLOAT);
+                    bytes.extend_from_slice(&value.to_bits().to_le_bytes());
+                }
+                ProgramConstant::String(value) => {
+                    bytes.push(CONSTANT_STRING);
+                    write_string(&mut bytes, value)?;
+                }
+            }
+        }
+
+        let files = self.debug_info.files();
+        write_u32(&mut bytes, files.len(), "debug file count")?;
+        for file in files {
+            write_string(&mut bytes, file)?;
+        }
+
+        let entries = self.debug_info.entries();
+        write_u32(&mut bytes, entries.len(), "debug entry count")?;
+        for entry in entries {
+            write_u32(&mut bytes, entry.instruction, "debug instruction index")?;
+            match entry.file {
+                Some(file) => write_u32(&mut bytes, file, "debug file index")?,
+                None => bytes.extend_from_slice(&NO_FILE.to_le_bytes()),
+            }
+            write_u32(&mut bytes, entry.line, "debug line")?;
+            write_u32(&mut bytes, entry.column, "debug column")?;
+        }
+
+        let variables = self.debug_info.variables();
+        write_u32(&mut bytes, variables.len(), "debug variable count")?;
+        for variable in variables {
+            write_string(&mut bytes, &variable.name)?;
+            write_u32(&mut bytes, variable.register, "debug variable register")?;
+            bytes.push(variable.kind as u8);
+            write_u32(
+                &mut bytes,
+                variable.instruction,
+                "debug variable instruction",
+            )?;
+        }
+
+        let checksum = crc32(&bytes);
+        bytes.extend_from_slice(&checksum.to_le_bytes());
+
+        writer
+            .write_all(&bytes)
+            .map_err(|error| KirinError::General(format!("failed to write program: {}", error)))
+    }
+
+    pub fn read_from(reader: &mut impl Read) -> Result<Program, KirinError> {
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .map_err(|error| KirinError::General(format!("failed to read program: {}", error)))?;
+
+        if bytes.len() < PROGRAM_MAGIC.len() + 4 || bytes[..PROGRAM_MAGIC.len()] != PROGRAM_MAGIC {
+            return Err(KirinError::General(
+                "not a compiled kirin program: invalid magic bytes".to_string(),
+            ));
+        }
+
+        let (content, checksum) = bytes.split_at(bytes.len() - 4);
+        let expected = u32::from_le_bytes([checksum[0], checksum[1], checksum[2], checksum[3]]);
+        if crc32(content) != expected {
+            return Err(KirinError::General(
+                "compiled program is corrupted: checksum mismatch".to_string(),
+            ));
+        }
+
+        let mut reader = ByteReader::new(&content[PROGRAM_MAGIC.len()..]);
+
+        let version_major = reader.read_u16()? as usize;
+        let version_minor = reader.read_u16()? as usize;
+
+        let (current_major, current_minor) = FORMAT_VERSION;
+        if version_major != current_major {
+            return Err(KirinError::General(format!(
+                "incompatible program version {}.{}, this VM runs version {}.{}",
+                version_major, version_minor, current_major, current_minor
+            )));
+        }
+
+        let instruction_count = reader.read_u32()? as usize;
+        let constant_count = reader.read_u32()? as usize;
+
+        let mut instructions = Vec::with_capacity(instruction_count.min(reader.remaining() / 4));
+        for _ in 0..instruction_count {
+            instructions.push(reader.read_u32()?);
+        }
+
+        let mut constants = Vec::with_capacity(constant_count.min(reader.remaining()));
+        for _ in 0..constant_count {
+            let constant = match reader.read_u8()? {
+                CONSTANT_INT32 => ProgramConstant::Int32(reader.read_u32()? as i32),
+                CONSTANT_INT64 => ProgramConstant::Int64(reader.read_u64()? as i64),
+                CONSTANT_FLOAT => ProgramConstant::Float(f64::from_bits(reader.read_u64()?)),
+                CONSTANT_STRING => ProgramConstant::String(reader.read_string()?),
+                tag => {
+                    return Err(KirinError::General(format!(
+                        "unknown constant tag {} in compiled program",
+                        tag
+                    )));
+                }
+            };
+
+            constants.push(constant);
+        }
+
+        let file_count = reader.read_u32()? as usize;
+        let mut files = Vec::with_capacity(file_count.min(reader.remaining()));
+        for _ in 0..file_count {
+            files.push(reader.read_string()?);
+        }
+
+        let entry_count = reader.read_u32()? as usize;
+        let mut entries = Vec::with_capacity(entry_count.min(reader.remaining() / 16));
+        for _ in 0..entry_count {
+            let instruction = reader.read_u32()? as usize;
+            let file = match reader.read_u32()? {
+                NO_FILE => None,
+                file if (file as usize) < files.len() => Some(file as usize),
+                file => {
+                    return Err(KirinError::General(format!(
+                        "debug entry references unknown file {}",
+                        file
+                    )));
+                }
+            };
+            let line = reader.read_u32()? as usize;
+            let column = reader.read_u32()? as usize;
+
+            entries.push(DebugEntry {
+                instruction,
+                file,
+                line,
+                column,
+            });
+        }
+
+        let variable_count = reader.read_u32()? as usize;
+        let mut variables = Vec::with_capacity(variable_count.min(reader.remaining() / 13));
+        for _ in 0..variable_count {
+            let name = reader.read_string()?;
+            let register = reader.read_u32()? as usize;
+            let kind = reader.read_u8()?;
+            let Some(kind) = KirinType::from_u8(kind) else {
+                return Err(KirinError::General(format!(
+                    "unknown type {} of debug variable `{}`",
+                    kind, name
+                )));
+            };
+            let instruction = reader.read_u32()? as usize;
+
+            variables.push(DebugVariable {
+                name,
+                register,
+                kind,
+                instruction,
+            });
+        }
+
+        if reader.remaining() != 0 {
+            return Err(KirinError::General(
+                "unexpected trailing bytes in compiled program".to_string(),
+            ));
+        }
+
+        let mut program = Program::new(instructions, constants)
+            .with_debug_info(DebugInfo::from_parts(files, entries).with_variables(variables));
+        program.metadata = ProgramMetadata {
+            version_major,
+            version_minor,
+            instruction_count,
+            constant_count,
+        };
+
+        Ok(program)
+    }
+}
+
+fn write_u16(bytes: &mut Vec<u8>, value: usize, field: &str) -> Result<(), KirinError> {
+    let value = u16::try_from(value)
+        .map_err(|_| KirinError::General(format!("{} {} does not fit in 16 bits", field, value)))?;
+
+    bytes.extend_from_slice(&value.to_le_bytes());
+    Ok(())
+}
+
+fn write_u32(bytes: &mut Vec<u8>, value: usize, field: &str) -> Result<(), KirinError> {
+    let value = u32::try_from(value)
+        .map_err(|_| KirinError::General(format!("{} {} does not fit in 32 bits", field, value)))?;
+
+    bytes.extend_from_slice(&value.to_le_bytes());
+    Ok(())
+}
+
+fn write_string(bytes: &mut Vec<u8>, value: &str) -> Result<(), KirinError> {
+    write_u32(bytes, value.len(), "string length")?;
+    bytes.extend_from_slice(value.as_bytes());
+    Ok(())
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    fn remaining(&self) -> usize {
+        self.bytes.len() - self.position
+    }
+
+    fn read_bytes(&mut self, count: usize) -> Result<&'a [u8], KirinError> {
+        if self.remaining() < count {
+            return Err(KirinError::General(
+                "unexpected end of compiled program".to_string(),
+            ));
+        }
+
+        let bytes = &self.bytes[self.position..self.position + count];
+        self.position += count;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, KirinError> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_u16(&mut self) -> Result<u16, KirinError> {
+        let bytes = self.read_bytes(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn read_u32(&mut self) -> Result<u32, KirinError> {
+        let bytes = self.read_bytes(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, KirinError> {
+        let mut buffer = [0u8; 8];
+        buffer.copy_from_slice(self.read_bytes(8)?);
+        Ok(u64::from_le_bytes(buffer))
+    }
+
+    fn read_string(&mut self) -> Result<String, KirinError> {
+        let length = self.read_u32()? as usize;
+        let bytes = self.read_bytes(length)?;
+
+        String::from_utf8(bytes.to_vec()).map_err(|_| {
+            KirinError::General("invalid utf-8 string in compiled program".to_string())
+        })
+    }
+}
+
+/// CRC-32 (IEEE 802.3) of `bytes`
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB88320 & mask);
+        }
+    }
+
+    !crc
+}
+
+#[cfg(test)]
+mod serialization_tests {
+    use super::crc32;
+    use crate::{DebugInfo, Program, ProgramConstant};
+    use instructions::{InstructionBuilder, OpCode};
+    use types::KirinType;
+
+    fn sample_program() -> Program {
+        let instructions = vec![
+            InstructionBuilder::allocate_registers(2),
+            InstructionBuilder::load_constant(0, 3),
+            InstructionBuilder::deallocate_registers(2),
+            InstructionBuilder::simple(OpCode::Return),
+            InstructionBuilder::simple(OpCode::Halt),
+        ];
+        let constants = vec![
+            ProgramConstant::Int32(-7),
+            ProgramConstant::Int64(i64::MIN),
+            ProgramConstant::Float(2.5),
+            ProgramConstant::String("kirin ✓".to_string()),
+        ];
+
+        let mut debug_info = DebugInfo::new();
+        debug_info.push_unknown(0);
+        debug_info.push(1, Some("main.kn"), 4, 2);
+        debug_info.push_unknown(2);
+        debug_info.declare("total", 0, KirinType::Any, 2);
+
+        Program::new(instructions, constants).with_debug_info(debug_info)
+    }
+
+    fn serialize(program: &Program) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        program.write_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let program = sample_program();
+        let bytes = serialize(&program);
+
+        let read = Program::read_from(&mut bytes.as_slice()).unwrap();
+
+        assert_eq!(read.instructions, program.instructions);
+        assert_eq!(read.constants, program.constants);
+        assert_eq!(read.debug_info, program.debug_info);
+        assert_eq!(read.metadata(), program.metadata());
+    }
+
+    #[test]
+    fn test_rejects_corrupted_program() {
+        let mut bytes = serialize(&sample_program());
+        bytes[12] ^= 0xFF;
+
+        assert!(Program::read_from(&mut bytes.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_rejects_invalid_magic() {
+        let mut bytes = serialize(&sample_program());
+        bytes[0] = b'X';
+
+        assert!(Program::read_from(&mut bytes.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_rejects_incompatible_major_version() {
+        let mut bytes = serialize(&sample_program());
+        bytes[4] = bytes[4].wrapping_add(1);
+
+        // fix up the checksum so only the version is rejected
+        let length = bytes.len();
+        let checksum = crc32(&bytes[..length - 4]);
+        bytes[length - 4..].copy_from_slice(&checksum.to_le_bytes());
+
+        let error = Program::read_from(&mut bytes.as_slice()).unwrap_err();
+        assert!(error.to_string().contains("incompatible program version"));
+    }
+
+    #[test]
+    fn test_rejects_layout_without_debug_variables() {
+        let mut bytes = serialize(&Program::new(
+            vec![InstructionBuilder::simple(OpCode::Halt)],
+            Vec::new(),
+        ));
+
+        // version 0.1 ended with the debug entries
+        let length = bytes.len();
+        bytes.drain(length - 8..);
+        bytes[4..8].copy_from_slice(&[0, 0, 1, 0]);
+        let checksum = crc32(&bytes);
+        bytes.extend_from_slice(&checksum.to_le_bytes());
+
+        let error = Program::read_from(&mut bytes.as_slice()).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("incompatible program version 0.1, this VM runs version 1.0")
+        );
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b"123456789"), 0xCBF43926);
+    }
+}
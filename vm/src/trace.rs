@@ -0,0 +1,148 @@
+use crate::{DebugInfo, Register};
+use std::io::Write;
+
+/// Writes a line for each executed instruction with the registers it
+/// changed, preceded by the source location when it changes
+pub(crate) struct Tracer {
+    writer: Box<dyn Write>,
+    /// file and line of the previous instruction
+    location: Option<(Option<String>, usize)>,
+}
+
+/// Registers of the executing frame before an instruction
+pub(crate) struct Snapshot {
+    pub(crate) registers: Vec<Register>,
+    pub(crate) register_offset: usize,
+}
+
+impl Tracer {
+    pub(crate) fn new(writer: Box<dyn Write>) -> Self {
+        Self {
+            writer,
+            location: None,
+        }
+    }
+
+    pub(crate) fn record(
+        &mut self,
+        address: usize,
+        disassembly: &str,
+        debug_info: &DebugInfo,
+        before: &Snapshot,
+        registers: &[Register],
+    ) {
+        if let Some(location) = debug_info.lookup(address) {
+            let line = (location.file.map(str::to_string), location.line);
+
+            if self.location.as_ref() != Some(&line) {
+                writeln!(
+                    self.writer,
+                    "; {}:{}",
+                    location.file.unwrap_or("<unknown>"),
+                    location.line
+                )
+                .ok();
+                self.location = Some(line);
+            }
+        }
+
+        let deltas = deltas(before, registers);
+        if deltas.is_empty() {
+            writeln!(self.writer, "{:>6}  {}", address, disassembly).ok();
+        } else {
+            writeln!(
+                self.writer,
+                "{:>6}  {:<32}{}",
+                address,
+                disassembly,
+                deltas.join(", ")
+            )
+            .ok();
+        }
+    }
+
+    pub(crate) fn flush(&mut self) {
+        self.writer.flush().ok();
+    }
+}
+
+/// The changed registers, named relative to the frame the instruction ran
+/// in, and the number of registers allocated or released
+fn deltas(before: &Snapshot, registers: &[Register]) -> Vec<String> {
+    let mut deltas = before
+        .registers
+        .iter()
+        .zip(registers)
+        .enumerate()
+        .filter(|(_, (old, new))| old != new)
+        .map(|(index, (&old, &new))| {
+            let name = match index.checked_sub(before.register_offset) {
+                Some(register) => format!("r{}", register),
+                None => format!("caller r{}", index),
+            };
+
+            format!("{}: {} -> {}", name, old as i64, new as i64)
+        })
+        .collect::<Vec<String>>();
+
+    let (old, new) = (before.registers.len(), registers.len());
+    if new > old {
+        deltas.push(format!("+{} registers", new - old));
+    } else if new < old {
+        deltas.push(format!("-{} registers", old - new));
+    }
+
+    deltas
+}
+
+#[cfg(test)]
+mod trace_tests {
+    use crate::{VM, assemble};
+    use std::cell::RefCell;
+    use std::io::Write;
+    use std::rc::Rc;
+
+    #[derive(Clone, Default)]
+    struct Output(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_trace_register_deltas() {
+        let program = "
+            ALLOC_REG 2
+            LOAD_INT16 r0, 7
+            LOAD_INT16 r1, 7
+            ADD_INT r1, r0, r1
+            DEALLOC_REG 2
+            RETURN
+            HALT
+            ";
+
+        let output = Output::default();
+        let mut vm = VM::new();
+        vm.trace(output.clone());
+        vm.load_program(assemble(program).unwrap()).unwrap();
+        vm.start_with_offset(0).unwrap();
+
+        let trace = String::from_utf8(output.0.borrow().clone()).unwrap();
+        let lines = trace.lines().collect::<Vec<&str>>();
+        assert_eq!(lines[0], "; <unknown>:2");
+        assert_eq!(
+            lines[1].split_whitespace().collect::<Vec<&str>>(),
+            ["0", "ALLOC_REG", "2", "+2", "registers"]
+        );
+        assert!(lines[5].ends_with("  r1: 0 -> 7"));
+        assert!(lines[7].ends_with("  r1: 7 -> 14"));
+        assert!(lines[9].ends_with("  -2 registers"));
+        assert_eq!(lines[11], "     5  RETURN");
+    }
+}
@@ -0,0 +1,639 @@
+use crate::Program;
+use errors::KirinError;
+use instructions::{Instruction, InstructionDecoder, OpCode, OperandKind, OperandSlot};
+use std::collections::{HashMap, HashSet};
+
+/// Checks a program before it is loaded so malformed bytecode is rejected
+/// with an error instead of panicking during execution.
+///
+/// Instructions are checked in order. `AllocReg`/`DeallocReg` grow and shrink
+/// the register window of the current frame, `InitFrame`/`DropFrame` open and
+/// close nested windows, and every `Return` ends a function body whose window
+/// must be fully released. Jumps must reach their target with the windows it
+/// is reached with from anywhere else. Calls must target the start of a
+/// function, the first instruction or one after a `Return` or `Halt`, which
+/// starts without registers.
+pub(crate) struct Verifier<'a> {
+    program: &'a Program,
+    /// instructions already loaded into the VM, call targets are absolute
+    instruction_offset: usize,
+    /// constants already loaded into the VM
+    constant_offset: usize,
+    /// allocated registers of each open frame, innermost last
+    windows: Vec<usize>,
+    /// the top level continues a previous program and keeps its registers
+    resumed: bool,
+    /// the first instruction continues a previous program instead of
+    /// starting a function
+    continues: bool,
+    /// `Extend` prefix of the instruction being verified
+    extension: Option<Instruction>,
+    /// instructions jumped to, by their index in the program
+    targets: HashSet<usize>,
+    /// windows the jump targets are reached with, once known
+    entries: HashMap<usize, Vec<usize>>,
+    /// the previous instruction continues with the next one
+    falls_through: bool,
+}
+
+impl<'a> Verifier<'a> {
+    pub(crate) fn new(
+        program: &'a Program,
+        instruction_offset: usize,
+        constant_offset: usize,
+    ) -> Self {
+        Self {
+            program,
+            instruction_offset,
+            constant_offset,
+            windows: vec![0],
+            resumed: false,
+            continues: false,
+            extension: None,
+            targets: HashSet::new(),
+            entries: HashMap::new(),
+            falls_through: true,
+        }
+    }
+
+    /// Verify a program that continues the top level frame of the loaded
+    /// programs, which already holds `registers` registers. Its top level
+    /// returns without releasing them.
+    pub(crate) fn resuming(mut self, registers: usize) -> Self {
+        self.windows = vec![registers];
+        self.resumed = true;
+        self.continues = true;
+        self
+    }
+
+    pub(crate) fn verify(mut self) -> Result<(), KirinError> {
+        self.targets = self.jump_targets();
+
+        for (index, &instruction) in self.program.instructions.iter().enumerate() {
+            self.enter(index)
+                .and_then(|_| self.verify_instruction(instruction))
+                .map_err(|message| self.error(index, instruction, message))?;
+        }
+
+        if let Some(extension) = self.extension {
+            let index = self.program.instructions.len() - 1;
+            return Err(self.error(
+                index,
+                extension,
+                "extension prefix is not followed by an instruction".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Indices of the instructions targeted by jumps of the program
+    fn jump_targets(&self) -> HashSet<usize> {
+        let mut targets = HashSet::new();
+        let mut extension = None;
+
+        for &instruction in &self.program.instructions {
+            match InstructionDecoder::decode_known_opcode(instruction) {
+                Some(OpCode::Extend) => {
+                    extension = Some(instruction);
+                    continue;
+                }
+                Some(OpCode::Jump | OpCode::JumpIfFalse) => {
+                    let address = InstructionDecoder::decode_extended_operand(
+                        extension,
+                        instruction,
+                        OperandSlot::Immediate,
+                    ) as usize;
+
+                    if let Some(index) = address.checked_sub(self.instruction_offset) {
+                        targets.insert(index);
+                    }
+                }
+                _ => {}
+            }
+
+            extension = None;
+        }
+
+        targets
+    }
+
+    /// Continue with the windows an instruction is reached with, which must
+    /// be the same for every jump to it and for the instruction before it
+    fn enter(&mut self, index: usize) -> Result<(), String> {
+        if self.targets.contains(&index) {
+            match self.entries.get(&index) {
+                Some(expected) if self.falls_through && *expected != self.windows => {
+                    return Err(format!(
+                        "reached with {} but jumped to with {}",
+                        describe(&self.windows),
+                        describe(expected)
+                    ));
+                }
+                Some(expected) => self.windows = expected.clone(),
+                // unreachable instructions keep the windows before them
+                None => {
+                    self.entries.insert(index, self.windows.clone());
+                }
+            }
+        }
+
+        self.falls_through = true;
+        Ok(())
+    }
+
+    fn jump(&mut self, address: usize) -> Result<(), String> {
+        let Some(target) = address.checked_sub(self.instruction_offset) else {
+            return Err(format!(
+                "jump target {} is outside of the program starting at {}",
+                address, self.instruction_offset
+            ));
+        };
+
+        match self.entries.get(&target) {
+            Some(expected) if *expected != self.windows => Err(format!(
+                "jumps to instruction {} with {} but it is reached with {}",
+                address,
+                describe(&self.windows),
+                describe(expected)
+            )),
+            Some(_) => Ok(()),
+            // a forward jump, targets behind it were entered already
+            None => {
+                self.entries.insert(target, self.windows.clone());
+                Ok(())
+            }
+        }
+    }
+
+    fn call(&mut self, address: usize) -> Result<(), String> {
+        let target = address
+            .checked_sub(self.instruction_offset)
+            .filter(|&target| self.is_function_entry(target))
+            .ok_or_else(|| format!("call target {} is not the start of a function", address))?;
+
+        match self.entries.get(&target) {
+            Some(expected) if *expected != [0] => Err(format!(
+                "calls instruction {} but it is reached with {}",
+                address,
+                describe(expected)
+            )),
+            Some(_) => Ok(()),
+            None => {
+                self.entries.insert(target, vec![0]);
+                Ok(())
+            }
+        }
+    }
+
+    /// Whether the instruction at `index` starts a function body
+    fn is_function_entry(&self, index: usize) -> bool {
+        if index == 0 {
+            return !self.continues;
+        }
+
+        let previous = self.program.instructions[index - 1];
+        matches!(
+            InstructionDecoder::decode_known_opcode(previous),
+            Some(OpCode::Return | OpCode::Halt)
+        )
+    }
+
+    fn verify_instruction(&mut self, instruction: Instruction) -> Result<(), String> {
+        let Some(opcode) = InstructionDecoder::decode_known_opcode(instruction) else {
+            return Err(format!(
+                "unknown opcode {}",
+                InstructionDecoder::decode_opcode(instruction)
+            ));
+        };
+
+        let extension = self.extension.take();
+
+        if opcode == OpCode::Extend {
+            if extension.is_some() {
+                return Err("an extended instruction cannot be extended again".to_string());
+            }
+
+            self.extension = Some(instruction);
+            return Ok(());
+        }
+
+        for operand in opcode.operands() {
+            let value =
+                InstructionDecoder::decode_extended_operand(extension, instruction, operand.slot);
+
+            match operand.kind {
+                OperandKind::Register => self.register(value)?,
+                OperandKind::RegisterPair => self.register_pair(value)?,
+                OperandKind::Constant => self.constant(value as usize)?,
+                OperandKind::Address => self.jump_target(value as usize)?,
+                OperandKind::Int
+                | OperandKind::Count
+                | OperandKind::Bool
+                | OperandKind::Type
+                | OperandKind::Char => {}
+            }
+        }
+
+        let value = InstructionDecoder::decode_extended_operand(
+            extension,
+            instruction,
+            OperandSlot::Immediate,
+        ) as usize;
+
+        match opcode {
+            OpCode::AllocReg => *self.window() += value,
+            OpCode::DeallocReg => {
+                let allocated = *self.window();
+                if value > allocated {
+                    return Err(format!(
+                        "deallocates {} registers but only {} are allocated",
+                        value, allocated
+                    ));
+                }
+
+                *self.window() -= value;
+            }
+
+            OpCode::InitFrame => self.windows.push(0),
+            // dropping a frame releases all of its registers
+            OpCode::DropFrame => {
+                if self.windows.len() < 2 {
+                    return Err("drops a frame that was never initialized".to_string());
+                }
+
+                self.windows.pop();
+            }
+
+            OpCode::Jump => {
+                self.jump(value)?;
+                self.falls_through = false;
+            }
+            OpCode::JumpIfFalse => self.jump(value)?,
+            OpCode::Call => self.call(value)?,
+
+            OpCode::Return => {
+                if std::mem::take(&mut self.resumed) {
+                    self.frames_closed()?;
+                } else {
+                    self.balanced()?;
+                }
+
+                // the following instructions belong to another function body
+                self.windows = vec![0];
+                self.falls_through = false;
+            }
+            OpCode::Halt => {
+                self.balanced()?;
+                self.falls_through = false;
+            }
+
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn window(&mut self) -> &mut usize {
+        self.windows
+            .last_mut()
+            .expect("the verifier always has an open frame")
+    }
+
+    fn register(&mut self, register: Instruction) -> Result<(), String> {
+        let allocated = *self.window();
+
+        if register as usize >= allocated {
+            return Err(format!(
+                "register r{} is outside of the {} allocated registers",
+                register, allocated
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Any values occupy two consecutive registers
+    fn register_pair(&mut self, register: Instruction) -> Result<(), String> {
+        let allocated = *self.window();
+
+        if register as usize + 1 >= allocated {
+            return Err(format!(
+                "register pair r{}:r{} is outside of the {} allocated registers",
+                register,
+                register + 1,
+                allocated
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn constant(&self, index: usize) -> Result<(), String> {
+        let count = self.constant_offset + self.program.constants.len();
+
+        if index >= count {
+            return Err(format!(
+                "constant index {} is out of bounds for {} constants",
+                index, count
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn jump_target(&self, address: usize) -> Result<(), String> {
+        let count = self.instruction_offset + self.program.instructions.len();
+
+        if address >= count {
+            return Err(format!(
+                "jump target {} is out of bounds for {} instructions",
+                address, count
+            ));
+        }
+
+        // jumping between a prefix and its instruction would drop the prefix
+        let previous = address
+            .checked_sub(self.instruction_offset + 1)
+            .and_then(|index| self.program.instructions.get(index));
+        if let Some(&previous) = previous
+            && InstructionDecoder::decode_known_opcode(previous) == Some(OpCode::Extend)
+        {
+            return Err(format!(
+                "jump target {} is inside an extended instruction",
+                address
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn frames_closed(&self) -> Result<(), String> {
+        if self.windows.len() > 1 {
+            return Err(format!("{} frames are still open", self.windows.len() - 1));
+        }
+
+        Ok(())
+    }
+
+    fn balanced(&self) -> Result<(), String> {
+        self.frames_closed()?;
+
+        if self.windows[0] != 0 {
+            return Err(format!("{} registers are still allocated", self.windows[0]));
+        }
+
+        Ok(())
+    }
+
+    fn error(&self, index: usize, instruction: Instruction, message: String) -> KirinError {
+        let location = match self.program.location(index) {
+            Some(location) if location.line != 0 => format!(" ({})", location),
+            _ => String::new(),
+        };
+
+        KirinError::General(format!(
+            "invalid bytecode at instruction {}{}: {} [{:#010x}]",
+            index, location, message, instruction
+        ))
+    }
+}
+
+/// The allocated registers of open frames, for error messages
+fn describe(windows: &[usize]) -> String {
+    match windows {
+        [registers] => format!("{} allocated registers", registers),
+        windows => format!(
+            "{} allocated registers in {} frames",
+            windows.iter().sum::<usize>(),
+            windows.len()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod verifier_tests {
+    use super::Verifier;
+    use crate::{DebugInfo, Program, ProgramConstant, VM, assemble};
+    use errors::KirinError;
+    use instructions::{Instruction, InstructionBuilder, OpCode};
+
+    fn load(body: Vec<Instruction>, constants: Vec<ProgramConstant>) -> Result<(), KirinError> {
+        let mut instructions = vec![InstructionBuilder::allocate_registers(4)];
+        instructions.extend(body);
+        instructions.push(InstructionBuilder::deallocate_registers(4));
+        instructions.push(InstructionBuilder::simple(OpCode::Return));
+        instructions.push(InstructionBuilder::simple(OpCode::Halt));
+
+        VM::new().load_program(Program::new(instructions, constants))
+    }
+
+    fn error_message(result: Result<(), KirinError>) -> String {
+        match result {
+            Err(KirinError::General(message)) => message,
+            result => panic!("expected verification error, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_accepts_valid_program() {
+        let result = load(
+            vec![
+                InstructionBuilder::load_constant(0, 0),
+                InstructionBuilder::cast(OpCode::IntToAny, 2, 0),
+                InstructionBuilder::print_any(2),
+                InstructionBuilder::simple(OpCode::InitFrame),
+                InstructionBuilder::allocate_registers(1),
+                InstructionBuilder::load_16bit_int(0, 1),
+                InstructionBuilder::simple(OpCode::DropFrame),
+            ],
+            vec![ProgramConstant::Int32(3)],
+        );
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_rejects_unknown_opcode() {
+        let message = error_message(load(vec![0xFF00_0000], Vec::new()));
+
+        assert!(message.contains("instruction 1"));
+        assert!(message.contains("unknown opcode 255"));
+    }
+
+    #[test]
+    fn test_rejects_registers_outside_window() {
+        let message = error_message(load(
+            vec![InstructionBuilder::binary_operation(
+                OpCode::AddInt,
+                0,
+                1,
+                4,
+            )],
+            Vec::new(),
+        ));
+        assert!(message.contains("register r4 is outside of the 4 allocated registers"));
+
+        // the second register of the pair is out of bounds
+        let message = error_message(load(vec![InstructionBuilder::load_null(3)], Vec::new()));
+        assert!(message.contains("register pair r3:r4"));
+    }
+
+    #[test]
+    fn test_rejects_constant_out_of_bounds() {
+        let message = error_message(load(
+            vec![InstructionBuilder::load_constant(0, 1)],
+            vec![ProgramConstant::Int32(3)],
+        ));
+
+        assert!(message.contains("constant index 1 is out of bounds for 1 constants"));
+    }
+
+    #[test]
+    fn test_rejects_call_out_of_bounds() {
+        let message = error_message(load(vec![InstructionBuilder::call(40)], Vec::new()));
+
+        assert!(message.contains("jump target 40 is out of bounds"));
+    }
+
+    #[test]
+    fn test_rejects_unbalanced_allocation() {
+        let message = error_message(load(
+            vec![InstructionBuilder::allocate_registers(2)],
+            Vec::new(),
+        ));
+        assert!(message.contains("2 registers are still allocated"));
+
+        let message = error_message(load(
+            vec![InstructionBuilder::deallocate_registers(5)],
+            Vec::new(),
+        ));
+        assert!(message.contains("deallocates 5 registers but only 4 are allocated"));
+
+        let message = error_message(load(
+            vec![InstructionBuilder::simple(OpCode::DropFrame)],
+            Vec::new(),
+        ));
+        assert!(message.contains("never initialized"));
+    }
+
+    #[test]
+    fn test_rejects_malformed_extensions() {
+        let prefix = InstructionBuilder::simple(OpCode::Extend);
+
+        let message = error_message(load(
+            vec![prefix, prefix, InstructionBuilder::load_16bit_int(0, 1)],
+            Vec::new(),
+        ));
+        assert!(message.contains("cannot be extended again"));
+
+        // the prefix widens r1 to r257
+        let message = error_message(load(
+            InstructionBuilder::extended(OpCode::Move, &[0, 257]),
+            Vec::new(),
+        ));
+        assert!(message.contains("register r257 is outside of the 4 allocated registers"));
+
+        let mut body = InstructionBuilder::extended(OpCode::LoadInt16, &[0, 100_000]);
+        body.push(InstructionBuilder::call(2));
+        let message = error_message(load(body, Vec::new()));
+        assert!(message.contains("jump target 2 is inside an extended instruction"));
+
+        // loaded programs end with `Halt`, so only the verifier sees a trailing prefix
+        let program = Program::new(vec![prefix], Vec::new());
+        let message = error_message(Verifier::new(&program, 0, 0).verify());
+        assert!(message.contains("not followed by an instruction"));
+    }
+
+    #[test]
+    fn test_jumps_keep_register_windows() {
+        let message = error_message(VM::new().load_program(
+            assemble(
+                "ALLOC_REG 2\nJUMP skip\nALLOC_REG 4\nskip: LOAD_INT16 r5, 1\nDEALLOC_REG 6\nRETURN\nHALT",
+            )
+            .unwrap(),
+        ));
+        assert!(message.contains("instruction 3"));
+        assert!(message.contains(
+            "reached with 6 allocated registers but jumped to with 2 allocated registers"
+        ));
+
+        // a loop must release what its body allocates before jumping back
+        let message =
+            error_message(VM::new().load_program(
+                assemble("ALLOC_REG 1\nstart: ALLOC_REG 1\nJUMP start\nHALT").unwrap(),
+            ));
+        assert!(message.contains(
+            "jumps to instruction 1 with 2 allocated registers but it is reached with 1"
+        ));
+
+        let result = VM::new().load_program(
+            assemble(
+                "
+                ALLOC_REG 1
+                LOAD_BOOL r0, false
+                JUMP_IF_FALSE r0, done
+                INIT_FRAME
+                ALLOC_REG 3
+                DROP_FRAME
+                done:
+                DEALLOC_REG 1
+                RETURN
+                HALT
+                ",
+            )
+            .unwrap(),
+        );
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_calls_target_function_entries() {
+        // the call skips the allocation of the function it enters
+        let message = error_message(VM::new().load_program(
+            assemble(
+                "ALLOC_REG 1\nCALL mid\nDEALLOC_REG 1\nRETURN\nHALT\nALLOC_REG 3\nmid: LOAD_INT16 r2, 1\nDEALLOC_REG 3\nRETURN\nHALT",
+            )
+            .unwrap(),
+        ));
+        assert!(message.contains("instruction 1"));
+        assert!(message.contains("call target 6 is not the start of a function"));
+
+        // a jump must not enter a function with the caller's registers
+        let message = error_message(VM::new().load_program(
+            assemble("ALLOC_REG 1\nCALL f\nJUMP f\nHALT\nf: DEALLOC_REG 1\nRETURN\nHALT").unwrap(),
+        ));
+        assert!(message.contains(
+            "jumps to instruction 4 with 1 allocated registers but it is reached with 0"
+        ));
+
+        let result = VM::new().load_program(
+            assemble(
+                "ALLOC_REG 1\nCALL f\nDEALLOC_REG 1\nRETURN\nHALT\nf: ALLOC_REG 3\nLOAD_INT16 r2, 1\nDEALLOC_REG 3\nRETURN\nHALT",
+            )
+            .unwrap(),
+        );
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_error_includes_source_location() {
+        let instructions = vec![
+            InstructionBuilder::allocate_registers(1),
+            InstructionBuilder::load_16bit_int(1, 7),
+            InstructionBuilder::deallocate_registers(1),
+            InstructionBuilder::simple(OpCode::Return),
+            InstructionBuilder::simple(OpCode::Halt),
+        ];
+
+        let mut debug_info = DebugInfo::new();
+        debug_info.push_unknown(0);
+        debug_info.push(1, Some("main.kn"), 2, 5);
+        debug_info.push_unknown(2);
+
+        let program = Program::new(instructions, Vec::new()).with_debug_info(debug_info);
+        let message = error_message(VM::new().load_program(program));
+
+        assert!(message.contains("instruction 1 (main.kn:2:5)"));
+    }
+}